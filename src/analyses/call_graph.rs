@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, HashSet};
+use std::hash::Hash;
+
+use petgraph;
+use petgraph::graphmap::{GraphMap, NodeTrait};
+
+use yaxpeax_arch::Address;
+
+/// how a call site names the function it calls.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum CallKind {
+    /// the callee is written in the call instruction itself, like `call 0x1234`.
+    Direct,
+    /// the call goes through a register or memory, but something figured out where it goes - an
+    /// import slot, a constant function pointer, and so on.
+    IndirectResolved,
+    /// the call goes through a register or memory, and where it goes is anyone's guess.
+    Unresolved,
+    /// a jump to another function in place of a return. see `control_flow::EdgeKind::TailCall`.
+    TailCall,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CallSite<A> {
+    /// address of the call instruction.
+    pub at: A,
+    /// entry of the function the call is in.
+    pub caller: A,
+    /// entry of the called function, if known. only `Unresolved` calls have no callee.
+    pub callee: Option<A>,
+    pub kind: CallKind,
+}
+
+/// calls between functions across a whole program. `graph` has an edge from each caller to each
+/// function it calls, at least once; `sites` has every call separately, including unresolved
+/// calls that aren't in `graph` at all.
+pub struct CallGraph<A: Address + NodeTrait> {
+    pub graph: GraphMap<A, (), petgraph::Directed>,
+    pub sites: BTreeMap<A, CallSite<A>>,
+}
+
+impl <A: Address + NodeTrait + Hash> Default for CallGraph<A> {
+    fn default() -> Self {
+        CallGraph::new()
+    }
+}
+
+impl <A: Address + NodeTrait + Hash> CallGraph<A> {
+    pub fn new() -> CallGraph<A> {
+        CallGraph {
+            graph: GraphMap::new(),
+            sites: BTreeMap::new(),
+        }
+    }
+
+    /// add `function` to the graph, even if it makes and receives no calls.
+    pub fn add_function(&mut self, function: A) {
+        self.graph.add_node(function);
+    }
+
+    /// record a call at `at`, in `caller`, to `callee`. this replaces any call already recorded
+    /// at `at`.
+    pub fn add_call(&mut self, caller: A, at: A, callee: Option<A>, kind: CallKind) {
+        self.graph.add_node(caller);
+        if let Some(callee) = callee {
+            self.graph.add_edge(caller, callee, ());
+        }
+        self.sites.insert(at, CallSite { at, caller, callee, kind });
+    }
+
+    /// forget calls at addresses in `[start, end]`. edges in `graph` stay only if some other call
+    /// still makes them.
+    pub fn remove_calls_in(&mut self, start: A, end: A) {
+        let removed: Vec<CallSite<A>> = self.sites.range(start..=end).map(|(_, site)| site.clone()).collect();
+        for site in removed.iter() {
+            self.sites.remove(&site.at);
+        }
+        for site in removed.into_iter() {
+            if let Some(callee) = site.callee {
+                let still_called = self.sites.values().any(|other| other.caller == site.caller && other.callee == Some(callee));
+                if !still_called {
+                    self.graph.remove_edge(site.caller, callee);
+                }
+            }
+        }
+    }
+
+    pub fn functions(&self) -> Vec<A> {
+        let mut functions: Vec<A> = self.graph.nodes().collect();
+        functions.sort();
+        functions
+    }
+
+    /// functions with a call to `function`.
+    pub fn callers(&self, function: A) -> Vec<A> {
+        let mut callers: Vec<A> = self.graph.neighbors_directed(function, petgraph::Direction::Incoming).collect();
+        callers.sort();
+        callers
+    }
+
+    /// functions `function` has a call to.
+    pub fn callees(&self, function: A) -> Vec<A> {
+        let mut callees: Vec<A> = self.graph.neighbors_directed(function, petgraph::Direction::Outgoing).collect();
+        callees.sort();
+        callees
+    }
+
+    /// every call in `function`, resolved or not, in address order.
+    pub fn calls_from(&self, function: A) -> Vec<&CallSite<A>> {
+        self.sites.values().filter(|site| site.caller == function).collect()
+    }
+
+    /// every call to `function`, in address order.
+    pub fn calls_to(&self, function: A) -> Vec<&CallSite<A>> {
+        self.sites.values().filter(|site| site.callee == Some(function)).collect()
+    }
+
+    pub fn unresolved_calls(&self) -> Vec<&CallSite<A>> {
+        self.sites.values().filter(|site| site.kind == CallKind::Unresolved).collect()
+    }
+
+    /// strongly connected components of the call graph. each is either a single function, or a
+    /// set of mutually recursive functions. components are ordered callees-first: no component
+    /// calls into a component after it. functions within a component are in address order.
+    pub fn sccs(&self) -> Vec<Vec<A>> {
+        let mut sccs = petgraph::algo::tarjan_scc(&self.graph);
+        for scc in sccs.iter_mut() {
+            scc.sort();
+        }
+        sccs
+    }
+
+    /// can `function` end up calling itself?
+    pub fn is_recursive(&self, function: A) -> bool {
+        if self.graph.contains_edge(function, function) {
+            return true;
+        }
+        self.sccs().into_iter().any(|scc| scc.len() > 1 && scc.contains(&function))
+    }
+
+    /// every function, with callees before their callers - the order a bottom-up interprocedural
+    /// analysis wants to visit functions in. mutually recursive functions can't all be before
+    /// each other, so they're just adjacent in the order.
+    pub fn reverse_topological_order(&self) -> Vec<A> {
+        self.sccs().into_iter().flat_map(|scc| scc.into_iter()).collect()
+    }
+
+    /// functions that call nothing else, directly or otherwise - except possibly through
+    /// unresolved calls.
+    pub fn leaves(&self) -> Vec<A> {
+        let with_unresolved: HashSet<A> = self.unresolved_calls().iter().map(|site| site.caller).collect();
+        self.functions().into_iter()
+            .filter(|f| self.callees(*f).is_empty() && !with_unresolved.contains(f))
+            .collect()
+    }
+}
+
+#[test]
+fn test_call_graph_queries() {
+    /*
+     * 0x10 -> 0x20 -> 0x30 -> 0x20
+     *  |       `----> 0x40
+     *  `-> 0x50 -> 0x50, and an unresolved call in 0x40
+     */
+    let mut cg: CallGraph<u32> = CallGraph::new();
+    cg.add_call(0x10, 0x11, Some(0x20), CallKind::Direct);
+    cg.add_call(0x10, 0x12, Some(0x50), CallKind::IndirectResolved);
+    cg.add_call(0x20, 0x21, Some(0x30), CallKind::Direct);
+    cg.add_call(0x20, 0x22, Some(0x40), CallKind::TailCall);
+    cg.add_call(0x30, 0x31, Some(0x20), CallKind::Direct);
+    cg.add_call(0x40, 0x41, None, CallKind::Unresolved);
+    cg.add_call(0x50, 0x51, Some(0x50), CallKind::Direct);
+    cg.add_function(0x60);
+
+    assert_eq!(cg.callers(0x20), vec![0x10, 0x30]);
+    assert_eq!(cg.callees(0x10), vec![0x20, 0x50]);
+    assert_eq!(cg.calls_from(0x40).len(), 1);
+    assert_eq!(cg.unresolved_calls()[0].at, 0x41);
+    assert_eq!(cg.calls_to(0x50).iter().map(|site| site.at).collect::<Vec<u32>>(), vec![0x12, 0x51]);
+
+    assert!(cg.is_recursive(0x20));
+    assert!(cg.is_recursive(0x30));
+    assert!(cg.is_recursive(0x50));
+    assert!(!cg.is_recursive(0x10));
+    assert!(!cg.is_recursive(0x40));
+
+    assert!(cg.sccs().contains(&vec![0x20, 0x30]));
+    assert_eq!(cg.leaves(), vec![0x60]);
+
+    let order = cg.reverse_topological_order();
+    assert_eq!(order.len(), 6);
+    let position = |f: u32| order.iter().position(|x| *x == f).unwrap();
+    assert!(position(0x40) < position(0x20));
+    assert!(position(0x40) < position(0x30));
+    assert!(position(0x20) < position(0x10));
+    assert!(position(0x50) < position(0x10));
+}
@@ -0,0 +1,627 @@
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use petgraph::Direction as EdgeDirection;
+
+use yaxpeax_arch::{Address, AddressBase, AddressDiff, LengthedInstruction};
+
+use analyses::{CompletionStatus, DFG, OpaqueIndirection, Value, ValueRes};
+use analyses::control_flow::ControlFlowGraph;
+use analyses::liveness::{function_blocks, phi_operand};
+use analyses::static_single_assignment::{DefSource, DFGRef, SSA, SSAValues, UseSite};
+use arch::{DecodeFrom, InstructionSpan};
+use data::Direction;
+use memory::MemoryRange;
+
+/// what constant propagation knows about a value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Constant {
+    /// nothing has reached this value yet. if it's still `Undefined` at the end, whatever defines
+    /// it never executes.
+    Undefined,
+    Const(i64),
+    /// the value isn't always the same, or the semantics couldn't say what it is.
+    Overdefined,
+}
+
+impl Constant {
+    pub fn as_const(&self) -> Option<i64> {
+        if let Constant::Const(c) = self {
+            Some(*c)
+        } else {
+            None
+        }
+    }
+
+    pub fn meet(&self, other: &Constant) -> Constant {
+        match (self, other) {
+            (Constant::Undefined, x) |
+            (x, Constant::Undefined) => *x,
+            (Constant::Const(l), Constant::Const(r)) if l == r => Constant::Const(*l),
+            _ => Constant::Overdefined,
+        }
+    }
+
+    fn fold<F: FnOnce(i64, i64) -> Option<i64>>(&self, other: &Constant, f: F) -> Constant {
+        match (self, other) {
+            (Constant::Const(l), Constant::Const(r)) => {
+                f(*l, *r).map(Constant::Const).unwrap_or(Constant::Overdefined)
+            }
+            (Constant::Overdefined, _) |
+            (_, Constant::Overdefined) => Constant::Overdefined,
+            _ => Constant::Undefined,
+        }
+    }
+
+    fn map<F: FnOnce(i64) -> Option<i64>>(&self, f: F) -> Constant {
+        match self {
+            Constant::Const(c) => f(*c).map(Constant::Const).unwrap_or(Constant::Overdefined),
+            other => *other,
+        }
+    }
+}
+
+impl<A: Address> From<AddressDiff<A>> for Constant {
+    fn from(diff: AddressDiff<A>) -> Self {
+        Constant::Const(A::zero().wrapping_offset(diff).to_linear() as i64)
+    }
+}
+
+/// constants are 64 bits wide, and wrap like it. comparisons are signed, and produce `1` or `0`.
+impl Value for Constant {
+    fn unknown() -> Self {
+        Constant::Overdefined
+    }
+
+    fn from_const(c: i64) -> Self {
+        Constant::Const(c)
+    }
+
+    fn from_set(xs: &[Self]) -> Self {
+        xs.iter().fold(Constant::Undefined, |acc, x| acc.meet(x))
+    }
+
+    fn to_const(&self) -> Option<i64> {
+        self.as_const()
+    }
+
+    fn add(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes {
+            value: self.fold(other, |l, r| Some(l.wrapping_add(r))),
+            carry: self.fold(other, |l, r| Some((l as u64).overflowing_add(r as u64).1 as i64)),
+        }
+    }
+
+    fn sub(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes {
+            value: self.fold(other, |l, r| Some(l.wrapping_sub(r))),
+            carry: self.fold(other, |l, r| Some(((l as u64) < (r as u64)) as i64)),
+        }
+    }
+
+    fn mul(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes {
+            value: self.fold(other, |l, r| Some(l.wrapping_mul(r))),
+            carry: Constant::Overdefined,
+        }
+    }
+
+    fn or(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.fold(other, |l, r| Some(l | r)))
+    }
+
+    fn and(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.fold(other, |l, r| Some(l & r)))
+    }
+
+    fn xor(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.fold(other, |l, r| Some(l ^ r)))
+    }
+
+    fn modulo(&self, other: &Self) -> Self {
+        self.fold(other, |l, r| l.checked_rem(r))
+    }
+
+    fn ne(&self, other: &Self) -> Self {
+        self.fold(other, |l, r| Some((l != r) as i64))
+    }
+
+    fn le(&self, other: &Self) -> Self {
+        self.fold(other, |l, r| Some((l <= r) as i64))
+    }
+
+    fn lt(&self, other: &Self) -> Self {
+        self.fold(other, |l, r| Some((l < r) as i64))
+    }
+
+    fn eq(&self, other: &Self) -> Self {
+        self.fold(other, |l, r| Some((l == r) as i64))
+    }
+
+    /// semantics use `not` to negate conditions, so this is a logical not rather than a bitwise
+    /// one. bitwise nots are an `xor` with -1.
+    fn not(&self) -> Self {
+        self.map(|c| Some((c == 0) as i64))
+    }
+
+    fn shr(&self, amt: &Self) -> Self {
+        self.fold(amt, |l, r| (l as u64).checked_shr(r as u32).map(|x| x as i64))
+    }
+
+    fn sar(&self, amt: &Self) -> Self {
+        self.fold(amt, |l, r| l.checked_shr(r as u32))
+    }
+
+    fn shl(&self, amt: &Self) -> Self {
+        self.fold(amt, |l, r| l.checked_shl(r as u32))
+    }
+
+    fn sal(&self, amt: &Self) -> Self {
+        self.shl(amt)
+    }
+}
+
+/// what an architecture needs to provide for `propagate_constants`: mostly, its
+/// `semantic::evaluate`.
+pub trait ConstantSemantics: SSAValues + Sized {
+    /// the location holding the address of the next instruction. reads of it are the address
+    /// after the instruction being evaluated, and a constant write picks which successor a block
+    /// goes to.
+    fn program_counter() -> Self::Location;
+
+    /// the width of `loc` in bytes, if it holds a fixed-width value. locations narrower than
+    /// `Constant`'s 64 bits are extended when they're read and truncated when they're written.
+    /// semantics compute with 64-bit values, so an instruction with narrow inputs is evaluated
+    /// with them both sign- and zero-extended, and only results that agree are kept.
+    fn value_width(loc: &Self::Location) -> Option<usize>;
+
+    /// evaluate `instr` at `addr`. this is where an architecture's `semantic::evaluate` goes.
+    /// locations it doesn't write, but `SSA` says `instr` does, are `Overdefined`.
+    fn evaluate<D: DFG<Constant, Self, Self::Address>>(addr: Self::Address, instr: &Self::Instruction, dfg: &mut D) -> CompletionStatus;
+
+    /// data for a value at `loc` that's always `value`.
+    fn constant_data(loc: &Self::Location, value: i64) -> Self::Data;
+}
+
+/// the results of `propagate_constants`.
+#[derive(Debug)]
+pub struct Constants<A: SSAValues> {
+    /// every value defined by an instruction or phi that was reached. values that aren't here are
+    /// `Undefined` if they're defined somewhere that never executes, and `Overdefined` if they're
+    /// from somewhere constant propagation doesn't look, like function inputs.
+    pub values: HashMap<DFGRef<A>, Constant>,
+    /// blocks that can execute.
+    pub executable: BTreeSet<A::Address>,
+    /// edges in the control flow graph that can never be taken, as `(from, to)` block pairs.
+    pub infeasible_edges: BTreeSet<(A::Address, A::Address)>,
+}
+
+impl<A: SSAValues> Constants<A> {
+    pub fn value(&self, value: &DFGRef<A>) -> Option<Constant> {
+        self.values.get(value).cloned()
+    }
+
+    pub fn is_executable(&self, block: A::Address) -> bool {
+        self.executable.contains(&block)
+    }
+
+    pub fn is_feasible(&self, from: A::Address, to: A::Address) -> bool {
+        !self.infeasible_edges.contains(&(from, to))
+    }
+}
+
+/// an `SSA` read through the constants found so far, at one instruction.
+struct Evaluation<'a, A: SSAValues> {
+    ssa: &'a SSA<A>,
+    values: &'a HashMap<DFGRef<A>, Constant>,
+    next: i64,
+    writes: Vec<(A::Location, Constant)>,
+    /// some input hasn't been reached yet, so anything computed is premature.
+    pending: Cell<bool>,
+    /// how inputs narrower than 64 bits are extended.
+    extension: Extension,
+    /// some input was narrower than 64 bits.
+    narrow: Cell<bool>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Extension {
+    Sign,
+    Zero,
+}
+
+fn truncate(c: i64, width: usize) -> i64 {
+    if width >= 8 {
+        c
+    } else {
+        c & ((1i64 << (width * 8)) - 1)
+    }
+}
+
+fn extend(c: i64, width: usize, extension: Extension) -> i64 {
+    if width >= 8 {
+        return c;
+    }
+    let shift = 64 - width * 8;
+    match extension {
+        Extension::Sign => (c << shift) >> shift,
+        Extension::Zero => truncate(c, width),
+    }
+}
+
+fn current<A: SSAValues>(ssa: &SSA<A>, values: &HashMap<DFGRef<A>, Constant>, value: &DFGRef<A>) -> Constant {
+    if let Some(c) = values.get(value) {
+        return *c;
+    }
+    match ssa.try_get_def_site(*value) {
+        Some((_, DefSource::Instruction)) |
+        Some((_, DefSource::Phi)) => Constant::Undefined,
+        // inputs, and values from modifiers or edges, could be anything.
+        _ => Constant::Overdefined,
+    }
+}
+
+impl<'a, A: ConstantSemantics> DFG<Constant, A, A::Address> for Evaluation<'a, A> {
+    type Indirect = OpaqueIndirection<Constant>;
+
+    fn read_loc(&self, when: A::Address, loc: A::Location) -> Constant {
+        if loc == A::program_counter() {
+            return Constant::Const(self.next);
+        }
+        let (value, defined_width) = match self.ssa.try_get_use(when, loc.clone()) {
+            Some(value) => (current(self.ssa, self.values, &value), A::value_width(&self.ssa.value(value).location)),
+            None => { return Constant::Overdefined; }
+        };
+        if value == Constant::Undefined {
+            self.pending.set(true);
+        }
+        match A::value_width(&loc) {
+            // a write to part of this location says nothing about the rest of it.
+            Some(width) if defined_width.map(|defined| defined < width).unwrap_or(false) => Constant::Overdefined,
+            Some(width) if width < 8 => {
+                self.narrow.set(true);
+                value.map(|c| Some(extend(c, width, self.extension)))
+            }
+            Some(width) if width > 8 => Constant::Overdefined,
+            _ => value,
+        }
+    }
+
+    fn write_loc(&mut self, _when: A::Address, loc: A::Location, value: Constant) {
+        self.writes.push((loc, value));
+    }
+
+    fn indirect_loc(&self, _when: A::Address, _loc: A::Location) -> OpaqueIndirection<Constant> {
+        OpaqueIndirection::inst()
+    }
+}
+
+/// where control can go after a block.
+enum Branch {
+    /// the block's last instruction depends on something that hasn't been reached yet.
+    Pending,
+    /// the block ends by writing this to the program counter, or doesn't write it at all.
+    Evaluated(Option<Constant>),
+}
+
+/// what evaluating one instruction wrote.
+struct Evaluated<A: SSAValues> {
+    results: HashMap<A::Location, Constant>,
+    /// some input hasn't been reached yet.
+    pending: bool,
+    /// some input was narrower than 64 bits.
+    narrow: bool,
+    /// some result didn't fit the location it was written to.
+    overflowed: bool,
+}
+
+struct Propagation<'a, A: ConstantSemantics> {
+    cfg: &'a ControlFlowGraph<A::Address>,
+    ssa: &'a SSA<A>,
+    dominators: petgraph::algo::dominators::Dominators<A::Address>,
+    values: HashMap<DFGRef<A>, Constant>,
+    executable: BTreeSet<A::Address>,
+    feasible: HashSet<(A::Address, A::Address)>,
+    work: Vec<A::Address>,
+}
+
+impl<'a, A> Propagation<'a, A> where
+    A: ConstantSemantics,
+    A::Address: petgraph::graphmap::NodeTrait,
+{
+    fn update(&mut self, value: &DFGRef<A>, new: Constant) {
+        let old = current(self.ssa, &self.values, value);
+        let merged = old.meet(&new);
+        if merged == old && self.values.contains_key(value) {
+            return;
+        }
+        self.values.insert(*value, merged);
+        if merged == old {
+            return;
+        }
+        for site in self.ssa.uses_of(*value).iter() {
+            let block = match site {
+                UseSite::Instruction(addr) |
+                UseSite::Modifier(addr, _) => self.cfg.get_block(*addr).start,
+                UseSite::Phi(block, _) => *block,
+                UseSite::Between(_, _) => { continue; }
+            };
+            if self.executable.contains(&block) {
+                self.work.push(block);
+            }
+        }
+    }
+
+    fn visit_phis(&mut self, block: A::Address) {
+        let phis = match self.ssa.phi.get(&block) {
+            Some(phis) => phis,
+            None => { return; }
+        };
+        let preds: Vec<A::Address> = self.cfg.graph.neighbors_directed(block, EdgeDirection::Incoming)
+            .filter(|pred| self.feasible.contains(&(*pred, block)))
+            .collect();
+        for phi in phis.values() {
+            let mut result = Constant::Undefined;
+            for pred in preds.iter() {
+                if let Some(value) = phi_operand(self.cfg, self.ssa, &self.dominators, phi, *pred) {
+                    result = result.meet(&current(self.ssa, &self.values, value));
+                }
+            }
+            if block == self.cfg.entrypoint {
+                // the function can be entered from its caller, too.
+                result = Constant::Overdefined;
+            }
+            self.update(&phi.out, result);
+        }
+    }
+
+    /// evaluate `instr` with narrow inputs extended by `extension`. results are truncated to
+    /// their locations' widths, and the flag is whether any result didn't fit: `Sign`
+    /// evaluations check that results fit as signed values, `Zero` ones as unsigned values.
+    fn evaluate(&self, addr: A::Address, instr: &A::Instruction, extension: Extension) -> Evaluated<A> {
+        let mut evaluation = Evaluation {
+            ssa: self.ssa,
+            values: &self.values,
+            next: addr.wrapping_offset(instr.len()).to_linear() as i64,
+            writes: Vec::new(),
+            pending: Cell::new(false),
+            extension,
+            narrow: Cell::new(false),
+        };
+        A::evaluate(addr, instr, &mut evaluation);
+
+        let mut results: HashMap<A::Location, Constant> = HashMap::new();
+        let mut overflowed = false;
+        for (loc, value) in evaluation.writes.into_iter() {
+            let value = match (A::value_width(&loc), value) {
+                (Some(width), Constant::Const(c)) if width < 8 => {
+                    overflowed |= extend(c, width, extension) != c;
+                    Constant::Const(truncate(c, width))
+                }
+                (Some(width), _) if width > 8 => Constant::Overdefined,
+                (_, value) => value,
+            };
+            results.insert(loc, value);
+        }
+        Evaluated {
+            results,
+            pending: evaluation.pending.get(),
+            narrow: evaluation.narrow.get(),
+            overflowed,
+        }
+    }
+
+    fn visit_instruction(&mut self, addr: A::Address, instr: &A::Instruction) -> Branch {
+        let Evaluated { mut results, pending, narrow, mut overflowed } = self.evaluate(addr, instr, Extension::Sign);
+        if pending {
+            return Branch::Pending;
+        }
+        if narrow {
+            // a 32-bit `shr` or `cmp` computed with 64 bits depends on how its inputs were
+            // extended, so only keep what comes out the same either way.
+            let zero = self.evaluate(addr, instr, Extension::Zero);
+            overflowed |= zero.overflowed;
+            for (loc, value) in results.iter_mut() {
+                if zero.results.get(loc) != Some(value) {
+                    *value = Constant::Overdefined;
+                }
+            }
+        }
+        let pc = results.get(&A::program_counter()).cloned();
+
+        let rwmap = match self.ssa.instruction_values.get(&addr) {
+            Some(rwmap) => rwmap,
+            None => { return Branch::Evaluated(pc); }
+        };
+        for ((loc, dir), value) in rwmap.iter() {
+            if *dir != Direction::Write {
+                continue;
+            }
+            if self.ssa.try_get_def_site(*value) != Some(&(addr, DefSource::Instruction)) {
+                continue;
+            }
+            let result = match results.get(loc) {
+                // if a write didn't fit its location, anything else the instruction computed,
+                // like flags, came from the untruncated value and can't be trusted.
+                Some(_) if overflowed && A::value_width(loc).is_none() => Constant::Overdefined,
+                Some(result) => *result,
+                None => Constant::Overdefined,
+            };
+            self.update(value, result);
+        }
+
+        Branch::Evaluated(pc)
+    }
+
+    fn visit_edges(&mut self, block: A::Address, branch: Branch) {
+        let successors: Vec<A::Address> = self.cfg.graph.neighbors_directed(block, EdgeDirection::Outgoing).collect();
+        let taken: Vec<A::Address> = match branch {
+            Branch::Pending => { return; }
+            Branch::Evaluated(Some(Constant::Undefined)) => { return; }
+            Branch::Evaluated(Some(Constant::Const(target))) => {
+                // calls write the program counter too, but go on to the next block - so a
+                // target that isn't a successor says nothing about which successor is next.
+                let known: Vec<A::Address> = successors.iter()
+                    .filter(|succ| succ.to_linear() as i64 == target)
+                    .cloned()
+                    .collect();
+                if known.is_empty() { successors } else { known }
+            }
+            Branch::Evaluated(_) => successors,
+        };
+        for next in taken.into_iter() {
+            if self.feasible.insert((block, next)) {
+                self.executable.insert(next);
+                self.work.push(next);
+            }
+        }
+    }
+}
+
+/// sparse conditional constant propagation over `ssa`, which should have been built from `cfg`.
+/// `data` is where instructions in `cfg` are decoded from.
+///
+/// this finds values that are always the same constant, ignoring any paths through branches
+/// that those constants decide are never taken. constants are written into `Value::data` with
+/// `ConstantSemantics::constant_data`, replacing whatever was there before. memory is never
+/// tracked, so loads are always `Overdefined`.
+pub fn propagate_constants<A, M>(data: &M, cfg: &ControlFlowGraph<A::Address>, ssa: &mut SSA<A>) -> Constants<A> where
+    A: ConstantSemantics + DecodeFrom<M>,
+    A::Address: petgraph::graphmap::NodeTrait,
+    M: MemoryRange<A>,
+{
+    let mut propagation = Propagation {
+        cfg,
+        ssa: &*ssa,
+        dominators: petgraph::algo::dominators::simple_fast(&cfg.graph, cfg.entrypoint),
+        values: HashMap::new(),
+        executable: BTreeSet::new(),
+        feasible: HashSet::new(),
+        work: vec![cfg.entrypoint],
+    };
+    propagation.executable.insert(cfg.entrypoint);
+
+    while let Some(start) = propagation.work.pop() {
+        propagation.visit_phis(start);
+        let block = cfg.get_block(start);
+        let mut branch = Branch::Evaluated(None);
+        let mut iter = A::instructions_spanning(data, block.start, block.end);
+        while let Some((addr, instr)) = iter.next() {
+            branch = propagation.visit_instruction(addr, instr);
+        }
+        propagation.visit_edges(start, branch);
+    }
+
+    let Propagation { values, executable, feasible, .. } = propagation;
+    for (value, constant) in values.iter() {
+        if let Constant::Const(c) = constant {
+            let data = A::constant_data(&ssa.value(*value).location, *c);
+            ssa.value_mut(*value).data = Some(data);
+        }
+    }
+
+    let (order, successors) = function_blocks(cfg);
+    let mut infeasible_edges = BTreeSet::new();
+    for block in order.iter() {
+        for next in successors[block].iter() {
+            if !feasible.contains(&(*block, *next)) {
+                infeasible_edges.insert((*block, *next));
+            }
+        }
+    }
+
+    Constants {
+        values,
+        executable,
+        infeasible_edges,
+    }
+}
+
+#[test]
+fn test_constant_propagation() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Data, Location, NoDisambiguation};
+
+    let data: Vec<u8> = vec![
+        0x48, 0xc7, 0xc1, 0x00, 0x00, 0x00, 0x00,   // 0x00: mov rcx, 0
+        0x48, 0xc7, 0xc0, 0x05, 0x00, 0x00, 0x00,   // 0x07: mov rax, 5
+        0x48, 0x85, 0xc9,                           // 0x0e: test rcx, rcx
+        0x74, 0x07,                                 // 0x11: je 0x1a
+        0x48, 0xc7, 0xc0, 0x07, 0x00, 0x00, 0x00,   // 0x13: mov rax, 7
+        0x48, 0x83, 0xc0, 0x01,                     // 0x1a: add rax, 1
+        0xc3,                                       // 0x1e: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let mut dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let constants = propagate_constants(&data, &cfg, &mut dfg);
+
+    // rcx is always zero, so `je` is always taken and `mov rax, 7` never runs..
+    assert_eq!(constants.value(&dfg.get_def(0x0e, Location::ZF).as_rc()), Some(Constant::Const(1)));
+    assert!(!constants.is_executable(0x13));
+    assert!(constants.is_executable(0x1a));
+    assert_eq!(constants.infeasible_edges.iter().cloned().collect::<Vec<(u64, u64)>>(), vec![(0x00, 0x13), (0x13, 0x1a)]);
+    assert_eq!(constants.value(&dfg.get_def(0x13, Location::rax()).as_rc()), None);
+    // .. so only `mov rax, 5` reaches the add.
+    assert_eq!(constants.value(&dfg.get_use(0x1a, Location::rax()).as_rc()), Some(Constant::Const(5)));
+    let sum = dfg.get_def(0x1a, Location::rax()).as_rc();
+    assert_eq!(constants.value(&sum), Some(Constant::Const(6)));
+    assert_eq!(dfg.value(sum).data, Some(Data::Concrete(6, None)));
+    assert_eq!(constants.value(&dfg.get_def(0x1a, Location::ZF).as_rc()), Some(Constant::Const(0)));
+    // eax was written too, but `add` only said what rax is.
+    assert_eq!(constants.value(&dfg.get_def(0x1a, Location::eax()).as_rc()), Some(Constant::Overdefined));
+}
+
+#[test]
+fn test_constant_propagation_narrow() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+
+    let data: Vec<u8> = vec![
+        0x31, 0xc0,                                 // 0x00: xor eax, eax
+        0x85, 0xc0,                                 // 0x02: test eax, eax
+        0x75, 0x0f,                                 // 0x04: jne 0x15
+        0xb9, 0x0f, 0x0f, 0x0f, 0x0f,               // 0x06: mov ecx, 0x0f0f0f0f
+        0xc1, 0xe9, 0x04,                           // 0x0b: shr ecx, 4
+        0xf7, 0xd1,                                 // 0x0e: not ecx
+        0x83, 0xc1, 0x10,                           // 0x10: add ecx, 0x10
+        0x85, 0xc9,                                 // 0x13: test ecx, ecx
+        0xc3,                                       // 0x15: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let mut dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let constants = propagate_constants(&data, &cfg, &mut dfg);
+
+    // 32-bit operations fold like 64-bit ones..
+    assert_eq!(constants.value(&dfg.get_def(0x00, Location::eax()).as_rc()), Some(Constant::Const(0)));
+    assert_eq!(constants.value(&dfg.get_def(0x02, Location::ZF).as_rc()), Some(Constant::Const(1)));
+    assert!(!constants.is_feasible(0x00, 0x15));
+    assert_eq!(constants.value(&dfg.get_def(0x0b, Location::ecx()).as_rc()), Some(Constant::Const(0x00f0f0f0)));
+    assert_eq!(constants.value(&dfg.get_def(0x0e, Location::ecx()).as_rc()), Some(Constant::Const(0xff0f0f0f)));
+    assert_eq!(constants.value(&dfg.get_def(0x10, Location::ecx()).as_rc()), Some(Constant::Const(0xff0f0f1f)));
+    assert_eq!(constants.value(&dfg.get_def(0x10, Location::CF).as_rc()), Some(Constant::Const(0)));
+    assert_eq!(constants.value(&dfg.get_def(0x13, Location::ZF).as_rc()), Some(Constant::Const(0)));
+    // .. but the sign of a negative 32-bit value isn't the sign of its 64-bit zero-extension.
+    assert_eq!(constants.value(&dfg.get_def(0x10, Location::SF).as_rc()), Some(Constant::Overdefined));
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Debug;
+
+use petgraph;
+use petgraph::algo::dominators::Dominators;
+use petgraph::graphmap::GraphMap;
+
+use yaxpeax_arch::Address;
+
+use analyses::control_flow::ControlFlowGraph;
+
+/// a node in the reversed control flow graph post-dominators are computed over. functions can
+/// have any number of exits (or none at all, for something that ends in an infinite loop), so
+/// they're all tied together with a single `VirtualExit` that every exit flows to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Node<A> {
+    Block(A),
+    VirtualExit,
+}
+
+impl<A: Copy> Node<A> {
+    pub fn block(&self) -> Option<A> {
+        match self {
+            Node::Block(addr) => Some(*addr),
+            Node::VirtualExit => None,
+        }
+    }
+}
+
+pub struct PostDominators<A: Address + petgraph::graphmap::NodeTrait> {
+    /// the function's control flow graph, reversed, with edges from `VirtualExit` to each
+    /// exiting block.
+    pub reversed: GraphMap<Node<A>, (), petgraph::Directed>,
+    /// blocks that were tied to `VirtualExit`. this is every block without a successor, blocks
+    /// the caller said end in a non-returning call, and for regions that never exit (infinite
+    /// loops), one arbitrary block of the region.
+    pub exits: BTreeSet<A>,
+    idom: Dominators<Node<A>>,
+}
+
+impl<A: Address + Debug + petgraph::graphmap::NodeTrait> PostDominators<A> {
+    /// compute post-dominators for blocks in `cfg` reachable from `cfg.entrypoint`.
+    ///
+    /// `noreturn_blocks` are blocks that end in something control flow can't continue past, but
+    /// that might still have successors in `cfg` - typically a call to a function that never
+    /// returns, where construction linked the call to whatever bytes follow it. their outgoing
+    /// edges are ignored, and they are treated as exits.
+    pub fn compute(cfg: &ControlFlowGraph<A>, noreturn_blocks: &[A]) -> PostDominators<A> {
+        let noreturn: HashSet<A> = noreturn_blocks.iter().cloned().collect();
+
+        // only consider blocks actually in the function
+        let mut reachable: Vec<A> = Vec::new();
+        let mut seen: HashSet<A> = HashSet::new();
+        let mut worklist: Vec<A> = vec![cfg.entrypoint];
+        seen.insert(cfg.entrypoint);
+        while let Some(block) = worklist.pop() {
+            reachable.push(block);
+            if noreturn.contains(&block) {
+                continue;
+            }
+            for dest in cfg.destinations(block) {
+                if seen.insert(dest) {
+                    worklist.push(dest);
+                }
+            }
+        }
+        reachable.sort();
+
+        let mut reversed: GraphMap<Node<A>, (), petgraph::Directed> = GraphMap::new();
+        reversed.add_node(Node::VirtualExit);
+        let mut exits: BTreeSet<A> = BTreeSet::new();
+        for block in reachable.iter() {
+            reversed.add_node(Node::Block(*block));
+            let dests = if noreturn.contains(block) {
+                Vec::new()
+            } else {
+                cfg.destinations(*block)
+            };
+            if dests.is_empty() {
+                exits.insert(*block);
+                reversed.add_edge(Node::VirtualExit, Node::Block(*block), ());
+            }
+            for dest in dests {
+                reversed.add_edge(Node::Block(dest), Node::Block(*block), ());
+            }
+        }
+
+        // blocks that can't reach an exit (because they're stuck in an infinite loop) wouldn't be
+        // post-dominated by anything. tie one block of each such region to the exit and go
+        // again until everything is covered. picking the highest address is arbitrary, but
+        // deterministic, and tends to be the bottom of a loop.
+        loop {
+            let mut reaches_exit: HashSet<Node<A>> = HashSet::new();
+            let mut dfs = petgraph::visit::Dfs::new(&reversed, Node::VirtualExit);
+            while let Some(node) = dfs.next(&reversed) {
+                reaches_exit.insert(node);
+            }
+            let stuck = reachable.iter().rev().find(|block| !reaches_exit.contains(&Node::Block(**block)));
+            match stuck {
+                Some(block) => {
+                    exits.insert(*block);
+                    reversed.add_edge(Node::VirtualExit, Node::Block(*block), ());
+                }
+                None => { break; }
+            }
+        }
+
+        let idom = petgraph::algo::dominators::simple_fast(&reversed, Node::VirtualExit);
+
+        PostDominators {
+            reversed,
+            exits,
+            idom,
+        }
+    }
+
+    /// the closest block that every path from `block` to an exit goes through. `VirtualExit` if
+    /// paths from `block` leave the function in different ways, `None` if `block` isn't part of
+    /// the function at all.
+    pub fn immediate_post_dominator(&self, block: A) -> Option<Node<A>> {
+        self.idom.immediate_dominator(Node::Block(block))
+    }
+
+    /// does every path from `block` to the exit go through `post_dominator`? like dominance,
+    /// this is reflexive: blocks post-dominate themselves.
+    pub fn post_dominates(&self, post_dominator: A, block: A) -> bool {
+        match self.idom.dominators(Node::Block(block)) {
+            Some(mut dominators) => dominators.any(|n| n == Node::Block(post_dominator)),
+            None => false,
+        }
+    }
+
+    pub fn dominators(&self) -> &Dominators<Node<A>> {
+        &self.idom
+    }
+}
+
+/// `dependent` is control dependent on the branch at the end of `branch` when one of `branch`'s
+/// successors always reaches `dependent`, but another can avoid it. this is the relation
+/// `SSA::control_dependent_values` is keyed on, but for blocks rather than values.
+pub struct ControlDependenceGraph<A: Address + petgraph::graphmap::NodeTrait> {
+    /// edges from a branching block to each block that is control dependent on it. a block that
+    /// decides whether a loop runs again is control dependent on itself.
+    pub graph: GraphMap<A, (), petgraph::Directed>,
+    /// for each `(branch, dependent)` edge in `graph`, the successors of `branch` that lead to
+    /// `dependent` being executed.
+    pub edges: HashMap<(A, A), BTreeSet<A>>,
+}
+
+impl<A: Address + Debug + petgraph::graphmap::NodeTrait> ControlDependenceGraph<A> {
+    /// the standard construction from Ferrante et al.: for each edge `a -> b` where `b` does not
+    /// post-dominate `a`, every block on the post-dominator tree path from `b` up to (but not
+    /// including) `a`'s immediate post-dominator is control dependent on `a`.
+    pub fn compute(post_dominators: &PostDominators<A>) -> ControlDependenceGraph<A> {
+        let mut graph: GraphMap<A, (), petgraph::Directed> = GraphMap::new();
+        let mut edges: HashMap<(A, A), BTreeSet<A>> = HashMap::new();
+
+        for (dest, src, _) in post_dominators.reversed.all_edges() {
+            let (src, dest) = match (src.block(), dest.block()) {
+                (Some(src), Some(dest)) => (src, dest),
+                // edges to the virtual exit don't make anything control dependent.
+                _ => { continue; }
+            };
+            if post_dominators.post_dominates(dest, src) {
+                continue;
+            }
+
+            let stop = post_dominators.immediate_post_dominator(src);
+            let mut curr = Some(Node::Block(dest));
+            while curr != stop {
+                let block = match curr {
+                    Some(Node::Block(block)) => block,
+                    // walked to the root without finding `stop`; nothing left to mark.
+                    _ => { break; }
+                };
+                graph.add_edge(src, block, ());
+                edges.entry((src, block)).or_insert_with(BTreeSet::new).insert(dest);
+                curr = post_dominators.immediate_post_dominator(block);
+            }
+        }
+
+        // and nodes that are only reachable by straight-line code still belong in the graph
+        for node in post_dominators.reversed.nodes() {
+            if let Some(block) = node.block() {
+                graph.add_node(block);
+            }
+        }
+
+        ControlDependenceGraph { graph, edges }
+    }
+
+    /// branching blocks `block` is control dependent on.
+    pub fn dependencies_of(&self, block: A) -> Vec<A> {
+        self.graph.neighbors_directed(block, petgraph::Direction::Incoming).collect()
+    }
+
+    /// blocks that are control dependent on the branch ending `block`.
+    pub fn dependents_of(&self, block: A) -> Vec<A> {
+        self.graph.neighbors_directed(block, petgraph::Direction::Outgoing).collect()
+    }
+
+    /// successors of `branch` that, when taken, cause `dependent` to execute.
+    pub fn deciding_successors(&self, branch: A, dependent: A) -> Option<&BTreeSet<A>> {
+        self.edges.get(&(branch, dependent))
+    }
+}
+
+impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::NodeTrait {
+    /// post-dominators of this graph, treating `noreturn_blocks` as exits. see
+    /// `PostDominators::compute`.
+    pub fn post_dominators(&self, noreturn_blocks: &[A]) -> PostDominators<A> {
+        PostDominators::compute(self, noreturn_blocks)
+    }
+
+    /// the control dependence graph of this graph. see `ControlDependenceGraph::compute`.
+    pub fn control_dependence(&self, noreturn_blocks: &[A]) -> ControlDependenceGraph<A> {
+        ControlDependenceGraph::compute(&self.post_dominators(noreturn_blocks))
+    }
+}
+
+#[test]
+fn test_post_dominators() {
+    /*
+     * 0 -> 1 -> 3 -> 4
+     *  `-> 2 -'    `-> 5
+     * with 4 and 5 both returning, and 2 calling a non-returning function before falling through
+     * to 3.
+     */
+    let mut cfg: ControlFlowGraph<u32> = ControlFlowGraph::from(0);
+    cfg.graph = GraphMap::from_edges(&[
+        (0, 1), (0, 2), (1, 3), (2, 3), (3, 4), (3, 5),
+    ]);
+
+    let pdom = cfg.post_dominators(&[]);
+    assert_eq!(pdom.exits, [4, 5].iter().cloned().collect());
+    assert_eq!(pdom.immediate_post_dominator(0), Some(Node::Block(3)));
+    assert_eq!(pdom.immediate_post_dominator(1), Some(Node::Block(3)));
+    assert_eq!(pdom.immediate_post_dominator(3), Some(Node::VirtualExit));
+    assert!(pdom.post_dominates(3, 0));
+    assert!(!pdom.post_dominates(1, 0));
+
+    let pdom = cfg.post_dominators(&[2]);
+    assert_eq!(pdom.exits, [2, 4, 5].iter().cloned().collect());
+    assert_eq!(pdom.immediate_post_dominator(0), Some(Node::VirtualExit));
+    assert_eq!(pdom.immediate_post_dominator(1), Some(Node::Block(3)));
+    assert!(!pdom.post_dominates(3, 0));
+}
+
+#[test]
+fn test_control_dependence() {
+    /*
+     * 0 -> 1 -> 2 -> 4 -> 5
+     *       `-> 3 -'|
+     *       ^-------'
+     * an if/else in the body of a loop, exiting from the bottom at 4.
+     */
+    let mut cfg: ControlFlowGraph<u32> = ControlFlowGraph::from(0);
+    cfg.graph = GraphMap::from_edges(&[
+        (0, 1), (1, 2), (1, 3), (2, 4), (3, 4), (4, 1), (4, 5),
+    ]);
+
+    let cdg = cfg.control_dependence(&[]);
+    let mut deps = cdg.dependents_of(1);
+    deps.sort();
+    assert_eq!(deps, vec![2, 3]);
+    // the loop runs again, and decides to run again, depending on 4
+    let mut deps = cdg.dependents_of(4);
+    deps.sort();
+    assert_eq!(deps, vec![1, 4]);
+    assert_eq!(cdg.dependencies_of(0), Vec::<u32>::new());
+    assert_eq!(cdg.dependencies_of(5), Vec::<u32>::new());
+    assert_eq!(cdg.dependencies_of(2), vec![1]);
+    assert_eq!(cdg.deciding_successors(4, 4), Some(&[1].iter().cloned().collect()));
+    assert_eq!(cdg.deciding_successors(1, 3), Some(&[3].iter().cloned().collect()));
+
+    /*
+     * 0 -> 1 -> 2
+     *       `-> 3 -> 3
+     * where 3 never exits, but is still control dependent on 1.
+     */
+    let mut cfg: ControlFlowGraph<u32> = ControlFlowGraph::from(0);
+    cfg.graph = GraphMap::from_edges(&[
+        (0, 1), (1, 2), (1, 3), (3, 3),
+    ]);
+
+    let pdom = cfg.post_dominators(&[]);
+    assert_eq!(pdom.exits, [2, 3].iter().cloned().collect());
+    let cdg = cfg.control_dependence(&[]);
+    let mut deps = cdg.dependents_of(1);
+    deps.sort();
+    assert_eq!(deps, vec![2, 3]);
+    assert_eq!(cdg.dependents_of(3), Vec::<u32>::new());
+}
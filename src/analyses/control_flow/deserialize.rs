@@ -8,7 +8,15 @@ use serde::de::{self, Deserialize, Deserializer, Visitor, SeqAccess, MapAccess};
 
 #[derive(Deserialize)]
 #[serde(field_identifier, rename_all = "lowercase")]
-enum Field { Entrypoint, Blocks, Graph }
+enum Field {
+    Entrypoint,
+    Blocks,
+    Graph,
+    #[serde(rename = "tail_calls")] TailCalls,
+    Decoded,
+    Diagnostics,
+    #[serde(rename = "overlapping_streams")] OverlappingStreams,
+}
 
 struct CFGVisitor<A> { _marker: std::marker::PhantomData<A> }
 
@@ -29,10 +37,20 @@ impl<'de, A: Address + Hash> Visitor<'de> for CFGVisitor<A> {
             .ok_or_else(|| de::Error::invalid_length(1, &self))?;
         let graph: crate::serialize::GraphDeserializer<A> = seq.next_element()?
             .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+        // graphs serialized before tail calls were tracked just don't have any.
+        let tail_calls = seq.next_element()?.unwrap_or_default();
+        // and the same for anything about overlapping instructions.
+        let decoded = seq.next_element()?.unwrap_or_default();
+        let diagnostics = seq.next_element()?.unwrap_or_default();
+        let overlapping_streams = seq.next_element()?.unwrap_or_default();
         Ok(ControlFlowGraph {
             entrypoint,
             blocks,
             graph: graph.into_inner(),
+            tail_calls,
+            decoded,
+            diagnostics,
+            overlapping_streams,
         })
     }
 
@@ -43,6 +61,10 @@ impl<'de, A: Address + Hash> Visitor<'de> for CFGVisitor<A> {
         let mut entrypoint = None;
         let mut blocks = None;
         let mut graph = None;
+        let mut tail_calls = None;
+        let mut decoded = None;
+        let mut diagnostics = None;
+        let mut overlapping_streams = None;
         while let Some(key) = map.next_key()? {
             match key {
                 Field::Entrypoint => {
@@ -63,6 +85,30 @@ impl<'de, A: Address + Hash> Visitor<'de> for CFGVisitor<A> {
                     }
                     graph = Some(map.next_value()?);
                 }
+                Field::TailCalls => {
+                    if tail_calls.is_some() {
+                        return Err(de::Error::duplicate_field("tail_calls"));
+                    }
+                    tail_calls = Some(map.next_value()?);
+                }
+                Field::Decoded => {
+                    if decoded.is_some() {
+                        return Err(de::Error::duplicate_field("decoded"));
+                    }
+                    decoded = Some(map.next_value()?);
+                }
+                Field::Diagnostics => {
+                    if diagnostics.is_some() {
+                        return Err(de::Error::duplicate_field("diagnostics"));
+                    }
+                    diagnostics = Some(map.next_value()?);
+                }
+                Field::OverlappingStreams => {
+                    if overlapping_streams.is_some() {
+                        return Err(de::Error::duplicate_field("overlapping_streams"));
+                    }
+                    overlapping_streams = Some(map.next_value()?);
+                }
             }
         }
         let entrypoint = entrypoint.ok_or_else(|| de::Error::missing_field("entrypoint"))?;
@@ -72,6 +118,10 @@ impl<'de, A: Address + Hash> Visitor<'de> for CFGVisitor<A> {
             entrypoint,
             blocks,
             graph: graph.into_inner(),
+            tail_calls: tail_calls.unwrap_or_default(),
+            decoded: decoded.unwrap_or_default(),
+            diagnostics: diagnostics.unwrap_or_default(),
+            overlapping_streams: overlapping_streams.unwrap_or_default(),
         })
     }
 }
@@ -82,7 +132,7 @@ impl<'de, A: Address + Hash> Deserialize<'de> for ControlFlowGraph<A> {
         D: Deserializer<'de>,
     {
 
-        const FIELDS: &'static [&'static str] = &["entrypoint", "blocks", "graph"];
+        const FIELDS: &'static [&'static str] = &["entrypoint", "blocks", "graph", "tail_calls", "decoded", "diagnostics", "overlapping_streams"];
         let visitor: CFGVisitor<A> = CFGVisitor { _marker: std::marker::PhantomData };
         deserializer.deserialize_struct("CFG<A>", FIELDS, visitor)
     }
@@ -0,0 +1,312 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+use petgraph::graphmap::NodeTrait;
+
+use yaxpeax_arch::{Address, AddressDiff};
+
+use analyses::call_graph::{CallGraph, CallKind};
+use analyses::control_flow::{BasicBlock, ControlFlowGraph};
+
+/// what an edge in an exported graph stands for. `ControlFlowGraph` edges don't record why they
+/// exist, so `Fallthrough`, `Conditional` and `Jump` are told apart by where they go: an edge to
+/// the next address is a fallthrough, and other edges are conditional if the block also has a
+/// fallthrough, or unconditional jumps otherwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ExportEdgeKind {
+    Fallthrough,
+    Conditional,
+    Jump,
+    Call,
+    TailCall,
+}
+
+impl ExportEdgeKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportEdgeKind::Fallthrough => "fallthrough",
+            ExportEdgeKind::Conditional => "conditional",
+            ExportEdgeKind::Jump => "jump",
+            ExportEdgeKind::Call => "call",
+            ExportEdgeKind::TailCall => "tail call",
+        }
+    }
+
+    /// a color name both graphviz and most GraphML viewers understand.
+    pub fn color(&self) -> &'static str {
+        match self {
+            ExportEdgeKind::Fallthrough => "blue",
+            ExportEdgeKind::Conditional => "green",
+            ExportEdgeKind::Jump => "black",
+            ExportEdgeKind::Call => "orange",
+            ExportEdgeKind::TailCall => "red",
+        }
+    }
+}
+
+/// the nodes and edges of an exported graph. `blocks` are the blocks of `cfg` that were explored
+/// or have edges; `external` are destinations of calls and tail calls that aren't in `blocks`,
+/// like other functions when exporting a single function.
+struct Export<A: Address> {
+    blocks: Vec<BasicBlock<A>>,
+    external: BTreeSet<A>,
+    edges: Vec<(A, A, ExportEdgeKind)>,
+}
+
+fn collect<A: Address + fmt::Debug + NodeTrait>(cfg: &ControlFlowGraph<A>, calls: Option<&CallGraph<A>>) -> Export<A> {
+    // `cfg.blocks` covers every address, explored or not, so only take the explored ones.
+    let blocks: Vec<BasicBlock<A>> = cfg.blocks.values()
+        .filter(|block| cfg.is_explored(block.start))
+        .cloned()
+        .collect();
+    let starts: BTreeSet<A> = blocks.iter().map(|block| block.start).collect();
+
+    let mut edges = Vec::new();
+    for block in blocks.iter() {
+        let mut dests = cfg.destinations(block.start);
+        dests.sort();
+        let fallthrough = block.end.wrapping_offset(AddressDiff::one());
+        let has_fallthrough = block.end != A::max_value() && dests.contains(&fallthrough);
+        for dest in dests.into_iter() {
+            let kind = if has_fallthrough && dest == fallthrough {
+                ExportEdgeKind::Fallthrough
+            } else if has_fallthrough {
+                ExportEdgeKind::Conditional
+            } else {
+                ExportEdgeKind::Jump
+            };
+            edges.push((block.start, dest, kind));
+        }
+    }
+
+    let mut external = BTreeSet::new();
+    let mut link = |at: A, callee: A, kind: ExportEdgeKind, edges: &mut Vec<(A, A, ExportEdgeKind)>| {
+        let from = cfg.get_block(at).start;
+        if !starts.contains(&from) {
+            return;
+        }
+        if !starts.contains(&callee) {
+            external.insert(callee);
+        }
+        edges.push((from, callee, kind));
+    };
+    for (at, callee) in cfg.tail_calls.iter() {
+        link(*at, *callee, ExportEdgeKind::TailCall, &mut edges);
+    }
+    if let Some(calls) = calls {
+        for site in calls.sites.values() {
+            if let Some(callee) = site.callee {
+                let kind = if site.kind == CallKind::TailCall {
+                    // already drawn from `cfg.tail_calls`, if `cfg` has it.
+                    if cfg.tail_calls.get(&site.at) == Some(&callee) {
+                        continue;
+                    }
+                    ExportEdgeKind::TailCall
+                } else {
+                    ExportEdgeKind::Call
+                };
+                link(site.at, callee, kind, &mut edges);
+            }
+        }
+    }
+
+    Export { blocks, external, edges }
+}
+
+fn node_id<A: Address>(addr: A) -> String {
+    format!("{:#x}", addr.to_linear())
+}
+
+/// `text` without terminal escape sequences or other control characters, other than newlines and
+/// tabs. labels are usually rendered for a terminal - `render_frame` colors comments and function
+/// declarations no matter what colors it's asked for - and neither DOT nor XML wants the escapes.
+fn strip_control(text: &str) -> String {
+    let mut stripped = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                // `ESC [ params final` is a control sequence, with a final byte in `@..=~`. any
+                // other escape is just one more character.
+                if chars.peek() == Some(&'[') {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                } else {
+                    chars.next();
+                }
+            }
+            '\n' | '\t' => stripped.push(c),
+            c if c.is_control() => {}
+            c => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+fn escape_dot(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in strip_control(text).chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            // left-justify each line, like a listing.
+            '\n' => escaped.push_str("\\l"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in strip_control(text).chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// write `cfg` to `dest` as a graphviz DOT digraph. each block is labeled with `label(block)` -
+/// `arch::display::render_block` gives its disassembly - and edges are colored by
+/// `ExportEdgeKind`. with `calls`, calls made from blocks in `cfg` are drawn too.
+///
+/// this works the same for a global graph or one from `ControlFlowGraph::get_function`.
+pub fn write_dot<A, W, L>(
+    cfg: &ControlFlowGraph<A>,
+    calls: Option<&CallGraph<A>>,
+    mut label: L,
+    dest: &mut W,
+) -> fmt::Result where
+    A: Address + fmt::Debug + NodeTrait,
+    W: Write,
+    L: FnMut(&BasicBlock<A>) -> String,
+{
+    let export = collect(cfg, calls);
+    writeln!(dest, "digraph cfg {{")?;
+    writeln!(dest, "    node [shape=box, fontname=\"monospace\"];")?;
+    for block in export.blocks.iter() {
+        let text = format!("{}:\n{}", node_id(block.start), label(block));
+        writeln!(dest, "    \"{}\" [label=\"{}\"];", node_id(block.start), escape_dot(&text))?;
+    }
+    for addr in export.external.iter() {
+        writeln!(dest, "    \"{}\" [label=\"{}\", style=dashed];", node_id(*addr), node_id(*addr))?;
+    }
+    for (from, to, kind) in export.edges.iter() {
+        writeln!(
+            dest,
+            "    \"{}\" -> \"{}\" [color={}, label=\"{}\"];",
+            node_id(*from), node_id(*to), kind.color(), kind.name()
+        )?;
+    }
+    writeln!(dest, "}}")
+}
+
+/// write `cfg` to `dest` as GraphML, with the same nodes and edges as `write_dot`. labels and
+/// edge kinds and colors are `data` elements keyed `label`, `kind` and `color`.
+pub fn write_graphml<A, W, L>(
+    cfg: &ControlFlowGraph<A>,
+    calls: Option<&CallGraph<A>>,
+    mut label: L,
+    dest: &mut W,
+) -> fmt::Result where
+    A: Address + fmt::Debug + NodeTrait,
+    W: Write,
+    L: FnMut(&BasicBlock<A>) -> String,
+{
+    let export = collect(cfg, calls);
+    writeln!(dest, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(dest, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+    writeln!(dest, "  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>")?;
+    writeln!(dest, "  <key id=\"external\" for=\"node\" attr.name=\"external\" attr.type=\"boolean\"/>")?;
+    writeln!(dest, "  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>")?;
+    writeln!(dest, "  <key id=\"color\" for=\"edge\" attr.name=\"color\" attr.type=\"string\"/>")?;
+    writeln!(dest, "  <graph id=\"cfg\" edgedefault=\"directed\">")?;
+    for block in export.blocks.iter() {
+        writeln!(
+            dest,
+            "    <node id=\"{}\"><data key=\"label\">{}</data></node>",
+            node_id(block.start), escape_xml(&label(block))
+        )?;
+    }
+    for addr in export.external.iter() {
+        writeln!(
+            dest,
+            "    <node id=\"{}\"><data key=\"label\">{}</data><data key=\"external\">true</data></node>",
+            node_id(*addr), node_id(*addr)
+        )?;
+    }
+    for (from, to, kind) in export.edges.iter() {
+        writeln!(
+            dest,
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"kind\">{}</data><data key=\"color\">{}</data></edge>",
+            node_id(*from), node_id(*to), kind.name(), kind.color()
+        )?;
+    }
+    writeln!(dest, "  </graph>")?;
+    writeln!(dest, "</graphml>")
+}
+
+#[test]
+fn test_export_edge_kinds() {
+    use analyses::control_flow::{Effect, Target};
+
+    /*
+     * 0x10: a conditional branch to 0x30, falling through to 0x20, which jumps back to 0x10.
+     * 0x28 is padding, 0x30 tail calls 0x50, and 0x20 also calls 0x60.
+     */
+    let mut cfg: ControlFlowGraph<u32> = ControlFlowGraph::new();
+    cfg.with_effect(0x10 - 1, 0x10, &Effect::stop());
+    cfg.with_effect(0x1e, 0x20, &Effect::cont_and(Target::Absolute(0x30)));
+    cfg.with_effect(0x26, 0x28, &Effect::stop_and(Target::Absolute(0x10)));
+    cfg.with_effect(0x3e, 0x40, &Effect::stop_and(Target::Absolute(0x50)));
+    cfg.mark_tail_call(0x3e, 0x50);
+    for (at, end) in [(0x10, 0x1f), (0x20, 0x27), (0x30, 0x3f)].iter() {
+        cfg.decoded.insert(*at, *end);
+    }
+    let mut calls: CallGraph<u32> = CallGraph::new();
+    calls.add_call(0x10, 0x24, Some(0x60), CallKind::Direct);
+
+    let export = collect(&cfg, Some(&calls));
+    assert_eq!(export.blocks.iter().map(|block| block.start).collect::<Vec<u32>>(), vec![0x10, 0x20, 0x30]);
+    assert_eq!(export.external.iter().cloned().collect::<Vec<u32>>(), vec![0x50, 0x60]);
+    let mut edges = export.edges.clone();
+    edges.sort();
+    assert_eq!(edges, vec![
+        (0x10, 0x20, ExportEdgeKind::Fallthrough),
+        (0x10, 0x30, ExportEdgeKind::Conditional),
+        (0x20, 0x10, ExportEdgeKind::Jump),
+        (0x20, 0x60, ExportEdgeKind::Call),
+        (0x30, 0x50, ExportEdgeKind::TailCall),
+    ]);
+
+    let mut dot = String::new();
+    write_dot(&cfg, Some(&calls), |block| format!("block \"{:#x}\"\n", block.start), &mut dot).unwrap();
+    assert!(dot.contains("\"0x10\" [label=\"0x10:\\lblock \\\"0x10\\\"\\l\"];"));
+    assert!(dot.contains("\"0x30\" -> \"0x50\" [color=red, label=\"tail call\"];"));
+    assert!(dot.contains("\"0x60\" [label=\"0x60\", style=dashed];"));
+
+    let mut graphml = String::new();
+    write_graphml(&cfg, Some(&calls), |block| format!("<{:#x}>", block.start), &mut graphml).unwrap();
+    assert!(graphml.contains("<node id=\"0x20\"><data key=\"label\">&lt;0x20&gt;</data></node>"));
+    assert!(graphml.contains("<edge source=\"0x10\" target=\"0x30\"><data key=\"kind\">conditional</data><data key=\"color\">green</data></edge>"));
+
+    // colors meant for a terminal don't make it into either format.
+    let colored = |block: &BasicBlock<u32>| format!("\x1b[38;5;4m{:#x}\x1b[39m: ret\x07\n", block.start);
+    let mut dot = String::new();
+    write_dot(&cfg, Some(&calls), colored, &mut dot).unwrap();
+    assert!(dot.contains("\"0x10\" [label=\"0x10:\\l0x10: ret\\l\"];"));
+    let mut graphml = String::new();
+    write_graphml(&cfg, Some(&calls), colored, &mut graphml).unwrap();
+    assert!(graphml.contains("<node id=\"0x20\"><data key=\"label\">0x20: ret\n</data></node>"));
+    assert!(!graphml.contains('\x1b'));
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use petgraph;
+
+use yaxpeax_arch::{AddressBase, Arch, LengthedInstruction};
+
+use analyses::control_flow::{AnalysisBuilder, BasicBlock, ControlFlowGraph, Determinant, Effect, Target};
+use arch::DecodeFrom;
+use memory::MemoryRange;
+
+use ContextRead;
+use ContextWrite;
+
+/// a table of code addresses that some indirect branch selects a destination from. this is
+/// almost always how `switch` statements end up being compiled when the cases are dense enough.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JumpTable<Addr> {
+    /// the address of the indirect branch that dispatches through this table.
+    pub dispatch: Addr,
+    /// the address of the table itself.
+    pub table: Addr,
+    /// destinations read out of the table, in table order. destinations may repeat - a switch
+    /// where several cases share a body will list that body several times.
+    pub entries: Vec<Addr>,
+}
+
+pub trait JumpTableResolver<A: Arch, U> {
+    /// Given a basic block, decide if it ends in an indirect branch through a table, and if so,
+    /// find the table and read its entries. `aux_data` is whatever an implementor needs to bound
+    /// the index into the table - for architectures with `ConditionalBoundInference`, this is
+    /// most likely the `InstructionModifiers` that inference produced.
+    ///
+    /// It's fine (preferable, really) to give up and return `None` when the index can't be
+    /// bounded. reading past the end of a table yields edges into whatever follows it, and those
+    /// are very hard to get rid of later.
+    fn resolve_jump_table<M: MemoryRange<A> + ?Sized>(block: &BasicBlock<A::Address>, cfg: &ControlFlowGraph<A::Address>, data: &M, aux_data: &U) -> Option<JumpTable<A::Address>>
+        where A: DecodeFrom<M>;
+
+    /// Look for jump tables in every block of `cfg`, link their dispatch instruction to each
+    /// entry, and explore the newly-reachable code into `cfg`. Code discovered this way may end
+    /// in its own jump tables, so this repeats until no new tables are found.
+    ///
+    /// `aux_data` is not updated in the process, so tables in new code can only be resolved if
+    /// `aux_data` already knows how to bound them. a caller with more precise information
+    /// (f.ex after recomputing SSA and conditional bounds over the larger `cfg`) can just call
+    /// this again; tables that were already resolved are resolved again to the same entries.
+    fn recover_jump_tables<M, Ctx, Update, Contexts>(data: &M, contexts: &mut Contexts, cfg: &mut ControlFlowGraph<A::Address>, aux_data: &U) -> Vec<JumpTable<A::Address>>
+        where
+            A: DecodeFrom<M>,
+            M: MemoryRange<A> + ?Sized,
+            Contexts: ContextRead<A, Ctx> + ContextWrite<A, Update>,
+            A::Address: Hash + petgraph::graphmap::NodeTrait + num_traits::WrappingAdd,
+            A::Instruction: Debug + Determinant<Ctx, A::Address>,
+    {
+        let mut tables: Vec<JumpTable<A::Address>> = Vec::new();
+        let mut dispatches: HashSet<A::Address> = HashSet::new();
+
+        loop {
+            let found: Vec<JumpTable<A::Address>> = cfg.blocks.values()
+                .filter(|block| cfg.graph.contains_node(block.start))
+                .filter_map(|block| Self::resolve_jump_table(block, cfg, data, aux_data))
+                .filter(|table| !dispatches.contains(&table.dispatch))
+                .collect();
+
+            if found.is_empty() {
+                return tables;
+            }
+
+            for table in found.into_iter() {
+                let next = match data.range_from(table.dispatch).and_then(|range| A::decode_from(&range).ok()) {
+                    Some(instr) => table.dispatch.wrapping_offset(instr.len()),
+                    None => {
+                        // the resolver found a dispatch instruction we can't decode? that's a bug
+                        // in the resolver, but not something worth tearing down the cfg for.
+                        continue;
+                    }
+                };
+
+                let targets: Vec<Target<A::Address>> = table.entries.iter()
+                    .map(|entry| Target::Absolute(*entry))
+                    .collect();
+                cfg.with_effect(table.dispatch, next, &Effect::stop_and(Target::Multiple(targets)));
+
+                let mut starts: Vec<A::Address> = Vec::new();
+                for entry in table.entries.iter() {
+                    if !starts.contains(entry) {
+                        starts.push(*entry);
+                    }
+                }
+                AnalysisBuilder::new(data, contexts)
+                    .with_entrypoints(starts)
+                    .evaluate_into(cfg);
+
+                dispatches.insert(table.dispatch);
+                tables.push(table);
+            }
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Debug;
+
+use petgraph;
+use petgraph::algo::dominators::Dominators;
+
+use yaxpeax_arch::Address;
+
+use analyses::control_flow::ControlFlowGraph;
+
+/// a natural loop: the blocks that can reach a back edge's source without going through the
+/// header it targets. loops that share a header are merged, so each header has exactly one loop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NaturalLoop<A: Address> {
+    /// the single entry to the loop. the header dominates every block in `body`.
+    pub header: A,
+    /// blocks with a back edge to `header`.
+    pub latches: Vec<A>,
+    /// every block in the loop, including `header` and `latches`.
+    pub body: BTreeSet<A>,
+    /// edges leaving the loop, as `(block in the loop, block outside it)`.
+    pub exits: Vec<(A, A)>,
+}
+
+impl<A: Address> NaturalLoop<A> {
+    pub fn contains(&self, block: A) -> bool {
+        self.body.contains(&block)
+    }
+
+    /// blocks outside the loop that control flow can leave to. a block is reported once even if
+    /// several loop blocks exit to it.
+    pub fn exit_blocks(&self) -> BTreeSet<A> {
+        self.exits.iter().map(|(_from, to)| *to).collect()
+    }
+}
+
+/// natural loops of a function, arranged by nesting. loops are indexes into `loops`; a loop's
+/// parent is the smallest other loop containing it, and loops without a parent are roots.
+///
+/// this only describes reducible loops. control flow that enters a cycle somewhere other than one
+/// header (irreducible control flow, f.ex jumping into the middle of a loop) has no back edge, as
+/// far as dominators are concerned, and is not reported at all.
+#[derive(Debug)]
+pub struct LoopForest<A: Address> {
+    pub loops: Vec<NaturalLoop<A>>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    innermost: HashMap<A, usize>,
+}
+
+impl<A: Address + Debug + petgraph::graphmap::NodeTrait> LoopForest<A> {
+    /// find loops in `cfg`, as reached from `cfg.entrypoint`. this is most useful on a single
+    /// function's graph, as produced by `ControlFlowGraph::get_function`.
+    pub fn of(cfg: &ControlFlowGraph<A>) -> LoopForest<A> {
+        let idom = petgraph::algo::dominators::simple_fast(&cfg.graph, cfg.entrypoint);
+        LoopForest::with_dominators(cfg, &idom)
+    }
+
+    /// find loops in `cfg` with dominators that were already computed - presumably rooted at
+    /// `cfg.entrypoint`.
+    pub fn with_dominators(cfg: &ControlFlowGraph<A>, idom: &Dominators<A>) -> LoopForest<A> {
+        fn dominates<A: Copy + Eq + std::hash::Hash>(idom: &Dominators<A>, dominator: A, node: A) -> bool {
+            match idom.dominators(node) {
+                Some(mut dominators) => dominators.any(|n| n == dominator),
+                None => false,
+            }
+        }
+
+        // collect back edges by header so loops with a shared header come out merged
+        let mut latches: HashMap<A, Vec<A>> = HashMap::new();
+        for (from, to, _) in cfg.graph.all_edges() {
+            if dominates(idom, to, from) {
+                latches.entry(to).or_insert_with(Vec::new).push(from);
+            }
+        }
+
+        let mut headers: Vec<A> = latches.keys().cloned().collect();
+        headers.sort();
+
+        let mut loops: Vec<NaturalLoop<A>> = Vec::new();
+        for header in headers.into_iter() {
+            let mut latches = latches.remove(&header).unwrap();
+            latches.sort();
+            latches.dedup();
+
+            let mut body = BTreeSet::new();
+            body.insert(header);
+            let mut worklist: Vec<A> = latches.clone();
+            while let Some(block) = worklist.pop() {
+                if body.insert(block) {
+                    for pred in cfg.sources(block) {
+                        // blocks not reachable from the entrypoint can't be in the loop, even if
+                        // they have an edge into it.
+                        if idom.immediate_dominator(pred).is_some() || pred == cfg.entrypoint {
+                            worklist.push(pred);
+                        }
+                    }
+                }
+            }
+
+            let mut exits = Vec::new();
+            for block in body.iter() {
+                let mut dests = cfg.destinations(*block);
+                dests.sort();
+                for dest in dests {
+                    if !body.contains(&dest) {
+                        exits.push((*block, dest));
+                    }
+                }
+            }
+
+            loops.push(NaturalLoop { header, latches, body, exits });
+        }
+
+        // natural loops with different headers are either disjoint or nested, so the smallest
+        // loop containing another loop's header is its parent.
+        let mut parents: Vec<Option<usize>> = vec![None; loops.len()];
+        let mut children: Vec<Vec<usize>> = vec![Vec::new(); loops.len()];
+        for (i, inner) in loops.iter().enumerate() {
+            let parent = loops.iter().enumerate()
+                .filter(|(j, outer)| *j != i && outer.body.len() > inner.body.len() && outer.contains(inner.header))
+                .min_by_key(|(_, outer)| outer.body.len())
+                .map(|(j, _)| j);
+            parents[i] = parent;
+            if let Some(parent) = parent {
+                children[parent].push(i);
+            }
+        }
+
+        let mut innermost: HashMap<A, usize> = HashMap::new();
+        for (i, l) in loops.iter().enumerate() {
+            for block in l.body.iter() {
+                let replace = match innermost.get(block) {
+                    Some(prev) => loops[*prev].body.len() > l.body.len(),
+                    None => true,
+                };
+                if replace {
+                    innermost.insert(*block, i);
+                }
+            }
+        }
+
+        LoopForest { loops, parents, children, innermost }
+    }
+
+    /// loops not nested in any other loop.
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.loops.len()).filter(|i| self.parents[*i].is_none()).collect()
+    }
+
+    pub fn parent(&self, idx: usize) -> Option<usize> {
+        self.parents[idx]
+    }
+
+    pub fn children(&self, idx: usize) -> &[usize] {
+        &self.children[idx]
+    }
+
+    /// how many loops enclose loop `idx`. roots are at depth 0.
+    pub fn depth(&self, idx: usize) -> usize {
+        let mut depth = 0;
+        let mut curr = idx;
+        while let Some(parent) = self.parents[curr] {
+            depth += 1;
+            curr = parent;
+        }
+        depth
+    }
+
+    /// the loop headed by `header`, if `header` heads a loop.
+    pub fn loop_at(&self, header: A) -> Option<&NaturalLoop<A>> {
+        self.loops.iter().find(|l| l.header == header)
+    }
+
+    /// the smallest loop `block` is a part of, if any.
+    pub fn innermost_loop_of(&self, block: A) -> Option<usize> {
+        self.innermost.get(&block).cloned()
+    }
+
+    pub fn is_header(&self, block: A) -> bool {
+        self.loop_at(block).is_some()
+    }
+}
+
+impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::NodeTrait {
+    /// natural loops in this graph, as reached from `entrypoint`. see `LoopForest`.
+    pub fn loops(&self) -> LoopForest<A> {
+        LoopForest::of(self)
+    }
+}
+
+#[test]
+fn test_loop_nesting() {
+    use petgraph::graphmap::GraphMap;
+    /*
+     * 0 -> 1 -> 2 -> 3 -> 2
+     *           |    `--> 4 -> 1
+     *           |         `--> 5
+     *           `-> 6 -> 6
+     * with a self loop at 6, a loop {2, 3} inside {1, 2, 3, 4}, and 7 -> 3 unreachable.
+     */
+    let mut cfg: ControlFlowGraph<u32> = ControlFlowGraph::from(0);
+    cfg.graph = GraphMap::from_edges(&[
+        (0, 1), (1, 2), (2, 3), (3, 2), (3, 4), (4, 1), (4, 5), (2, 6), (6, 6), (7, 3),
+    ]);
+
+    let forest = cfg.loops();
+    assert_eq!(forest.loops.len(), 3);
+
+    let outer = forest.loop_at(1).expect("1 heads a loop");
+    assert_eq!(outer.latches, vec![4]);
+    assert_eq!(outer.body, [1, 2, 3, 4].iter().cloned().collect());
+    assert_eq!(outer.exits, vec![(2, 6), (4, 5)]);
+
+    let inner = forest.loop_at(2).expect("2 heads a loop");
+    assert_eq!(inner.latches, vec![3]);
+    assert_eq!(inner.body, [2, 3].iter().cloned().collect());
+    assert_eq!(inner.exit_blocks(), [4, 6].iter().cloned().collect());
+
+    let selfloop = forest.loop_at(6).expect("6 heads a loop");
+    assert_eq!(selfloop.latches, vec![6]);
+    assert_eq!(selfloop.body, [6].iter().cloned().collect());
+    assert_eq!(selfloop.exits, vec![]);
+
+    let outer_idx = forest.innermost_loop_of(1).unwrap();
+    let inner_idx = forest.innermost_loop_of(3).unwrap();
+    let self_idx = forest.innermost_loop_of(6).unwrap();
+    assert_eq!(forest.parent(inner_idx), Some(outer_idx));
+    assert_eq!(forest.children(outer_idx), &[inner_idx]);
+    assert_eq!(forest.depth(inner_idx), 1);
+    let mut roots = forest.roots();
+    roots.sort();
+    let mut expected_roots = vec![outer_idx, self_idx];
+    expected_roots.sort();
+    assert_eq!(roots, expected_roots);
+    assert_eq!(forest.innermost_loop_of(0), None);
+    assert_eq!(forest.innermost_loop_of(7), None);
+}
@@ -1,6 +1,6 @@
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::hash::Hash;
-use std::ops::Bound::Included;
+use std::ops::Bound::{Excluded, Included, Unbounded};
 use std::fmt::{self, Debug};
 
 use smallvec::SmallVec;
@@ -19,7 +19,11 @@ use ContextWrite;
 
 use memory::MemoryRange;
 
+pub mod control_dependence;
 pub mod deserialize;
+pub mod export;
+pub mod jump_tables;
+pub mod loops;
 
 use serialize::GraphSerializer;
 
@@ -75,7 +79,7 @@ pub enum Target<Addr: Address + Debug> {
     #[serde(bound(deserialize = "Addr: Address"))]
     Absolute(Addr),
     #[serde(bound(deserialize = "Addr: Address"))]
-    Multiple(Vec<Target<Addr>>), // TODO: ?? jump tables?
+    Multiple(Vec<Target<Addr>>), // jump tables, or any other dispatch with known destinations
     Indeterminate       // Unknowns? rets? idk
 }
 
@@ -126,19 +130,108 @@ impl<Addr: Copy + Clone> BasicBlock<Addr> {
     }
 }
 
+/// the ways control can get from one block to another.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeKind {
+    /// an ordinary branch or fallthrough, within a function.
+    Branch,
+    /// a jump that leaves the function it's in by transferring to another function in place of
+    /// returning. tail calls are not edges in `ControlFlowGraph::graph`, so walking the graph does
+    /// not wander into the callee.
+    TailCall,
+}
+
+/// something about the code being explored that a `ControlFlowGraph` can't describe in its blocks
+/// and edges.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Diagnostic<A> {
+    /// control flow reaches `target`, which is partway through the instruction at `instruction`
+    /// that some other path already decoded. `from` is the branch that goes there, or `None` if
+    /// `target` was given as a place to start exploring. blocks can't start at `target` without
+    /// cutting that instruction in half, so the code at `target` is explored into
+    /// `ControlFlowGraph::overlapping_streams` instead.
+    IntoInstruction { from: Option<A>, target: A, instruction: A },
+    /// decoding straight on from earlier instructions reached `instruction`, which overlaps the
+    /// instruction at `other` that some other path already decoded. exploration stops before
+    /// `instruction`, and the code from `instruction` on is explored into
+    /// `ControlFlowGraph::overlapping_streams` instead.
+    OverlappingInstruction { instruction: A, other: A },
+}
+
+/// what an edit to a `ControlFlowGraph` changed, so results computed from the graph before the
+/// edit - SSA for functions, xrefs from code - can be thrown out and recomputed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invalidation<A> {
+    /// blocks whose extent or edges changed, by where they started before the edit.
+    pub blocks: Vec<A>,
+    /// address ranges, inclusive on both ends, that are no longer explored code at all.
+    pub removed: Vec<(A, A)>,
+}
+
+impl <A> Default for Invalidation<A> {
+    fn default() -> Self {
+        Invalidation {
+            blocks: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+}
+
+impl <A: Address> Invalidation<A> {
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn extend(&mut self, other: Invalidation<A>) {
+        for block in other.blocks.into_iter() {
+            if !self.blocks.contains(&block) {
+                self.blocks.push(block);
+            }
+        }
+        self.removed.extend(other.removed);
+    }
+
+    /// does this change anything about `block`?
+    pub fn affects(&self, block: &BasicBlock<A>) -> bool {
+        self.blocks.iter().any(|start| block.start <= *start && *start <= block.end) ||
+            self.removed.iter().any(|(start, end)| *start <= block.end && block.start <= *end)
+    }
+
+    /// is `addr` in code this change removed?
+    pub fn removes(&self, addr: A) -> bool {
+        self.removed.iter().any(|(start, end)| *start <= addr && addr <= *end)
+    }
+}
+
 #[derive(Default)]
 pub struct ControlFlowGraph<A> where A: Address {
     pub entrypoint: A,
     pub blocks: BTreeMap<A, BasicBlock<A>>,
-    pub graph: GraphMap<A, (), petgraph::Directed>
+    pub graph: GraphMap<A, (), petgraph::Directed>,
+    /// tail calls, from the address of the jump to the function it calls.
+    pub tail_calls: BTreeMap<A, A>,
+    /// every instruction exploration has decoded, from its address to the address of its last
+    /// byte. this is how a branch into the middle of an instruction is told apart from a branch
+    /// to the middle of a block.
+    pub decoded: BTreeMap<A, A>,
+    /// problems found while exploring, in the order they were found.
+    pub diagnostics: Vec<Diagnostic<A>>,
+    /// code that overlaps an instruction in this graph, each explored into a graph of its own
+    /// from the address it's reached at. the same bytes are then decoded one
+    /// way in `self` and another way here.
+    pub overlapping_streams: BTreeMap<A, ControlFlowGraph<A>>,
 }
 
 impl <A: Address + Hash> Serialize for ControlFlowGraph<A> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut struc = serializer.serialize_struct("CFG<A>", 3)?;
+        let mut struc = serializer.serialize_struct("CFG<A>", 7)?;
         struc.serialize_field("entrypoint", &self.entrypoint)?;
         struc.serialize_field("blocks", &self.blocks)?;
         struc.serialize_field("graph", &GraphSerializer::from(&self.graph))?;
+        struc.serialize_field("tail_calls", &self.tail_calls)?;
+        struc.serialize_field("decoded", &self.decoded)?;
+        struc.serialize_field("diagnostics", &self.diagnostics)?;
+        struc.serialize_field("overlapping_streams", &self.overlapping_streams)?;
         struc.end()
     }
 }
@@ -235,6 +328,74 @@ fn control_flow_graph_construction_3() {
     }
 }
 
+#[test]
+fn control_flow_graph_construction_multiple() {
+    /*
+     * a jump table at 9 dispatching to 20, 30, and back into its own block at 4. duplicate and
+     * indeterminate targets shouldn't produce anything extra.
+     */
+    let mut cfg: ControlFlowGraph<u32> = ControlFlowGraph::new();
+    let nexts = cfg.with_effect(9, 10, &Effect::stop_and(Target::Multiple(vec![
+        Target::Absolute(20),
+        Target::Relative(AddressDiff::from_const(20)),
+        Target::Absolute(20),
+        Target::Absolute(4),
+        Target::Indeterminate,
+    ])));
+    assert_eq!(nexts.len(), 3);
+    for n in [20, 30, 4].iter() {
+        assert!(nexts.contains(n));
+    }
+    /*
+     * so now we have [0, 3], [4, 9], [10, 19], [20, 29], [30, ..]
+     * with 0 -> 4, and 4 -> {4, 20, 30}
+     */
+    assert_eq!(cfg.get_block(0).end, 3);
+    assert_eq!(cfg.get_block(4).end, 9);
+    assert_eq!(cfg.get_block(10).end, 19);
+    assert_eq!(cfg.get_block(20).end, 29);
+    for (start, dest) in [(0, 4), (4, 4), (4, 20), (4, 30)].iter() {
+        assert!(cfg.graph.contains_edge(*start, *dest));
+    }
+    assert!(!cfg.graph.contains_edge(0, 20));
+    assert!(!cfg.graph.contains_edge(4, 10));
+}
+
+#[test]
+fn control_flow_graph_edits() {
+    /*
+     * [0x10, 0x1f] branches to 0x40 and falls through to [0x20, 0x2f], which jumps to 0x40.
+     * [0x30, 0x3f] is padding, and [0x40, 0x44] returns.
+     */
+    let mut cfg: ControlFlowGraph<u32> = ControlFlowGraph::new();
+    cfg.with_effect(0x10 - 1, 0x10, &Effect::stop());
+    cfg.with_effect(0x1e, 0x20, &Effect::cont_and(Target::Absolute(0x40)));
+    cfg.with_effect(0x2e, 0x30, &Effect::stop_and(Target::Absolute(0x40)));
+    cfg.with_effect(0x44, 0x45, &Effect::stop());
+
+    // the branch turns out to be bogus, so 0x20 is only split off for no reason now.
+    let change = cfg.remove_edge(0x10, 0x40);
+    assert_eq!(change.blocks, vec![0x10]);
+    assert!(cfg.remove_edge(0x10, 0x40).is_empty());
+    assert!(cfg.merge_block(0x40).is_none());
+    let change = cfg.merge_block(0x20).expect("nothing else needs the split");
+    assert!(change.affects(&BasicBlock::new(0x10, 0x1f)));
+    assert_eq!(cfg.get_block(0x20).start, 0x10);
+    assert_eq!(cfg.get_block(0x10).end, 0x2f);
+    assert_eq!(cfg.destinations(0x10), vec![0x40]);
+
+    // the split at 0x30 left the padding linked to 0x40, but nothing reaches the padding.
+    let change = cfg.remove_unreachable(&[0x10]);
+    assert_eq!(change.removed, vec![(0x30, 0x3f)]);
+    assert!(change.removes(0x38));
+    assert_eq!(cfg.sources(0x40), vec![0x10]);
+    assert!(!cfg.is_explored(0x30));
+
+    let change = cfg.remove_block(0x40);
+    assert_eq!(change.blocks, vec![0x40, 0x10]);
+    assert_eq!(cfg.destinations(0x10), Vec::<u32>::new());
+}
+
 impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::NodeTrait {
     pub fn new() -> ControlFlowGraph<A> {
         let mut blocks = BTreeMap::new();
@@ -242,7 +403,11 @@ impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::Node
         let mut cfg = ControlFlowGraph {
             entrypoint: A::zero(),
             blocks,
-            graph: GraphMap::new()
+            graph: GraphMap::new(),
+            tail_calls: BTreeMap::new(),
+            decoded: BTreeMap::new(),
+            diagnostics: Vec::new(),
+            overlapping_streams: BTreeMap::new(),
         };
         cfg.graph.add_node(A::min_value());
         cfg
@@ -254,7 +419,11 @@ impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::Node
         let mut cfg = ControlFlowGraph {
             entrypoint: addr,
             blocks,
-            graph: GraphMap::new()
+            graph: GraphMap::new(),
+            tail_calls: BTreeMap::new(),
+            decoded: BTreeMap::new(),
+            diagnostics: Vec::new(),
+            overlapping_streams: BTreeMap::new(),
         };
         cfg.graph.add_node(A::min_value());
         cfg
@@ -272,6 +441,227 @@ impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::Node
         self.graph.neighbors_directed(block, petgraph::Direction::Incoming).into_iter().collect()
     }
 
+    /// record the jump at `at` as a tail call to `callee`, and remove the branch from its block to
+    /// `callee` if there was one. later exploration through `at` keeps the jump a tail call.
+    pub fn mark_tail_call(&mut self, at: A, callee: A) {
+        self.tail_calls.insert(at, callee);
+        let from = self.get_block(at).start;
+        self.graph.remove_edge(from, callee);
+    }
+
+    /// functions that `block` tail calls - at most one, unless `block` somehow has several
+    /// tail-calling jumps.
+    pub fn tail_calls_from(&self, block: A) -> Vec<A> {
+        let block = self.get_block(block);
+        self.tail_calls.range((Included(block.start), Included(block.end)))
+            .map(|(_at, callee)| *callee)
+            .collect()
+    }
+
+    /// how control gets from block `from` to `to`, if it does at all.
+    pub fn edge_kind(&self, from: A, to: A) -> Option<EdgeKind> {
+        if self.graph.contains_edge(from, to) {
+            Some(EdgeKind::Branch)
+        } else if self.tail_calls_from(from).contains(&to) {
+            Some(EdgeKind::TailCall)
+        } else {
+            None
+        }
+    }
+
+    /// was the block at `start` explored, rather than just being the space between explored code?
+    /// splitting a block leaves an edge from the unexplored part before the split, so edges alone
+    /// don't mean a block is real - unless nothing was decoded at all, like in a graph built up by
+    /// hand with `with_effect`.
+    pub fn is_explored(&self, start: A) -> bool {
+        if self.decoded.is_empty() {
+            self.graph.neighbors_directed(start, petgraph::Direction::Outgoing).next().is_some() ||
+                self.graph.neighbors_directed(start, petgraph::Direction::Incoming).next().is_some()
+        } else {
+            self.decoded.contains_key(&start)
+        }
+    }
+
+    /// remove the edge from block `from` to `to`. block boundaries are left as they are; see
+    /// `merge_block` to undo a split that's no longer needed.
+    pub fn remove_edge(&mut self, from: A, to: A) -> Invalidation<A> {
+        let mut change = Invalidation::default();
+        if self.graph.remove_edge(from, to).is_some() {
+            change.blocks.push(from);
+        }
+        change
+    }
+
+    /// forget the block at `start` was ever explored: its edges in and out, instructions decoded
+    /// in it, and tail calls from it are all removed. its addresses stay covered by a block, as
+    /// all addresses are, but that block is just unexplored space now.
+    pub fn remove_block(&mut self, start: A) -> Invalidation<A> {
+        let block = *self.get_block(start);
+        let mut change = Invalidation::default();
+        if block.start != start {
+            return change;
+        }
+
+        change.blocks.push(start);
+        for source in self.sources(start).into_iter() {
+            if source != start {
+                change.blocks.push(source);
+            }
+            self.graph.remove_edge(source, start);
+        }
+        change.removed.push((block.start, block.end));
+
+        // remove edges one by one before the node: petgraph 0.4's `GraphMap::remove_node` leaves
+        // incoming edges behind in their sources' adjacency lists.
+        for dest in self.destinations(start).into_iter() {
+            self.graph.remove_edge(start, dest);
+        }
+        self.graph.remove_node(start);
+        let decoded: Vec<A> = self.decoded.range((Included(block.start), Included(block.end))).map(|(addr, _)| *addr).collect();
+        for addr in decoded.into_iter() {
+            self.decoded.remove(&addr);
+        }
+        let tail_calls: Vec<A> = self.tail_calls.range((Included(block.start), Included(block.end))).map(|(addr, _)| *addr).collect();
+        for addr in tail_calls.into_iter() {
+            self.tail_calls.remove(&addr);
+        }
+        change
+    }
+
+    /// remove every explored block that can't be reached from any of `roots`. this is the cleanup
+    /// after removing a bad function hint, or cutting control flow after a call that turned out
+    /// to not return - whatever was only reachable that way goes too.
+    pub fn remove_unreachable(&mut self, roots: &[A]) -> Invalidation<A> {
+        let mut reachable: HashSet<A> = HashSet::new();
+        let mut worklist: Vec<A> = roots.iter().cloned().filter(|root| self.blocks.contains_key(root)).collect();
+        while let Some(next) = worklist.pop() {
+            if reachable.insert(next) {
+                worklist.extend(self.destinations(next));
+                worklist.extend(self.tail_calls_from(next));
+            }
+        }
+
+        let unreachable: Vec<A> = self.blocks.keys()
+            .filter(|start| !reachable.contains(*start) && self.is_explored(**start))
+            .cloned()
+            .collect();
+        let mut change = Invalidation::default();
+        for start in unreachable.into_iter() {
+            change.extend(self.remove_block(start));
+        }
+        change
+    }
+
+    /// merge the block at `start` into the block before it, if the only reason for the split is
+    /// gone: the block before only falls through to `start`, and nothing else reaches `start`.
+    /// returns `None` if the split is still needed.
+    pub fn merge_block(&mut self, start: A) -> Option<Invalidation<A>> {
+        if start == A::min_value() || start == self.entrypoint || !self.blocks.contains_key(&start) {
+            return None;
+        }
+        let prev = *self.get_block(start - AddressDiff::one());
+        // don't glue explored code onto unexplored space - unexplored blocks still have the edges
+        // from being split off explored ones.
+        if !self.is_explored(prev.start) || !self.is_explored(start) {
+            return None;
+        }
+        if self.destinations(prev.start) != vec![start] || self.sources(start) != vec![prev.start] {
+            return None;
+        }
+        if !self.tail_calls_from(prev.start).is_empty() {
+            return None;
+        }
+
+        let block = self.blocks.remove(&start).expect("start is a block");
+        self.blocks.get_mut(&prev.start).expect("prev is a block").end = block.end;
+        self.graph.remove_edge(prev.start, start);
+        for dest in self.destinations(start).into_iter() {
+            self.graph.add_edge(prev.start, dest, ());
+        }
+        self.graph.remove_node(start);
+
+        Some(Invalidation {
+            blocks: vec![prev.start, start],
+            removed: vec![],
+        })
+    }
+
+    /// `merge_block` everything that can be merged, except for blocks starting a function in
+    /// `function_table` - those are starts whether anything branches to them or not.
+    pub fn merge_blocks<U>(&mut self, function_table: &HashMap<A, U>) -> Invalidation<A> {
+        let starts: Vec<A> = self.blocks.keys().cloned().collect();
+        let mut change = Invalidation::default();
+        for start in starts.into_iter() {
+            if function_table.contains_key(&start) {
+                continue;
+            }
+            if let Some(merged) = self.merge_block(start) {
+                change.extend(merged);
+            }
+        }
+        change
+    }
+
+    /// the decoded instruction `addr` is partway through, if there is one. an instruction starting
+    /// at `addr` doesn't count.
+    pub fn instruction_containing(&self, addr: A) -> Option<A> {
+        self.decoded.range((Unbounded, Excluded(addr))).next_back()
+            .and_then(|(start, end)| if *end >= addr { Some(*start) } else { None })
+    }
+
+    /// a decoded instruction that overlaps an instruction spanning `[start, end]` without starting
+    /// at `start` itself.
+    pub fn overlapping_instruction(&self, start: A, end: A) -> Option<A> {
+        self.instruction_containing(start).or_else(|| {
+            self.decoded.range((Excluded(start), Included(end))).next().map(|(addr, _)| *addr)
+        })
+    }
+
+    /// a block was split at `target` for a branch there, but decoding straight on from before it
+    /// has since found `target` is partway through the instruction at `instruction`. glue the
+    /// block back onto the one before it and record each branch to `target` the same as if
+    /// `instruction` had been decoded before the branch was followed.
+    fn unsplit(&mut self, target: A, instruction: A) {
+        let prev = *self.get_block(target - AddressDiff::one());
+        for source in self.sources(target).into_iter() {
+            // `prev` only falls through to `target`, from when the split was made.
+            if source == prev.start {
+                continue;
+            }
+            let source_end = self.get_block(source).end;
+            let from = self.decoded.range((Unbounded, Included(source_end))).next_back().map(|(addr, _)| *addr);
+            self.graph.remove_edge(source, target);
+            let diagnostic = Diagnostic::IntoInstruction { from, target, instruction };
+            if !self.diagnostics.contains(&diagnostic) {
+                self.diagnostics.push(diagnostic);
+            }
+        }
+
+        let block = self.blocks.remove(&target).expect("target is a block");
+        self.blocks.get_mut(&prev.start).expect("prev is a block").end = block.end;
+        self.graph.remove_edge(prev.start, target);
+        for dest in self.destinations(target).into_iter() {
+            self.graph.remove_edge(target, dest);
+            self.graph.add_edge(prev.start, dest, ());
+        }
+        self.graph.remove_node(target);
+    }
+
+    /// record that control flow from `from` reaches `target`, if `target` is partway through a
+    /// decoded instruction. returns if it is, in which case no block should start at `target`.
+    fn check_target(&mut self, from: Option<A>, target: A) -> bool {
+        match self.instruction_containing(target) {
+            Some(instruction) => {
+                let diagnostic = Diagnostic::IntoInstruction { from, target, instruction };
+                if !self.diagnostics.contains(&diagnostic) {
+                    self.diagnostics.push(diagnostic);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
     /*
      * U should be a function, function_table should be an oracle
      * we can query to answer "does there exist a function at this place?"
@@ -284,6 +674,8 @@ impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::Node
         result.graph = GraphMap::new();
         result.graph.add_node(start);
         result.blocks = BTreeMap::new();
+        // tail calls aren't graph edges, so they're never walked into. keep them, though, so the
+        // function still says where it leaves to.
 
         let mut bfs_deque = VecDeque::new();
         bfs_deque.push_back(start);
@@ -303,7 +695,14 @@ impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::Node
                 }
             }
 
-            result.blocks.insert(next, *self.get_block(next));
+            let block = *self.get_block(next);
+            for (at, callee) in self.tail_calls.range((Included(block.start), Included(block.end))) {
+                result.tail_calls.insert(*at, *callee);
+            }
+            for (at, end) in self.decoded.range((Included(block.start), Included(block.end))) {
+                result.decoded.insert(*at, *end);
+            }
+            result.blocks.insert(next, block);
         }
         return result;
     }
@@ -405,47 +804,54 @@ impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::Node
             // if this is not going to the start of an existing basic block
             Some(Target::Relative(rel)) => {
                 let dest_addr = next.wrapping_offset(*rel);
+                if self.check_target(Some(at), dest_addr) {
+                    return result;
+                }
                 if add_split(self, dest_addr, true) {
                 }
                     result.push(dest_addr);
                 let enclosing_block_start: A = self.get_block(at).start;
-                self.graph.add_edge(enclosing_block_start, dest_addr, ());
+                if self.tail_calls.get(&at) != Some(&dest_addr) {
+                    self.graph.add_edge(enclosing_block_start, dest_addr, ());
+                }
             },
             Some(Target::Absolute(dest)) => {
                 let dest_addr = *dest;
+                if self.check_target(Some(at), dest_addr) {
+                    return result;
+                }
                 if add_split(self, dest_addr, true) {
                 }
                     result.push(dest_addr);
 //                let enclosing_block_start: A = self.get_block(at).start;
-                self.graph.add_edge(enclosing_block_start, dest_addr, ());
+                if self.tail_calls.get(&at) != Some(&dest_addr) {
+                    self.graph.add_edge(enclosing_block_start, dest_addr, ());
+                }
             }
-            Some(Target::Multiple(_targets)) => {
-                /*
+            Some(Target::Multiple(targets)) => {
                 for target in targets {
-                    match target {
-                        Target::Relative(rel) => {
-                            let dest_addr = next.wrapping_offset(*rel);
-                            if add_split(self, dest_addr, true) {
-                            }
-                                result.push(dest_addr);
-//                            let enclosing_block_start: A = self.get_block(at).start;
-                            self.graph.add_edge(enclosing_block_start, dest_addr, ());
-                        },
-                        Target::Absolute(dest) => {
-                            let dest_addr = *dest;
-                            if add_split(self, dest_addr, true) {
-                            }
-                                result.push(dest_addr);
-//                            let enclosing_block_start: A = self.get_block(at).start;
-                            self.graph.add_edge(enclosing_block_start, dest_addr, ());
-                        }
+                    let dest_addr = match target {
+                        Target::Relative(rel) => next.wrapping_offset(*rel),
+                        Target::Absolute(dest) => *dest,
                         _ => {
-                            // TODO: handle these.
-                            panic!("Unhandled");
+                            // nested multiple-target sets or indeterminate entries don't name a
+                            // block to link to - a jump table with a hole, say. skip them and
+                            // link what we can.
+                            continue;
                         }
+                    };
+                    if self.check_target(Some(at), dest_addr) {
+                        continue;
+                    }
+                    add_split(self, dest_addr, true);
+                    if !result.contains(&dest_addr) {
+                        result.push(dest_addr);
                     }
+                    // splitting at `dest_addr` may have split the block `at` is in, so look it up
+                    // again each time.
+                    let enclosing_block_start: A = self.get_block(at).start;
+                    self.graph.add_edge(enclosing_block_start, dest_addr, ());
                 }
-                */
             },
             _ => {
                 // TODO: unhandled!
@@ -455,12 +861,13 @@ impl <A> ControlFlowGraph<A> where A: Address + Debug + petgraph::graphmap::Node
     }
 }
 
-pub fn explore_all<'a, A, U, M, Contexts, Update, InstrCallback>(
+pub fn explore_all<'a, A, U, M, Contexts, Update, InstrCallback, EffectCallback>(
     data: &M,
     contexts: &'a mut Contexts,
     cfg: &mut ControlFlowGraph<A::Address>,
     starts: Vec<A::Address>,
-    on_instruction_discovered: &InstrCallback
+    on_instruction_discovered: &InstrCallback,
+    refine_effect: &EffectCallback,
 ) where
     A: Arch + DecodeFrom<M>,
     M: MemoryRange<A> + ?Sized,
@@ -468,28 +875,81 @@ pub fn explore_all<'a, A, U, M, Contexts, Update, InstrCallback>(
     A::Address: Hash + petgraph::graphmap::NodeTrait + num_traits::WrappingAdd,
     A::Instruction: Debug + Determinant<U, A::Address>,
     InstrCallback: Fn(&A::Instruction, A::Address, &Effect<A::Address>, &Contexts) -> Vec<(A::Address, Update)>,
+    EffectCallback: Fn(&A::Instruction, A::Address, Effect<A::Address>, &Contexts) -> Effect<A::Address>,
     // for<'x, 'y> crate::memory::repr::cursor::UnboundedReader<'x, 'y, A, M>: yaxpeax_arch::Reader<A::Address, A::Word>
+{
+    // whichever of two overlapping instructions is decoded first is the one `cfg` keeps, so
+    // explore starts in a fixed order rather than the order they were given in.
+    let mut starts = starts;
+    starts.sort();
+    starts.dedup();
+    explore_from(data, contexts, cfg, starts, on_instruction_discovered, refine_effect);
+
+    // anything reached partway through an instruction, or that ran into an instruction decoded
+    // first, gets a graph of its own. either way the same bytes have two readings, and which one
+    // `cfg` got depends only on which was decoded first. code in those graphs may overlap yet
+    // other instructions, but that's left as diagnostics in the graph it was found in rather than
+    // chasing alignments forever.
+    let overlapping: Vec<A::Address> = cfg.diagnostics.iter()
+        .map(|diagnostic| match diagnostic {
+            Diagnostic::IntoInstruction { target, .. } => *target,
+            Diagnostic::OverlappingInstruction { instruction, .. } => *instruction,
+        })
+        .collect();
+    for target in overlapping.into_iter() {
+        if cfg.overlapping_streams.contains_key(&target) {
+            continue;
+        }
+        let mut stream = ControlFlowGraph::from(target);
+        explore_from(data, contexts, &mut stream, vec![target], on_instruction_discovered, refine_effect);
+        cfg.overlapping_streams.insert(target, stream);
+    }
+}
+
+fn explore_from<'a, A, U, M, Contexts, Update, InstrCallback, EffectCallback>(
+    data: &M,
+    contexts: &'a mut Contexts,
+    cfg: &mut ControlFlowGraph<A::Address>,
+    starts: Vec<A::Address>,
+    on_instruction_discovered: &InstrCallback,
+    refine_effect: &EffectCallback,
+) where
+    A: Arch + DecodeFrom<M>,
+    M: MemoryRange<A> + ?Sized,
+    Contexts: ContextRead<A, U> + ContextWrite<A, Update>,
+    A::Address: Hash + petgraph::graphmap::NodeTrait + num_traits::WrappingAdd,
+    A::Instruction: Debug + Determinant<U, A::Address>,
+    InstrCallback: Fn(&A::Instruction, A::Address, &Effect<A::Address>, &Contexts) -> Vec<(A::Address, Update)>,
+    EffectCallback: Fn(&A::Instruction, A::Address, Effect<A::Address>, &Contexts) -> Effect<A::Address>,
 {
     let mut to_explore: VecDeque<A::Address> = VecDeque::new();
     let mut seen: HashSet<A::Address> = HashSet::new();
 
-    for addr in starts.iter() {
-        to_explore.push_back(*addr);
-        seen.insert(*addr);
+    // explore everything reachable from one start before looking at the next, so a start that
+    // lands partway through an instruction found from an earlier start is seen as such, rather
+    // than splitting blocks before anything has been decoded.
+    for start in starts.into_iter() {
+        if cfg.check_target(None, start) {
+            continue;
+        }
 
-        if *addr > A::Address::zero() {
+        if start > A::Address::zero() {
             // we've been told by `starts` that control flow leads here
             // so it must be the start of a basic block.
-            cfg.with_effect(*addr - AddressDiff::one(), *addr, &Effect::stop());
+            cfg.with_effect(start - AddressDiff::one(), start, &Effect::stop());
         }
-    }
 
-    while let Some(addr) = to_explore.pop_front() {
-        let dests = explore_control_flow(data, contexts, cfg, addr, on_instruction_discovered);
-        for next in dests.into_iter() {
-            if !seen.contains(&next) {
-                to_explore.push_back(next);
-                seen.insert(next);
+        if seen.insert(start) {
+            to_explore.push_back(start);
+        }
+
+        while let Some(addr) = to_explore.pop_front() {
+            let dests = explore_control_flow(data, contexts, cfg, addr, on_instruction_discovered, refine_effect);
+            for next in dests.into_iter() {
+                if !seen.contains(&next) {
+                    to_explore.push_back(next);
+                    seen.insert(next);
+                }
             }
         }
     }
@@ -508,6 +968,7 @@ pub struct AnalysisBuilder<
     starts: Option<Vec<A::Address>>,
     contexts: &'ctx mut Contexts,
     on_instruction_discovered: fn(&A::Instruction, A::Address, &Effect<A::Address>, &Contexts) -> Vec<(A::Address, Update)>,
+    refine_effect: fn(&A::Instruction, A::Address, Effect<A::Address>, &Contexts) -> Effect<A::Address>,
     _u: std::marker::PhantomData<U>,
 }
 
@@ -526,11 +987,20 @@ impl<'memory, 'ctx, A, M: MemoryRange<A> + ?Sized, U, Update, Contexts> Analysis
         >(_inst: &A::Instruction, _addr: A::Address, _effect: &Effect<A::Address>, _ctx: &Contexts) -> Vec<(A::Address, Update)> {
             Vec::new()
         }
+        fn as_determined<
+            A: Arch,
+            U,
+            Update,
+            Contexts: ContextWrite<A, Update> + ContextRead<A, U>
+        >(_inst: &A::Instruction, _addr: A::Address, effect: Effect<A::Address>, _ctx: &Contexts) -> Effect<A::Address> {
+            effect
+        }
         Self {
             memory,
             starts: None,
             contexts,
             on_instruction_discovered: do_nothing,
+            refine_effect: as_determined,
             _u: std::marker::PhantomData,
         }
     }
@@ -540,6 +1010,14 @@ impl<'memory, 'ctx, A, M: MemoryRange<A> + ?Sized, U, Update, Contexts> Analysis
         self
     }
 
+    /// adjust the control flow `Determinant` computes for each instruction, with access to
+    /// contexts that `Determinant` can't see. f.ex, a call to a function known not to return
+    /// should stop control flow, not continue past the call.
+    pub fn with_effect_refinement(mut self, refine_effect: fn(&A::Instruction, A::Address, Effect<A::Address>, &Contexts) -> Effect<A::Address>) -> Self {
+        self.refine_effect = refine_effect;
+        self
+    }
+
     pub fn evaluate(self) -> ControlFlowGraph<A::Address> {
         let mut cfg = ControlFlowGraph::new();
         self.evaluate_into(&mut cfg);
@@ -552,29 +1030,32 @@ impl<'memory, 'ctx, A, M: MemoryRange<A> + ?Sized, U, Update, Contexts> Analysis
             contexts,
             starts,
             on_instruction_discovered,
+            refine_effect,
             ..
         } = self;
         if let Some(starts) = starts {
-            explore_all(memory, contexts, cfg, starts, &on_instruction_discovered);
+            explore_all(memory, contexts, cfg, starts, &on_instruction_discovered, &refine_effect);
         } else {
-            explore_all(memory, contexts, cfg, vec![A::Address::zero()], &on_instruction_discovered);
+            explore_all(memory, contexts, cfg, vec![A::Address::zero()], &on_instruction_discovered, &refine_effect);
         }
     }
 }
 
-pub fn explore_control_flow<'a, A, U, M, Contexts, Update, InstrCallback>(
+pub fn explore_control_flow<'a, A, U, M, Contexts, Update, InstrCallback, EffectCallback>(
     data: &M,
     contexts: &'a mut Contexts,
     cfg: &mut ControlFlowGraph<A::Address>,
     start: A::Address,
-    on_instruction_discovered: &InstrCallback
+    on_instruction_discovered: &InstrCallback,
+    refine_effect: &EffectCallback,
 ) -> SmallVec<[A::Address; 2]> where
     A: Arch + DecodeFrom<M>,
     M: MemoryRange<A> + ?Sized,
     Contexts: ContextWrite<A, Update> + ContextRead<A, U>,
     A::Address: Hash + petgraph::graphmap::NodeTrait + num_traits::WrappingAdd,
     A::Instruction: Debug + Determinant<U, A::Address>,
-    InstrCallback: Fn(&A::Instruction, A::Address, &Effect<A::Address>, &Contexts) -> Vec<(A::Address, Update)> {
+    InstrCallback: Fn(&A::Instruction, A::Address, &Effect<A::Address>, &Contexts) -> Vec<(A::Address, Update)>,
+    EffectCallback: Fn(&A::Instruction, A::Address, Effect<A::Address>, &Contexts) -> Effect<A::Address> {
     // we don't know if we've just observed some flow to start,
     // or that start has already been explored,
     // so for now just go through start to end like normal
@@ -584,6 +1065,7 @@ pub fn explore_control_flow<'a, A, U, M, Contexts, Update, InstrCallback>(
     // would ambiguify single instruction basic blocks
 
     let mut addr = start;
+    let mut prev: Option<A::Address> = None;
     loop {
         let range = match data.range_from(addr) {
             Some(range) => range,
@@ -600,11 +1082,32 @@ pub fn explore_control_flow<'a, A, U, M, Contexts, Update, InstrCallback>(
         };
         match A::decode_from(&range) {
             Ok(instr) => {
+                let next = addr.wrapping_offset(instr.len());
+                if let Some(other) = cfg.overlapping_instruction(addr, next - AddressDiff::one()) {
+                    if prev.is_none() && cfg.blocks.contains_key(&addr) {
+                        if let Some(instruction) = cfg.instruction_containing(addr) {
+                            // a branch here was followed before the instruction it lands in the
+                            // middle of was decoded.
+                            cfg.unsplit(addr, instruction);
+                            return SmallVec::new();
+                        }
+                    }
+                    cfg.diagnostics.push(Diagnostic::OverlappingInstruction { instruction: addr, other });
+                    // end the block at the last instruction that did fit. `addr` can't be partway
+                    // through `other` here, or the instruction before it would have overlapped
+                    // too, so this doesn't cut `other` in half.
+                    if let Some(prev) = prev {
+                        cfg.with_effect(prev, addr, &Effect::stop());
+                    }
+                    return SmallVec::new();
+                }
+                cfg.decoded.insert(addr, next - AddressDiff::one());
                 let effect = {
                     let ctx = contexts.at(&addr);
                     println!("computing control flow at {}", addr.show());
                     instr.control_flow(Some(&ctx))
                 };
+                let effect = refine_effect(&instr, addr, effect, contexts);
                 let results = on_instruction_discovered(&instr, addr, &effect, contexts);
                 for (addr, update) in results.into_iter() {
                     contexts.put(addr, update);
@@ -612,11 +1115,12 @@ pub fn explore_control_flow<'a, A, U, M, Contexts, Update, InstrCallback>(
                 match effect {
                     Effect { stop_after: false, dest: None } => {
                         // we can continue!
-                        addr += instr.len();
+                        prev = Some(addr);
+                        addr = next;
                     },
                     // and for any other cases...
                     effect @ _ => {
-                        return cfg.with_effect(addr, addr.wrapping_offset(instr.len()), &effect);
+                        return cfg.with_effect(addr, next, &effect);
                     }
                 }
             },
@@ -683,8 +1187,7 @@ pub fn build_global_cfgs<'a, A: Arch, U, Update, UTable>(ts: &Vec<(A::Address, A
             Some(Target::Absolute(dest)) => {
                 add_split(&mut cfg.blocks, dest);
             }
-            Some(Target::Multiple(_targets)) => {
-                /*
+            Some(Target::Multiple(targets)) => {
                 for target in targets {
                     match target {
                         Target::Relative(rel) => {
@@ -694,12 +1197,10 @@ pub fn build_global_cfgs<'a, A: Arch, U, Update, UTable>(ts: &Vec<(A::Address, A
                             add_split(&mut cfg.blocks, dest);
                         }
                         _ => {
-                            // TODO: handle these.
-                            panic!("Unhandled");
+                            // nothing to split at for these.
                         }
                     }
                 }
-                */
             },
             _ => {
                 // TODO: unhandled!
@@ -730,8 +1231,7 @@ pub fn build_global_cfgs<'a, A: Arch, U, Update, UTable>(ts: &Vec<(A::Address, A
                 Some(Target::Absolute(dest)) => {
                     cfg.graph.add_edge(curr_block.start, dest, ());
                 },
-                Some(Target::Multiple(_targets)) => {
-                    /*
+                Some(Target::Multiple(targets)) => {
                     for target in targets {
                         match target {
                             Target::Relative(rel) => {
@@ -741,12 +1241,10 @@ pub fn build_global_cfgs<'a, A: Arch, U, Update, UTable>(ts: &Vec<(A::Address, A
                                 cfg.graph.add_edge(curr_block.start, dest, ());
                             }
                             _ => {
-                                // TODO: handle these.
-                                panic!("Unhandled");
+                                // nothing to link to for these.
                             }
                         }
                     }
-                    */
                 },
                 _ => {
                     // TODO: handle these cases too...
@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 use yaxpeax_arch::Arch;
 use memory::MemoryRange;
 use arch::DecodeFrom;
@@ -11,7 +13,7 @@ use data::modifier::NoModifiers;
 use analyses::static_single_assignment::SSAValues;
 use analyses::control_flow::ControlFlowGraph;
 use analyses::static_single_assignment::SSA;
-use analyses::static_single_assignment::cytron::{generate_ssa, generate_refined_ssa};
+use analyses::static_single_assignment::cytron::{generate_ssa, generate_refined_ssa, update_ssa, SSAChanges};
 use arch::AbiDefaults;
 
 use data::Direction;
@@ -145,4 +147,19 @@ impl<
 
         generate_refined_ssa(memory, cfg.entrypoint, &cfg, &cfg.graph, prior_dfg, modifiers, disambiguator, functions)
     }
+
+    /// update `ssa`, built for an earlier version of this function, rather than building it again
+    /// from scratch. returns the locations whose values were renumbered. see `update_ssa`.
+    pub fn ssa_cytron_update(self, ssa: &mut SSA<A>, changes: &SSAChanges<A::Address, A::Location>) -> HashSet<A::Location> {
+        let Self {
+            memory,
+            cfg,
+            functions,
+            disambiguator,
+            modifiers,
+            ..
+        } = self;
+
+        update_ssa(memory, cfg.entrypoint, &cfg, &cfg.graph, ssa, changes, modifiers, disambiguator, functions)
+    }
 }
@@ -0,0 +1,182 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use petgraph::Direction as EdgeDirection;
+
+use analyses::control_flow::ControlFlowGraph;
+use analyses::static_single_assignment::{DefSource, DFGRef, SSA, SSAValues, UseSite};
+use data::Direction;
+
+/// who can see a write to a location, which decides when a write nothing reads is dead.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Visibility {
+    /// writes may be seen outside the function no matter what happens after, like stores to
+    /// memory. these are never dead, and neither is anything else the same instruction writes
+    /// unless it's `Local`.
+    Observable,
+    /// writes may be seen after the function returns, like registers holding return values, so
+    /// they're only dead if they're overwritten on every path out of the function.
+    Exit,
+    /// only this function can see writes, like flags.
+    Local,
+}
+
+/// values and instructions in an `SSA` that nothing needs.
+#[derive(Debug)]
+pub struct DeadCode<A: SSAValues> {
+    /// values written by instructions that are never read, and don't otherwise escape.
+    pub values: HashSet<DFGRef<A>>,
+    /// instructions where every write is dead. instructions that don't write anything, like
+    /// `nop` or `syscall` on x86, are never here - they're either that simple or too
+    /// complicated for `SSA` to describe.
+    pub instructions: BTreeSet<A::Address>,
+}
+
+impl<A: SSAValues> DeadCode<A> {
+    pub fn is_dead(&self, value: &DFGRef<A>) -> bool {
+        self.values.contains(value)
+    }
+
+    pub fn is_dead_instruction(&self, addr: A::Address) -> bool {
+        self.instructions.contains(&addr)
+    }
+
+    /// locations written at `addr` whose values are dead. for an instruction that's still needed,
+    /// this is the part of it that isn't - often just some flags.
+    pub fn dead_writes(&self, ssa: &SSA<A>, addr: A::Address) -> Vec<A::Location> {
+        let mut writes = Vec::new();
+        if let Some(rwmap) = ssa.instruction_values.get(&addr) {
+            for ((loc, dir), value) in rwmap.iter() {
+                if *dir == Direction::Write && self.is_dead(value) {
+                    writes.push(loc.clone());
+                }
+            }
+        }
+        writes
+    }
+}
+
+/// whether anything reads `value`. phis count only if their result is used too, which
+/// `generate_ssa` already worked out as `Value::used`.
+fn is_used<A: SSAValues>(ssa: &SSA<A>, value: &DFGRef<A>) -> bool {
+    ssa.uses_of(*value).iter().any(|site| {
+        match site {
+            UseSite::Phi(block, loc) => {
+                ssa.phi.get(block)
+                    .and_then(|phis| phis.get(loc))
+                    .map(|phi| ssa.value(phi.out).used)
+                    .unwrap_or(true)
+            }
+            _ => true,
+        }
+    })
+}
+
+/// find dead values and instructions in `ssa`, which should have been built from `cfg`.
+///
+/// a value is dead if nothing but dead phis use it and, unless its location is `Local`, it's
+/// overwritten on every path to a block with no successors. only values written by instructions
+/// are considered; phis and modifiers aren't really there to be dead.
+///
+/// `opaque` picks out instructions that may read any non-`Local` location without `ssa` saying
+/// so, like calls to a function whose ABI isn't known - the callee could take arguments in any
+/// register. a value that may reach one of these is no more dead than one that reaches the end of
+/// the function.
+pub fn find_dead_code<A, F, G>(cfg: &ControlFlowGraph<A::Address>, ssa: &SSA<A>, visibility: F, opaque: G) -> DeadCode<A> where
+    A: SSAValues,
+    A::Address: petgraph::graphmap::NodeTrait,
+    F: Fn(&A::Location) -> Visibility,
+    G: Fn(A::Address) -> bool,
+{
+    // instructions in each block, in order, to tell if a value is overwritten before the function
+    // exits or something opaque might read it. phis don't count as writes: a value that reaches a
+    // phi is still there after it.
+    let mut block_instructions: HashMap<A::Address, Vec<A::Address>> = HashMap::new();
+    for addr in ssa.instruction_values.keys() {
+        block_instructions.entry(cfg.get_block(*addr).start).or_insert_with(Vec::new).push(*addr);
+    }
+    for instructions in block_instructions.values_mut() {
+        instructions.sort();
+    }
+
+    // `Some(true)` if `loc` is overwritten by one of `instructions` before anything opaque,
+    // `Some(false)` if something opaque comes first, and `None` if neither happens.
+    let scan = |instructions: &[A::Address], loc: &A::Location| {
+        for addr in instructions.iter() {
+            // an opaque instruction reads before it writes anything.
+            if opaque(*addr) {
+                return Some(false);
+            }
+            if ssa.instruction_values[addr].contains_key(&(loc.clone(), Direction::Write)) {
+                return Some(true);
+            }
+        }
+        None
+    };
+
+    let overwritten_later = |addr: A::Address, loc: &A::Location| {
+        let block = cfg.get_block(addr);
+        let instructions = &block_instructions[&block.start];
+        let after = instructions.iter().position(|other| *other > addr).unwrap_or(instructions.len());
+        if let Some(overwritten) = scan(&instructions[after..], loc) {
+            return overwritten;
+        }
+
+        let mut seen = HashSet::new();
+        let mut work = vec![block.start];
+        while let Some(next) = work.pop() {
+            let mut successors = cfg.graph.neighbors_directed(next, EdgeDirection::Outgoing).peekable();
+            if successors.peek().is_none() {
+                // the value makes it out of the function.
+                return false;
+            }
+            for succ in successors {
+                if !seen.insert(succ) {
+                    continue;
+                }
+                let instructions = block_instructions.get(&succ).map(|instrs| instrs.as_slice()).unwrap_or(&[]);
+                match scan(instructions, loc) {
+                    Some(true) => {}
+                    Some(false) => { return false; }
+                    None => { work.push(succ); }
+                }
+            }
+        }
+        true
+    };
+
+    let mut values = HashSet::new();
+    let mut instructions = BTreeSet::new();
+    for (addr, rwmap) in ssa.instruction_values.iter() {
+        let observable = rwmap.keys().any(|(loc, dir)| {
+            *dir == Direction::Write && visibility(loc) == Visibility::Observable
+        });
+        let mut writes = 0;
+        let mut dead = 0;
+        for ((loc, dir), value) in rwmap.iter() {
+            if *dir != Direction::Write {
+                continue;
+            }
+            writes += 1;
+            if ssa.try_get_def_site(*value) != Some(&(*addr, DefSource::Instruction)) {
+                continue;
+            }
+            if is_used(ssa, value) {
+                continue;
+            }
+            let is_dead = match visibility(loc) {
+                Visibility::Observable => false,
+                Visibility::Exit => !observable && overwritten_later(*addr, loc),
+                Visibility::Local => true,
+            };
+            if is_dead {
+                values.insert(*value);
+                dead += 1;
+            }
+        }
+        if writes > 0 && writes == dead {
+            instructions.insert(*addr);
+        }
+    }
+
+    DeadCode { values, instructions }
+}
@@ -17,7 +17,7 @@ pub mod const_evaluator;
 pub struct Evaluator<'program, 'function, 'ssa, A: Arch + SSAValues, M: MemoryRange<A>> {
     program: &'program M,
     fn_graph: &'function ControlFlowGraph<A::Address>,
-    ssa: &'ssa SSA<A>,
+    ssa: &'ssa mut SSA<A>,
 }
 
 impl<'program, 'function, 'ssa, A, M: MemoryRange<A>> Evaluator<'program, 'function, 'ssa, A, M> where
@@ -27,7 +27,7 @@ impl<'program, 'function, 'ssa, A, M: MemoryRange<A>> Evaluator<'program, 'funct
     A: ConstEvaluator<A, (), ValueSetDomain>,
     A::Instruction: std::fmt::Display,
 {
-    pub fn new(program: &'program M, fn_graph: &'function ControlFlowGraph<A::Address>, ssa: &'ssa SSA<A>) -> Self {
+    pub fn new(program: &'program M, fn_graph: &'function ControlFlowGraph<A::Address>, ssa: &'ssa mut SSA<A>) -> Self {
         Evaluator {
             program,
             fn_graph,
@@ -35,20 +35,20 @@ impl<'program, 'function, 'ssa, A, M: MemoryRange<A>> Evaluator<'program, 'funct
         }
     }
 
-    pub fn iterate_basic_block(&self, block: A::Address) {
+    pub fn iterate_basic_block(&mut self, block: A::Address) {
         let block = self.fn_graph.get_block(block);
         let mut iter = A::instructions_spanning(self.program, block.start, block.end);
         while let Some((address, instr)) = iter.next() {
             use yaxpeax_arch::AddressDisplay;
             println!("evaluating {}: {}", address.show(), instr);
-            <A as ConstEvaluator<A, (), ConcreteDomain>>::evaluate_instruction(&instr, address, self.ssa, &(), self.program);
-            <A as ConstEvaluator<A, (), SymbolicDomain>>::evaluate_instruction(&instr, address, self.ssa, &(), self.program);
-            <A as ConstEvaluator<A, (), ValueSetDomain>>::evaluate_instruction(&instr, address, self.ssa, &(), self.program);
+            <A as ConstEvaluator<A, (), ConcreteDomain>>::evaluate_instruction(&instr, address, &mut *self.ssa, &(), self.program);
+            <A as ConstEvaluator<A, (), SymbolicDomain>>::evaluate_instruction(&instr, address, &mut *self.ssa, &(), self.program);
+            <A as ConstEvaluator<A, (), ValueSetDomain>>::evaluate_instruction(&instr, address, &mut *self.ssa, &(), self.program);
             // `fn_query_ptr` from `program_info.rs`?
         }
     }
 
-    pub fn full_function_iterate(&self) {
+    pub fn full_function_iterate(&mut self) {
         let mut bfs = Bfs::new(&self.fn_graph.graph, self.fn_graph.entrypoint);
         while let Some(k) = bfs.next(&self.fn_graph.graph) {
             self.iterate_basic_block(k);
@@ -0,0 +1,291 @@
+use std::collections::{BTreeSet, HashMap};
+
+use petgraph::Direction as EdgeDirection;
+
+use analyses::control_flow::ControlFlowGraph;
+use analyses::control_flow::loops::LoopForest;
+use analyses::liveness::{function_blocks, phi_operand};
+use analyses::static_single_assignment::{DefSource, DFGRef, SSA, SSAValues, UseSite};
+use arch::{AbiDefaults, DecodeFrom, InstructionSpan};
+use data::Direction;
+use data::modifier::{InstructionModifiers, ModifierExpression};
+use memory::MemoryRange;
+
+/// how many times a phi at a loop header can grow before it's widened.
+const WIDENING_DELAY: usize = 3;
+/// loops `LoopForest` doesn't see (irreducible ones) have no header to widen at, so anything
+/// that grows this many times is widened wherever it is.
+const WIDENING_LIMIT: usize = 32;
+/// how many times the whole function is re-evaluated to tighten widened values.
+const NARROWING_PASSES: usize = 2;
+
+/// an abstract domain `Fixpoint` can iterate to a fixed point over.
+pub trait Lattice: Clone + PartialEq {
+    /// no values: what something holds if whatever defines it never executes.
+    fn bottom() -> Self;
+    /// every value.
+    fn top() -> Self;
+    fn join(&self, other: &Self) -> Self;
+    fn meet(&self, other: &Self) -> Self;
+    /// `next` is `self` joined with something new. jump far enough past it that this can't
+    /// keep growing forever.
+    fn widen(&self, next: &Self) -> Self;
+    /// `self` was widened, and `next` is what it's computed as now. take back what widening
+    /// added that `next` doesn't need.
+    fn narrow(&self, next: &Self) -> Self;
+}
+
+/// a value for every `SSA` value of a function, found by evaluating blocks until nothing
+/// changes. analyses say how instructions and edges compute values by implementing `Transfer`;
+/// this tracks what's been found, which blocks are reachable, and what needs another look.
+pub struct Fixpoint<'a, A: SSAValues, D: Lattice> where A::Location: AbiDefaults {
+    pub cfg: &'a ControlFlowGraph<A::Address>,
+    pub ssa: &'a SSA<A>,
+    pub modifiers: Option<&'a InstructionModifiers<A>>,
+    dominators: petgraph::algo::dominators::Dominators<A::Address>,
+    loops: LoopForest<A::Address>,
+    values: HashMap<DFGRef<A>, D>,
+    updates: HashMap<DFGRef<A>, usize>,
+    executable: BTreeSet<A::Address>,
+    work: Vec<A::Address>,
+    /// re-evaluating everything after a fixed point is reached, to recover from widening.
+    narrowing: bool,
+    /// what a value holds if it's from outside the function, like an argument, given the
+    /// function and the value's location.
+    input: fn(A::Address, &A::Location) -> D,
+}
+
+impl<'a, A, D> Fixpoint<'a, A, D> where
+    A: SSAValues,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    D: Lattice,
+{
+    pub fn new(cfg: &'a ControlFlowGraph<A::Address>, ssa: &'a SSA<A>, modifiers: Option<&'a InstructionModifiers<A>>, input: fn(A::Address, &A::Location) -> D) -> Self {
+        let dominators = petgraph::algo::dominators::simple_fast(&cfg.graph, cfg.entrypoint);
+        let loops = LoopForest::with_dominators(cfg, &dominators);
+        let mut executable = BTreeSet::new();
+        executable.insert(cfg.entrypoint);
+        Fixpoint {
+            cfg,
+            ssa,
+            modifiers,
+            dominators,
+            loops,
+            values: HashMap::new(),
+            updates: HashMap::new(),
+            executable,
+            work: vec![cfg.entrypoint],
+            narrowing: false,
+            input,
+        }
+    }
+
+    /// what's known about `value` so far. values defined in this function start at the bottom
+    /// of the lattice, and anything else is whatever `input` says.
+    pub fn current(&self, value: &DFGRef<A>) -> D {
+        if let Some(known) = self.values.get(value) {
+            return known.clone();
+        }
+        match self.ssa.try_get_def_site(*value) {
+            Some((_, DefSource::Instruction)) |
+            Some((_, DefSource::Phi)) |
+            Some((_, DefSource::Between(_))) => D::bottom(),
+            _ => (self.input)(self.cfg.entrypoint, &self.ssa.value(*value).location),
+        }
+    }
+
+    /// keep what `input` says about `value`, a function input, with the results.
+    pub fn record_input(&mut self, value: DFGRef<A>) {
+        let known = (self.input)(self.cfg.entrypoint, &self.ssa.value(value).location);
+        self.values.entry(value).or_insert(known);
+    }
+
+    /// record that `value` is in `new`. while looking for a fixed point, values only grow, and
+    /// `at_header` says if `value` is a phi that may need widening to stop growing.
+    pub fn update(&mut self, value: &DFGRef<A>, new: D, at_header: bool) {
+        let key = *value;
+        let old = self.current(value);
+        if self.narrowing {
+            let narrowed = if at_header { old.narrow(&new) } else { old.meet(&new) };
+            self.values.insert(key, narrowed);
+            return;
+        }
+
+        let mut merged = old.join(&new);
+        if merged != old {
+            let updates = self.updates.entry(*value).or_insert(0);
+            *updates += 1;
+            if (at_header && *updates > WIDENING_DELAY) || *updates > WIDENING_LIMIT {
+                merged = old.widen(&merged);
+            }
+        }
+        if merged == old && self.values.contains_key(&key) {
+            return;
+        }
+        let changed = merged != old;
+        self.values.insert(key, merged);
+        if !changed {
+            return;
+        }
+        for site in self.ssa.uses_of(*value).iter() {
+            let block = match site {
+                UseSite::Instruction(addr) |
+                UseSite::Modifier(addr, _) => self.cfg.get_block(*addr).start,
+                UseSite::Phi(block, _) => *block,
+                UseSite::Between(from, _) => *from,
+            };
+            if self.executable.contains(&block) {
+                self.work.push(block);
+            }
+        }
+    }
+
+    /// update each value `addr` defines with what `result` says of its location.
+    pub fn update_writes<F: Fn(&A::Location) -> D>(&mut self, addr: A::Address, result: F) {
+        let rwmap = match self.ssa.instruction_values.get(&addr) {
+            Some(rwmap) => rwmap,
+            None => { return; }
+        };
+        for ((loc, dir), value) in rwmap.iter() {
+            if *dir != Direction::Write {
+                continue;
+            }
+            if self.ssa.try_get_def_site(*value) != Some(&(addr, DefSource::Instruction)) {
+                continue;
+            }
+            self.update(value, result(loc), false);
+        }
+    }
+
+    fn visit_phis(&mut self, block: A::Address) {
+        let phis = match self.ssa.phi.get(&block) {
+            Some(phis) => phis,
+            None => { return; }
+        };
+        let preds: Vec<A::Address> = self.cfg.graph.neighbors_directed(block, EdgeDirection::Incoming)
+            .filter(|pred| self.executable.contains(pred))
+            .collect();
+        let at_header = self.loops.is_header(block);
+        for phi in phis.values() {
+            let mut result = D::bottom();
+            for pred in preds.iter() {
+                if let Some(value) = phi_operand(self.cfg, self.ssa, &self.dominators, phi, *pred) {
+                    result = result.join(&self.current(value));
+                }
+            }
+            if block == self.cfg.entrypoint {
+                // the function can be entered from its caller, too.
+                result = result.join(&(self.input)(block, &self.ssa.value(phi.out).location));
+            }
+            self.update(&phi.out, result, at_header);
+        }
+    }
+
+    /// bound values on the edge from `from` to `to` by what `bound` makes of the edge's
+    /// modifiers.
+    fn visit_edge<F: Fn(&D, &ModifierExpression) -> D>(&mut self, from: A::Address, to: A::Address, bound: F) {
+        let rwmap = match self.ssa.control_dependent_values.get(&from).and_then(|tos| tos.get(&to)) {
+            Some(rwmap) => rwmap,
+            None => { return; }
+        };
+        for ((loc, dir), value) in rwmap.iter() {
+            if *dir != Direction::Write {
+                continue;
+            }
+            let mut bounded = match rwmap.get(&(loc.clone(), Direction::Read)) {
+                Some(read) => self.current(read),
+                None => D::top(),
+            };
+            let exprs = self.modifiers
+                .and_then(|modifiers| modifiers.modifiers_between(from, to))
+                .and_then(|edge| edge.get(&Some(loc.clone())));
+            for expr in exprs.into_iter().flat_map(|exprs| exprs.iter()) {
+                bounded = bound(&bounded, expr);
+            }
+            self.update(value, bounded, false);
+        }
+    }
+
+    /// evaluate `block` again, if it's reachable, because something it reads that isn't a value
+    /// in `ssa`, like memory, changed.
+    pub fn revisit(&mut self, block: A::Address) {
+        if self.executable.contains(&block) {
+            self.work.push(block);
+        }
+    }
+
+    /// the values found, and the blocks reachable from the function's entry.
+    pub fn into_results(self) -> (HashMap<DFGRef<A>, D>, BTreeSet<A::Address>) {
+        (self.values, self.executable)
+    }
+}
+
+/// how some analysis evaluates instructions and edges for `Fixpoint`.
+pub trait Transfer<'a, A: SSAValues> where A::Location: AbiDefaults {
+    type Domain: Lattice;
+
+    fn engine(&mut self) -> &mut Fixpoint<'a, A, Self::Domain>;
+
+    /// evaluate `instr` with the values `engine` has found so far, and `update` what it writes.
+    fn visit_instruction(&mut self, addr: A::Address, instr: &A::Instruction);
+
+    /// what's left of `value` on an edge `expr` holds on.
+    fn bound(value: &Self::Domain, expr: &ModifierExpression) -> Self::Domain;
+
+    /// `block` is about to be evaluated, after its phis. analyses that track more than `SSA`
+    /// values, like memory, can pick up what they knew on the way in here..
+    fn enter_block(&mut self, _block: A::Address) {}
+
+    /// .. and save what they know on the way out here, before its edges are visited.
+    fn leave_block(&mut self, _block: A::Address) {}
+}
+
+fn visit_block<'a, A, M, T>(analysis: &mut T, data: &M, start: A::Address) where
+    A: SSAValues + DecodeFrom<M> + 'a,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    M: MemoryRange<A>,
+    T: Transfer<'a, A>,
+{
+    analysis.engine().visit_phis(start);
+    analysis.enter_block(start);
+    let block = analysis.engine().cfg.get_block(start);
+    let mut iter = A::instructions_spanning(data, block.start, block.end);
+    while let Some((addr, instr)) = iter.next() {
+        analysis.visit_instruction(addr, instr);
+    }
+    analysis.leave_block(start);
+    let engine = analysis.engine();
+    let successors: Vec<A::Address> = engine.cfg.graph.neighbors_directed(start, EdgeDirection::Outgoing).collect();
+    for next in successors.into_iter() {
+        if engine.executable.insert(next) {
+            engine.work.push(next);
+        }
+        engine.visit_edge(start, next, T::bound);
+    }
+}
+
+/// evaluate blocks reachable from the function's entry until no value changes, widening phis at
+/// loop headers that keep growing, then re-evaluate the whole function a few times, narrowing
+/// widened values back to what the loop actually allows. `data` is where instructions are
+/// decoded from.
+pub fn solve<'a, A, M, T>(analysis: &mut T, data: &M) where
+    A: SSAValues + DecodeFrom<M> + 'a,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    M: MemoryRange<A>,
+    T: Transfer<'a, A>,
+{
+    while let Some(start) = analysis.engine().work.pop() {
+        visit_block(analysis, data, start);
+    }
+
+    analysis.engine().narrowing = true;
+    let (order, _) = function_blocks(analysis.engine().cfg);
+    for _ in 0..NARROWING_PASSES {
+        for block in order.iter() {
+            visit_block(analysis, data, *block);
+        }
+    }
+}
@@ -0,0 +1,379 @@
+use std::collections::HashMap;
+
+use yaxpeax_arch::Arch;
+
+use arch::interface::Data;
+use memory::repr::process::{ModuleData, ModuleInfo};
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 8)?;
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    Some(u64::from_le_bytes(buf))
+}
+
+fn read_uleb(data: &[u8], offset: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        if shift < 64 {
+            value |= ((byte & 0x7f) as u64) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+}
+
+fn read_sleb(data: &[u8], offset: &mut usize) -> Option<i64> {
+    let mut value = 0i64;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        if shift < 64 {
+            value |= ((byte & 0x7f) as i64) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                value |= -1i64 << shift;
+            }
+            return Some(value);
+        }
+    }
+}
+
+const DW_EH_PE_OMIT: u8 = 0xff;
+const DW_EH_PE_PCREL: u8 = 0x10;
+
+/// read a pointer with `DW_EH_PE_*` encoding `encoding` from `data` at `offset`, where `offset`
+/// is at address `base + offset`. only absolute and pc-relative pointers are supported - those
+/// are all that compilers emit for FDE addresses in practice.
+fn read_encoded(data: &[u8], offset: &mut usize, encoding: u8, base: u64, address_size: u8) -> Option<u64> {
+    if encoding == DW_EH_PE_OMIT {
+        return None;
+    }
+    let field = base.wrapping_add(*offset as u64);
+    let value = match encoding & 0x0f {
+        0x00 => {
+            let value = if address_size == 8 { read_u64(data, *offset)? } else { read_u32(data, *offset)? as u64 };
+            *offset += address_size as usize;
+            value
+        }
+        0x01 => read_uleb(data, offset)?,
+        0x02 => { let value = read_u16(data, *offset)? as u64; *offset += 2; value }
+        0x03 => { let value = read_u32(data, *offset)? as u64; *offset += 4; value }
+        0x04 => { let value = read_u64(data, *offset)?; *offset += 8; value }
+        0x09 => read_sleb(data, offset)? as u64,
+        0x0a => { let value = read_u16(data, *offset)? as i16 as i64 as u64; *offset += 2; value }
+        0x0b => { let value = read_u32(data, *offset)? as i32 as i64 as u64; *offset += 4; value }
+        0x0c => { let value = read_u64(data, *offset)?; *offset += 8; value }
+        _ => { return None; }
+    };
+    match encoding & 0x70 {
+        0x00 => Some(value),
+        DW_EH_PE_PCREL => Some(value.wrapping_add(field)),
+        _ => None,
+    }
+}
+
+/// the pointer encoding FDEs using the CIE at `offset` have for their addresses.
+fn cie_fde_encoding(data: &[u8], offset: usize, base: u64, address_size: u8) -> Option<u8> {
+    let length = read_u32(data, offset)?;
+    if length == 0xffff_ffff || read_u32(data, offset + 4)? != 0 {
+        // 64-bit DWARF, or not a CIE at all.
+        return None;
+    }
+    let mut p = offset + 8;
+    let version = *data.get(p)?;
+    p += 1;
+    let augmentation_start = p;
+    while *data.get(p)? != 0 {
+        p += 1;
+    }
+    let augmentation = &data[augmentation_start..p];
+    p += 1;
+    if augmentation.starts_with(b"eh") {
+        // an old gcc extension with a pointer here - old enough to not bother with.
+        return None;
+    }
+    read_uleb(data, &mut p)?; // code alignment
+    read_sleb(data, &mut p)?; // data alignment
+    if version == 1 {
+        p += 1;
+    } else {
+        read_uleb(data, &mut p)?;
+    }
+
+    if augmentation.first() != Some(&b'z') {
+        return Some(0);
+    }
+    read_uleb(data, &mut p)?; // augmentation data length
+    for c in augmentation[1..].iter() {
+        match c {
+            b'R' => { return data.get(p).cloned(); }
+            b'L' => { p += 1; }
+            b'P' => {
+                let encoding = *data.get(p)?;
+                p += 1;
+                // the personality pointer itself doesn't matter, only getting past it.
+                read_encoded(data, &mut p, encoding & 0x0f, base, address_size)?;
+            }
+            b'S' | b'B' => {}
+            _ => { return None; }
+        }
+    }
+    Some(0)
+}
+
+/// addresses of the functions described by FDEs in `data`, the contents of an `.eh_frame`
+/// section loaded at `base`. little-endian only.
+pub fn parse_eh_frame(data: &[u8], base: u64, address_size: u8) -> Vec<u64> {
+    let mut encodings: HashMap<usize, Option<u8>> = HashMap::new();
+    let mut starts = Vec::new();
+
+    let mut offset = 0;
+    while let Some(length) = read_u32(data, offset) {
+        if length == 0 || length == 0xffff_ffff {
+            // the terminator, or 64-bit DWARF which .eh_frame doesn't really get.
+            break;
+        }
+        let body = offset + 4;
+        let next = body + length as usize;
+        let id = match read_u32(data, body) {
+            Some(id) => id,
+            None => break,
+        };
+        if id != 0 && (id as usize) <= body {
+            // an FDE. `id` is how far back its CIE is, from where `id` is.
+            let cie = body - id as usize;
+            let encoding = *encodings.entry(cie)
+                .or_insert_with(|| cie_fde_encoding(data, cie, base, address_size));
+            if let Some(encoding) = encoding {
+                let mut p = body + 4;
+                if let Some(start) = read_encoded(data, &mut p, encoding, base, address_size) {
+                    // FDEs for code the linker threw away are left pointing at 0.
+                    if start != 0 {
+                        starts.push(start);
+                    }
+                }
+            }
+        }
+        offset = next;
+    }
+
+    starts
+}
+
+const UNW_FLAG_CHAININFO: u8 = 0x4;
+
+/// addresses of the functions described by RUNTIME_FUNCTION entries in `data`, the contents of a
+/// PE exception directory for an image at `image_base`. `unwind_flags` reads the flags out of the
+/// UNWIND_INFO at an RVA; entries with chained unwind info describe part of a function that
+/// starts somewhere else, so they aren't function starts.
+pub fn parse_pdata<F: Fn(u32) -> Option<u8>>(data: &[u8], image_base: u64, unwind_flags: F) -> Vec<u64> {
+    data.chunks(12)
+        .filter_map(|entry| {
+            let begin = read_u32(entry, 0)?;
+            let unwind = read_u32(entry, 8)?;
+            if begin == 0 {
+                return None;
+            }
+            if unwind_flags(unwind).map(|flags| flags & UNW_FLAG_CHAININFO != 0).unwrap_or(false) {
+                return None;
+            }
+            Some(image_base + begin as u64)
+        })
+        .collect()
+}
+
+/// function starts from the `.eh_frame` section of `module`, if it's an ELF with one.
+pub fn eh_frame_starts(module: &ModuleData) -> Vec<u64> {
+    match &module.module_info {
+        ModuleInfo::ELF(_, header, _, sections, _, _, _, _, _) => {
+            let address_size = if header.e_ident[goblin::elf::header::EI_CLASS] == goblin::elf::header::ELFCLASS64 { 8 } else { 4 };
+            sections.iter()
+                .filter(|section| section.name == ".eh_frame")
+                .filter_map(|section| module.bytes(section.start, section.size).map(|data| (section.start, data)))
+                .flat_map(|(start, data)| parse_eh_frame(data, start, address_size).into_iter())
+                .collect()
+        }
+        _ => vec![],
+    }
+}
+
+/// function starts from the exception directory (typically `.pdata`) of `module`, if it's a PE
+/// with one. this assumes x86_64 RUNTIME_FUNCTION entries.
+pub fn pdata_starts(module: &ModuleData) -> Vec<u64> {
+    match &module.module_info {
+        ModuleInfo::PE(_, header, _, image_base, _, _, _, _) => {
+            let directory = header.optional_header.as_ref()
+                .and_then(|optional| *optional.data_directories.get_exception_table());
+            let directory = match directory {
+                Some(directory) => directory,
+                None => { return vec![]; }
+            };
+            match module.bytes(image_base + directory.virtual_address as u64, directory.size as u64) {
+                Some(data) => {
+                    parse_pdata(data, *image_base, |rva| {
+                        // the first byte of UNWIND_INFO is version in the low three bits and
+                        // flags in the rest.
+                        module.bytes(image_base + rva as u64, 1).map(|b| b[0] >> 3)
+                    })
+                }
+                None => vec![],
+            }
+        }
+        _ => vec![],
+    }
+}
+
+/// finds function starts by looking for instruction sequences that typically start functions.
+/// each pattern is a sequence of bytes, where `None` matches any byte, and only addresses that are
+/// a multiple of the configured alignment are checked.
+#[derive(Debug, Clone)]
+pub struct PrologueScanner {
+    patterns: Vec<Vec<Option<u8>>>,
+    alignment: u64,
+}
+
+impl Default for PrologueScanner {
+    fn default() -> Self {
+        PrologueScanner::new()
+    }
+}
+
+impl PrologueScanner {
+    /// a scanner with no patterns, which finds nothing.
+    pub fn new() -> PrologueScanner {
+        PrologueScanner {
+            patterns: Vec::new(),
+            alignment: 1,
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: &[Option<u8>]) -> Self {
+        self.patterns.push(pattern.to_vec());
+        self
+    }
+
+    pub fn with_alignment(mut self, alignment: u64) -> Self {
+        self.alignment = std::cmp::max(alignment, 1);
+        self
+    }
+
+    /// addresses in `data`, which is loaded at `start`, where some pattern matches.
+    pub fn scan_bytes(&self, data: &[u8], start: u64) -> Vec<u64> {
+        let mut found = Vec::new();
+        if self.patterns.is_empty() {
+            return found;
+        }
+        let first = (self.alignment - start % self.alignment) % self.alignment;
+        let mut offset = first as usize;
+        while offset < data.len() {
+            let rest = &data[offset..];
+            let matches = self.patterns.iter().any(|pattern| {
+                pattern.len() <= rest.len() &&
+                    pattern.iter().zip(rest.iter()).all(|(p, b)| p.map(|p| p == *b).unwrap_or(true))
+            });
+            if matches {
+                found.push(start + offset as u64);
+            }
+            offset += self.alignment as usize;
+        }
+        found
+    }
+
+    /// addresses in executable segments of `module` where some pattern matches.
+    pub fn scan(&self, module: &ModuleData) -> Vec<u64> {
+        module.segments.iter()
+            .filter(|segment| segment.is_executable())
+            .flat_map(|segment| self.scan_bytes(segment.data(), segment.start() as u64).into_iter())
+            .collect()
+    }
+}
+
+/// every function start in `module` that unwind info or `scanner` can find, sorted and without
+/// duplicates. starts outside executable segments are dropped, since they're certainly wrong.
+pub fn function_starts(module: &ModuleData, scanner: &PrologueScanner) -> Vec<u64> {
+    let mut starts = eh_frame_starts(module);
+    starts.extend(pdata_starts(module));
+    starts.extend(scanner.scan(module));
+    starts.retain(|addr| {
+        module.segments.iter().any(|segment| {
+            segment.is_executable() && (segment.start() as u64) <= *addr && *addr < segment.end() as u64
+        })
+    });
+    starts.sort();
+    starts.dedup();
+    starts
+}
+
+/// `function_starts`, as function hints to apply to an analysis of `module`.
+pub fn function_hints<A: Arch>(module: &ModuleData, scanner: &PrologueScanner) -> Vec<Data<A>> where A::Address: From<u64> {
+    function_starts(module, scanner).into_iter()
+        .map(|addr| Data::AddFunctionHint(A::Address::from(addr)))
+        .collect()
+}
+
+#[test]
+fn test_function_start_sources() {
+    // a CIE with augmentation "zR", FDE pointers pcrel|sdata4, then two FDEs and a terminator.
+    let mut eh_frame: Vec<u8> = vec![
+        0x14, 0x00, 0x00, 0x00,                     // 0x00: length
+        0x00, 0x00, 0x00, 0x00,                     // 0x04: CIE id
+        0x01, b'z', b'R', 0x00,                     // 0x08: version, augmentation
+        0x01, 0x78, 0x10,                           // 0x0c: code align, data align, return reg
+        0x01, 0x1b,                                 // 0x0f: augmentation length, pcrel|sdata4
+        0x0c, 0x07, 0x08, 0x90, 0x01,               // 0x11: initial instructions
+        0x00, 0x00,                                 // 0x16: padding
+        0x14, 0x00, 0x00, 0x00,                     // 0x18: length
+        0x1c, 0x00, 0x00, 0x00,                     // 0x1c: CIE pointer, back to 0x00
+    ];
+    // 0x20: pc_begin, relative to 0x1000 + 0x20.
+    eh_frame.extend_from_slice(&(0x400i32 - 0x1020).to_le_bytes());
+    eh_frame.extend_from_slice(&[0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    eh_frame.extend_from_slice(&[0x14, 0x00, 0x00, 0x00, 0x34, 0x00, 0x00, 0x00]);
+    // 0x38: pc_begin, relative to 0x1000 + 0x38.
+    eh_frame.extend_from_slice(&(0x480i32 - 0x1038).to_le_bytes());
+    eh_frame.extend_from_slice(&[0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+    eh_frame.extend_from_slice(&[0x00, 0x00, 0x00, 0x00]);
+    assert_eq!(parse_eh_frame(&eh_frame, 0x1000, 8), vec![0x400, 0x480]);
+
+    // three functions, where the second's unwind info at 0x2010 is chained.
+    let mut pdata: Vec<u8> = Vec::new();
+    for (begin, end, unwind) in [(0x1000u32, 0x1040u32, 0x2000u32), (0x1040, 0x1060, 0x2010), (0x1080, 0x10a0, 0x2020)].iter() {
+        pdata.extend_from_slice(&begin.to_le_bytes());
+        pdata.extend_from_slice(&end.to_le_bytes());
+        pdata.extend_from_slice(&unwind.to_le_bytes());
+    }
+    let starts = parse_pdata(&pdata, 0x140000000, |rva| Some(if rva == 0x2010 { UNW_FLAG_CHAININFO } else { 0 }));
+    assert_eq!(starts, vec![0x140001000, 0x140001080]);
+
+    let scanner = PrologueScanner::new()
+        .with_pattern(&[Some(0x55), Some(0x48), Some(0x89), Some(0xe5)])
+        .with_pattern(&[Some(0x48), Some(0x83), Some(0xec), None])
+        .with_alignment(4);
+    let code: Vec<u8> = vec![
+        0x55, 0x48, 0x89, 0xe5,                     // 0x102: push rbp; mov rbp, rsp (misaligned)
+        0x90, 0x90,
+        0x48, 0x83, 0xec, 0x28,                     // 0x108: sub rsp, 0x28
+        0x55, 0x48, 0x89, 0xe5,                     // 0x10c: push rbp; mov rbp, rsp
+        0x48, 0x83,                                 // 0x110: cut off
+    ];
+    assert_eq!(scanner.scan_bytes(&code, 0x102), vec![0x108, 0x10c]);
+}
@@ -0,0 +1,469 @@
+use yaxpeax_arch::{Address, AddressDiff};
+
+use analyses::{Value, ValueRes};
+
+const SIGN: u64 = 1 << 63;
+
+/// which bits of a 64-bit value are known. a bit set in `zeros` is always `0`, a bit set in `ones`
+/// is always `1`, and a bit in neither could be either. a bit in both means no value fits, which
+/// is what `KnownBits::empty` is: nothing has reached the value yet, or nothing ever will.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KnownBits {
+    pub zeros: u64,
+    pub ones: u64,
+}
+
+impl KnownBits {
+    pub fn new(zeros: u64, ones: u64) -> KnownBits {
+        KnownBits { zeros, ones }
+    }
+
+    /// no value at all.
+    pub fn empty() -> KnownBits {
+        KnownBits { zeros: u64::MAX, ones: u64::MAX }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zeros & self.ones != 0
+    }
+
+    /// bits that could be either.
+    pub fn unknown_bits(&self) -> u64 {
+        !(self.zeros | self.ones)
+    }
+
+    /// the bits of `value`, except those in `unknown`.
+    fn with_unknown(value: u64, unknown: u64) -> KnownBits {
+        KnownBits {
+            zeros: !value & !unknown,
+            ones: value & !unknown,
+        }
+    }
+
+    /// the smallest and largest values these bits allow, unsigned.
+    pub fn unsigned_bounds(&self) -> (u64, u64) {
+        (self.ones, self.ones | self.unknown_bits())
+    }
+
+    /// the smallest and largest values these bits allow, signed.
+    pub fn signed_bounds(&self) -> (i64, i64) {
+        let sign = self.unknown_bits() & SIGN;
+        ((self.ones | sign) as i64, ((self.ones | self.unknown_bits()) & !sign) as i64)
+    }
+
+    /// values that could be either this or `other`: only bits both agree on are known.
+    pub fn join(&self, other: &KnownBits) -> KnownBits {
+        KnownBits {
+            zeros: self.zeros & other.zeros,
+            ones: self.ones & other.ones,
+        }
+    }
+
+    /// values that are both this and `other`: bits either knows are known.
+    pub fn meet(&self, other: &KnownBits) -> KnownBits {
+        KnownBits {
+            zeros: self.zeros | other.zeros,
+            ones: self.ones | other.ones,
+        }
+    }
+
+    /// `0` or `1`, unless `always` or `never` says which.
+    fn boolean(always: bool, never: bool) -> KnownBits {
+        if always {
+            KnownBits::from_const(1)
+        } else if never {
+            KnownBits::from_const(0)
+        } else {
+            KnownBits { zeros: !1, ones: 0 }
+        }
+    }
+
+    /// the join of `f(amount)` for every amount `amt` could be. amounts that could be negative or
+    /// at least 64 are shifts by more than the value is wide, which aren't described.
+    fn by_amount<F: Fn(u32) -> KnownBits>(&self, amt: &KnownBits, f: F) -> KnownBits {
+        if self.is_empty() || amt.is_empty() {
+            return KnownBits::empty();
+        }
+        if amt.zeros | 63 != u64::MAX {
+            return KnownBits::unknown();
+        }
+        (0..64u64)
+            .filter(|k| k & amt.zeros == 0 && k & amt.ones == amt.ones)
+            .fold(KnownBits::empty(), |acc, k| acc.join(&f(k as u32)))
+    }
+
+    /// the add of `Linux`'s `tnum`s: bits are known where no unknown bit could carry into them.
+    fn sum(&self, other: &KnownBits) -> KnownBits {
+        let (lm, rm) = (self.unknown_bits(), other.unknown_bits());
+        let known_sum = self.ones.wrapping_add(other.ones);
+        let unknown_sum = lm.wrapping_add(rm);
+        let carries = known_sum.wrapping_add(unknown_sum) ^ known_sum;
+        KnownBits::with_unknown(known_sum, carries | lm | rm)
+    }
+
+    fn difference(&self, other: &KnownBits) -> KnownBits {
+        let (lm, rm) = (self.unknown_bits(), other.unknown_bits());
+        let known_difference = self.ones.wrapping_sub(other.ones);
+        let borrows = known_difference.wrapping_add(lm) ^ known_difference.wrapping_sub(rm);
+        KnownBits::with_unknown(known_difference, borrows | lm | rm)
+    }
+}
+
+impl<A: Address> From<AddressDiff<A>> for KnownBits {
+    fn from(diff: AddressDiff<A>) -> Self {
+        KnownBits::from_const(A::zero().wrapping_offset(diff).to_linear() as i64)
+    }
+}
+
+/// known bits are of 64-bit values, and wrap like them. comparisons are signed, and produce `0`
+/// or `1`, and `not` is a logical not, like `Constant`. shift and rotate amounts are in bits, as
+/// are the widths `sxt` and `zxt` extend from.
+impl Value for KnownBits {
+    fn unknown() -> Self {
+        KnownBits { zeros: 0, ones: 0 }
+    }
+
+    fn from_const(c: i64) -> Self {
+        KnownBits { zeros: !(c as u64), ones: c as u64 }
+    }
+
+    fn from_set(xs: &[Self]) -> Self {
+        xs.iter().fold(KnownBits::empty(), |acc, x| acc.join(x))
+    }
+
+    fn to_const(&self) -> Option<i64> {
+        if !self.is_empty() && self.unknown_bits() == 0 {
+            Some(self.ones as i64)
+        } else {
+            None
+        }
+    }
+
+    fn add(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes { value: KnownBits::empty(), carry: KnownBits::empty() };
+        }
+        let (lmin, lmax) = self.unsigned_bounds();
+        let (rmin, rmax) = other.unsigned_bounds();
+        ValueRes {
+            value: self.sum(other),
+            carry: KnownBits::boolean(lmin.overflowing_add(rmin).1, !lmax.overflowing_add(rmax).1),
+        }
+    }
+
+    fn sub(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes { value: KnownBits::empty(), carry: KnownBits::empty() };
+        }
+        let (lmin, lmax) = self.unsigned_bounds();
+        let (rmin, rmax) = other.unsigned_bounds();
+        ValueRes {
+            value: self.difference(other),
+            carry: KnownBits::boolean(lmax < rmin, lmin >= rmax),
+        }
+    }
+
+    /// long multiplication, adding `other` shifted by each bit of this value that could be set.
+    fn mul(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes { value: KnownBits::empty(), carry: KnownBits::empty() };
+        }
+        let (mut left, mut right) = (*self, *other);
+        let mut partial = KnownBits::from_const(0);
+        while left.ones | left.unknown_bits() != 0 {
+            if left.ones & 1 != 0 {
+                partial = partial.sum(&KnownBits::with_unknown(0, right.unknown_bits()));
+            } else if left.unknown_bits() & 1 != 0 {
+                partial = partial.sum(&KnownBits::with_unknown(0, right.ones | right.unknown_bits()));
+            }
+            left = left.shr(&KnownBits::from_const(1));
+            right = right.shl(&KnownBits::from_const(1));
+        }
+        ValueRes {
+            value: KnownBits::from_const(self.ones.wrapping_mul(other.ones) as i64).sum(&partial),
+            carry: KnownBits::unknown(),
+        }
+    }
+
+    fn or(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes::literal(KnownBits::empty());
+        }
+        ValueRes::literal(KnownBits {
+            zeros: self.zeros & other.zeros,
+            ones: self.ones | other.ones,
+        })
+    }
+
+    fn and(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes::literal(KnownBits::empty());
+        }
+        ValueRes::literal(KnownBits {
+            zeros: self.zeros | other.zeros,
+            ones: self.ones & other.ones,
+        })
+    }
+
+    fn xor(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes::literal(KnownBits::empty());
+        }
+        let unknown = self.unknown_bits() | other.unknown_bits();
+        ValueRes::literal(KnownBits::with_unknown(self.ones ^ other.ones, unknown))
+    }
+
+    fn modulo(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return KnownBits::empty();
+        }
+        match (self.to_const(), other.to_const()) {
+            (Some(l), Some(r)) => l.checked_rem(r).map(KnownBits::from_const).unwrap_or_else(KnownBits::unknown),
+            // the remainder of a non-negative value by a power of two is just its low bits.
+            (_, Some(r)) if r > 0 && (r & (r - 1)) == 0 && self.zeros & SIGN != 0 => {
+                self.and(&KnownBits::from_const(r - 1)).value()
+            }
+            _ => KnownBits::unknown(),
+        }
+    }
+
+    fn ne(&self, other: &Self) -> Self {
+        Value::eq(self, other).not()
+    }
+
+    fn le(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return KnownBits::empty();
+        }
+        let (lmin, lmax) = self.signed_bounds();
+        let (rmin, rmax) = other.signed_bounds();
+        KnownBits::boolean(lmax <= rmin, lmin > rmax)
+    }
+
+    fn lt(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return KnownBits::empty();
+        }
+        let (lmin, lmax) = self.signed_bounds();
+        let (rmin, rmax) = other.signed_bounds();
+        KnownBits::boolean(lmax < rmin, lmin >= rmax)
+    }
+
+    fn eq(&self, other: &Self) -> Self {
+        if self.is_empty() || other.is_empty() {
+            return KnownBits::empty();
+        }
+        let same = self.to_const().is_some() && self.to_const() == other.to_const();
+        let differ = (self.ones & other.zeros) | (self.zeros & other.ones) != 0;
+        KnownBits::boolean(same, differ)
+    }
+
+    /// semantics use `not` to negate conditions, so this is a logical not rather than a bitwise
+    /// one, as with `Constant`.
+    fn not(&self) -> Self {
+        if self.is_empty() {
+            return KnownBits::empty();
+        }
+        KnownBits::boolean(self.to_const() == Some(0), self.ones != 0)
+    }
+
+    /// extend the sign bit of the low `width` bits through the rest of the value.
+    fn sxt(&self, width: &Self) -> Self {
+        match width.to_const() {
+            Some(width) if width > 0 && width < 64 => {
+                let high = u64::MAX << width;
+                let sign = 1u64 << (width - 1);
+                KnownBits {
+                    zeros: if self.zeros & sign != 0 { self.zeros | high } else { self.zeros & !high },
+                    ones: if self.ones & sign != 0 { self.ones | high } else { self.ones & !high },
+                }
+            }
+            Some(64) => *self,
+            _ => KnownBits::unknown(),
+        }
+    }
+
+    /// clear everything above the low `width` bits.
+    fn zxt(&self, width: &Self) -> Self {
+        match width.to_const() {
+            Some(width) if (0..64).contains(&width) => {
+                let high = u64::MAX << width;
+                KnownBits {
+                    zeros: self.zeros | high,
+                    ones: self.ones & !high,
+                }
+            }
+            Some(64) => *self,
+            _ => KnownBits::unknown(),
+        }
+    }
+
+    fn shr(&self, amt: &Self) -> Self {
+        self.by_amount(amt, |k| KnownBits {
+            zeros: (self.zeros >> k) | !(u64::MAX >> k),
+            ones: self.ones >> k,
+        })
+    }
+
+    fn sar(&self, amt: &Self) -> Self {
+        self.by_amount(amt, |k| KnownBits {
+            zeros: ((self.zeros as i64) >> k) as u64,
+            ones: ((self.ones as i64) >> k) as u64,
+        })
+    }
+
+    fn shl(&self, amt: &Self) -> Self {
+        self.by_amount(amt, |k| KnownBits {
+            zeros: (self.zeros << k) | !(u64::MAX << k),
+            ones: self.ones << k,
+        })
+    }
+
+    fn sal(&self, amt: &Self) -> Self {
+        self.shl(amt)
+    }
+
+    /// rotate left through a carry bit, which isn't known: the bit it's rotated into is unknown.
+    fn rcl(&self, amt: &Self) -> Self {
+        self.by_amount(amt, |k| {
+            if k == 0 {
+                return *self;
+            }
+            let rotate = |bits: u64| (bits << k) | bits.checked_shr(65 - k).unwrap_or(0);
+            let carry = 1u64 << (k - 1);
+            KnownBits {
+                zeros: rotate(self.zeros) & !carry,
+                ones: rotate(self.ones) & !carry,
+            }
+        })
+    }
+
+    /// rotate right through a carry bit, which isn't known: the bit it's rotated into is unknown.
+    fn rcr(&self, amt: &Self) -> Self {
+        self.by_amount(amt, |k| {
+            if k == 0 {
+                return *self;
+            }
+            let rotate = |bits: u64| (bits >> k) | bits.checked_shl(65 - k).unwrap_or(0);
+            let carry = 1u64 << (64 - k);
+            KnownBits {
+                zeros: rotate(self.zeros) & !carry,
+                ones: rotate(self.ones) & !carry,
+            }
+        })
+    }
+
+    fn rol(&self, amt: &Self) -> Self {
+        self.by_amount(amt, |k| KnownBits {
+            zeros: self.zeros.rotate_left(k),
+            ones: self.ones.rotate_left(k),
+        })
+    }
+
+    fn ror(&self, amt: &Self) -> Self {
+        self.by_amount(amt, |k| KnownBits {
+            zeros: self.zeros.rotate_right(k),
+            ones: self.ones.rotate_right(k),
+        })
+    }
+}
+
+#[test]
+fn test_known_bits() {
+    let aligned = KnownBits::unknown().and(&KnownBits::from_const(!0xfff)).value();
+    assert_eq!(aligned, KnownBits::new(0xfff, 0));
+
+    // adding to an aligned value only carries out of the low bits if the addend is large enough.
+    let offset = aligned.add(&KnownBits::from_const(0x7f8));
+    assert_eq!(offset.value, KnownBits::new(0x807, 0x7f8));
+    assert_eq!(offset.carry, KnownBits::from_const(0));
+    assert_eq!(KnownBits::new(!0xf, 0).add(&KnownBits::new(!0x3, 0x2)).value, KnownBits::new(!0x1f, 0));
+    assert_eq!(aligned.sub(&KnownBits::from_const(1)).value, KnownBits::new(0, 0xfff));
+    assert_eq!(KnownBits::from_const(3).sub(&KnownBits::new(!0x3, 0)).carry, KnownBits::from_const(0));
+    // multiplying by 8 is a shift by 3.
+    let scaled = KnownBits::new(!0xff, 0).mul(&KnownBits::from_const(8)).value;
+    assert_eq!(scaled, KnownBits::new(!0x7f8, 0));
+    assert_eq!(KnownBits::from_const(6).mul(&KnownBits::from_const(7)).value, KnownBits::from_const(42));
+
+    let flags = KnownBits::new(0xf0, 0x01);
+    assert_eq!(flags.or(&KnownBits::from_const(0x8)).value, KnownBits::new(0xf0, 0x09));
+    assert_eq!(flags.xor(&KnownBits::from_const(0x81)).value, KnownBits::new(0x71, 0x80));
+    assert_eq!(Value::eq(&flags, &KnownBits::from_const(0x10)), KnownBits::from_const(0));
+    assert_eq!(flags.and(&KnownBits::from_const(1)).value().not(), KnownBits::from_const(0));
+    assert_eq!(Value::eq(&flags, &KnownBits::from_const(0x3)), KnownBits::new(!1, 0));
+    // the sign bit isn't known, so this could be negative.
+    assert_eq!(flags.lt(&KnownBits::from_const(0)), KnownBits::new(!1, 0));
+    assert_eq!(flags.meet(&KnownBits::new(SIGN, 0)).lt(&KnownBits::from_const(0)), KnownBits::from_const(0));
+
+    assert_eq!(flags.shl(&KnownBits::from_const(4)), KnownBits::new(0xf0f, 0x10));
+    assert_eq!(flags.shr(&KnownBits::from_const(60)), KnownBits::new(!0xf, 0));
+    assert_eq!(KnownBits::new(0, SIGN).sar(&KnownBits::from_const(4)), KnownBits::new(0, 0xf8 << 56));
+    // an amount anywhere from 0 to 3 keeps only what all four shifts agree on.
+    assert_eq!(KnownBits::from_const(1).shl(&KnownBits::new(!0x3, 0)), KnownBits::new(!0xf, 0));
+    assert_eq!(KnownBits::from_const(1).shl(&KnownBits::unknown()), KnownBits::unknown());
+    assert_eq!(KnownBits::from_const(1).ror(&KnownBits::from_const(1)), KnownBits::from_const(SIGN as i64));
+    assert_eq!(KnownBits::from_const(SIGN as i64).rol(&KnownBits::from_const(2)), KnownBits::from_const(2));
+    // rotating through carry moves the value around a 65th bit, which isn't known.
+    assert_eq!(KnownBits::from_const(SIGN as i64).rcl(&KnownBits::from_const(2)), KnownBits::new(!0x3, 0x1));
+    assert_eq!(KnownBits::from_const(1).rcr(&KnownBits::from_const(1)), KnownBits::new(0x7fff_ffff_ffff_ffff, 0));
+
+    assert_eq!(KnownBits::new(0x80, 0).sxt(&KnownBits::from_const(8)), KnownBits::new(!0x7f, 0));
+    assert_eq!(KnownBits::new(0, 0x80).sxt(&KnownBits::from_const(8)), KnownBits::new(0, !0x7f));
+    assert_eq!(KnownBits::unknown().sxt(&KnownBits::from_const(8)), KnownBits::unknown());
+    assert_eq!(KnownBits::unknown().zxt(&KnownBits::from_const(8)), KnownBits::new(!0xff, 0));
+    assert_eq!(KnownBits::unknown().modulo(&KnownBits::from_const(8)), KnownBits::unknown());
+    assert_eq!(KnownBits::new(SIGN, 0).modulo(&KnownBits::from_const(8)), KnownBits::new(!0x7, 0));
+
+    assert_eq!(KnownBits::from_set(&[KnownBits::from_const(4), KnownBits::from_const(6)]), KnownBits::new(!0x6, 0x4));
+    assert!(KnownBits::from_set(&[]).is_empty());
+}
+
+#[test]
+fn test_known_bits_semantics() {
+    use std::collections::HashMap;
+    use analyses::{DFG, OpaqueIndirection};
+    use arch::x86_64::analyses::data_flow::Location;
+    use yaxpeax_arch::{Decoder, U8Reader};
+    use yaxpeax_x86::x86_64;
+
+    struct Registers {
+        values: HashMap<Location, KnownBits>,
+    }
+
+    impl DFG<KnownBits, x86_64, u64> for Registers {
+        type Indirect = OpaqueIndirection<KnownBits>;
+
+        fn read_loc(&self, _when: u64, loc: Location) -> KnownBits {
+            self.values.get(&loc).cloned().unwrap_or_else(KnownBits::unknown)
+        }
+
+        fn write_loc(&mut self, _when: u64, loc: Location, value: KnownBits) {
+            self.values.insert(loc, value);
+        }
+
+        fn indirect_loc(&self, _when: u64, _loc: Location) -> OpaqueIndirection<KnownBits> {
+            OpaqueIndirection::inst()
+        }
+    }
+
+    let program: &[&[u8]] = &[
+        &[0x48, 0x25, 0x00, 0xf0, 0xff, 0xff],      // and rax, -0x1000
+        &[0x48, 0x83, 0xc8, 0x07],                  // or rax, 7
+        &[0x48, 0xc1, 0xe0, 0x04],                  // shl rax, 4
+        &[0x48, 0xa9, 0x08, 0x00, 0x00, 0x00],      // test rax, 8
+        &[0x48, 0xf7, 0xd0],                        // not rax
+    ];
+    let decoder = <x86_64 as yaxpeax_arch::Arch>::Decoder::default();
+    let mut registers = Registers { values: HashMap::new() };
+    for bytes in program.iter() {
+        let instr = decoder.decode(&mut U8Reader::new(bytes)).unwrap();
+        crate::arch::x86_64::semantic::evaluate(0u64, &instr, &mut registers);
+    }
+
+    // the low twelve bits were cleared, three set again, and all of it moved up four bits, then
+    // every bit flipped.
+    assert_eq!(registers.values[&Location::rax()], KnownBits::new(0x70, 0xff8f));
+    // so bit 3 is always clear.
+    assert_eq!(registers.values[&Location::ZF], KnownBits::from_const(1));
+    assert_eq!(registers.values[&Location::CF], KnownBits::from_const(0));
+}
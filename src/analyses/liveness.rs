@@ -0,0 +1,408 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::hash::Hash;
+
+use petgraph::Direction as EdgeDirection;
+use petgraph::visit::Bfs;
+
+use yaxpeax_arch::Arch;
+
+use analyses::control_flow::ControlFlowGraph;
+use analyses::static_single_assignment::{DefSource, DFGRef, PhiOp, RWMap, SSA, SSAValues, UseSite};
+use data::{Direction, ValueLocations};
+use data::modifier::Precedence;
+
+/// what is live going into and coming out of a block or instruction.
+#[derive(Debug, Clone)]
+pub struct LiveSets<K: Hash + Eq> {
+    pub live_in: HashSet<K>,
+    pub live_out: HashSet<K>,
+}
+
+impl<K: Hash + Eq> Default for LiveSets<K> {
+    fn default() -> Self {
+        LiveSets {
+            live_in: HashSet::new(),
+            live_out: HashSet::new(),
+        }
+    }
+}
+
+/// liveness of `K` at every block and instruction of a function.
+///
+/// a block's `live_in` is what's live before any of its phis, so phi results are never in it,
+/// and phi operands are live out of the predecessor they come from rather than into the block
+/// with the phi. instructions are keyed by address, and include the modifiers before and after
+/// that address. instructions that read or write nothing don't get an entry; what's live there is
+/// whatever's live into the next instruction.
+#[derive(Debug)]
+pub struct Liveness<Addr: Hash + Eq, K: Hash + Eq> {
+    pub blocks: HashMap<Addr, LiveSets<K>>,
+    pub instructions: HashMap<Addr, LiveSets<K>>,
+}
+
+impl<Addr: Hash + Eq, K: Hash + Eq> Liveness<Addr, K> {
+    pub fn block(&self, start: Addr) -> Option<&LiveSets<K>> {
+        self.blocks.get(&start)
+    }
+
+    pub fn instruction(&self, addr: Addr) -> Option<&LiveSets<K>> {
+        self.instructions.get(&addr)
+    }
+
+    /// the largest number of things live into any instruction, and where that happens. for
+    /// register pressure this is most useful with a `location_liveness` narrowed to registers.
+    pub fn max_live(&self) -> Option<(&Addr, usize)> {
+        self.instructions.iter()
+            .map(|(addr, sets)| (addr, sets.live_in.len()))
+            .max_by_key(|(_addr, count)| *count)
+    }
+}
+
+pub type ValueLiveness<A> = Liveness<<A as Arch>::Address, DFGRef<A>>;
+pub type LocationLiveness<A> = Liveness<<A as Arch>::Address, <A as ValueLocations>::Location>;
+
+/// reads and writes at one point in a block. reads happen before writes, so an instruction that
+/// reads and writes the same location needs it live coming in.
+struct Effect<K> {
+    uses: Vec<K>,
+    defs: Vec<K>,
+}
+
+impl<K> Effect<K> {
+    fn new() -> Self {
+        Effect { uses: Vec::new(), defs: Vec::new() }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Effect<K> {
+    fn apply(&self, live: &mut HashSet<K>) {
+        for def in self.defs.iter() {
+            live.remove(def);
+        }
+        for used in self.uses.iter() {
+            live.insert(used.clone());
+        }
+    }
+}
+
+/// everything a block does, in program order. `entry` is for phis.
+struct BlockEffects<Addr, K> {
+    entry: Effect<K>,
+    instructions: Vec<(Addr, Vec<Effect<K>>)>,
+}
+
+/// `order` is every block in the function, with the entrypoint first. `edges` are effects on the
+/// edge between two blocks, like phi operands and `control_dependent_values`.
+fn solve<Addr: Hash + Eq + Copy, K: Hash + Eq + Clone>(
+    order: &[Addr],
+    successors: &HashMap<Addr, Vec<Addr>>,
+    blocks: &HashMap<Addr, BlockEffects<Addr, K>>,
+    edges: &HashMap<(Addr, Addr), Effect<K>>,
+) -> Liveness<Addr, K> {
+    let block_in = |block: &BlockEffects<Addr, K>, live_out: &HashSet<K>| {
+        let mut live = live_out.clone();
+        for (_addr, effects) in block.instructions.iter().rev() {
+            for effect in effects.iter().rev() {
+                effect.apply(&mut live);
+            }
+        }
+        block.entry.apply(&mut live);
+        live
+    };
+
+    let mut sets: HashMap<Addr, LiveSets<K>> = order.iter().map(|addr| (*addr, LiveSets::default())).collect();
+
+    // blocks are in bfs order from the entrypoint, so walking backwards gets most successors
+    // before their predecessors.
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in order.iter().rev() {
+            let mut live_out = HashSet::new();
+            for next in successors[block].iter() {
+                let mut live = sets[next].live_in.clone();
+                if let Some(edge) = edges.get(&(*block, *next)) {
+                    edge.apply(&mut live);
+                }
+                live_out.extend(live);
+            }
+            let live_in = block_in(&blocks[block], &live_out);
+            let entry = sets.get_mut(block).unwrap();
+            if entry.live_in != live_in || entry.live_out != live_out {
+                entry.live_in = live_in;
+                entry.live_out = live_out;
+                changed = true;
+            }
+        }
+    }
+
+    let mut instructions = HashMap::new();
+    for block in order.iter() {
+        let mut live = sets[block].live_out.clone();
+        for (addr, effects) in blocks[block].instructions.iter().rev() {
+            let live_out = live.clone();
+            for effect in effects.iter().rev() {
+                effect.apply(&mut live);
+            }
+            instructions.insert(*addr, LiveSets {
+                live_in: live.clone(),
+                live_out,
+            });
+        }
+    }
+
+    Liveness {
+        blocks: sets,
+        instructions,
+    }
+}
+
+/// blocks reachable from `cfg.entrypoint`, in bfs order, and their successors.
+pub(crate) fn function_blocks<Addr: yaxpeax_arch::Address + petgraph::graphmap::NodeTrait>(cfg: &ControlFlowGraph<Addr>) -> (Vec<Addr>, HashMap<Addr, Vec<Addr>>) {
+    let mut order = Vec::new();
+    let mut bfs = Bfs::new(&cfg.graph, cfg.entrypoint);
+    while let Some(block) = bfs.next(&cfg.graph) {
+        order.push(block);
+    }
+    if order.is_empty() {
+        order.push(cfg.entrypoint);
+    }
+    let successors = order.iter().map(|block| {
+        (*block, cfg.graph.neighbors_directed(*block, EdgeDirection::Outgoing).collect())
+    }).collect();
+    (order, successors)
+}
+
+fn rwmap_effect<A: SSAValues>(ssa: &SSA<A>, rwmap: Option<&RWMap<A>>, site: UseSite<A::Address, A::Location>) -> Effect<DFGRef<A>> {
+    let mut effect = Effect::new();
+    if let Some(rwmap) = rwmap {
+        for ((loc, dir), value) in rwmap.iter() {
+            let value = *value;
+            match dir {
+                Direction::Read => {
+                    if !ssa.is_overwrite(&site, loc) {
+                        effect.uses.push(value);
+                    }
+                }
+                Direction::Write => effect.defs.push(value),
+            }
+        }
+    }
+    effect
+}
+
+/// liveness of the values in `ssa`, which should have been built from `cfg`.
+///
+/// this counts every use `SSA::uses_of` would report: instructions, modifiers, phi operands and
+/// values on edges from `control_dependent_values`.
+pub fn value_liveness<A>(cfg: &ControlFlowGraph<A::Address>, ssa: &SSA<A>) -> ValueLiveness<A> where
+    A: SSAValues,
+    A::Address: petgraph::graphmap::NodeTrait,
+{
+    let (order, successors) = function_blocks(cfg);
+
+    let mut addresses: BTreeSet<A::Address> = ssa.instruction_values.keys().cloned().collect();
+    addresses.extend(ssa.modifier_values.keys().map(|(addr, _precedence)| *addr));
+
+    let mut blocks = HashMap::new();
+    for start in order.iter() {
+        let block = cfg.get_block(*start);
+        let mut entry = Effect::new();
+        if let Some(phis) = ssa.phi.get(start) {
+            for phi in phis.values() {
+                entry.defs.push(phi.out);
+            }
+        }
+        let instructions = addresses.range(block.start..=block.end).map(|addr| {
+            (*addr, vec![
+                rwmap_effect(ssa, ssa.modifier_values.get(&(*addr, Precedence::Before)), UseSite::Modifier(*addr, Precedence::Before)),
+                rwmap_effect(ssa, ssa.instruction_values.get(addr), UseSite::Instruction(*addr)),
+                rwmap_effect(ssa, ssa.modifier_values.get(&(*addr, Precedence::After)), UseSite::Modifier(*addr, Precedence::After)),
+            ])
+        }).collect();
+        blocks.insert(*start, BlockEffects { entry, instructions });
+    }
+
+    let dominators = petgraph::algo::dominators::simple_fast(&cfg.graph, cfg.entrypoint);
+    let mut edges = HashMap::new();
+    for from in order.iter() {
+        for to in successors[from].iter() {
+            let between = ssa.control_dependent_values.get(from).and_then(|dests| dests.get(to));
+            let mut effect = rwmap_effect(ssa, between, UseSite::Between(*from, *to));
+            if let Some(phis) = ssa.phi.get(to) {
+                for phi in phis.values() {
+                    if let Some(value) = phi_operand(cfg, ssa, &dominators, phi, *from) {
+                        effect.uses.push(*value);
+                    }
+                }
+            }
+            edges.insert((*from, *to), effect);
+        }
+    }
+
+    solve(&order, &successors, &blocks, &edges)
+}
+
+/// which of `phi`'s operands comes in from `pred`. `PhiOp` doesn't record this, but each operand
+/// is the reaching definition at the end of some predecessor - so it's the operand whose def
+/// dominates `pred` and is closest to it.
+pub(crate) fn phi_operand<'ssa, A>(
+    cfg: &ControlFlowGraph<A::Address>,
+    ssa: &SSA<A>,
+    dominators: &petgraph::algo::dominators::Dominators<A::Address>,
+    phi: &'ssa PhiOp<A>,
+    pred: A::Address,
+) -> Option<&'ssa DFGRef<A>> where
+    A: SSAValues,
+    A::Address: petgraph::graphmap::NodeTrait,
+{
+    let pred_dominators: Vec<A::Address> = match dominators.dominators(pred) {
+        Some(doms) => doms.collect(),
+        None => { return None; }
+    };
+    // `dominators` walks from `pred` up to the entrypoint, so a lower index is closer to `pred`.
+    let mut best: Option<(usize, u8, A::Address, &DFGRef<A>)> = None;
+    for value in phi.ins.iter() {
+        let (block, rank, addr) = match ssa.try_get_def_site(*value) {
+            Some((addr, DefSource::Instruction)) |
+            Some((addr, DefSource::Modifier(_))) => (cfg.get_block(*addr).start, 2, *addr),
+            Some((addr, DefSource::Phi)) => (*addr, 0, *addr),
+            Some((_from, DefSource::Between(to))) => (*to, 1, *to),
+            Some((_, DefSource::External)) | None => {
+                // inputs to the function reach everywhere, but anything else is closer.
+                if best.is_none() {
+                    best = Some((pred_dominators.len(), 0, cfg.entrypoint, value));
+                }
+                continue;
+            }
+        };
+        let distance = match pred_dominators.iter().position(|dom| *dom == block) {
+            Some(distance) => distance,
+            None => { continue; }
+        };
+        let closer = match best {
+            None => true,
+            Some((best_distance, best_rank, best_addr, _)) => {
+                distance < best_distance ||
+                    (distance == best_distance && (rank, addr) > (best_rank, best_addr))
+            }
+        };
+        if closer {
+            best = Some((distance, rank, addr, value));
+        }
+    }
+    best.map(|(_, _, _, value)| value)
+}
+
+/// liveness of architectural locations in `cfg`, from the reads and writes recorded in
+/// `ssa.instruction_values`. unlike `value_liveness`, modifiers are ignored - they describe what
+/// is known about values, not what the program does.
+///
+/// reads and writes are the ones `generate_ssa` recorded, aliases included, so as far as this is
+/// concerned a write to `al` is a write to `rax` too - the same as `SSA` sees it. reads that only
+/// link a write to the value it replaces (`SSA::overwrites`) aren't reads here either.
+///
+/// a write only ends the liveness of a location if `overwrites(loc)` says a write to `loc`
+/// replaces all of it. locations standing for many places, like memory, generally shouldn't: one
+/// store doesn't make every earlier store to memory dead.
+pub fn location_liveness<A, F>(cfg: &ControlFlowGraph<A::Address>, ssa: &SSA<A>, overwrites: F) -> LocationLiveness<A> where
+    A: SSAValues,
+    A::Address: petgraph::graphmap::NodeTrait,
+    F: Fn(&A::Location) -> bool,
+{
+    let (order, successors) = function_blocks(cfg);
+
+    let addresses: BTreeSet<A::Address> = ssa.instruction_values.keys().cloned().collect();
+
+    let mut blocks = HashMap::new();
+    for start in order.iter() {
+        let block = cfg.get_block(*start);
+        let instructions = addresses.range(block.start..=block.end).map(|addr| {
+            let mut effect = Effect::new();
+            let site = UseSite::Instruction(*addr);
+            for (loc, dir) in ssa.instruction_values[addr].keys() {
+                match dir {
+                    Direction::Read => {
+                        if !ssa.is_overwrite(&site, loc) {
+                            effect.uses.push(loc.clone());
+                        }
+                    }
+                    Direction::Write => {
+                        if overwrites(loc) {
+                            effect.defs.push(loc.clone());
+                        }
+                    }
+                }
+            }
+            (*addr, vec![effect])
+        }).collect();
+        blocks.insert(*start, BlockEffects { entry: Effect::new(), instructions });
+    }
+
+    solve(&order, &successors, &blocks, &HashMap::new())
+}
+
+#[test]
+fn test_liveness() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+
+    let data: Vec<u8> = vec![
+        0x48, 0xc7, 0xc0, 0x01, 0x00, 0x00, 0x00,   // 0x00: mov rax, 1
+        0x48, 0x85, 0xc9,                           // 0x07: test rcx, rcx
+        0x74, 0x07,                                 // 0x0a: je 0x13
+        0x48, 0xc7, 0xc0, 0x02, 0x00, 0x00, 0x00,   // 0x0c: mov rax, 2
+        0x48, 0x01, 0xc8,                           // 0x13: add rax, rcx
+        0xc3,                                       // 0x16: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let values = value_liveness(&cfg, &dfg);
+    let value = |v: DFGRef<yaxpeax_x86::x86_64>| v;
+    let first = value(dfg.get_def(0x00, Location::rax()).as_rc());
+    let second = value(dfg.get_def(0x0c, Location::rax()).as_rc());
+    let joined = value(dfg.get_use(0x13, Location::rax()).as_rc());
+    let rcx = value(dfg.get_use(0x07, Location::rcx()).as_rc());
+
+    // each write of rax is live out of the block it reaches the phi from, and only that block.
+    assert!(values.block(0x00).unwrap().live_out.contains(&first));
+    assert!(!values.block(0x00).unwrap().live_out.contains(&second));
+    assert!(values.block(0x0c).unwrap().live_out.contains(&second));
+    assert!(!values.block(0x0c).unwrap().live_out.contains(&first));
+    // the phi's result is defined at the start of 0x13, so it's not live into the block..
+    assert!(!values.block(0x13).unwrap().live_in.contains(&joined));
+    // .. but is live into the add that reads it.
+    assert!(values.instruction(0x13).unwrap().live_in.contains(&joined));
+    // rcx is an input, and is live everywhere until the add.
+    for block in [0x00u64, 0x0c, 0x13].iter() {
+        assert!(values.block(*block).unwrap().live_in.contains(&rcx));
+    }
+    assert!(!values.instruction(0x13).unwrap().live_out.contains(&rcx));
+
+    let locations = location_liveness(&cfg, &dfg, |loc| {
+        match loc {
+            Location::Memory(_) | Location::MemoryLocation(..) => false,
+            _ => true,
+        }
+    });
+    assert!(locations.block(0x00).unwrap().live_in.contains(&Location::rcx()));
+    // `mov rax, 1` doesn't need rax's old value.
+    assert!(!locations.block(0x00).unwrap().live_in.contains(&Location::rax()));
+    assert!(locations.instruction(0x13).unwrap().live_in.contains(&Location::rcx()));
+    assert!(!locations.instruction(0x13).unwrap().live_out.contains(&Location::rcx()));
+    // `test` writes ZF for `je` to read, and nothing reads the ZF `add` writes.
+    assert!(locations.instruction(0x07).unwrap().live_out.contains(&Location::ZF));
+    assert!(!locations.instruction(0x07).unwrap().live_in.contains(&Location::ZF));
+    assert!(!locations.instruction(0x13).unwrap().live_out.contains(&Location::ZF));
+}
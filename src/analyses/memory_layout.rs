@@ -2,9 +2,13 @@ use yaxpeax_arch::Arch;
 use std::collections::HashMap;
 use analyses::DFG;
 use data::ValueLocations;
+use analyses::simplify::offset_between;
 use analyses::static_single_assignment::{DFGRef, SSA, SSAValues};
+use analyses::value_numbering::ValueNumbering;
+use analyses::value_set::{ValueSet, ValueSets};
 use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::Arc;
 use std::fmt;
 
 /// some kind of a description of memory usage in a dfg
@@ -25,7 +29,7 @@ pub struct MemoryRegion<A: Arch + ValueLocations + SSAValues> where A::Data: Eq
     // this is to support cases where an offset is accessed by two different sizes
     // as might be the case for unions or truncation.
     // TODO: these could be keyed by A::Address
-    pub accesses: HashMap<Rc<Item<ValueOrImmediate<A>>>, HashMap<u64, UsePattern>>,
+    pub accesses: HashMap<Arc<Item<ValueOrImmediate<A>>>, HashMap<u64, UsePattern>>,
     // TODO: how to avoid recording memcpy-like accesses that disregard internal structure? does
     // that get resolved here, or by MemoryLayout? something else entirely? hm...
 }
@@ -98,7 +102,7 @@ impl<A: Arch + ValueLocations + SSAValues> MemoryRegion<A> where A::Data: Eq + f
 }
 
 #[derive(Debug)]
-pub struct IndirectLayout<A: Arch + ValueLocations + SSAValues> where A::Data: Eq + fmt::Display {
+pub struct IndirectLayout<'ssa, A: Arch + ValueLocations + SSAValues> where A::Data: Eq + fmt::Display {
     /// a mapping to distinct regions in this indirect area from the base values inferred for their
     /// accesses.
     ///
@@ -110,6 +114,19 @@ pub struct IndirectLayout<A: Arch + ValueLocations + SSAValues> where A::Data: E
     regions_defs: Option<Rc<RefCell<HashMap<ValueOrImmediate<A>, MemoryRegion<A>>>>>,
     ssa_use: Option<DFGRef<A>>,
     ssa_def: Option<DFGRef<A>>,
+    stores: Rc<RefCell<HashMap<DFGRef<A>, Store<A>>>>,
+    values: LayoutValues<'ssa, A>,
+    value_numbering: Option<&'ssa ValueNumbering<A>>,
+}
+
+/// the last store to write some version of memory.
+#[derive(Debug)]
+pub struct Store<A: Arch + ValueLocations + SSAValues> where A::Data: Eq + fmt::Display {
+    pub address: Arc<Item<ValueOrImmediate<A>>>,
+    pub size: usize,
+    pub value: Arc<Item<ValueOrImmediate<A>>>,
+    /// the version of memory this store wrote over, if nothing else wrote to this version too.
+    pub previous: Option<DFGRef<A>>,
 }
 
 pub struct MemoryLayout<'ssa, A: Arch + ValueLocations + SSAValues> where A::Data: Eq + fmt::Display {
@@ -117,6 +134,17 @@ pub struct MemoryLayout<'ssa, A: Arch + ValueLocations + SSAValues> where A::Dat
     /// map SSA values at some `A::Location` to their referent layout.
     /// key is likely versions of an architecture's Location::Memory.
     pub segments: RefCell<HashMap<ValueOrImmediate<A>, Rc<RefCell<HashMap<ValueOrImmediate<A>, MemoryRegion<A>>>>>>,
+    /// what value-set analysis found for `ssa`, if it's been run. accesses it can place in some
+    /// region can be told apart even when no common base is found for them.
+    value_sets: Option<&'ssa ValueSets<A>>,
+    /// stores seen so far, by the version of memory they define. loads of a version that was just
+    /// stored to can read the stored value rather than something unknown.
+    stores: Rc<RefCell<HashMap<DFGRef<A>, Store<A>>>>,
+    /// `ssa`, and what this layout has found values written in it compute.
+    values: LayoutValues<'ssa, A>,
+    /// congruence classes of values in `ssa`, if they've been found. addresses are written in
+    /// terms of each class's leader, so a pointer computed twice is still one base.
+    value_numbering: Option<&'ssa ValueNumbering<A>>,
 }
 
 /*
@@ -128,7 +156,7 @@ pub enum LocationAccess<A: Arch> {
 */
 
 /// in most cases, `Base` and `Addend` should both be `Self`.
-pub trait MemoryAccessBaseInference {
+pub trait MemoryAccessBaseInference<V: ?Sized> {
     /// type of the base address for some. as a common example, the value of a machine's
     /// stack pointer will often be of this type.
     type Base;
@@ -141,7 +169,8 @@ pub trait MemoryAccessBaseInference {
     type Addend;
 
     /// given some `Value`, `self`, try to interpret `self` as a base plus addend-style memory access. the base should be a constant or a fully-unbounded variable, where `addend` may be a constant, bounded variable, or value set.
-    fn infer_base_and_addend(&self) -> Option<(Self::Base, Self::Addend)>;
+    /// `values` is where to look up what values in `self` are.
+    fn infer_base_and_addend(&self, values: &V) -> Option<(Self::Base, Self::Addend)>;
 }
 
 /// allow `Self` to have simple aliases for other values of the same type.
@@ -151,37 +180,102 @@ pub trait MemoryAccessBaseInference {
 pub trait Underlying {
     type Arch: yaxpeax_arch::Arch + SSAValues;
 
-    fn underlying(&self) -> Option<DFGRef<Self::Arch>>;
+    /// the value `self` aliases, following aliases of aliases through `ssa`.
+    fn underlying(&self, ssa: &SSA<Self::Arch>) -> Option<DFGRef<Self::Arch>>;
+
+    fn expression(&self) -> Option<Arc<Item<ValueOrImmediate<Self::Arch>>>> where <<Self as Underlying>::Arch as SSAValues>::Data: Eq + fmt::Display;
+
+    /// whether `self` is exactly what the value holds, like a constant, rather than only
+    /// something known about it, like a range it's in.
+    fn is_exact(&self) -> bool;
+}
+
+/// somewhere to look up what values in an `SSA` are. the `SSA` itself only knows what's in its
+/// values' data, but an analysis like `MemoryLayout` can know more than it has written there.
+pub trait ValueData<A: SSAValues> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    fn ssa(&self) -> &SSA<A>;
+
+    /// the value `value` is an alias of, if it's an alias.
+    fn underlying(&self, value: DFGRef<A>) -> Option<DFGRef<A>> {
+        let ssa = self.ssa();
+        ssa.value(value).data.as_ref().and_then(|data| data.underlying(ssa))
+    }
+
+    /// what `value` is computed from, if that's known.
+    fn expression(&self, value: DFGRef<A>) -> Option<Arc<Item<ValueOrImmediate<A>>>> {
+        self.ssa().value(value).data.as_ref().and_then(|data| data.expression())
+    }
+}
+
+impl<A: SSAValues> ValueData<A> for SSA<A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    fn ssa(&self) -> &SSA<A> {
+        self
+    }
+}
 
-    fn expression(&self) -> Option<Rc<Item<ValueOrImmediate<Self::Arch>>>> where <<Self as Underlying>::Arch as SSAValues>::Data: Eq + fmt::Display;
+/// an `SSA` and the expressions a `MemoryLayout` has found for values written in it, shared
+/// between the layout and the `IndirectLayout`s it hands out.
+pub struct LayoutValues<'ssa, A: SSAValues> where A::Data: Eq + fmt::Display {
+    ssa: &'ssa SSA<A>,
+    expressions: Rc<RefCell<HashMap<DFGRef<A>, Arc<Item<ValueOrImmediate<A>>>>>>,
 }
 
-impl<A: SSAValues> MemoryAccessBaseInference for Rc<Item<ValueOrImmediate<A>>> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+impl<'ssa, A: SSAValues> Clone for LayoutValues<'ssa, A> where A::Data: Eq + fmt::Display {
+    fn clone(&self) -> Self {
+        LayoutValues {
+            ssa: self.ssa,
+            expressions: Rc::clone(&self.expressions),
+        }
+    }
+}
+
+impl<'ssa, A: SSAValues> fmt::Debug for LayoutValues<'ssa, A> where A::Data: Eq + fmt::Display {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LayoutValues")
+            .field("expressions", &self.expressions.borrow().len())
+            .finish()
+    }
+}
+
+impl<'ssa, A: SSAValues> ValueData<A> for LayoutValues<'ssa, A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    fn ssa(&self) -> &SSA<A> {
+        self.ssa
+    }
+
+    fn expression(&self, value: DFGRef<A>) -> Option<Arc<Item<ValueOrImmediate<A>>>> {
+        if let Some(expr) = self.expressions.borrow().get(&value) {
+            return Some(Arc::clone(expr));
+        }
+        self.ssa.expression(value)
+    }
+}
+
+impl<A: SSAValues, V: ValueData<A> + ?Sized> MemoryAccessBaseInference<V> for Arc<Item<ValueOrImmediate<A>>> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
     type Base = Self;
     type Addend = Self;
 
-    fn infer_base_and_addend(&self) -> Option<(Self::Base, Self::Addend)> {
+    fn infer_base_and_addend(&self, values: &V) -> Option<(Self::Base, Self::Addend)> {
 //        println!("impl infer_base_and_addend: {:?}", self);
-        let res = match &self.value.dealiased() {
+        let res = match &self.value.dealiased(values) {
             Expression::Unknown => None,
             Expression::Value(ValueOrImmediate::Value(v)) => {
-                if let Some(expr) = v.borrow().data.as_ref().and_then(|x| x.expression()) {
-                    expr.infer_base_and_addend()
+                if let Some(expr) = values.expression(*v) {
+                    expr.infer_base_and_addend(values)
                 } else {
-                    Some((Item::value(ValueOrImmediate::Value(Rc::clone(v))), Item::untyped(Expression::Value(ValueOrImmediate::Immediate(0)))))
+                    Some((Item::value(ValueOrImmediate::Value(*v)), Item::untyped(Expression::Value(ValueOrImmediate::Immediate(0)))))
                 }
             }
             Expression::Value(ValueOrImmediate::Immediate(_i)) => None,
             Expression::Add { left, right } => {
                 if let Expression::Value(ValueOrImmediate::Immediate(i)) = right.value {
-                    if let Some((inner_base, inner_addend)) = left.infer_base_and_addend() {
+                    if let Some((inner_base, inner_addend)) = left.infer_base_and_addend(values) {
                         if let Expression::Value(ValueOrImmediate::Immediate(j)) = inner_addend.value {
-                            Some((inner_base.dealiased(), Item::untyped(Expression::Value(ValueOrImmediate::Immediate(i.wrapping_add(j))))))
+                            Some((inner_base.dealiased(values), Item::untyped(Expression::Value(ValueOrImmediate::Immediate(i.wrapping_add(j))))))
                         } else {
-                            Some((left.dealiased(), right.dealiased()))
+                            Some((left.dealiased(values), right.dealiased(values)))
                         }
                     } else {
-                        Some((left.dealiased(), right.dealiased()))
+                        Some((left.dealiased(values), right.dealiased(values)))
                     }
                 } else {
                     None
@@ -189,22 +283,22 @@ impl<A: SSAValues> MemoryAccessBaseInference for Rc<Item<ValueOrImmediate<A>>> w
             }
             Expression::Sub { left, right } => {
                 if let Expression::Value(ValueOrImmediate::Immediate(i)) = right.value {
-                    if let Some((inner_base, inner_addend)) = left.infer_base_and_addend() {
+                    if let Some((inner_base, inner_addend)) = left.infer_base_and_addend(values) {
                         if let Expression::Value(ValueOrImmediate::Immediate(j)) = inner_addend.value {
-                            Some((inner_base.dealiased(), Item::untyped(Expression::Value(ValueOrImmediate::Immediate(j.wrapping_sub(i))))))
+                            Some((inner_base.dealiased(values), Item::untyped(Expression::Value(ValueOrImmediate::Immediate(j.wrapping_sub(i))))))
                         } else {
                             let negated_right = Item {
                                 ty: right.ty.clone(),
                                 value: Expression::Value(ValueOrImmediate::Immediate(-i))
                             };
-                            Some((left.dealiased(), Rc::new(negated_right)))
+                            Some((left.dealiased(values), Arc::new(negated_right)))
                         }
                     } else {
                         let negated_right = Item {
                             ty: right.ty.clone(),
                             value: Expression::Value(ValueOrImmediate::Immediate(-i))
                         };
-                        Some((left.dealiased(), Rc::new(negated_right)))
+                        Some((left.dealiased(values), Arc::new(negated_right)))
                     }
                 } else {
                     None
@@ -217,6 +311,47 @@ impl<A: SSAValues> MemoryAccessBaseInference for Rc<Item<ValueOrImmediate<A>>> w
     }
 }
 
+/// how many values deep `expanded` looks through. each step can double an expression's size (as
+/// with a run of `add rax, rax`), so this can't be unbounded.
+const EXPANSION_DEPTH: u8 = 8;
+
+/// `expr`, with values computed from other values replaced by what they compute, `depth` values
+/// deep. this lets addresses like `rsp_1` and `(rsp_0 - 0x8)` be compared, if `rsp_1` is the
+/// latter.
+fn expanded<A: SSAValues, V: ValueData<A> + ?Sized>(values: &V, expr: &Arc<Item<ValueOrImmediate<A>>>, depth: u8) -> Arc<Item<ValueOrImmediate<A>>> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    if depth == 0 {
+        return Arc::clone(expr);
+    }
+    match &expr.value {
+        Expression::Unknown |
+        Expression::Value(ValueOrImmediate::Immediate(_)) => Arc::clone(expr),
+        Expression::Value(ValueOrImmediate::Value(v)) => {
+            match values.expression(*v) {
+                Some(computed) => expanded(values, &computed, depth - 1),
+                None => Arc::clone(expr),
+            }
+        }
+        Expression::Load { address, size } => Item::load(&expanded(values, address, depth), *size),
+        Expression::Add { left, right } => Item::add(&expanded(values, left, depth), &expanded(values, right, depth)),
+        Expression::Sub { left, right } => Item::sub(&expanded(values, left, depth), &expanded(values, right, depth)),
+        Expression::Mul { left, right } => Item::mul(&expanded(values, left, depth), &expanded(values, right, depth)),
+        Expression::Or { left, right } => Item::or(&expanded(values, left, depth), &expanded(values, right, depth)),
+        Expression::And { left, right } => Item::and(&expanded(values, left, depth), &expanded(values, right, depth)),
+        Expression::Xor { left, right } => Item::xor(&expanded(values, left, depth), &expanded(values, right, depth)),
+        Expression::Shl { value, amount } => Item::shl(&expanded(values, value, depth), &expanded(values, amount, depth)),
+        Expression::Shr { value, amount } => Item::shr(&expanded(values, value, depth), &expanded(values, amount, depth)),
+        Expression::SignExtend { value, width } => Item::sxt(&expanded(values, value, depth), *width),
+    }
+}
+
+/// `expr` with congruent values replaced by their class's leader, if values were numbered.
+fn canonical<A: SSAValues>(value_numbering: Option<&ValueNumbering<A>>, expr: &Arc<Item<ValueOrImmediate<A>>>) -> Arc<Item<ValueOrImmediate<A>>> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    match value_numbering {
+        Some(numbering) => numbering.canonical(expr),
+        None => Arc::clone(expr),
+    }
+}
+
 /*
 use yaxpeax_arch::AddressDiff;
 use analyses::Value;
@@ -245,9 +380,9 @@ impl From<AddressDiff<u64>> for LocationAccess<amd64> {
 
 use analyses::{IndirectQuery, ValueIndex};
 
-impl<A: Arch + ValueLocations + SSAValues> IndirectQuery<Rc<Item<ValueOrImmediate<A>>>> for IndirectLayout<A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
-    fn try_get_load(&self, index: ValueIndex<Rc<Item<ValueOrImmediate<A>>>>) -> Option<Rc<Item<ValueOrImmediate<A>>>> {
-        if let Some((base, addend)) = index.base.infer_base_and_addend() {
+impl<'ssa, A: Arch + ValueLocations + SSAValues> IndirectQuery<Arc<Item<ValueOrImmediate<A>>>> for IndirectLayout<'ssa, A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    fn try_get_load(&self, index: ValueIndex<Arc<Item<ValueOrImmediate<A>>>>) -> Option<Arc<Item<ValueOrImmediate<A>>>> {
+        if let Some((base, addend)) = self.base_and_addend(index.base) {
             // base must be a Value otherwise it's some complex composite, OR unknown, and not
             // eligible for a base of a memory region
             if let Expression::Value(v) = &base.as_ref().value {
@@ -265,8 +400,8 @@ impl<A: Arch + ValueLocations + SSAValues> IndirectQuery<Rc<Item<ValueOrImmediat
         }
         None
     }
-    fn try_get_store(&self, index: ValueIndex<Rc<Item<ValueOrImmediate<A>>>>) -> Option<()> {
-        if let Some((base, addend)) = index.base.infer_base_and_addend() {
+    fn try_get_store(&self, index: ValueIndex<Arc<Item<ValueOrImmediate<A>>>>) -> Option<()> {
+        if let Some((base, addend)) = self.base_and_addend(index.base) {
             // base must be a Value otherwise it's some complex composite, OR unknown, and not
             // eligible for a base of a memory region
             if let Expression::Value(v) = &base.as_ref().value {
@@ -284,8 +419,8 @@ impl<A: Arch + ValueLocations + SSAValues> IndirectQuery<Rc<Item<ValueOrImmediat
         }
         None
     }
-    fn load(&self, index: ValueIndex<Rc<Item<ValueOrImmediate<A>>>>) -> Rc<Item<ValueOrImmediate<A>>> {
-        if let Some((base, addend)) = index.base.infer_base_and_addend() {
+    fn load(&self, index: ValueIndex<Arc<Item<ValueOrImmediate<A>>>>) -> Arc<Item<ValueOrImmediate<A>>> {
+        if let Some((base, addend)) = self.base_and_addend(index.base) {
             // base must be a Value otherwise it's some complex composite, OR unknown, and not
             // eligible for a base of a memory region
             if let Expression::Value(v) = &base.as_ref().value {
@@ -302,9 +437,10 @@ impl<A: Arch + ValueLocations + SSAValues> IndirectQuery<Rc<Item<ValueOrImmediat
         } else {
             tracing::error!("unable to infer base/addend of {:?}", index.base);
         }
-        Item::untyped(Expression::Unknown)
+        self.forwarded(&self.store_address(index.base), index.size)
+            .unwrap_or_else(|| Item::untyped(Expression::Unknown))
     }
-    fn store(&self, index: ValueIndex<Rc<Item<ValueOrImmediate<A>>>>, _value: &Rc<Item<ValueOrImmediate<A>>>) {
+    fn store(&self, index: ValueIndex<Arc<Item<ValueOrImmediate<A>>>>, value: &Arc<Item<ValueOrImmediate<A>>>) {
 //        eprintln!("store: {:?}", index);
 //        eprintln!("old: {:?}", self.regions_uses);
 //        eprintln!("new: {:?}", self.regions_defs);
@@ -339,9 +475,25 @@ impl<A: Arch + ValueLocations + SSAValues> IndirectQuery<Rc<Item<ValueOrImmediat
             }
         }
 
-        // TODO: use (store?) `value`, which should get memory analysis in a place where it could
-        // try tracking spills/reloads.
-        if let Some((base, addend)) = index.base.infer_base_and_addend() {
+        if let Some(def) = self.ssa_def.as_ref() {
+            let mut stores = self.stores.borrow_mut();
+            let key = *def;
+            // if this instruction already stored to this version of memory, there's nothing
+            // certain to say about what was there before both stores.
+            let previous = if stores.contains_key(&key) {
+                None
+            } else {
+                self.ssa_use
+            };
+            stores.insert(key, Store {
+                address: self.store_address(index.base).simplified(),
+                size: index.size,
+                value: value.simplified(),
+                previous,
+            });
+        }
+
+        if let Some((base, addend)) = self.base_and_addend(index.base) {
             // base must be a Value otherwise it's some complex composite, OR unknown, and not
             // eligible for a base of a memory region
             if let Expression::Value(v) = &base.as_ref().value {
@@ -361,18 +513,101 @@ impl<A: Arch + ValueLocations + SSAValues> IndirectQuery<Rc<Item<ValueOrImmediat
     }
 }
 
+impl<'ssa, A: Arch + ValueLocations + SSAValues> IndirectLayout<'ssa, A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    /// the base and addend of an access at `address`, with congruent values numbered alike.
+    fn base_and_addend(&self, address: &Arc<Item<ValueOrImmediate<A>>>) -> Option<(Arc<Item<ValueOrImmediate<A>>>, Arc<Item<ValueOrImmediate<A>>>)> {
+        canonical(self.value_numbering, &address.dealiased(&self.values).simplified()).infer_base_and_addend(&self.values)
+    }
+
+    /// `address` expanded, and with congruent values numbered alike, to compare against stores.
+    fn store_address(&self, address: &Arc<Item<ValueOrImmediate<A>>>) -> Arc<Item<ValueOrImmediate<A>>> {
+        expanded(&self.values, &canonical(self.value_numbering, &address.dealiased(&self.values)), EXPANSION_DEPTH)
+    }
+
+    /// what a `size`-byte load at `address` reads, if a store wrote exactly that. stores known to
+    /// be somewhere else are looked past, but the search ends at anything else that might have
+    /// written memory - a phi where control flow joins, a call, or a store that might overlap.
+    fn forwarded(&self, address: &Arc<Item<ValueOrImmediate<A>>>, size: usize) -> Option<Arc<Item<ValueOrImmediate<A>>>> {
+        let stores = self.stores.borrow();
+        let mut memory = self.ssa_use;
+        while let Some(version) = memory.take() {
+            let store = stores.get(&version)?;
+            let offset = offset_between(address, &store.address)?;
+            if offset == 0 && size == store.size {
+                return Some(Arc::clone(&store.value));
+            } else if offset >= store.size as i64 || offset <= -(size as i64) {
+                memory = store.previous;
+            } else {
+                return None;
+            }
+        }
+        None
+    }
+}
+
 impl<'ssa, A: Arch + ValueLocations + SSAValues> MemoryLayout<'ssa, A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
-    fn get_segment(&self, indirection_value: DFGRef<A>) -> Rc<RefCell<HashMap<ValueOrImmediate<A>, MemoryRegion<A>>>> {
-        let mut underlying = Rc::clone(&indirection_value);
-        if let Some(inner) = indirection_value.borrow().data.as_ref().and_then(|x| x.underlying()) {
-            underlying = inner;
+    /// an empty layout for `ssa`, to be filled in by evaluating instructions against it.
+    pub fn new(ssa: &'ssa SSA<A>) -> Self {
+        MemoryLayout {
+            ssa,
+            segments: RefCell::new(HashMap::new()),
+            value_sets: None,
+            stores: Rc::new(RefCell::new(HashMap::new())),
+            values: LayoutValues {
+                ssa,
+                expressions: Rc::new(RefCell::new(HashMap::new())),
+            },
+            value_numbering: None,
         }
+    }
+
+    /// use `value_sets`, computed for the same `ssa`, to place accesses that have no common base.
+    pub fn with_value_sets(mut self, value_sets: &'ssa ValueSets<A>) -> Self {
+        self.value_sets = Some(value_sets);
+        self
+    }
+
+    /// name addresses by the leaders of `value_numbering`'s classes, from `number_values` on the
+    /// same `ssa`. `number_values` only sees expressions some layout has already found, so the
+    /// numbering should come from a layout evaluated without one.
+    pub fn with_value_numbering(mut self, value_numbering: &'ssa ValueNumbering<A>) -> Self {
+        self.value_numbering = Some(value_numbering);
+        self
+    }
+
+    /// the expressions this layout found for values its `ssa` had nothing for, to write back into
+    /// the `SSA` once the layout is done with it.
+    pub fn expressions(&self) -> HashMap<DFGRef<A>, Arc<Item<ValueOrImmediate<A>>>> {
+        self.values.expressions.borrow().clone()
+    }
+
+    /// `address` with congruent values replaced by their class's leader, if values were numbered.
+    pub fn canonical(&self, address: &Arc<Item<ValueOrImmediate<A>>>) -> Arc<Item<ValueOrImmediate<A>>> {
+        canonical(self.value_numbering, address)
+    }
+
+    fn get_segment(&self, indirection_value: DFGRef<A>) -> Rc<RefCell<HashMap<ValueOrImmediate<A>, MemoryRegion<A>>>> {
+        let underlying = self.values.underlying(indirection_value).unwrap_or(indirection_value);
 
         let segment_base = ValueOrImmediate::Value(underlying);
         Rc::clone(self.segments.borrow_mut().entry(segment_base)
             .or_insert_with(|| Rc::new(RefCell::new(HashMap::new()))))
     }
 
+    /// where value-set analysis says `address` could point, if it knows at all.
+    pub fn value_set(&self, address: &Item<ValueOrImmediate<A>>) -> Option<ValueSet<A::Address>> {
+        let value_sets = self.value_sets?;
+        Some(value_sets.expression(address)).filter(|set| !set.is_top())
+    }
+
+    /// whether a `left_size`-byte access at `left` could overlap a `right_size`-byte access at
+    /// `right`, by value-set analysis. `None` if it doesn't know where one of them is.
+    pub fn may_overlap(&self, left: &Item<ValueOrImmediate<A>>, left_size: u64, right: &Item<ValueOrImmediate<A>>, right_size: u64) -> Option<bool> {
+        let left = self.value_set(left)?;
+        let right = self.value_set(right)?;
+        Some(left.may_overlap(left_size, &right, right_size))
+    }
+
     pub fn render(&self) {
         let segments = self.segments.borrow();
         let mut regions: Vec<&ValueOrImmediate<A>> = segments.keys().collect();
@@ -383,8 +618,8 @@ impl<'ssa, A: Arch + ValueLocations + SSAValues> MemoryLayout<'ssa, A> where A::
             let segment = segments.get(region).unwrap();
             println!("at region {}:", region);
             for (base, segment) in segment.borrow().iter() {
-                println!("          --- address <{}> ---", base.name());
-                let mut exprs: Vec<Rc<Item<ValueOrImmediate<A>>>> = segment.accesses.keys().cloned().collect();
+                println!("          --- address <{}> ---", base.name(self.ssa));
+                let mut exprs: Vec<Arc<Item<ValueOrImmediate<A>>>> = segment.accesses.keys().cloned().collect();
                 use std::cmp::Ordering;
                 exprs.sort_by(|l, r| {
                     let lv = &l.value;
@@ -454,14 +689,24 @@ impl<'ssa, A: Arch + ValueLocations + SSAValues> MemoryLayout<'ssa, A> where A::
     }
 }
 
+impl<'ssa, A: Arch + ValueLocations + SSAValues> ValueData<A> for MemoryLayout<'ssa, A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    fn ssa(&self) -> &SSA<A> {
+        self.ssa
+    }
+
+    fn expression(&self, value: DFGRef<A>) -> Option<Arc<Item<ValueOrImmediate<A>>>> {
+        self.values.expression(value)
+    }
+}
+
 use yaxpeax_x86::long_mode::{Arch as amd64};
 use analyses::Expression;
 use analyses::Item;
 use analyses::ValueOrImmediate;
-impl<'ssa> DFG<Rc<Item<ValueOrImmediate<amd64>>>, amd64, <amd64 as Arch>::Address> for MemoryLayout<'ssa, amd64> {
-    type Indirect = IndirectLayout<amd64>;
+impl<'ssa> DFG<Arc<Item<ValueOrImmediate<amd64>>>, amd64, <amd64 as Arch>::Address> for MemoryLayout<'ssa, amd64> {
+    type Indirect = IndirectLayout<'ssa, amd64>;
 
-    fn indirect_loc(&self, when: <amd64 as Arch>::Address, loc: <amd64 as ValueLocations>::Location) -> IndirectLayout<amd64> {
+    fn indirect_loc(&self, when: <amd64 as Arch>::Address, loc: <amd64 as ValueLocations>::Location) -> IndirectLayout<'ssa, amd64> {
         let ssa_def = self.ssa.try_get_def(when, loc.clone());
         let ssa_use = self.ssa.try_get_use(when, loc.clone());
         if ssa_def.is_none() {
@@ -470,20 +715,19 @@ impl<'ssa> DFG<Rc<Item<ValueOrImmediate<amd64>>>, amd64, <amd64 as Arch>::Addres
         if ssa_use.is_none() {
             println!("no ssa use for {} at {}", loc, when);
         }
-        let regions_defs = ssa_def.as_ref().map(|value| {
-            self.get_segment(Rc::clone(value))
-        });
-        let regions_uses = ssa_use.as_ref().map(|value| {
-            self.get_segment(Rc::clone(value))
-        });
+        let regions_defs = ssa_def.map(|value| self.get_segment(value));
+        let regions_uses = ssa_use.map(|value| self.get_segment(value));
         IndirectLayout {
             regions_defs,
             regions_uses,
             ssa_def,
             ssa_use,
+            stores: Rc::clone(&self.stores),
+            values: self.values.clone(),
+            value_numbering: self.value_numbering,
         }
     }
-    fn read_loc(&self, when: <amd64 as Arch>::Address, loc: <amd64 as ValueLocations>::Location) -> Rc<Item<ValueOrImmediate<amd64>>> {
+    fn read_loc(&self, when: <amd64 as Arch>::Address, loc: <amd64 as ValueLocations>::Location) -> Arc<Item<ValueOrImmediate<amd64>>> {
         // TODO: HACK: ignore weird rip semantics for now
         if loc != crate::arch::x86_64::analyses::data_flow::Location::RIP {
             // TODO: return a value?
@@ -493,12 +737,12 @@ impl<'ssa> DFG<Rc<Item<ValueOrImmediate<amd64>>>, amd64, <amd64 as Arch>::Addres
         // LocationAccess::Unknown
         Item::untyped(Expression::Unknown)
     }
-    fn write_loc(&mut self, when: <amd64 as Arch>::Address, loc: <amd64 as ValueLocations>::Location, value: Rc<Item<ValueOrImmediate<amd64>>>) {
+    fn write_loc(&mut self, when: <amd64 as Arch>::Address, loc: <amd64 as ValueLocations>::Location, value: Arc<Item<ValueOrImmediate<amd64>>>) {
         // TODO: HACK: ignore weird rip semantics for now
         if loc != crate::arch::x86_64::analyses::data_flow::Location::RIP {
-            let dest = self.ssa.get_def(when, loc);
-            if dest.value.borrow().data.is_none() {
-                dest.update(crate::arch::x86_64::analyses::data_flow::Data::Expression(value));
+            let dest = self.ssa.get_def(when, loc).value;
+            if self.ssa.value(dest).data.is_none() {
+                self.values.expressions.borrow_mut().insert(dest, value.simplified());
             }
         }
     }
@@ -528,3 +772,64 @@ impl<'ssa, A: Arch + ValueLocations + SSAValues> DFG<LocationAccess, A, A::Addre
     }
 }
 */
+
+#[test]
+fn test_store_forwarding() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::InstructionSpan;
+    use arch::x86_64::semantic;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+    use yaxpeax_x86::x86_64;
+    use yaxpeax_x86::long_mode::RegSpec;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x89, 0x7c, 0x24, 0xf8,                               // 0x00: mov [rsp - 0x8], rdi
+        0x48, 0xc7, 0x44, 0x24, 0xf0, 0x05, 0x00, 0x00, 0x00,       // 0x05: mov qword [rsp - 0x10], 5
+        0x48, 0x8b, 0x44, 0x24, 0xf8,                               // 0x0e: mov rax, [rsp - 0x8]
+        0x48, 0x63, 0x4c, 0x24, 0xf0,                               // 0x13: movsxd rcx, dword [rsp - 0x10]
+        0x56,                                                       // 0x18: push rsi
+        0x5a,                                                       // 0x19: pop rdx
+        0x48, 0x8d, 0x74, 0x3f, 0x08,                               // 0x1a: lea rsi, [rdi + rdi * 1 + 0x8]
+        0x4c, 0x8d, 0x04, 0x7d, 0x08, 0x00, 0x00, 0x00,             // 0x1f: lea r8, [rdi * 2 + 0x8]
+        0xc3,                                                       // 0x27: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let mut layout = MemoryLayout::new(&dfg);
+    for block in cfg.blocks() {
+        let block = cfg.get_block(block);
+        let mut iter = x86_64::instructions_spanning(&data, block.start, block.end);
+        while let Some((address, instr)) = iter.next() {
+            semantic::evaluate(address, &instr, &mut layout);
+        }
+    }
+
+    let expression = |addr: u64, loc: Location| {
+        layout.expression(dfg.get_def(addr, loc).as_rc()).unwrap()
+    };
+    let input = |addr: u64, loc: Location| Item::value(ValueOrImmediate::Value(dfg.get_use(addr, loc).as_rc()));
+
+    // the store to `[rsp - 0x10]` is past the end of `[rsp - 0x8]`, so the load sees `rdi`..
+    assert_eq!(expression(0x0e, Location::rax()), input(0x00, Location::rdi()));
+    // .. but only the low half of `[rsp - 0x10]` is read, which isn't a value that was stored.
+    assert_eq!(expression(0x13, Location::rcx()), Item::unknown().sxt(32));
+    // `rsp` is moved between the push and pop, but they're the same place.
+    assert_eq!(expression(0x19, Location::rdx()), input(0x18, Location::rsi()));
+
+    // both `lea` compute the same thing, and look the same for it.
+    let rsi = expression(0x1a, Location::rsi());
+    let r8 = expression(0x1f, Location::Register(RegSpec::r8()));
+    assert_eq!(rsi, r8);
+    assert_eq!(rsi.to_string(), r8.to_string());
+}
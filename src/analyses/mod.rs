@@ -1,15 +1,30 @@
 use yaxpeax_arch::Arch;
 use data::ValueLocations;
 
+pub mod call_graph;
+pub mod constant_propagation;
 #[macro_use]
 pub mod control_flow;
 pub mod data_flow;
+pub mod dead_code;
+pub mod fixpoint;
 pub mod function_signatures;
+pub mod function_starts;
+pub mod known_bits;
+pub mod liveness;
 pub mod memory_layout;
+pub mod noreturn;
+pub mod simplify;
+pub mod smt;
+pub mod slicing;
+pub mod stack_pointer;
 pub mod static_single_assignment;
+pub mod taint;
 pub mod xrefs;
 pub mod evaluators;
+pub mod value_numbering;
 pub mod value_range;
+pub mod value_set;
 
 pub enum CompletionStatus {
     Incomplete,
@@ -57,9 +72,10 @@ impl<V: Value> ValueRes<V> {
 
 /// interface to query a data flow graph (dfg). this interface is .... in flux.
 ///
+/// `SSA` can look up the def site of a value with `get_def_site`, and iterate its use sites with
+/// `uses_of`. these aren't part of `DFG` yet, since not every `DFG` has values to ask about.
+///
 /// TODOs in order of "how hard i think they are":
-/// * it should be possible to look up a def site for a value
-/// * it should be possible to iterate the use sites of a value
 /// * perhaps it should be possible to insert new values to the dfg? optionally? this approaches
 /// supporting general patching
 /// * it should be possible to detach and move values
@@ -460,11 +476,17 @@ pub trait Value: Sized {
     fn ror(&self, _width: &Self) -> Self {
         Self::unknown()
     }
+
+    /// `then` if `self` is nonzero, `otherwise` if it's zero. by default the condition is
+    /// forgotten, and the result is whatever `from_set` makes of both.
+    fn select(&self, then: Self, otherwise: Self) -> Self {
+        Self::from_set(&[then, otherwise])
+    }
 }
 
 use SSAValues;
-use std::rc::Rc;
-use analyses::static_single_assignment::{DataDisplay, DFGRef};
+use std::sync::Arc;
+use analyses::static_single_assignment::{DataDisplay, DFGRef, SSA};
 use crate::ColorSettings;
 use data::types::TypeSpec;
 
@@ -475,12 +497,12 @@ pub struct Item<Leaf> {
 }
 
 pub struct ItemDisplay<'data, 'colors, Leaf> where Leaf: DataDisplay<'data, 'colors> {
-    data: &'data Rc<Item<Leaf>>,
+    data: &'data Arc<Item<Leaf>>,
     detailed: bool,
     colors: Option<&'colors ColorSettings>,
 }
 
-impl<'data, 'colors, Leaf: 'data + DataDisplay<'data, 'colors>> DataDisplay<'data, 'colors> for Rc<Item<Leaf>> {
+impl<'data, 'colors, Leaf: 'data + DataDisplay<'data, 'colors>> DataDisplay<'data, 'colors> for Arc<Item<Leaf>> {
     type Displayer = ItemDisplay<'data, 'colors, Leaf>;
     fn display(&'data self, detailed: bool, colors: Option<&'colors ColorSettings>) -> Self::Displayer {
         ItemDisplay {
@@ -508,17 +530,17 @@ impl<Leaf: fmt::Display> fmt::Display for Item<Leaf> {
 }
 
 impl<A: SSAValues> Item<ValueOrImmediate<A>> where A::Data: Eq + fmt::Display {
-    pub fn immediate(v: i64) -> Rc<Self> {
+    pub fn immediate(v: i64) -> Arc<Self> {
         Self::untyped(Expression::value(ValueOrImmediate::Immediate(v)))
     }
 }
 
-use analyses::memory_layout::Underlying;
+use analyses::memory_layout::{Underlying, ValueData};
 impl<A: SSAValues> Item<ValueOrImmediate<A>> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
-    pub fn dealiased(&self) -> Rc<Self> {
+    pub fn dealiased<V: ValueData<A> + ?Sized>(&self, values: &V) -> Arc<Self> {
         let ty = self.ty.clone();
-        let value = self.value.dealiased();
-        Rc::new(Item { ty, value })
+        let value = self.value.dealiased(values);
+        Arc::new(Item { ty, value })
     }
 }
 
@@ -526,78 +548,82 @@ impl<A: SSAValues> Item<ValueOrImmediate<A>> where A::Data: Eq + fmt::Display{
 }
 
 impl<Leaf> Item<Leaf> {
-    pub fn untyped(value: Expression<Leaf>) -> Rc<Self> {
-        Rc::new(Item {
+    pub fn untyped(value: Expression<Leaf>) -> Arc<Self> {
+        Arc::new(Item {
             ty: None,
             value,
         })
     }
 
-    pub fn opaque(ty: TypeSpec) -> Rc<Self> {
-        Rc::new(Item {
+    pub fn opaque(ty: TypeSpec) -> Arc<Self> {
+        Arc::new(Item {
             ty: Some(ty),
             value: Expression::unknown()
         })
     }
 
-    pub fn unknown() -> Rc<Self> {
+    pub fn unknown() -> Arc<Self> {
         Self::untyped(Expression::unknown())
     }
 
-    pub fn value(leaf: Leaf) -> Rc<Self> {
+    pub fn value(leaf: Leaf) -> Arc<Self> {
         Self::untyped(Expression::value(leaf))
     }
 
-    pub fn loadb(self: &Rc<Self>) -> Rc<Self> {
+    pub fn loadb(self: &Arc<Self>) -> Arc<Self> {
         self.load(1)
     }
 
-    pub fn loadw(self: &Rc<Self>) -> Rc<Self> {
+    pub fn loadw(self: &Arc<Self>) -> Arc<Self> {
         self.load(2)
     }
 
-    pub fn loadd(self: &Rc<Self>) -> Rc<Self> {
+    pub fn loadd(self: &Arc<Self>) -> Arc<Self> {
         self.load(4)
     }
 
-    pub fn loadq(self: &Rc<Self>) -> Rc<Self> {
+    pub fn loadq(self: &Arc<Self>) -> Arc<Self> {
         self.load(8)
     }
 
-    pub fn load(self: &Rc<Self>, size: u8) -> Rc<Self> {
-        Self::untyped(Expression::Load { address: Rc::clone(self), size })
+    pub fn load(self: &Arc<Self>, size: u8) -> Arc<Self> {
+        Self::untyped(Expression::Load { address: Arc::clone(self), size })
     }
 
-    pub fn add(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::Add { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn add(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::Add { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn sub(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::Sub { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn sub(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::Sub { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn mul(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::Mul { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn mul(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::Mul { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn or(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::Or { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn or(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::Or { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn and(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::And { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn and(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::And { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn xor(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::Xor { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn xor(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::Xor { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn shl(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::Shl { value: Rc::clone(self), amount: Rc::clone(other) })
+    pub fn shl(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::Shl { value: Arc::clone(self), amount: Arc::clone(other) })
     }
 
-    pub fn shr(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Self::untyped(Expression::Shr { value: Rc::clone(self), amount: Rc::clone(other) })
+    pub fn shr(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Self::untyped(Expression::Shr { value: Arc::clone(self), amount: Arc::clone(other) })
+    }
+
+    pub fn sxt(self: &Arc<Self>, width: u8) -> Arc<Self> {
+        Self::untyped(Expression::SignExtend { value: Arc::clone(self), width })
     }
 }
 
@@ -609,17 +635,19 @@ impl<Leaf> Item<Leaf> {
 pub enum Expression<Leaf> {
     Unknown,
     Value(Leaf),
-    Load { address: Rc<Item<Leaf>>, size: u8 },
+    Load { address: Arc<Item<Leaf>>, size: u8 },
     // TODO: should there be a corresponding "store"?
-    // Store { address: Rc<Item<Leaf>>, size: u8, value: Rc<Item<Leaf>> },
-    Add { left: Rc<Item<Leaf>>, right: Rc<Item<Leaf>> },
-    Sub { left: Rc<Item<Leaf>>, right: Rc<Item<Leaf>> },
-    Mul { left: Rc<Item<Leaf>>, right: Rc<Item<Leaf>> },
-    Or { left: Rc<Item<Leaf>>, right: Rc<Item<Leaf>> },
-    And { left: Rc<Item<Leaf>>, right: Rc<Item<Leaf>> },
-    Xor { left: Rc<Item<Leaf>>, right: Rc<Item<Leaf>> },
-    Shl { value: Rc<Item<Leaf>>, amount: Rc<Item<Leaf>> },
-    Shr { value: Rc<Item<Leaf>>, amount: Rc<Item<Leaf>> },
+    // Store { address: Arc<Item<Leaf>>, size: u8, value: Arc<Item<Leaf>> },
+    Add { left: Arc<Item<Leaf>>, right: Arc<Item<Leaf>> },
+    Sub { left: Arc<Item<Leaf>>, right: Arc<Item<Leaf>> },
+    Mul { left: Arc<Item<Leaf>>, right: Arc<Item<Leaf>> },
+    Or { left: Arc<Item<Leaf>>, right: Arc<Item<Leaf>> },
+    And { left: Arc<Item<Leaf>>, right: Arc<Item<Leaf>> },
+    Xor { left: Arc<Item<Leaf>>, right: Arc<Item<Leaf>> },
+    Shl { value: Arc<Item<Leaf>>, amount: Arc<Item<Leaf>> },
+    Shr { value: Arc<Item<Leaf>>, amount: Arc<Item<Leaf>> },
+    /// `value`, sign-extended from its low `width` bits.
+    SignExtend { value: Arc<Item<Leaf>>, width: u8 },
 }
 
 pub struct ExpressionDisplay<'data, 'colors, Leaf: DataDisplay<'data, 'colors>> {
@@ -675,6 +703,9 @@ impl<'data, 'colors, Leaf: 'data + DataDisplay<'data, 'colors>> fmt::Display for
             Expression::Shr { value, amount } => {
                 write!(f, "({} >> {})", value.display(self.detailed, self.colors), amount.display(self.detailed, self.colors))
             }
+            Expression::SignExtend { value, width } => {
+                write!(f, "sxt{}({})", width, value.display(self.detailed, self.colors))
+            }
         }
     }
 }
@@ -715,6 +746,9 @@ impl<Leaf: fmt::Display> fmt::Display for Expression<Leaf> {
             Expression::Shr { value, amount } => {
                 write!(f, "({} >> {})", value, amount)
             }
+            Expression::SignExtend { value, width } => {
+                write!(f, "sxt{}({})", width, value)
+            }
         }
     }
 }
@@ -730,41 +764,45 @@ impl<T: SSAValues> Expression<ValueOrImmediate<T>> where T::Data: Eq + fmt::Disp
 }
 
 impl<A: SSAValues> Expression<ValueOrImmediate<A>> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
-    pub fn dealiased(&self) -> Self {
+    /// `self`, but with values that are aliases of other values replaced by what they alias.
+    pub fn dealiased<V: ValueData<A> + ?Sized>(&self, values: &V) -> Self {
         match self {
             Expression::Unknown => Expression::Unknown,
             Expression::Value(ValueOrImmediate::Immediate(i)) => Expression::Value(ValueOrImmediate::Immediate(*i)),
             Expression::Value(ValueOrImmediate::Value(v)) => {
-                if let Some(underlying) = v.borrow().data.as_ref().and_then(|x| x.underlying()) {
+                if let Some(underlying) = values.underlying(*v) {
                     Expression::Value(ValueOrImmediate::Value(underlying))
                 } else {
-                    Expression::Value(ValueOrImmediate::Value(Rc::clone(v)))
+                    Expression::Value(ValueOrImmediate::Value(*v))
                 }
             }
-            Expression::Load { address, size } => Expression::Load { address: address.dealiased(), size: *size },
+            Expression::Load { address, size } => Expression::Load { address: address.dealiased(values), size: *size },
             Expression::Add { left, right } => Expression::Add {
-                left: left.dealiased(), right: right.dealiased(),
+                left: left.dealiased(values), right: right.dealiased(values),
             },
             Expression::Sub { left, right } => Expression::Sub {
-                left: left.dealiased(), right: right.dealiased(),
+                left: left.dealiased(values), right: right.dealiased(values),
             },
             Expression::Mul { left, right } => Expression::Mul {
-                left: left.dealiased(), right: right.dealiased(),
+                left: left.dealiased(values), right: right.dealiased(values),
             },
             Expression::Or { left, right } => Expression::Or {
-                left: left.dealiased(), right: right.dealiased(),
+                left: left.dealiased(values), right: right.dealiased(values),
             },
             Expression::And { left, right } => Expression::And {
-                left: left.dealiased(), right: right.dealiased(),
+                left: left.dealiased(values), right: right.dealiased(values),
             },
             Expression::Xor { left, right } => Expression::Xor {
-                left: left.dealiased(), right: right.dealiased(),
+                left: left.dealiased(values), right: right.dealiased(values),
             },
             Expression::Shl { value, amount } => Expression::Shl {
-                value: value.dealiased(), amount: amount.dealiased(),
+                value: value.dealiased(values), amount: amount.dealiased(values),
             },
             Expression::Shr { value, amount } => Expression::Shr {
-                value: value.dealiased(), amount: amount.dealiased(),
+                value: value.dealiased(values), amount: amount.dealiased(values),
+            },
+            Expression::SignExtend { value, width } => Expression::SignExtend {
+                value: value.dealiased(values), width: *width,
             },
         }
     }
@@ -780,36 +818,36 @@ impl<Leaf> Expression<Leaf> {
     }
 
     /*
-    pub fn add(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::Add { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn add(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::Add { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn sub(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::Sub { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn sub(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::Sub { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn mul(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::Mul { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn mul(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::Mul { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn or(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::Or { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn or(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::Or { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn and(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::And { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn and(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::And { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn xor(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::Xor { left: Rc::clone(self), right: Rc::clone(other) })
+    pub fn xor(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::Xor { left: Arc::clone(self), right: Arc::clone(other) })
     }
 
-    pub fn shl(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::Shl { value: Rc::clone(self), amount: Rc::clone(other) })
+    pub fn shl(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::Shl { value: Arc::clone(self), amount: Arc::clone(other) })
     }
 
-    pub fn shr(self: &Rc<Self>, other: &Rc<Self>) -> Rc<Self> {
-        Rc::new(Expression::Shr { value: Rc::clone(self), amount: Rc::clone(other) })
+    pub fn shr(self: &Arc<Self>, other: &Arc<Self>) -> Arc<Self> {
+        Arc::new(Expression::Shr { value: Arc::clone(self), amount: Arc::clone(other) })
     }
     */
 }
@@ -826,21 +864,18 @@ impl<A: SSAValues> ValueOrImmediate<A> where A::Data: Eq + fmt::Display {
         Expression::value(ValueOrImmediate::Immediate(v))
     }
 
-    fn name(&self) -> String {
+    fn name(&self, ssa: &SSA<A>) -> String {
         match self {
             ValueOrImmediate::Immediate(v) => {
                 format!("{}", v)
             }
             ValueOrImmediate::Value(v) => {
-                let version_string = match v.borrow().version {
+                let value = ssa.value(*v);
+                let version_string = match value.version {
                     Some(v) => { v.to_string() },
                     None => "input".to_string()
                 };
-                if let Some(data) = v.borrow().data.as_ref() {
-                    format!("{:?}_{}", v.borrow().location, version_string)
-                } else {
-                    format!("{:?}_{}", v.borrow().location, version_string)
-                }
+                format!("{:?}_{}", value.location, version_string)
             }
         }
     }
@@ -867,15 +902,8 @@ impl<A: SSAValues> fmt::Display for ValueOrImmediate<A> where A::Data: Eq + fmt:
                 write!(f, "{}", v)
             }
             ValueOrImmediate::Value(v) => {
-                let version_string = match v.borrow().version {
-                    Some(v) => { v.to_string() },
-                    None => "input".to_string()
-                };
-                if let Some(data) = v.borrow().data.as_ref() {
-                    write!(f, "{} ({:?}_{})", data, v.borrow().location, version_string)
-                } else {
-                    write!(f, "<unknown value> ({:?}_{})", v.borrow().location, version_string)
-                }
+                // what the value is lives in the `SSA` it's from, which isn't here.
+                write!(f, "{}", v)
             }
         }
     }
@@ -883,16 +911,14 @@ impl<A: SSAValues> fmt::Display for ValueOrImmediate<A> where A::Data: Eq + fmt:
 
 pub struct LeafDisplay<'data, 'colors, A: SSAValues> where A::Data: Eq + fmt::Display {
     data: &'data ValueOrImmediate<A>,
-    detailed: bool,
     colors: Option<&'colors ColorSettings>,
 }
 
 impl<'data, 'colors, A: 'data + SSAValues> DataDisplay<'data, 'colors> for ValueOrImmediate<A> where A::Data: Eq + fmt::Display {
     type Displayer = LeafDisplay<'data, 'colors, A>;
-    fn display(&'data self, detailed: bool, colors: Option<&'colors ColorSettings>) -> Self::Displayer {
+    fn display(&'data self, _detailed: bool, colors: Option<&'colors ColorSettings>) -> Self::Displayer {
         LeafDisplay {
             data: self,
-            detailed,
             colors
         }
     }
@@ -906,7 +932,9 @@ impl<'data, 'colors, A: SSAValues> fmt::Display for LeafDisplay<'data, 'colors,
                 write!(f, "{:#x}", i)
             }
             ValueOrImmediate::Value(v) => {
-                fmt::Display::fmt(&v.borrow().display(self.detailed, self.colors), f)
+                // there's no `SSA` here to say more about `v`, so it's shown like a register.
+                use yaxpeax_arch::YaxColors;
+                write!(f, "{}", self.colors.register(v))
             }
         }
     }
@@ -921,7 +949,7 @@ impl<A: SSAValues> PartialEq for ValueOrImmediate<A> where A::Data: Eq + fmt::Di
                 l == r
             }
             (ValueOrImmediate::Value(l), ValueOrImmediate::Value(r)) => {
-                Rc::ptr_eq(l, r)
+                l == r
             }
             _ => {
                 false
@@ -937,7 +965,7 @@ impl<A: SSAValues> Clone for ValueOrImmediate<A> where A::Data: Eq + fmt::Displa
                 ValueOrImmediate::Immediate(*v)
             }
             ValueOrImmediate::Value(v) => {
-                ValueOrImmediate::Value(Rc::clone(v))
+                ValueOrImmediate::Value(*v)
             }
         }
     }
@@ -952,14 +980,14 @@ impl<A: SSAValues> Hash for ValueOrImmediate<A> where A::Data: Eq + fmt::Display
             }
             ValueOrImmediate::Value(value) => {
                 state.write_u8(2);
-                (crate::analyses::static_single_assignment::HashedValue { value: Rc::clone(value) }).hash(state);
+                value.hash(state);
             }
         }
     }
 }
 
-use analyses::static_single_assignment::{DFGRebase, DefSource, HashedValue, SSA};
-impl<A: SSAValues + Arch> DFGRebase<A> for Rc<Item<ValueOrImmediate<A>>> where A::Data: Hash + Eq + fmt::Display, A::Location: DFGRebase<A> {
+use analyses::static_single_assignment::{DFGRebase, DefSource};
+impl<A: SSAValues + Arch> DFGRebase<A> for Arc<Item<ValueOrImmediate<A>>> where A::Data: Hash + Eq + fmt::Display, A::Location: DFGRebase<A> {
     fn rebase_references(&self, old_dfg: &SSA<A>, new_dfg: &SSA<A>) -> Self {
         match &self.value {
             Expression::Unknown => Item::unknown(),
@@ -969,41 +997,41 @@ impl<A: SSAValues + Arch> DFGRebase<A> for Rc<Item<ValueOrImmediate<A>>> where A
                         Item::untyped(Expression::Value(ValueOrImmediate::Immediate(*v)))
                     }
                     ValueOrImmediate::Value(v) => {
-                        if !new_dfg.defs.contains_key(&HashedValue { value: Rc::clone(v) }) {
-                            let (old_def_addr, old_def_source) = old_dfg.get_def_site(Rc::clone(v));
-                            let new_use = match old_def_source {
-                                DefSource::Instruction => {
-                                    new_dfg.get_use(old_def_addr, v.borrow().location.rebase_references(old_dfg, new_dfg))
-                                        .value
-                                },
-                                DefSource::External => {
-                                    new_dfg.instruction_values.values()
-                                        .find_map(|values| {
-                                            values.iter().find_map(|((loc, dir), dfg_ref)| {
-                                                if dir == &crate::data::Direction::Read && loc == &v.borrow().location && v.borrow().version.is_none() {
-                                                    Some(Rc::clone(dfg_ref))
-                                                } else {
-                                                    None
-                                                }
-                                            })
+                        // values are indices into the SSA they're from, so there's no telling if
+                        // `v` is already in `new_dfg`; it's always found again by where it was.
+                        let old_value = old_dfg.value(*v);
+                        let (old_def_addr, old_def_source) = old_dfg.get_def_site(*v);
+                        let new_use = match old_def_source {
+                            DefSource::Instruction => {
+                                let location = old_value.location.rebase_references(old_dfg, new_dfg);
+                                new_dfg.try_get_def(old_def_addr, location.clone())
+                                    .unwrap_or_else(|| new_dfg.get_use(old_def_addr, location).value)
+                            },
+                            DefSource::External => {
+                                new_dfg.instruction_values.values()
+                                    .find_map(|values| {
+                                        values.iter().find_map(|((loc, dir), dfg_ref)| {
+                                            if dir == &crate::data::Direction::Read && loc == &old_value.location && old_value.version.is_none() {
+                                                Some(*dfg_ref)
+                                            } else {
+                                                None
+                                            }
                                         })
-                                        .expect(&format!("corresponding external def exists for location {:?}", v.borrow().location))
-                                }
-                                DefSource::Between(addr) => {
-                                    Rc::clone(new_dfg.control_dependent_values
-                                        .get(&old_def_addr).expect("old def addr is valid")
-                                        .get(&addr).expect("between's prior addr is valid")
-                                        .get(&(v.borrow().location.rebase_references(old_dfg, new_dfg), crate::data::Direction::Write))
-                                        .expect("corresponding def exists in new dfg"))
-                                }
-                                other => {
-                                    panic!("aaaa {}", other);
-                                }
-                            };
-                            Item::untyped(Expression::Value(ValueOrImmediate::Value(new_use)))
-                        } else {
-                            Item::untyped(Expression::Value(ValueOrImmediate::Value(Rc::clone(v))))
-                        }
+                                    })
+                                    .expect(&format!("corresponding external def exists for location {:?}", old_value.location))
+                            }
+                            DefSource::Between(addr) => {
+                                *new_dfg.control_dependent_values
+                                    .get(&old_def_addr).expect("old def addr is valid")
+                                    .get(&addr).expect("between's prior addr is valid")
+                                    .get(&(old_value.location.rebase_references(old_dfg, new_dfg), crate::data::Direction::Write))
+                                    .expect("corresponding def exists in new dfg")
+                            }
+                            other => {
+                                panic!("aaaa {}", other);
+                            }
+                        };
+                        Item::untyped(Expression::Value(ValueOrImmediate::Value(new_use)))
                     }
                 }
             }
@@ -1058,11 +1086,14 @@ impl<A: SSAValues + Arch> DFGRebase<A> for Rc<Item<ValueOrImmediate<A>>> where A
                     &amount.rebase_references(old_dfg, new_dfg),
                 )
             },
+            Expression::SignExtend { value, width } => {
+                Item::sxt(&value.rebase_references(old_dfg, new_dfg), *width)
+            },
         }
     }
 }
 
-impl<A: SSAValues> Value for Rc<Item<ValueOrImmediate<A>>> where A::Data: Hash + Eq + fmt::Display {
+impl<A: SSAValues> Value for Arc<Item<ValueOrImmediate<A>>> where A::Data: Hash + Eq + fmt::Display {
     // type Indirect = OpaqueIndirection<Self>;
 
     fn unknown() -> Self {
@@ -1092,48 +1123,51 @@ impl<A: SSAValues> Value for Rc<Item<ValueOrImmediate<A>>> where A::Data: Hash +
 
     fn add(&self, other: &Self) -> ValueRes<Self> {
         ValueRes {
-            value: Item::untyped(Expression::Add { left: Rc::clone(self), right: Rc::clone(other) }),
+            value: Item::untyped(Expression::Add { left: Arc::clone(self), right: Arc::clone(other) }),
             carry: Self::unknown()
         }
     }
 
     fn sub(&self, other: &Self) -> ValueRes<Self> {
         ValueRes {
-            value: Item::untyped(Expression::Sub { left: Rc::clone(self), right: Rc::clone(other) }),
+            value: Item::untyped(Expression::Sub { left: Arc::clone(self), right: Arc::clone(other) }),
             carry: Self::unknown()
         }
     }
 
     fn mul(&self, other: &Self) -> ValueRes<Self> {
         ValueRes {
-            value: Item::untyped(Expression::Mul { left: Rc::clone(self), right: Rc::clone(other) }),
+            value: Item::untyped(Expression::Mul { left: Arc::clone(self), right: Arc::clone(other) }),
             carry: Self::unknown()
         }
     }
 
     fn or(&self, other: &Self) -> ValueRes<Self> {
         ValueRes {
-            value: Item::untyped(Expression::Or { left: Rc::clone(self), right: Rc::clone(other) }),
+            value: Item::untyped(Expression::Or { left: Arc::clone(self), right: Arc::clone(other) }),
             carry: Self::unknown()
         }
     }
 
     fn and(&self, other: &Self) -> ValueRes<Self> {
         ValueRes {
-            value: Item::untyped(Expression::And { left: Rc::clone(self), right: Rc::clone(other) }),
+            value: Item::untyped(Expression::And { left: Arc::clone(self), right: Arc::clone(other) }),
             carry: Self::unknown()
         }
     }
 
     fn xor(&self, other: &Self) -> ValueRes<Self> {
         ValueRes {
-            value: Item::untyped(Expression::Xor { left: Rc::clone(self), right: Rc::clone(other) }),
+            value: Item::untyped(Expression::Xor { left: Arc::clone(self), right: Arc::clone(other) }),
             carry: Self::unknown()
         }
     }
 
-    fn sxt(&self, _width: &Self) -> Self {
-        Self::unknown()
+    fn sxt(&self, width: &Self) -> Self {
+        match width.to_const() {
+            Some(width @ 1..=64) => Item::sxt(self, width as u8),
+            _ => Self::unknown(),
+        }
     }
 
     fn zxt(&self, _width: &Self) -> Self {
@@ -1141,10 +1175,10 @@ impl<A: SSAValues> Value for Rc<Item<ValueOrImmediate<A>>> where A::Data: Hash +
     }
 
     fn shr(&self, amount: &Self) -> Self {
-        Item::untyped(Expression::Shr { value: Rc::clone(self), amount: Rc::clone(amount) })
+        Item::untyped(Expression::Shr { value: Arc::clone(self), amount: Arc::clone(amount) })
     }
 
     fn shl(&self, amount: &Self) -> Self {
-        Item::untyped(Expression::Shl { value: Rc::clone(self), amount: Rc::clone(amount) })
+        Item::untyped(Expression::Shl { value: Arc::clone(self), amount: Arc::clone(amount) })
     }
 }
@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use memory::repr::process::ModuleInfo;
+
+/// library functions that never return to their caller. this is not exhaustive, just the
+/// functions that show up often enough to be worth not decoding past.
+pub const NORETURN_FUNCTIONS: &[&str] = &[
+    // libc and friends
+    "exit",
+    "_exit",
+    "_Exit",
+    "quick_exit",
+    "abort",
+    "__assert_fail",
+    "__assert_rtn",
+    "__stack_chk_fail",
+    "__fortify_fail",
+    "__chk_fail",
+    "err",
+    "errx",
+    "verr",
+    "verrx",
+    "longjmp",
+    "_longjmp",
+    "siglongjmp",
+    "pthread_exit",
+    "__cxa_throw",
+    "__cxa_rethrow",
+    "_Unwind_Resume",
+    // windows
+    "ExitProcess",
+    "ExitThread",
+    "FatalExit",
+    "RaiseFailFastException",
+    "_invalid_parameter_noinfo_noreturn",
+    "__report_gsfailure",
+    "_CxxThrowException",
+];
+
+pub fn is_noreturn_name(name: &str) -> bool {
+    NORETURN_FUNCTIONS.contains(&name)
+}
+
+/// imports of `module` named in `NORETURN_FUNCTIONS`, as `(address, name)`. the address is where
+/// a call to the import reads its destination from: the IAT slot for PE, and the .got slot the
+/// plt stub jumps through for ELF.
+pub fn noreturn_imports(module: &ModuleInfo) -> Vec<(u64, String)> {
+    match module {
+        ModuleInfo::PE(_, _, _, image_base, _, imports, _, _) => {
+            imports.iter()
+                .filter(|import| is_noreturn_name(&import.name))
+                .map(|import| (image_base.wrapping_add(import.offset as u64), import.name.clone()))
+                .collect()
+        }
+        ModuleInfo::ELF(_, _, _, _, _, _, imports, _, _) => {
+            imports.iter()
+                .filter(|import| is_noreturn_name(&import.name))
+                .map(|import| (import.value, import.name.clone()))
+                .collect()
+        }
+    }
+}
+
+/// grow `noreturn` to a fixed point over `functions`. `may_return(f, noreturn)` decides if some
+/// path through `f` returns, given that calls to anything in `noreturn` don't. a function is added
+/// once no path through it returns, and since more noreturn callees only ever cut more paths, this
+/// stops as soon as a pass over `functions` adds nothing.
+///
+/// `may_return` should answer `true` whenever it isn't sure - claiming a function does not return
+/// removes all code after calls to it, which is much worse than decoding a bit of junk.
+pub fn propagate_noreturn<A, F>(functions: &[A], mut noreturn: HashSet<A>, mut may_return: F) -> HashSet<A>
+    where
+        A: Copy + Eq + Hash,
+        F: FnMut(A, &HashSet<A>) -> bool,
+{
+    loop {
+        let mut changed = false;
+        for f in functions.iter() {
+            if noreturn.contains(f) {
+                continue;
+            }
+            if !may_return(*f, &noreturn) {
+                noreturn.insert(*f);
+                changed = true;
+            }
+        }
+        if !changed {
+            return noreturn;
+        }
+    }
+}
+
+#[test]
+fn test_propagate_noreturn() {
+    use std::collections::HashMap;
+    // 1 calls 0 (abort) on every path, 2 calls 1 on every path, 3 calls 2 on only one path, and
+    // 4 and 5 call each other and 0 - a cycle with no way out but `abort`.
+    let mut calls: HashMap<u32, Vec<Vec<u32>>> = HashMap::new();
+    calls.insert(1, vec![vec![0]]);
+    calls.insert(2, vec![vec![7, 1], vec![1]]);
+    calls.insert(3, vec![vec![2], vec![]]);
+    calls.insert(4, vec![vec![5], vec![0]]);
+    calls.insert(5, vec![vec![4]]);
+
+    let noreturn = propagate_noreturn(
+        &[5, 4, 3, 2, 1],
+        [0].iter().cloned().collect(),
+        |f, noreturn| {
+            // a path returns if none of its calls are to a noreturn function.
+            calls[&f].iter().any(|path| path.iter().all(|callee| !noreturn.contains(callee)))
+        },
+    );
+
+    // 4 and 5 can't return either, but only because of each other. finding that takes assuming
+    // they don't return and checking it holds, which this deliberately doesn't do - missing a
+    // noreturn function costs a bit of junk, wrongly claiming one costs real code.
+    let expected: HashSet<u32> = [0, 1, 2].iter().cloned().collect();
+    assert_eq!(noreturn, expected);
+}
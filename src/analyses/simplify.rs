@@ -0,0 +1,424 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+use analyses::{Expression, Item, ValueOrImmediate};
+use analyses::static_single_assignment::SSAValues;
+
+type Expr<A> = Arc<Item<ValueOrImmediate<A>>>;
+
+impl<A: SSAValues> Item<ValueOrImmediate<A>> where A::Data: Eq + fmt::Display {
+    /// this expression in canonical form. two expressions that compute the same thing in the same
+    /// way should simplify to equal expressions, so they compare (and display) the same. see
+    /// `simplify`.
+    pub fn simplified(self: &Arc<Self>) -> Arc<Self> {
+        simplify(self)
+    }
+}
+
+/// put `expr` in a canonical form:
+/// * constants are folded, wherever they are,
+/// * sums are flattened to a linear combination of terms in a fixed order, with the constant
+///   last. shifts and multiplications by a constant become coefficients, so `x << 3` and `x * 8`
+///   are both `(x * 0x8)`, and `((rsp + -8) + -16) + 8` is `(rsp - 0x10)`,
+/// * chains of `&`, `|`, and `^` are flattened and ordered the same way, so `x ^ x` is `0`, and
+///   `x & x` is `x`,
+/// * sign extending something already sign extended from fewer bits is the inner extension.
+///
+/// `Unknown` is never the same as anything, even another `Unknown`. leaves are kept as they are,
+/// so this is best done after `dealiased` if leaves might be aliases of each other. types on
+/// operations that are rewritten are not kept.
+pub fn simplify<A: SSAValues>(expr: &Expr<A>) -> Expr<A> where A::Data: Eq + fmt::Display {
+    match &expr.value {
+        Expression::Unknown |
+        Expression::Value(_) => Arc::clone(expr),
+        Expression::Load { address, size } => {
+            Item::load(&simplify(address), *size)
+        }
+        Expression::Add { .. } |
+        Expression::Sub { .. } |
+        Expression::Mul { .. } |
+        Expression::Shl { .. } => {
+            let mut sum = Sum { constant: 0, terms: Vec::new() };
+            sum.collect(expr, 1);
+            sum.build()
+        }
+        Expression::And { .. } => Bitwise::And.simplify(expr),
+        Expression::Or { .. } => Bitwise::Or.simplify(expr),
+        Expression::Xor { .. } => Bitwise::Xor.simplify(expr),
+        Expression::Shr { value, amount } => {
+            let value = simplify(value);
+            let amount = simplify(amount);
+            match (value.value.as_immediate(), amount.value.as_immediate()) {
+                (_, Some(0)) => value,
+                (Some(value), Some(amount @ 1..=63)) => {
+                    Item::immediate(((value as u64) >> amount) as i64)
+                }
+                _ => Item::shr(&value, &amount),
+            }
+        }
+        Expression::SignExtend { value, width } => {
+            let value = simplify(value);
+            if *width == 0 {
+                // there's no sign bit to extend, like `Value::sxt` with a width of 0.
+                return Item::unknown();
+            }
+            if *width >= 64 {
+                return value;
+            }
+            if let Some(i) = value.value.as_immediate() {
+                let shift = 64 - *width as u32;
+                return Item::immediate(i.wrapping_shl(shift).wrapping_shr(shift));
+            }
+            if let Expression::SignExtend { width: inner, .. } = &value.value {
+                // the top `64 - inner` bits are already copies of the sign bit, and extending
+                // from any higher bit copies it again.
+                if inner <= width {
+                    return value;
+                }
+            }
+            Item::sxt(&value, *width)
+        }
+    }
+}
+
+/// how far `left` is past `right`, if they differ by a constant.
+pub fn offset_between<A: SSAValues>(left: &Expr<A>, right: &Expr<A>) -> Option<i64> where A::Data: Eq + fmt::Display {
+    simplify(&left.sub(right)).value.as_immediate()
+}
+
+/// whether `left` and `right` are certainly the same value. this is stricter than `==`, because
+/// two `Unknown` are not the same value.
+fn same<A: SSAValues>(left: &Expr<A>, right: &Expr<A>) -> bool where A::Data: Eq + fmt::Display {
+    left == right && !mentions_unknown(left)
+}
+
+fn mentions_unknown<A: SSAValues>(expr: &Expr<A>) -> bool where A::Data: Eq + fmt::Display {
+    match &expr.value {
+        Expression::Unknown => true,
+        Expression::Value(_) => false,
+        Expression::Load { address: value, .. } |
+        Expression::SignExtend { value, .. } => mentions_unknown(value),
+        Expression::Add { left, right } |
+        Expression::Sub { left, right } |
+        Expression::Mul { left, right } |
+        Expression::Or { left, right } |
+        Expression::And { left, right } |
+        Expression::Xor { left, right } |
+        Expression::Shl { value: left, amount: right } |
+        Expression::Shr { value: left, amount: right } => {
+            mentions_unknown(left) || mentions_unknown(right)
+        }
+    }
+}
+
+/// the order operands are put in. variables come first, by index, then more complex terms, and
+/// immediates last.
+fn order<A: SSAValues>(left: &Expr<A>, right: &Expr<A>) -> Ordering where A::Data: Eq + fmt::Display {
+    fn shape<A: SSAValues>(expr: &Expression<ValueOrImmediate<A>>) -> (u8, u8, [Option<&Expr<A>>; 2]) where A::Data: Eq + fmt::Display {
+        match expr {
+            Expression::Value(ValueOrImmediate::Value(_)) => (0, 0, [None, None]),
+            Expression::Load { address, size } => (1, *size, [Some(address), None]),
+            // extending from no bits at all is as unknown as `Unknown`.
+            Expression::SignExtend { width: 0, .. } => (11, 0, [None, None]),
+            Expression::SignExtend { value, width } => (2, *width, [Some(value), None]),
+            Expression::Mul { left, right } => (3, 0, [Some(left), Some(right)]),
+            Expression::Shl { value, amount } => (4, 0, [Some(value), Some(amount)]),
+            Expression::Shr { value, amount } => (5, 0, [Some(value), Some(amount)]),
+            Expression::And { left, right } => (6, 0, [Some(left), Some(right)]),
+            Expression::Or { left, right } => (7, 0, [Some(left), Some(right)]),
+            Expression::Xor { left, right } => (8, 0, [Some(left), Some(right)]),
+            Expression::Add { left, right } => (9, 0, [Some(left), Some(right)]),
+            Expression::Sub { left, right } => (10, 0, [Some(left), Some(right)]),
+            Expression::Unknown => (11, 0, [None, None]),
+            Expression::Value(ValueOrImmediate::Immediate(_)) => (12, 0, [None, None]),
+        }
+    }
+
+    match (&left.value, &right.value) {
+        (Expression::Value(ValueOrImmediate::Immediate(l)), Expression::Value(ValueOrImmediate::Immediate(r))) => {
+            l.cmp(r)
+        }
+        (Expression::Value(ValueOrImmediate::Value(l)), Expression::Value(ValueOrImmediate::Value(r))) => {
+            // values are ordered by where they are in their `SSA`, which doesn't change once
+            // it's built.
+            l.cmp(r)
+        }
+        (l, r) => {
+            let (l_rank, l_size, l_operands) = shape(l);
+            let (r_rank, r_size, r_operands) = shape(r);
+            l_rank.cmp(&r_rank)
+                .then(l_size.cmp(&r_size))
+                .then_with(|| {
+                    l_operands.iter().zip(r_operands.iter())
+                        .map(|pair| match pair {
+                            (Some(l), Some(r)) => order(l, r),
+                            _ => Ordering::Equal,
+                        })
+                        .find(|ordering| *ordering != Ordering::Equal)
+                        .unwrap_or(Ordering::Equal)
+                })
+        }
+    }
+}
+
+/// a linear combination of terms, plus a constant. all arithmetic wraps, as it would in a 64-bit
+/// register.
+struct Sum<A: SSAValues> where A::Data: Eq + fmt::Display {
+    constant: i64,
+    terms: Vec<(Expr<A>, i64)>,
+}
+
+impl<A: SSAValues> Sum<A> where A::Data: Eq + fmt::Display {
+    /// add `expr * scale` to this sum.
+    fn collect(&mut self, expr: &Expr<A>, scale: i64) {
+        match &expr.value {
+            Expression::Value(ValueOrImmediate::Immediate(i)) => {
+                self.constant = self.constant.wrapping_add(i.wrapping_mul(scale));
+            }
+            Expression::Add { left, right } => {
+                self.collect(left, scale);
+                self.collect(right, scale);
+            }
+            Expression::Sub { left, right } => {
+                self.collect(left, scale);
+                self.collect(right, scale.wrapping_neg());
+            }
+            Expression::Mul { left, right } => {
+                let left = simplify(left);
+                let right = simplify(right);
+                match (left.value.as_immediate(), right.value.as_immediate()) {
+                    (Some(factor), _) => self.collect(&right, scale.wrapping_mul(factor)),
+                    (_, Some(factor)) => self.collect(&left, scale.wrapping_mul(factor)),
+                    _ => {
+                        let term = if order(&left, &right) == Ordering::Greater {
+                            Item::mul(&right, &left)
+                        } else {
+                            Item::mul(&left, &right)
+                        };
+                        self.add_term(term, scale);
+                    }
+                }
+            }
+            Expression::Shl { value, amount } => {
+                let amount = simplify(amount);
+                match amount.value.as_immediate() {
+                    Some(amount @ 0..=63) => {
+                        self.collect(value, scale.wrapping_mul(1i64.wrapping_shl(amount as u32)));
+                    }
+                    _ => {
+                        self.add_term(Item::shl(&simplify(value), &amount), scale);
+                    }
+                }
+            }
+            _ => {
+                let simplified = simplify(expr);
+                match simplified.value {
+                    // whatever `expr` was, it simplified to something this sum can take apart
+                    Expression::Value(ValueOrImmediate::Immediate(_)) |
+                    Expression::Add { .. } |
+                    Expression::Sub { .. } => self.collect(&simplified, scale),
+                    _ => self.add_term(simplified, scale),
+                }
+            }
+        }
+    }
+
+    fn add_term(&mut self, term: Expr<A>, scale: i64) {
+        if let Some(existing) = self.terms.iter_mut().find(|(existing, _)| same(existing, &term)) {
+            existing.1 = existing.1.wrapping_add(scale);
+        } else {
+            self.terms.push((term, scale));
+        }
+    }
+
+    fn build(mut self) -> Expr<A> {
+        fn scaled<A: SSAValues>(term: Expr<A>, coefficient: i64) -> Expr<A> where A::Data: Eq + fmt::Display {
+            if coefficient == 1 {
+                term
+            } else {
+                Item::mul(&term, &Item::immediate(coefficient))
+            }
+        }
+
+        self.terms.retain(|(_, coefficient)| *coefficient != 0);
+        self.terms.sort_by(|(l, _), (r, _)| order(l, r));
+
+        let mut sum: Option<Expr<A>> = None;
+        for (term, coefficient) in self.terms.into_iter() {
+            sum = Some(match sum {
+                None => scaled(term, coefficient),
+                Some(sum) if coefficient < 0 && coefficient != i64::MIN => {
+                    sum.sub(&scaled(term, coefficient.wrapping_neg()))
+                }
+                Some(sum) => sum.add(&scaled(term, coefficient)),
+            });
+        }
+
+        match sum {
+            None => Item::immediate(self.constant),
+            Some(sum) if self.constant == 0 => sum,
+            Some(sum) if self.constant < 0 && self.constant != i64::MIN => {
+                sum.sub(&Item::immediate(self.constant.wrapping_neg()))
+            }
+            Some(sum) => sum.add(&Item::immediate(self.constant)),
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Bitwise {
+    And,
+    Or,
+    Xor,
+}
+
+impl Bitwise {
+    fn operands<A: SSAValues>(&self, expr: &Expr<A>) -> Option<(Expr<A>, Expr<A>)> where A::Data: Eq + fmt::Display {
+        match (self, &expr.value) {
+            (Bitwise::And, Expression::And { left, right }) |
+            (Bitwise::Or, Expression::Or { left, right }) |
+            (Bitwise::Xor, Expression::Xor { left, right }) => {
+                Some((Arc::clone(left), Arc::clone(right)))
+            }
+            _ => None,
+        }
+    }
+
+    fn apply<A: SSAValues>(&self, left: &Expr<A>, right: &Expr<A>) -> Expr<A> where A::Data: Eq + fmt::Display {
+        match self {
+            Bitwise::And => Item::and(left, right),
+            Bitwise::Or => Item::or(left, right),
+            Bitwise::Xor => Item::xor(left, right),
+        }
+    }
+
+    fn fold(&self, left: i64, right: i64) -> i64 {
+        match self {
+            Bitwise::And => left & right,
+            Bitwise::Or => left | right,
+            Bitwise::Xor => left ^ right,
+        }
+    }
+
+    /// `x op identity` is `x`.
+    fn identity(&self) -> i64 {
+        match self {
+            Bitwise::And => -1,
+            Bitwise::Or | Bitwise::Xor => 0,
+        }
+    }
+
+    /// `x op absorbing` is `absorbing`, if there is such a value.
+    fn absorbing(&self) -> Option<i64> {
+        match self {
+            Bitwise::And => Some(0),
+            Bitwise::Or => Some(-1),
+            Bitwise::Xor => None,
+        }
+    }
+
+    /// gather the operands of a chain of this operation in `expr`, simplifying each.
+    fn flatten<A: SSAValues>(&self, expr: &Expr<A>, operands: &mut Vec<Expr<A>>) where A::Data: Eq + fmt::Display {
+        if let Some((left, right)) = self.operands(expr) {
+            self.flatten(&left, operands);
+            self.flatten(&right, operands);
+        } else {
+            let simplified = simplify(expr);
+            if self.operands(&simplified).is_some() {
+                self.flatten(&simplified, operands);
+            } else {
+                operands.push(simplified);
+            }
+        }
+    }
+
+    fn simplify<A: SSAValues>(&self, expr: &Expr<A>) -> Expr<A> where A::Data: Eq + fmt::Display {
+        let mut operands = Vec::new();
+        self.flatten(expr, &mut operands);
+
+        let mut constant = self.identity();
+        let mut terms: Vec<Expr<A>> = Vec::new();
+        for operand in operands.into_iter() {
+            if let Some(i) = operand.value.as_immediate() {
+                constant = self.fold(constant, i);
+            } else if let Some(idx) = terms.iter().position(|term| same(term, &operand)) {
+                // `x & x` and `x | x` are `x`, and `x ^ x` is zero.
+                if *self == Bitwise::Xor {
+                    terms.remove(idx);
+                }
+            } else {
+                terms.push(operand);
+            }
+        }
+
+        if Some(constant) == self.absorbing() {
+            return Item::immediate(constant);
+        }
+
+        terms.sort_by(order);
+        if constant != self.identity() {
+            terms.push(Item::immediate(constant));
+        }
+        let mut terms = terms.into_iter();
+        match terms.next() {
+            Some(first) => terms.fold(first, |acc, term| self.apply(&acc, &term)),
+            None => Item::immediate(constant),
+        }
+    }
+}
+
+#[test]
+fn test_simplify() {
+    use analyses::static_single_assignment::{Value as SSAValue, ValueGraph};
+    use arch::x86_64::analyses::data_flow::Location;
+    use yaxpeax_x86::x86_64;
+
+    let mut values: ValueGraph<x86_64> = ValueGraph::new();
+    let mut value = |location: Location, version: u32| -> Expr<x86_64> {
+        Item::value(ValueOrImmediate::Value(values.alloc(SSAValue::new(location, Some(version)))))
+    };
+    let imm = Item::<ValueOrImmediate<x86_64>>::immediate;
+
+    let rsp = value(Location::rsp(), 0);
+    let rdi = value(Location::rdi(), 0);
+    let rsi = value(Location::rsi(), 0);
+
+    // the motivating case: stack adjustments pile up, but are all one offset from `rsp`.
+    let adjusted = rsp.add(&imm(-8)).add(&imm(-16)).add(&imm(8));
+    assert_eq!(adjusted.simplified(), rsp.sub(&imm(0x10)));
+    assert_eq!(adjusted.simplified().to_string(), rsp.sub(&imm(0x10)).to_string());
+    assert_eq!(rsp.sub(&imm(8)).add(&imm(8)).simplified(), rsp);
+
+    // shifts are multiplications, and terms are ordered the same however they're written.
+    assert_eq!(rdi.shl(&imm(3)).simplified(), rdi.mul(&imm(8)).simplified());
+    assert_eq!(
+        rsi.add(&rdi.shl(&imm(3))).add(&imm(0x10)).simplified(),
+        imm(0x10).add(&rdi.mul(&imm(8))).add(&rsi).simplified(),
+    );
+    assert_eq!(rdi.sub(&rdi).simplified(), imm(0));
+    assert_eq!(imm(3).mul(&imm(5)).sub(&imm(1)).simplified(), imm(14));
+
+    // `x ^ x` is zero even buried in a chain, and constants fold.
+    assert_eq!(rdi.xor(&rsi).xor(&rdi).simplified(), rsi);
+    assert_eq!(rdi.xor(&rdi).simplified(), imm(0));
+    assert_eq!(rdi.and(&imm(0xff)).and(&imm(0x0f)).simplified(), rdi.and(&imm(0x0f)));
+    assert_eq!(rdi.or(&rdi).simplified(), rdi);
+    assert_eq!(rdi.and(&imm(0)).simplified(), imm(0));
+
+    // nothing is known about `Unknown`, not even that it's the same as itself.
+    let unknown = Item::<ValueOrImmediate<x86_64>>::unknown();
+    assert_ne!(unknown.xor(&unknown).simplified(), imm(0));
+    assert_ne!(unknown.sub(&unknown).simplified(), imm(0));
+
+    // sign extensions from fewer bits are already all the extension there is.
+    assert_eq!(rdi.sxt(8).sxt(32).simplified(), rdi.sxt(8));
+    assert_eq!(rdi.sxt(32).sxt(8).simplified(), rdi.sxt(32).sxt(8));
+    assert_eq!(rdi.sxt(64).simplified(), rdi);
+    assert_eq!(imm(0x80).sxt(8).simplified(), imm(-0x80));
+    assert_eq!(imm(0x7f).sxt(8).simplified(), imm(0x7f));
+    assert!(matches!(imm(0x80).sxt(0).simplified().value, Expression::Unknown));
+
+    assert_eq!(offset_between(&rsp.sub(&imm(8)), &rsp.add(&imm(8))), Some(-16));
+    assert_eq!(offset_between(&rsp, &rdi), None);
+}
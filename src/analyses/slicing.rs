@@ -0,0 +1,456 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use analyses::control_flow::ControlFlowGraph;
+use analyses::control_flow::control_dependence::ControlDependenceGraph;
+use analyses::static_single_assignment::{DefSource, DFGRef, RWMap, SSA, SSAValues, UseSite};
+use arch::{AbiDefaults, FunctionImpl, FunctionQuery};
+use data::{AliasInfo, Direction};
+use data::modifier::Precedence;
+
+/// the instructions and SSA values on one side of a value: everything that contributes to it for a
+/// backward slice, or everything it influences for a forward slice.
+#[derive(Debug)]
+pub struct Slice<A: SSAValues> {
+    /// every value in the slice, including the one it started from.
+    pub values: HashSet<DFGRef<A>>,
+    /// instructions that write a value in a backward slice or read a value in a forward slice,
+    /// and, when following control dependence, the branches that decide if those run.
+    pub instructions: BTreeSet<A::Address>,
+    /// locations whose value coming into the function is in the slice. a backward slice that gets
+    /// here isn't done, and picks up again at the same locations in whatever calls this function.
+    pub inputs: HashSet<A::Location>,
+    /// calls the slice goes through, from an argument to a return value.
+    pub calls: BTreeSet<A::Address>,
+}
+
+impl<A: SSAValues> Slice<A> {
+    fn new() -> Self {
+        Slice {
+            values: HashSet::new(),
+            instructions: BTreeSet::new(),
+            inputs: HashSet::new(),
+            calls: BTreeSet::new(),
+        }
+    }
+
+    pub fn contains(&self, value: &DFGRef<A>) -> bool {
+        self.values.contains(value)
+    }
+
+    pub fn contains_instruction(&self, addr: A::Address) -> bool {
+        self.instructions.contains(&addr)
+    }
+}
+
+/// computes slices of an `SSA`.
+///
+/// slices only follow data flow until told otherwise. `with_calls` lets them go through calls
+/// whose callee has a known `FunctionLayout`, from argument locations to return locations,
+/// instead of stopping at whatever modifier the call was given. `with_control_dependence`
+/// includes the branches deciding whether an instruction runs, and for forward slices, what
+/// those branches decide.
+pub struct Slicer<'a, A: SSAValues, F> where A::Address: petgraph::graphmap::NodeTrait {
+    ssa: &'a SSA<A>,
+    calls: Option<(&'a HashMap<A::Address, A::Address>, &'a F)>,
+    control: Option<(&'a ControlFlowGraph<A::Address>, &'a ControlDependenceGraph<A::Address>)>,
+}
+
+impl<'a, A: SSAValues> Slicer<'a, A, HashMap<A::Address, FunctionImpl<A::Location>>> where
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+{
+    pub fn new(ssa: &'a SSA<A>) -> Self {
+        Slicer {
+            ssa,
+            calls: None,
+            control: None,
+        }
+    }
+}
+
+impl<'a, A: SSAValues, F> Slicer<'a, A, F> where
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    F: FunctionQuery<A::Address, Function=FunctionImpl<A::Location>>,
+{
+    /// slice through calls: `calls` is call sites and their targets, as in
+    /// `InstructionModifiers::calls`, and `functions` is where to find the targets' layouts. this
+    /// only helps if the `SSA` was built with modifiers that describe those calls.
+    pub fn with_calls<NewF: FunctionQuery<A::Address, Function=FunctionImpl<A::Location>>>(self, calls: &'a HashMap<A::Address, A::Address>, functions: &'a NewF) -> Slicer<'a, A, NewF> {
+        Slicer {
+            ssa: self.ssa,
+            calls: Some((calls, functions)),
+            control: self.control,
+        }
+    }
+
+    /// follow control dependence as well as data flow. `cfg` must be what the `SSA` was built
+    /// from, and `control_dependence` computed from it.
+    pub fn with_control_dependence(self, cfg: &'a ControlFlowGraph<A::Address>, control_dependence: &'a ControlDependenceGraph<A::Address>) -> Self {
+        Slicer {
+            control: Some((cfg, control_dependence)),
+            ..self
+        }
+    }
+
+    /// the backward slice of `loc` at `addr`: the value read there if there is one, either by the
+    /// instruction or a modifier around it, and otherwise the value written there.
+    pub fn backward_at(&self, addr: A::Address, loc: A::Location) -> Option<Slice<A>> {
+        value_at(self.ssa, addr, &loc, Direction::Read)
+            .or_else(|| value_at(self.ssa, addr, &loc, Direction::Write))
+            .map(|value| self.backward(value))
+    }
+
+    /// the forward slice of `loc` at `addr`: the value written there if there is one, and
+    /// otherwise the value read there.
+    pub fn forward_at(&self, addr: A::Address, loc: A::Location) -> Option<Slice<A>> {
+        value_at(self.ssa, addr, &loc, Direction::Write)
+            .or_else(|| value_at(self.ssa, addr, &loc, Direction::Read))
+            .map(|value| self.forward(value))
+    }
+
+    /// everything `value` is computed from.
+    pub fn backward(&self, value: DFGRef<A>) -> Slice<A> {
+        let mut walk = Walk::new(self.ssa);
+        let mut branches = HashSet::new();
+        walk.push(value);
+        while let Some(value) = walk.work.pop() {
+            let loc = self.ssa.value(value).location.clone();
+            // values without a def come from before the function, like `SSA::get_def_site`
+            // says.
+            let (addr, source) = self.ssa.get_def_site(value);
+            let site = match source {
+                DefSource::External => {
+                    walk.slice.inputs.insert(loc);
+                    continue;
+                }
+                DefSource::Phi => {
+                    if let Some(phi) = self.ssa.phi.get(&addr).and_then(|phis| phis.get(&loc)) {
+                        for operand in phi.ins.iter() {
+                            walk.push(*operand);
+                        }
+                    }
+                    self.control_dependencies(&mut walk, &mut branches, addr);
+                    continue;
+                }
+                DefSource::Instruction => UseSite::Instruction(addr),
+                DefSource::Modifier(precedence) => UseSite::Modifier(addr, precedence),
+                DefSource::Between(to) => UseSite::Between(addr, to),
+            };
+
+            // at a call, a return value comes from the arguments, and anything else the
+            // call writes comes from nowhere.
+            let call = self.call_at(&site);
+            let reads_from: Option<Vec<A::Location>> = match call {
+                Some((ref arguments, ref returns)) => {
+                    if covers(returns, &loc) {
+                        walk.slice.calls.insert(addr);
+                        Some(arguments.clone())
+                    } else {
+                        Some(vec![loc.clone()])
+                    }
+                }
+                None => None,
+            };
+            for ((read, dir), operand) in site_values(self.ssa, &site).into_iter().flat_map(|rwmap| rwmap.iter()) {
+                if *dir != Direction::Read || self.ssa.is_overwrite(&site, read) {
+                    continue;
+                }
+                if let Some(reads_from) = reads_from.as_ref() {
+                    if !covers(reads_from, read) {
+                        continue;
+                    }
+                }
+                walk.push(*operand);
+            }
+
+            match site {
+                UseSite::Instruction(addr) => {
+                    walk.slice.instructions.insert(addr);
+                    self.control_dependencies(&mut walk, &mut branches, addr);
+                }
+                UseSite::Modifier(addr, _) => {
+                    self.control_dependencies(&mut walk, &mut branches, addr);
+                }
+                UseSite::Between(from, _) => {
+                    // the edge decides this value, so the branch leaving `from` does too.
+                    if let Some(branch) = self.terminator(from) {
+                        if branches.insert(from) {
+                            walk.slice.instructions.insert(branch);
+                            walk.push_reads(branch);
+                        }
+                    }
+                    self.control_dependencies(&mut walk, &mut branches, from);
+                }
+                UseSite::Phi(_, _) => { }
+            }
+        }
+        walk.slice
+    }
+
+    /// everything computed from `value`.
+    pub fn forward(&self, value: DFGRef<A>) -> Slice<A> {
+        let mut walk = Walk::new(self.ssa);
+        let mut branches = HashSet::new();
+        walk.push(value);
+        while let Some(value) = walk.work.pop() {
+            let loc = self.ssa.value(value).location.clone();
+            for site in self.ssa.uses_of(value).iter() {
+                match site {
+                    UseSite::Phi(block, phi_loc) => {
+                        if let Some(phi) = self.ssa.phi.get(block).and_then(|phis| phis.get(phi_loc)) {
+                            walk.push(phi.out);
+                        }
+                        continue;
+                    }
+                    UseSite::Instruction(addr) => {
+                        walk.slice.instructions.insert(*addr);
+                        self.control_dependents(&mut walk, &mut branches, *addr);
+                    }
+                    UseSite::Modifier(_, _) |
+                    UseSite::Between(_, _) => { }
+                }
+
+                let writes_to: Option<Vec<A::Location>> = match self.call_at(site) {
+                    Some((ref arguments, ref returns)) => {
+                        if covers(arguments, &loc) {
+                            if let UseSite::Modifier(addr, _) = site {
+                                walk.slice.calls.insert(*addr);
+                            }
+                            Some(returns.clone())
+                        } else {
+                            Some(vec![loc.clone()])
+                        }
+                    }
+                    None => None,
+                };
+                for ((written, dir), result) in site_values(self.ssa, site).into_iter().flat_map(|rwmap| rwmap.iter()) {
+                    if *dir != Direction::Write {
+                        continue;
+                    }
+                    if let Some(writes_to) = writes_to.as_ref() {
+                        if !covers(writes_to, written) {
+                            continue;
+                        }
+                    }
+                    walk.push(*result);
+                }
+            }
+        }
+        walk.slice
+    }
+
+    /// argument and return locations of the function called at `site`, if it's the modifier
+    /// after a call with a known callee.
+    fn call_at(&self, site: &UseSite<A::Address, A::Location>) -> Option<(Vec<A::Location>, Vec<A::Location>)> {
+        let addr = match site {
+            UseSite::Modifier(addr, Precedence::After) => addr,
+            _ => { return None; }
+        };
+        let (calls, functions) = self.calls?;
+        let function = functions.function_at(*calls.get(addr)?)?;
+        let layout = function.layout();
+        let arguments = layout.arguments.iter().filter_map(|loc| loc.clone()).collect();
+        let returns = layout.returns.iter().filter_map(|loc| loc.clone()).collect();
+        Some((arguments, returns))
+    }
+
+    /// the last instruction in the block starting at `block`, which is the branch for blocks with
+    /// more than one successor.
+    fn terminator(&self, block: A::Address) -> Option<A::Address> {
+        let (cfg, _) = self.control?;
+        let end = cfg.get_block(block).end;
+        self.ssa.instruction_values.keys()
+            .filter(|addr| **addr >= block && **addr <= end)
+            .max()
+            .cloned()
+    }
+
+    /// add the branches deciding if `addr` runs, and what they read.
+    fn control_dependencies(&self, walk: &mut Walk<A>, branches: &mut HashSet<A::Address>, addr: A::Address) {
+        let (cfg, control_dependence) = match self.control {
+            Some(control) => control,
+            None => { return; }
+        };
+        for branch_block in control_dependence.dependencies_of(cfg.get_block(addr).start) {
+            if !branches.insert(branch_block) {
+                continue;
+            }
+            if let Some(branch) = self.terminator(branch_block) {
+                walk.slice.instructions.insert(branch);
+                walk.push_reads(branch);
+                // the branch only runs if whatever it depends on says so.
+                self.control_dependencies(walk, branches, branch);
+            }
+        }
+    }
+
+    /// if `addr` is a branch, add everything it decides: instructions and phis in the blocks
+    /// control dependent on it, and what they write.
+    fn control_dependents(&self, walk: &mut Walk<A>, branches: &mut HashSet<A::Address>, addr: A::Address) {
+        let (cfg, control_dependence) = match self.control {
+            Some(control) => control,
+            None => { return; }
+        };
+        let block = cfg.get_block(addr).start;
+        if self.terminator(block) != Some(addr) || !branches.insert(block) {
+            return;
+        }
+        for dependent in control_dependence.dependents_of(block) {
+            let end = cfg.get_block(dependent).end;
+            let instructions: Vec<A::Address> = self.ssa.instruction_values.keys()
+                .filter(|addr| **addr >= dependent && **addr <= end)
+                .cloned()
+                .collect();
+            for instruction in instructions {
+                walk.slice.instructions.insert(instruction);
+                walk.push_writes(instruction);
+                self.control_dependents(walk, branches, instruction);
+            }
+            if let Some(phis) = self.ssa.phi.get(&dependent) {
+                for phi in phis.values() {
+                    walk.push(phi.out);
+                }
+            }
+        }
+    }
+}
+
+/// values reached so far, and values whose neighbors haven't been looked at yet.
+struct Walk<'ssa, A: SSAValues> {
+    ssa: &'ssa SSA<A>,
+    slice: Slice<A>,
+    work: Vec<DFGRef<A>>,
+}
+
+impl<'ssa, A: SSAValues> Walk<'ssa, A> {
+    fn new(ssa: &'ssa SSA<A>) -> Self {
+        Walk {
+            ssa,
+            slice: Slice::new(),
+            work: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, value: DFGRef<A>) {
+        if self.slice.values.insert(value) {
+            self.work.push(value);
+        }
+    }
+
+    fn push_reads(&mut self, addr: A::Address) {
+        self.push_instruction(addr, Direction::Read);
+    }
+
+    fn push_writes(&mut self, addr: A::Address) {
+        self.push_instruction(addr, Direction::Write);
+    }
+
+    fn push_instruction(&mut self, addr: A::Address, direction: Direction) {
+        let site = UseSite::Instruction(addr);
+        let values: Vec<DFGRef<A>> = match self.ssa.instruction_values.get(&addr) {
+            Some(rwmap) => {
+                rwmap.iter()
+                    .filter(|((loc, dir), _)| *dir == direction && !(direction == Direction::Read && self.ssa.is_overwrite(&site, loc)))
+                    .map(|(_, value)| *value)
+                    .collect()
+            }
+            None => { return; }
+        };
+        for value in values {
+            self.push(value);
+        }
+    }
+}
+
+/// the reads and writes at `site`. phis aren't in an `RWMap`, so they have none.
+fn site_values<'ssa, A: SSAValues>(ssa: &'ssa SSA<A>, site: &UseSite<A::Address, A::Location>) -> Option<&'ssa RWMap<A>> {
+    match site {
+        UseSite::Instruction(addr) => ssa.instruction_values.get(addr),
+        UseSite::Modifier(addr, precedence) => ssa.modifier_values.get(&(*addr, *precedence)),
+        UseSite::Between(from, to) => ssa.control_dependent_values.get(from).and_then(|edges| edges.get(to)),
+        UseSite::Phi(_, _) => None,
+    }
+}
+
+/// the value of `loc` in `direction` at `addr`, looking at the instruction before the modifiers
+/// around it. reads that only link a write to the value it replaces don't count.
+fn value_at<A: SSAValues>(ssa: &SSA<A>, addr: A::Address, loc: &A::Location, direction: Direction) -> Option<DFGRef<A>> {
+    let sites = [
+        UseSite::Instruction(addr),
+        UseSite::Modifier(addr, Precedence::Before),
+        UseSite::Modifier(addr, Precedence::After),
+    ];
+    sites.iter()
+        .filter(|site| direction == Direction::Write || !ssa.is_overwrite(site, loc))
+        .filter_map(|site| site_values(ssa, site).and_then(|rwmap| rwmap.get(&(loc.clone(), direction))))
+        .next()
+        .cloned()
+}
+
+/// whether `loc` is one of `locs`, or part of one.
+pub(crate) fn covers<L: PartialEq + AliasInfo>(locs: &[L], loc: &L) -> bool {
+    locs.iter().any(|other| other == loc || other.aliases_of().contains(loc))
+}
+
+#[test]
+fn test_slicing() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::Parameter;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+    use data::modifier::InstructionModifiers;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x89, 0xd6,               // 0x00: mov rsi, rdx
+        0x48, 0x83, 0xc6, 0x08,         // 0x03: add rsi, 8
+        0x48, 0x89, 0xcf,               // 0x07: mov rdi, rcx
+        0xe8, 0x11, 0x00, 0x00, 0x00,   // 0x0a: call 0x20
+        0x48, 0x89, 0xc5,               // 0x0f: mov rbp, rax
+        0xc3,                           // 0x12: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+
+    let mut callee = FunctionImpl::new("alloc".to_string());
+    callee.append_arg((Some(Location::rdi()), Parameter::of("base")));
+    callee.append_arg((Some(Location::rsi()), Parameter::of("len")));
+    callee.layout_mut().returns.push(Some(Location::rax()));
+    x86_64_data.contexts.functions.borrow_mut().insert(0x20, callee);
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    modifiers.calls.insert(0x0a, 0x20);
+
+    let functions = x86_64_data.contexts.functions.borrow();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*functions,
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+
+    let slicer = Slicer::new(&dfg).with_calls(&modifiers.calls, &*functions);
+
+    // rbp is whatever `alloc` returned, which comes from both of its arguments.
+    let backward = slicer.backward_at(0x0f, Location::rbp()).unwrap();
+    assert_eq!(backward.instructions.iter().cloned().collect::<Vec<u64>>(), vec![0x00, 0x03, 0x07, 0x0f]);
+    assert_eq!(backward.calls.iter().cloned().collect::<Vec<u64>>(), vec![0x0a]);
+    // reads of a register are reads of everything it overlaps, so `ecx` and `edx` come along.
+    assert!(backward.inputs.contains(&Location::rcx()));
+    assert!(backward.inputs.contains(&Location::rdx()));
+    assert!(!backward.inputs.contains(&Location::rsi()));
+    assert!(backward.contains(&dfg.get_def(0x03, Location::rsi()).as_rc()));
+    assert!(!backward.contains(&dfg.get_def(0x03, Location::ZF).as_rc()));
+
+    // the length goes through `alloc` into rbp, but the flags `add` sets go nowhere.
+    let forward = slicer.forward_at(0x00, Location::rdx()).unwrap();
+    assert_eq!(forward.instructions.iter().cloned().collect::<Vec<u64>>(), vec![0x00, 0x03, 0x0f]);
+    assert!(forward.contains(&dfg.get_def(0x0f, Location::rbp()).as_rc()));
+    assert!(!forward.contains(&dfg.get_def(0x07, Location::rdi()).as_rc()));
+
+    // without knowing about calls, slices stop at the modifier after the call.
+    let backward = Slicer::new(&dfg).backward_at(0x0f, Location::rbp()).unwrap();
+    assert!(backward.calls.is_empty());
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use analyses::{Value, ValueRes};
+
+/// an SMT-LIB2 term over 64-bit bitvectors, built up by evaluating some instruction semantics with
+/// `Rc<Term>` as the `Value`. like every other `Value`, values are 64 bits wide and comparisons
+/// are signed, producing `1` or `0`.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum Term {
+    /// something the semantics don't model. every `Unknown` is its own unconstrained constant.
+    Unknown,
+    Const(i64),
+    /// a constant declared or defined in the script, by name.
+    Symbol(String),
+    /// `(op args..)`. `op` may be an indexed identifier, like `(_ extract 7 0)`.
+    Apply(String, Vec<Rc<Term>>),
+}
+
+/// the sorts of constants in an `SmtScript`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Sort {
+    /// `(_ BitVec 64)`, for registers and flags.
+    BitVec,
+    /// `(Array (_ BitVec 64) (_ BitVec 8))`, a byte-addressed memory.
+    Memory,
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sort::BitVec => f.write_str("(_ BitVec 64)"),
+            Sort::Memory => f.write_str("(Array (_ BitVec 64) (_ BitVec 8))"),
+        }
+    }
+}
+
+impl Term {
+    pub fn symbol(name: String) -> Rc<Term> {
+        Rc::new(Term::Symbol(name))
+    }
+
+    pub fn apply(op: &str, args: &[&Rc<Term>]) -> Rc<Term> {
+        Rc::new(Term::Apply(op.to_string(), args.iter().map(|arg| Rc::clone(arg)).collect()))
+    }
+
+    /// `1` if the `Bool`-sorted `condition` holds, `0` if it doesn't.
+    fn boolean(condition: Rc<Term>) -> Rc<Term> {
+        Term::apply("ite", &[&condition, &Rc::new(Term::Const(1)), &Rc::new(Term::Const(0))])
+    }
+
+    /// if `self` is a `0`-or-`1` value built by `Term::boolean`, the condition it's built from.
+    fn condition(&self) -> Option<(&Rc<Term>, bool)> {
+        if let Term::Apply(op, args) = self {
+            if op == "ite" && args.len() == 3 {
+                match (&*args[1], &*args[2]) {
+                    (Term::Const(1), Term::Const(0)) => { return Some((&args[0], true)); }
+                    (Term::Const(0), Term::Const(1)) => { return Some((&args[0], false)); }
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// a `Bool`-sorted term that holds when `self` is nonzero, or zero if `holds` is false.
+    pub fn truth(self: &Rc<Self>, holds: bool) -> Rc<Term> {
+        if let Some((condition, positive)) = self.condition() {
+            if positive == holds {
+                Rc::clone(condition)
+            } else {
+                Term::apply("not", &[condition])
+            }
+        } else {
+            let zero = Rc::new(Term::Const(0));
+            Term::apply(if holds { "distinct" } else { "=" }, &[self, &zero])
+        }
+    }
+
+    fn binary<F: Fn(i64, i64) -> Option<i64>>(self: &Rc<Self>, op: &str, other: &Rc<Self>, fold: F) -> Rc<Term> {
+        match (self.to_const(), other.to_const()) {
+            (Some(l), Some(r)) => match fold(l, r) {
+                Some(value) => Rc::new(Term::Const(value)),
+                None => Rc::new(Term::Unknown),
+            },
+            _ => Term::apply(op, &[self, other]),
+        }
+    }
+
+    fn compare<F: Fn(i64, i64) -> bool>(self: &Rc<Self>, op: &str, other: &Rc<Self>, fold: F) -> Rc<Term> {
+        match (self.to_const(), other.to_const()) {
+            (Some(l), Some(r)) => Rc::new(Term::Const(fold(l, r) as i64)),
+            _ => Term::boolean(Term::apply(op, &[self, other])),
+        }
+    }
+
+    /// `self`, extended from its low `width` bits with `extension` (`sign_extend` or
+    /// `zero_extend`).
+    fn extend(self: &Rc<Self>, extension: &str, width: &Rc<Self>) -> Rc<Term> {
+        match width.to_const() {
+            Some(width) if width >= 64 => Rc::clone(self),
+            Some(width @ 1..=63) => {
+                let low = Term::apply(&format!("(_ extract {} 0)", width - 1), &[self]);
+                Term::apply(&format!("(_ {} {})", extension, 64 - width), &[&low])
+            }
+            _ => Rc::new(Term::Unknown),
+        }
+    }
+
+    fn rotate(self: &Rc<Self>, rotation: &str, amount: &Rc<Self>) -> Rc<Term> {
+        match amount.to_const() {
+            Some(amount) if amount >= 0 => {
+                Term::apply(&format!("(_ {} {})", rotation, amount % 64), &[self])
+            }
+            _ => Rc::new(Term::Unknown),
+        }
+    }
+
+    /// write `self` to `out`. each `Unknown` gets a fresh constant from `script`, declared before
+    /// whatever command `self` ends up in.
+    fn render(&self, script: &mut SmtScript, out: &mut String) {
+        match self {
+            Term::Unknown => {
+                let name = script.fresh(Sort::BitVec);
+                out.push_str(&quoted(&name));
+            }
+            Term::Const(value) => {
+                write!(out, "#x{:016x}", *value as u64).unwrap();
+            }
+            Term::Symbol(name) => {
+                out.push_str(&quoted(name));
+            }
+            Term::Apply(op, args) => {
+                write!(out, "({}", op).unwrap();
+                for arg in args.iter() {
+                    out.push(' ');
+                    arg.render(script, out);
+                }
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// `name` as a quoted SMT-LIB2 symbol, so names can have any characters but `|` and `\`.
+fn quoted(name: &str) -> String {
+    format!("|{}|", name.replace(['|', '\\'], "_"))
+}
+
+impl Value for Rc<Term> {
+    fn unknown() -> Self {
+        Rc::new(Term::Unknown)
+    }
+
+    fn from_const(c: i64) -> Self {
+        Rc::new(Term::Const(c))
+    }
+
+    fn from_set(xs: &[Self]) -> Self {
+        match xs.split_first() {
+            Some((first, rest)) if **first != Term::Unknown && rest.iter().all(|x| x == first) => {
+                Rc::clone(first)
+            }
+            _ => Self::unknown(),
+        }
+    }
+
+    fn to_const(&self) -> Option<i64> {
+        if let Term::Const(c) = **self {
+            Some(c)
+        } else {
+            None
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        self.to_const().map(|x| x != 0)
+    }
+
+    fn add(&self, other: &Self) -> ValueRes<Self> {
+        let value = self.binary("bvadd", other, |l, r| Some(l.wrapping_add(r)));
+        let carry = value.compare("bvult", self, |sum, l| (sum as u64) < (l as u64));
+        ValueRes { value, carry }
+    }
+
+    fn sub(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes {
+            value: self.binary("bvsub", other, |l, r| Some(l.wrapping_sub(r))),
+            carry: self.compare("bvult", other, |l, r| (l as u64) < (r as u64)),
+        }
+    }
+
+    fn mul(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes {
+            value: self.binary("bvmul", other, |l, r| Some(l.wrapping_mul(r))),
+            carry: Self::unknown(),
+        }
+    }
+
+    fn or(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.binary("bvor", other, |l, r| Some(l | r)))
+    }
+
+    fn and(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.binary("bvand", other, |l, r| Some(l & r)))
+    }
+
+    fn xor(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.binary("bvxor", other, |l, r| Some(l ^ r)))
+    }
+
+    fn modulo(&self, other: &Self) -> Self {
+        self.binary("bvsrem", other, |l, r| l.checked_rem(r))
+    }
+
+    fn ne(&self, other: &Self) -> Self {
+        self.compare("distinct", other, |l, r| l != r)
+    }
+
+    fn le(&self, other: &Self) -> Self {
+        self.compare("bvsle", other, |l, r| l <= r)
+    }
+
+    fn lt(&self, other: &Self) -> Self {
+        self.compare("bvslt", other, |l, r| l < r)
+    }
+
+    fn eq(&self, other: &Self) -> Self {
+        self.compare("=", other, |l, r| l == r)
+    }
+
+    fn not(&self) -> Self {
+        match self.to_const() {
+            Some(c) => Rc::new(Term::Const((c == 0) as i64)),
+            None => Term::boolean(self.truth(false)),
+        }
+    }
+
+    fn sxt(&self, width: &Self) -> Self {
+        self.extend("sign_extend", width)
+    }
+
+    fn zxt(&self, width: &Self) -> Self {
+        self.extend("zero_extend", width)
+    }
+
+    fn shr(&self, amt: &Self) -> Self {
+        self.binary("bvlshr", amt, |l, r| (l as u64).checked_shr(r as u32).map(|x| x as i64))
+    }
+
+    fn sar(&self, amt: &Self) -> Self {
+        self.binary("bvashr", amt, |l, r| l.checked_shr(r as u32))
+    }
+
+    fn shl(&self, amt: &Self) -> Self {
+        self.binary("bvshl", amt, |l, r| l.checked_shl(r as u32))
+    }
+
+    fn sal(&self, amt: &Self) -> Self {
+        self.shl(amt)
+    }
+
+    fn rol(&self, amt: &Self) -> Self {
+        self.rotate("rotate_left", amt)
+    }
+
+    fn ror(&self, amt: &Self) -> Self {
+        self.rotate("rotate_right", amt)
+    }
+
+    fn select(&self, then: Self, otherwise: Self) -> Self {
+        match self.as_bool() {
+            Some(true) => then,
+            Some(false) => otherwise,
+            None => Term::apply("ite", &[&self.truth(true), &then, &otherwise]),
+        }
+    }
+}
+
+/// an SMT-LIB2 script, built one command at a time. rendered, it sets the logic, has every
+/// command in the order they were added, and ends with `(check-sat)`.
+#[derive(Debug, Default)]
+pub struct SmtScript {
+    commands: Vec<String>,
+    fresh: usize,
+}
+
+impl SmtScript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a new constant of sort `sort`, with nothing said about it.
+    pub fn fresh(&mut self, sort: Sort) -> String {
+        self.fresh += 1;
+        let name = format!("unknown_{}", self.fresh);
+        self.declare(&name, sort);
+        name
+    }
+
+    pub fn declare(&mut self, name: &str, sort: Sort) {
+        self.commands.push(format!("(declare-const {} {})", quoted(name), sort));
+    }
+
+    /// declare `name`, and that it's equal to `term`.
+    pub fn define(&mut self, name: &str, sort: Sort, term: &Rc<Term>) {
+        let mut body = String::new();
+        term.render(self, &mut body);
+        self.commands.push(format!("(define-fun {} () {} {})", quoted(name), sort, body));
+    }
+
+    /// assert the `Bool`-sorted `term`. `Term::truth` gets one of those from a value.
+    pub fn assert(&mut self, term: &Rc<Term>) {
+        let mut body = String::new();
+        term.render(self, &mut body);
+        self.commands.push(format!("(assert {})", body));
+    }
+
+    pub fn comment(&mut self, text: &str) {
+        self.commands.push(format!("; {}", text.replace('\n', " ")));
+    }
+}
+
+impl fmt::Display for SmtScript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "(set-logic QF_ABV)")?;
+        for command in self.commands.iter() {
+            writeln!(f, "{}", command)?;
+        }
+        writeln!(f, "(check-sat)")
+    }
+}
+
+#[test]
+fn test_terms() {
+    let x = Term::symbol("x".to_string());
+    let one = Rc::<Term>::from_const(1);
+
+    // constants fold, so semantics can still tell where branches go.
+    assert_eq!(one.add(&one).value().to_const(), Some(2));
+    assert_eq!(one.sub(&Rc::<Term>::from_const(2)).carry().to_const(), Some(1));
+    assert_eq!(Value::eq(&x, &one).not().to_const(), None);
+
+    let mut script = SmtScript::new();
+    script.declare("x", Sort::BitVec);
+    let is_one = Value::eq(&x, &one);
+    script.assert(&is_one.truth(true));
+    script.assert(&is_one.not().truth(true));
+    script.define("y", Sort::BitVec, &x.add(&Rc::<Term>::unknown()).value());
+    script.define("z", Sort::BitVec, &x.sxt(&Rc::<Term>::from_const(8)));
+    assert_eq!(script.to_string(), "\
+(set-logic QF_ABV)
+(declare-const |x| (_ BitVec 64))
+(assert (= |x| #x0000000000000001))
+(assert (not (= |x| #x0000000000000001)))
+(declare-const |unknown_1| (_ BitVec 64))
+(define-fun |y| () (_ BitVec 64) (bvadd |x| |unknown_1|))
+(define-fun |z| () (_ BitVec 64) ((_ sign_extend 56) ((_ extract 7 0) |x|)))
+(check-sat)
+");
+}
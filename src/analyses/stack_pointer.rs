@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use yaxpeax_arch::Address;
+
+use analyses::control_flow::ControlFlowGraph;
+use arch::{AbiDefaults, DecodeFrom, FunctionImpl, InstructionSpan};
+use data::ValueLocations;
+use memory::MemoryRange;
+
+/// where a stack or frame pointer is, relative to where the stack pointer was when the function
+/// was entered.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum StackDelta {
+    Known(i64),
+    /// it was moved by some amount that isn't known statically, like by an `alloca`, or paths
+    /// here disagree about where it is.
+    Unknown,
+}
+
+impl StackDelta {
+    pub fn known(&self) -> Option<i64> {
+        match self {
+            StackDelta::Known(delta) => Some(*delta),
+            StackDelta::Unknown => None,
+        }
+    }
+
+    fn offset(&self, amount: i64) -> StackDelta {
+        match self {
+            StackDelta::Known(delta) => StackDelta::Known(delta.wrapping_add(amount)),
+            StackDelta::Unknown => StackDelta::Unknown,
+        }
+    }
+
+    fn join(&self, other: &StackDelta) -> StackDelta {
+        if self == other {
+            *self
+        } else {
+            StackDelta::Unknown
+        }
+    }
+}
+
+impl fmt::Display for StackDelta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StackDelta::Known(0) => write!(f, "entry"),
+            StackDelta::Known(delta) if *delta < 0 => write!(f, "entry - {:#x}", delta.unsigned_abs()),
+            StackDelta::Known(delta) => write!(f, "entry + {:#x}", delta),
+            StackDelta::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// the stack and frame pointers at some point in a function.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct StackState {
+    pub stack: StackDelta,
+    /// the frame pointer only means something once a function sets it up; until then it's
+    /// whatever the caller left there, which is `Unknown`.
+    pub frame: StackDelta,
+}
+
+impl StackState {
+    /// how things are at a function's entrypoint.
+    pub fn entry() -> StackState {
+        StackState {
+            stack: StackDelta::Known(0),
+            frame: StackDelta::Unknown,
+        }
+    }
+
+    fn join(&self, other: &StackState) -> StackState {
+        StackState {
+            stack: self.stack.join(&other.stack),
+            frame: self.frame.join(&other.frame),
+        }
+    }
+}
+
+/// what an instruction sets a stack or frame pointer to, in terms of where both were before it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Adjustment {
+    /// moved by this many bytes. `Add(0)` leaves it alone.
+    Add(i64),
+    /// set to the stack pointer plus this many bytes.
+    FromStack(i64),
+    /// set to the frame pointer plus this many bytes.
+    FromFrame(i64),
+    /// set to something that isn't known statically.
+    Unknown,
+}
+
+impl Adjustment {
+    fn apply(&self, current: StackDelta, state: &StackState) -> StackDelta {
+        match self {
+            Adjustment::Add(amount) => current.offset(*amount),
+            Adjustment::FromStack(amount) => state.stack.offset(*amount),
+            Adjustment::FromFrame(amount) => state.frame.offset(*amount),
+            Adjustment::Unknown => StackDelta::Unknown,
+        }
+    }
+}
+
+/// how an instruction changes the stack and frame pointers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StackEffect {
+    pub stack: Adjustment,
+    pub frame: Adjustment,
+}
+
+impl StackEffect {
+    pub fn none() -> StackEffect {
+        StackEffect {
+            stack: Adjustment::Add(0),
+            frame: Adjustment::Add(0),
+        }
+    }
+
+    /// the stack pointer moves by `amount` bytes, and nothing else changes.
+    pub fn adjust(amount: i64) -> StackEffect {
+        StackEffect {
+            stack: Adjustment::Add(amount),
+            frame: Adjustment::Add(0),
+        }
+    }
+
+    pub fn with_stack(mut self, stack: Adjustment) -> StackEffect {
+        self.stack = stack;
+        self
+    }
+
+    pub fn with_frame(mut self, frame: Adjustment) -> StackEffect {
+        self.frame = frame;
+        self
+    }
+
+    pub fn apply(&self, state: &StackState) -> StackState {
+        StackState {
+            stack: self.stack.apply(state.stack, state),
+            frame: self.frame.apply(state.frame, state),
+        }
+    }
+}
+
+/// how many bytes of arguments a function pops off the stack as it returns, beyond its return
+/// address.
+pub trait StackCleanup<Addr> {
+    fn stack_cleanup(&self, function: Addr) -> u64;
+}
+
+impl<Addr: Address, Loc: AbiDefaults + PartialEq> StackCleanup<Addr> for HashMap<Addr, FunctionImpl<Loc>> {
+    fn stack_cleanup(&self, function: Addr) -> u64 {
+        self.get(&function).map(|f| f.stack_cleanup()).unwrap_or(0)
+    }
+}
+
+/// for when nothing is known about the functions being called: they all leave the stack as they
+/// found it.
+#[derive(Default)]
+pub struct NoCleanup;
+
+impl<Addr> StackCleanup<Addr> for NoCleanup {
+    fn stack_cleanup(&self, _function: Addr) -> u64 {
+        0
+    }
+}
+
+/// what an architecture needs to provide for `stack_deltas`.
+pub trait StackSemantics: ValueLocations {
+    /// the location holding the stack pointer.
+    fn stack_pointer() -> Self::Location;
+
+    /// how `instr` at `addr` changes the stack and frame pointers. calls should leave the stack
+    /// pointer where the callee returns it, which is where it was before the call for most
+    /// calling conventions, and `cleanup.stack_cleanup(callee)` bytes further for the rest.
+    /// instructions that move the stack pointer by an amount that isn't known statically, like
+    /// subtracting a register, should adjust it by `Adjustment::Unknown`.
+    fn stack_effect<C: StackCleanup<Self::Address> + ?Sized>(addr: Self::Address, instr: &Self::Instruction, cleanup: &C) -> StackEffect;
+}
+
+/// the results of `stack_deltas`.
+#[derive(Debug)]
+pub struct StackDeltas<A: StackSemantics> {
+    /// the stack and frame pointers before each instruction reachable from the entrypoint.
+    pub before: BTreeMap<A::Address, StackState>,
+    /// and after each of those instructions.
+    pub after: BTreeMap<A::Address, StackState>,
+    /// instructions that move the stack pointer by an amount that isn't known statically.
+    pub dynamic: BTreeSet<A::Address>,
+    /// blocks that are reached with the stack pointer in different places depending on the path
+    /// taken to them.
+    pub conflicts: BTreeSet<A::Address>,
+}
+
+impl<A: StackSemantics> StackDeltas<A> {
+    /// the stack pointer before the instruction at `addr`, if it's reachable.
+    pub fn at(&self, addr: A::Address) -> Option<StackDelta> {
+        self.before.get(&addr).map(|state| state.stack)
+    }
+
+    /// the frame pointer before the instruction at `addr`, if it's reachable.
+    pub fn frame_at(&self, addr: A::Address) -> Option<StackDelta> {
+        self.before.get(&addr).map(|state| state.frame)
+    }
+
+    /// the stack pointer before the instruction at `addr`, described like `rsp = entry - 0x48`.
+    pub fn annotation(&self, addr: A::Address) -> Option<String> where A::Location: fmt::Display {
+        self.at(addr).map(|delta| format!("{} = {}", A::stack_pointer(), delta))
+    }
+}
+
+/// find the stack pointer at every instruction in `cfg` relative to where it was at
+/// `cfg.entrypoint`. frame pointers are tracked alongside, so that a stack pointer restored from
+/// the frame pointer is known again even if it was unknown between the two, like around an
+/// `alloca` in a function with a frame pointer.
+pub fn stack_deltas<A, M, C>(data: &M, cfg: &ControlFlowGraph<A::Address>, cleanup: &C) -> StackDeltas<A> where
+    A: StackSemantics + DecodeFrom<M>,
+    A::Address: petgraph::graphmap::NodeTrait,
+    M: MemoryRange<A>,
+    C: StackCleanup<A::Address> + ?Sized,
+{
+    let mut deltas = StackDeltas {
+        before: BTreeMap::new(),
+        after: BTreeMap::new(),
+        dynamic: BTreeSet::new(),
+        conflicts: BTreeSet::new(),
+    };
+
+    let mut entries: HashMap<A::Address, StackState> = HashMap::new();
+    entries.insert(cfg.entrypoint, StackState::entry());
+    let mut work = vec![cfg.entrypoint];
+
+    while let Some(start) = work.pop() {
+        let block = cfg.get_block(start);
+        let mut state = entries[&start];
+        let mut iter = A::instructions_spanning(data, block.start, block.end);
+        while let Some((addr, instr)) = iter.next() {
+            let effect = A::stack_effect(addr, instr, cleanup);
+            if effect.stack == Adjustment::Unknown {
+                deltas.dynamic.insert(addr);
+            }
+            deltas.before.insert(addr, state);
+            state = effect.apply(&state);
+            deltas.after.insert(addr, state);
+        }
+
+        for next in cfg.destinations(start) {
+            let merged = match entries.get(&next) {
+                Some(existing) => {
+                    let merged = existing.join(&state);
+                    if merged.stack != existing.stack {
+                        deltas.conflicts.insert(next);
+                    }
+                    if merged == *existing {
+                        continue;
+                    }
+                    merged
+                }
+                None => state,
+            };
+            entries.insert(next, merged);
+            work.push(next);
+        }
+    }
+
+    deltas
+}
+
+#[test]
+fn test_stack_deltas() {
+    use analyses::control_flow;
+    use arch::x86_64::x86_64Data;
+    use yaxpeax_x86::x86_64;
+
+    let data: Vec<u8> = vec![
+        0x55,                           // 0x00: push rbp
+        0x48, 0x89, 0xe5,               // 0x01: mov rbp, rsp
+        0x53,                           // 0x04: push rbx
+        0x48, 0x83, 0xec, 0x28,         // 0x05: sub rsp, 0x28
+        0x6a, 0x01,                     // 0x09: push 1
+        0x6a, 0x02,                     // 0x0b: push 2
+        0xe8, 0xee, 0x0f, 0x00, 0x00,   // 0x0d: call 0x1000
+        0x48, 0x85, 0xff,               // 0x12: test rdi, rdi
+        0x74, 0x03,                     // 0x15: je 0x1a
+        0x48, 0x29, 0xfc,               // 0x17: sub rsp, rdi
+        0x48, 0x8d, 0x65, 0xf8,         // 0x1a: lea rsp, [rbp - 0x8]
+        0x5b,                           // 0x1e: pop rbx
+        0xc9,                           // 0x1f: leave
+        0xc3,                           // 0x20: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+
+    // the function at 0x1000 pops its two arguments.
+    let mut callee = FunctionImpl::new("callee".to_string());
+    callee.set_stack_cleanup(0x10);
+    x86_64_data.contexts.functions.borrow_mut().insert(0x1000, callee);
+
+    let deltas: StackDeltas<x86_64> = stack_deltas(&data, &cfg, &*x86_64_data.contexts.functions.borrow());
+    assert_eq!(deltas.at(0x00), Some(StackDelta::Known(0)));
+    assert_eq!(deltas.frame_at(0x04), Some(StackDelta::Known(-0x8)));
+    assert_eq!(deltas.annotation(0x0d), Some("rsp = entry - 0x48".to_string()));
+    assert_eq!(deltas.at(0x12), Some(StackDelta::Known(-0x38)));
+    // `sub rsp, rdi` is an alloca, and only happens on one path to 0x1a ..
+    assert_eq!(deltas.dynamic.iter().cloned().collect::<Vec<u64>>(), vec![0x17]);
+    assert_eq!(deltas.conflicts.iter().cloned().collect::<Vec<u64>>(), vec![0x1a]);
+    assert_eq!(deltas.at(0x1a), Some(StackDelta::Unknown));
+    // .. but the frame pointer says where the stack is again after it.
+    assert_eq!(deltas.at(0x1e), Some(StackDelta::Known(-0x10)));
+    assert_eq!(deltas.at(0x20), Some(StackDelta::Known(0)));
+    assert_eq!(deltas.frame_at(0x20), Some(StackDelta::Unknown));
+    assert_eq!(deltas.after[&0x20].stack, StackDelta::Known(8));
+
+    // without knowing about the callee, its arguments stay on the stack.
+    let deltas: StackDeltas<x86_64> = stack_deltas(&data, &cfg, &NoCleanup);
+    assert_eq!(deltas.at(0x12), Some(StackDelta::Known(-0x48)));
+}
+
+#[test]
+fn test_stack_effects() {
+    use num_traits::Zero;
+    use yaxpeax_arch::{Decoder, Reader, U8Reader};
+    use yaxpeax_arm::armv7::ARMv7;
+    use yaxpeax_arm::armv8::a64::ARMv8;
+    use yaxpeax_msp430::MSP430;
+
+    // the stack and frame pointers before each instruction in `bytes`, and after the last.
+    fn walk<A: StackSemantics>(bytes: &[u8]) -> Vec<(StackDelta, StackDelta)> where for<'a> U8Reader<'a>: Reader<A::Address, A::Word> {
+        let decoder = A::Decoder::default();
+        let mut reader = U8Reader::new(bytes);
+        let mut state = StackState::entry();
+        let mut states = vec![(state.stack, state.frame)];
+        while let Ok(instr) = decoder.decode(&mut reader) {
+            state = A::stack_effect(A::Address::zero(), &instr, &NoCleanup).apply(&state);
+            states.push((state.stack, state.frame));
+        }
+        states
+    }
+
+    use self::StackDelta::{Known, Unknown};
+
+    let armv7 = walk::<ARMv7>(&[
+        0x00, 0x48, 0x2d, 0xe9,     // push {r11, lr}
+        0x04, 0xb0, 0x8d, 0xe2,     // add r11, sp, #4
+        0x08, 0xd0, 0x4d, 0xe2,     // sub sp, sp, #8
+        0x04, 0x40, 0x2d, 0xe5,     // str r4, [sp, #-4]!
+        0x04, 0x40, 0x9d, 0xe4,     // ldr r4, [sp], #4
+        0x00, 0xd0, 0x4d, 0xe0,     // sub sp, sp, r0
+        0x04, 0xd0, 0x4b, 0xe2,     // sub sp, r11, #4
+        0x00, 0x88, 0xbd, 0xe8,     // pop {r11, pc}
+    ]);
+    assert_eq!(armv7, vec![
+        (Known(0), Unknown),
+        (Known(-0x8), Unknown),
+        (Known(-0x8), Known(-0x4)),
+        (Known(-0x10), Known(-0x4)),
+        (Known(-0x14), Known(-0x4)),
+        (Known(-0x10), Known(-0x4)),
+        (Unknown, Known(-0x4)),
+        (Known(-0x8), Known(-0x4)),
+        (Known(0), Unknown),
+    ]);
+
+    let aarch64 = walk::<ARMv8>(&[
+        0xfd, 0x7b, 0xbf, 0xa9,     // stp x29, x30, [sp, #-0x10]!
+        0xfd, 0x03, 0x00, 0x91,     // mov x29, sp
+        0xff, 0x83, 0x00, 0xd1,     // sub sp, sp, #0x20
+        0x1f, 0x01, 0x00, 0x91,     // mov sp, x8
+        0xbf, 0x03, 0x00, 0x91,     // mov sp, x29
+        0xfd, 0x7b, 0xc1, 0xa8,     // ldp x29, x30, [sp], #0x10
+    ]);
+    assert_eq!(aarch64, vec![
+        (Known(0), Unknown),
+        (Known(-0x10), Unknown),
+        (Known(-0x10), Known(-0x10)),
+        (Known(-0x30), Known(-0x10)),
+        (Unknown, Known(-0x10)),
+        (Known(-0x10), Known(-0x10)),
+        (Known(0), Unknown),
+    ]);
+
+    let msp430 = walk::<MSP430>(&[
+        0x04, 0x12,                 // push r4
+        0x04, 0x41,                 // mov sp, r4
+        0x31, 0x80, 0x10, 0x00,     // sub #0x10, sp
+        0x01, 0x44,                 // mov r4, sp
+        0x34, 0x41,                 // pop r4
+        0x30, 0x41,                 // ret
+    ]);
+    assert_eq!(msp430, vec![
+        (Known(0), Unknown),
+        (Known(-0x2), Unknown),
+        (Known(-0x2), Known(-0x2)),
+        (Known(-0x12), Known(-0x2)),
+        (Known(-0x2), Known(-0x2)),
+        (Known(0), Unknown),
+        (Known(0x2), Unknown),
+    ]);
+}
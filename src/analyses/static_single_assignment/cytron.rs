@@ -6,15 +6,12 @@ use petgraph::graphmap::GraphMap;
 use petgraph;
 use petgraph::visit::Bfs;
 
-use std::cell::RefCell;
-use std::rc::Rc;
-
 use std::fmt::Debug;
 
 use analyses::control_flow::{BasicBlock, ControlFlowGraph};
 use memory::MemoryRange;
 
-use analyses::static_single_assignment::{HashedValue, DefSource, DFGRef, Value, SSA, SSAValues, PhiLocations};
+use analyses::static_single_assignment::{DefSource, DFGRef, RWMap, Value, ValueGraph, SSA, SSAValues, PhiLocations, UseSite};
 use analyses::static_single_assignment::data::PhiOp;
 use analyses::static_single_assignment::data::DFGRebase;
 use data::{AliasInfo, Direction, Disambiguator, LocIterator};
@@ -139,6 +136,11 @@ impl <'lad, A: crate::data::ValueLocations, LAD: LocationAliasDescriptions<A>> U
     }
 
     fn track_def(&mut self, loc: A::Location, addr: A::Address) {
+        // values are numbered for a location's aliases wherever it's written, so they need phis
+        // wherever its writes meet too.
+        for alias in loc.aliases_of() {
+            self.assignments.entry(alias).or_default().insert(addr);
+        }
         self.assignments.entry(loc.clone()).or_insert_with(|| HashSet::new()).insert(addr);
         self.track_use(loc);
     }
@@ -181,7 +183,7 @@ struct ValueAllocator<A: Arch + SSAValues> {
 }
 
 impl<A: Arch + SSAValues> ValueAllocator<A> {
-    fn from_use_tracker<'lad, LAD: LocationAliasDescriptions<A>>(tracker: UseDefTracker<'lad, A, LAD>) -> Self {
+    fn from_use_tracker<'lad, LAD: LocationAliasDescriptions<A>>(tracker: UseDefTracker<'lad, A, LAD>, values: &mut ValueGraph<A>) -> Self {
         let mut allocator = ValueAllocator {
             C: HashMap::new(),
             S: HashMap::new(),
@@ -189,13 +191,13 @@ impl<A: Arch + SSAValues> ValueAllocator<A> {
 
         for loc in tracker.all_locations {
             allocator.C.insert(loc.clone(), 0);
-            allocator.S.insert(loc.clone(), vec![Rc::new(RefCell::new(Value::new(loc.clone(), None)))]);
+            allocator.S.insert(loc.clone(), vec![values.alloc(Value::new(loc.clone(), None))]);
         }
 
         allocator
     }
 
-    fn track_external(&mut self, loc: A::Location, value: Rc<RefCell<Value<A>>>) {
+    fn track_external(&mut self, loc: A::Location, value: DFGRef<A>) {
         self.S.get_mut(&loc).expect("S has entries for locations").push(value);
     }
 
@@ -214,29 +216,30 @@ impl<A: Arch + SSAValues> ValueAllocator<A> {
         self.S.get_mut(&loc).expect("S has entries for locations").pop();
     }
 
-    pub fn alloc_value(&mut self, loc: A::Location) -> Rc<RefCell<Value<A>>> {
-        let new_ref = Rc::new(RefCell::new(self.new_value(loc.clone())));
-        self.S.get_mut(&loc).expect("S should have entries for all locations.").push(Rc::clone(&new_ref));
+    pub fn alloc_value(&mut self, values: &mut ValueGraph<A>, loc: A::Location) -> DFGRef<A> {
+        let new_ref = values.alloc(self.new_value(loc.clone()));
+        self.S.get_mut(&loc).expect("S should have entries for all locations.").push(new_ref);
         new_ref
     }
-    pub fn alloc_value_with<F: FnMut(Value<A>) -> Rc<RefCell<Value<A>>>>(&mut self, loc: A::Location, mut f: F) -> Rc<RefCell<Value<A>>> {
+    pub fn alloc_value_with<F: FnMut(Value<A>) -> DFGRef<A>>(&mut self, loc: A::Location, mut f: F) -> DFGRef<A> {
         let new_ref = f(self.new_value(loc.clone()));
-        self.S.get_mut(&loc).expect("S should have entries for all locations.").push(Rc::clone(&new_ref));
+        self.S.get_mut(&loc).expect("S should have entries for all locations.").push(new_ref);
         new_ref
     }
 
-    pub fn current(&self, loc: &A::Location) -> &Rc<RefCell<Value<A>>> {
+    pub fn current(&self, loc: &A::Location) -> &DFGRef<A> {
         let stack = &self.S[loc];
         &stack[stack.len() - 1]
     }
 
-    pub fn previous(&self, loc: &A::Location) -> &Rc<RefCell<Value<A>>> {
+    pub fn previous(&self, loc: &A::Location) -> &DFGRef<A> {
         let stack = &self.S[loc];
         &stack[stack.len() - 2]
     }
 
     pub fn allocate_region<I: Iterator<Item=(Option<A::Location>, Direction)>, F: Fn(&mut SSA<A>, (A::Location, Direction), DFGRef<A>)>(&mut self, ssa: &mut SSA<A>, assignments: &mut Vec<A::Location>, items: I, insert_entry: &F, def_source: (A::Address, DefSource<A::Address>)) {
         let mut writelog: HashSet<A::Location> = HashSet::new();
+        let mut reads: HashSet<A::Location> = HashSet::new();
         for (maybeloc, direction) in items {
             if let Some(loc) = maybeloc {
                 // TODO: use a `LocationAliasDescriptions` here in place of `loc.aliases_of()`
@@ -248,8 +251,10 @@ impl<A: Arch + SSAValues> ValueAllocator<A> {
                             } else {
                                 self.current(&loc)
                             };
-                            value.borrow_mut().used = true;
-                            insert_entry(ssa, (loc, Direction::Read), Rc::clone(value));
+                            let value = *value;
+                            ssa.value_mut(value).used = true;
+                            reads.insert(loc.clone());
+                            insert_entry(ssa, (loc, Direction::Read), value);
                         },
                         Direction::Write => {
                             // treat writes also as reads of the location they write. this is to
@@ -263,7 +268,7 @@ impl<A: Arch + SSAValues> ValueAllocator<A> {
                             } else {
                                 self.current(&loc)
                             };
-                            insert_entry(ssa, (loc.clone(), Direction::Read), Rc::clone(value));
+                            insert_entry(ssa, (loc.clone(), Direction::Read), *value);
 
                             // original write logic.
                             if writelog.contains(&loc) {
@@ -271,10 +276,8 @@ impl<A: Arch + SSAValues> ValueAllocator<A> {
                             } else {
                                 writelog.insert(loc.clone());
                             }
-                            let new_value = self.alloc_value(loc.clone());
-                            ssa.defs.insert(HashedValue {
-                                value: Rc::clone(&new_value)
-                            }, def_source);
+                            let new_value = self.alloc_value(&mut ssa.values, loc.clone());
+                            ssa.values.set_def_site(new_value, def_source);
                             insert_entry(ssa, (loc.clone(), Direction::Write), new_value);
                             // for very assignment-heavy blocks maybe there's a good way to summarize multiple of the same location being assigned
                             // eg is it faster to store this and pop it back or is it faster to just
@@ -295,6 +298,20 @@ impl<A: Arch + SSAValues> ValueAllocator<A> {
                 }
             }
         }
+
+        // remember which of the reads above were only from writes, so they aren't mistaken for
+        // uses later.
+        let site = match def_source {
+            (addr, DefSource::Instruction) => UseSite::Instruction(addr),
+            (addr, DefSource::Modifier(precedence)) => UseSite::Modifier(addr, precedence),
+            (addr, DefSource::Between(to)) => UseSite::Between(addr, to),
+            (_, DefSource::Phi) | (_, DefSource::External) => { return; }
+        };
+        for loc in writelog.into_iter() {
+            if !reads.contains(&loc) {
+                ssa.overwrites.insert((site.clone(), loc));
+            }
+        }
     }
 }
 
@@ -345,10 +362,11 @@ pub fn generate_refined_ssa<
         for ((loc, dir), dfg_ref) in v {
             println!("location for {:?}, {:?}", loc, dir);
             if let Some(old_value) = old_dfg.get_value(*addr, loc.to_owned(), *dir) {
-                if dfg_ref.borrow().location != old_value.borrow().location || dfg_ref.borrow().version != old_value.borrow().version {
-                    println!("{} -> {}", old_value.borrow(), dfg_ref.borrow());
+                let (old_value, new_value) = (old_dfg.value(old_value), new_dfg.value(*dfg_ref));
+                if new_value.location != old_value.location || new_value.version != old_value.version {
+                    println!("{} -> {}", old_value, new_value);
                 } else {
-                    println!("{} == {}", old_value.borrow(), dfg_ref.borrow());
+                    println!("{} == {}", old_value, new_value);
                 }
             } else {
                 // okay this is some kinda location that didn't exist before. maybe this is a new
@@ -372,8 +390,8 @@ pub fn generate_refined_ssa<
         for ((loc, dir), value) in values.iter() {
             if loc_updates.contains_key(loc) {
                 new_values.remove(&(loc.to_owned(), *dir));
-                new_values.insert((loc_updates[loc].to_owned(), *dir), Rc::clone(value));
-                value.borrow_mut().location = loc_updates[loc].to_owned();
+                new_values.insert((loc_updates[loc].to_owned(), *dir), *value);
+                new_dfg.values.value_mut(*value).location = loc_updates[loc].to_owned();
             }
         }
         *values = new_values;
@@ -382,7 +400,7 @@ pub fn generate_refined_ssa<
     if true {
         println!("instruction values {:?}", &new_dfg.instruction_values);
         println!("modifier values {:?}", &new_dfg.modifier_values);
-        println!("defs {:?}", &new_dfg.defs);
+        println!("values {:?}", &new_dfg.values);
 //        println!("phi {:?}", &new_dfg.phi);
         /*
         for v in new_dfg.values() {
@@ -462,10 +480,11 @@ pub fn generate_ssa<
     // TODO: some nice abstraction to look up by (Address, Location) but also
     // find all Location for an Address
     let mut ssa = SSA {
+        values: ValueGraph::new(),
         instruction_values: HashMap::new(),
         modifier_values: HashMap::new(),
         control_dependent_values: HashMap::new(),
-        defs: HashMap::new(),
+        overwrites: HashSet::new(),
         phi: HashMap::new(),
         indirect_values: HashMap::new(),
         external_defs: HashMap::new(),
@@ -497,7 +516,7 @@ pub fn generate_ssa<
                     if has_already[Y] < iter_count {
                         // versioned at 0xffffffff to indicate a specialness to them.
                         // These should never be present after search().
-                        let new_value = Rc::new(RefCell::new(Value::new(loc.clone(), Some(0xffffffff))));
+                        let new_value = ssa.values.alloc(Value::new(loc.clone(), Some(0xffffffff)));
                         ssa.phi.entry(*Y).or_insert_with(|| HashMap::new())
                             .insert(loc.clone(), PhiOp { out: new_value, ins: vec![] });
                         // TODO: phi nodes are assignments too! this is definitely a bug.
@@ -553,22 +572,20 @@ pub fn generate_ssa<
         let mut assignments: Vec<A::Location> = Vec::new();
         // for each statement in block {
         // also check phis at start of the block...
-        if let Some(phis) = ssa.phi.get(&block.start) {
-            for (loc, _data) in phis {
-                // these are very clear reads vs assignments:
-                let phi_dest = value_allocator.alloc_value_with(loc.clone(), |value| {
-                    let phi_dest = Rc::clone(&ssa.phi[&block.start][loc].out);
-                    phi_dest.replace(value);
-                    phi_dest
-                });
-                ssa.defs.insert(HashedValue {
-                    value: Rc::clone(&phi_dest)
-                }, (block.start, DefSource::Phi));
-                // for very assignment-heavy blocks maybe there's a good way to summarize multiple of the same location being assigned
-                // eg is it faster to store this and pop it back or is it faster to just
-                // decode again..?
-                assignments.push(loc.clone()); // ???
-            }
+        let phis: Vec<(A::Location, DFGRef<A>)> = ssa.phi.get(&block.start)
+            .map(|phis| phis.iter().map(|(loc, phi)| (loc.clone(), phi.out)).collect())
+            .unwrap_or_default();
+        for (loc, phi_dest) in phis {
+            // these are very clear reads vs assignments:
+            value_allocator.alloc_value_with(loc.clone(), |value| {
+                *ssa.value_mut(phi_dest) = value;
+                phi_dest
+            });
+            ssa.values.set_def_site(phi_dest, (block.start, DefSource::Phi));
+            // for very assignment-heavy blocks maybe there's a good way to summarize multiple of the same location being assigned
+            // eg is it faster to store this and pop it back or is it faster to just
+            // decode again..?
+            assignments.push(loc); // ???
         }
 
         let mut iter = A::instructions_spanning(data, block.start, block.end);
@@ -605,7 +622,10 @@ pub fn generate_ssa<
                 let introduced_bounds = ssa.control_dependent_values.entry(block.start).or_insert_with(|| HashMap::new());
                 introduced_bounds.entry(Y).or_insert_with(|| HashMap::new()).insert(pos, value);
             };
-            value_allocator.allocate_region(ssa, &mut assignments, value_modifiers.between(block.start, Y).into_iter(), &introduced_bounds_fn, (block.start, DefSource::Between(Y)));
+            // values on this edge are only current along it - the next edge starts from the end
+            // of this block again, so they're freed once `Y`'s phis have them.
+            let mut edge_assignments = Vec::new();
+            value_allocator.allocate_region(ssa, &mut edge_assignments, value_modifiers.between(block.start, Y).into_iter(), &introduced_bounds_fn, (block.start, DefSource::Between(Y)));
 
 //            j = whichpred(Y, block);
 //            for each phi in Y {
@@ -614,9 +634,13 @@ pub fn generate_ssa<
                 for (loc, phi_op) in block_phis.iter_mut() {
 //                    phi.operands[j] = .. /* value for S[V] */
 //                    // not quite perfect, but good enough
-                    phi_op.ins.push(Rc::clone(value_allocator.current(loc)));
+                    phi_op.ins.push(*value_allocator.current(loc));
                 }
             }
+
+            for assignment in edge_assignments.into_iter() {
+                value_allocator.free(assignment);
+            }
         }
 
         // children == nodes immediately dominated by block
@@ -635,7 +659,7 @@ pub fn generate_ssa<
                                 // being on the relevant edge of `control_dependent_values`,
                                 // so we have to inform the allocator of these new transient
                                 // values.
-                                value_allocator.track_external(loc.clone(), Rc::clone(value));
+                                value_allocator.track_external(loc.clone(), *value);
                             }
                         }
                     }
@@ -670,7 +694,7 @@ pub fn generate_ssa<
         }
     }
 
-    let mut value_allocator = ValueAllocator::from_use_tracker(use_tracker);
+    let mut value_allocator = ValueAllocator::from_use_tracker(use_tracker, &mut ssa.values);
 
     search(
         data,
@@ -688,14 +712,14 @@ pub fn generate_ssa<
     fn mark_phi_used<A: SSAValues>(
         phi: &PhiOp<A>,
         phis: &HashMap<A::Address, PhiLocations<A>>,
-        defs: &HashMap<HashedValue<DFGRef<A>>, (A::Address, DefSource<A::Address>)>
+        values: &mut ValueGraph<A>,
     ) {
         for value in phi.ins.iter() {
-            if !value.borrow().used {
-                value.borrow_mut().used = true;
-                if let Some((addr, DefSource::Phi)) = defs.get(&HashedValue { value: Rc::clone(&value) }) {
-                    let dep_phi = phis.get(addr).unwrap().get(&value.borrow().location).unwrap();
-                    mark_phi_used(dep_phi, phis, defs);
+            if !values.value(*value).used {
+                values.value_mut(*value).used = true;
+                if let Some((addr, DefSource::Phi)) = values.def_site(*value).cloned() {
+                    let dep_phi = phis.get(&addr).unwrap().get(&values.value(*value).location).unwrap();
+                    mark_phi_used(dep_phi, phis, values);
                 }
             }
         }
@@ -703,11 +727,1036 @@ pub fn generate_ssa<
 
     for (_block, phi_locs) in ssa.phi.iter() {
         for (_loc, phi) in phi_locs.iter() {
-            if phi.out.borrow().used {
-                mark_phi_used(phi, &ssa.phi, &ssa.defs)
+            if ssa.values.value(phi.out).used {
+                mark_phi_used(phi, &ssa.phi, &mut ssa.values)
             }
         }
     }
 
+    ssa.index_uses();
+
     ssa
 }
+
+/// what's different about a function since its SSA was built, for `update_ssa`.
+///
+/// instructions, modifiers, and edges that are gone are found by comparing the old SSA against the
+/// function as it is now, but anything added has to be described here.
+#[derive(Debug)]
+pub struct SSAChanges<Addr: Hash + Eq, Loc: Hash + Eq> {
+    /// locations to renumber in `blocks` even if they're read and written there just like before,
+    /// like when a modifier's expression changed but not what it modifies.
+    pub locations: HashSet<Loc>,
+    /// blocks that are new, or whose instructions, modifiers, or predecessors changed. a modifier
+    /// on an edge is in the block the edge leaves. for a newly resolved indirect branch, that's its
+    /// targets and any blocks reachable only through them.
+    pub blocks: HashSet<Addr>,
+}
+
+impl<Addr: Hash + Eq, Loc: Hash + Eq> Default for SSAChanges<Addr, Loc> {
+    fn default() -> Self {
+        SSAChanges {
+            locations: HashSet::new(),
+            blocks: HashSet::new(),
+        }
+    }
+}
+
+impl<Addr: Hash + Eq, Loc: Hash + Eq> SSAChanges<Addr, Loc> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn location(mut self, loc: Loc) -> Self {
+        self.locations.insert(loc);
+        self
+    }
+
+    pub fn block(mut self, block: Addr) -> Self {
+        self.blocks.insert(block);
+        self
+    }
+}
+
+/// bring `ssa` up to date with `changes` to the function at `entry` without building it all again.
+///
+/// only the blocks in `changes` are decoded, and phis are only placed again in them and their
+/// iterated dominance frontier; phis anywhere else stay where they were. locations those blocks
+/// read or write differently than before, or whose phis moved, are renumbered there. reads of them
+/// anywhere else that might see a different value now are found through `uses` and pointed at it
+/// without decoding the instructions they're in.
+///
+/// values of every other location are left exactly as they were, names and data included.
+/// renumbered values defined where a value was before reuse that value's `DFGRef`, so they keep
+/// its name and version and anything referring to it still does, but their data is cleared for
+/// whatever analysis produced it to work out again. new values are numbered after the versions
+/// their location already has, so versions may not be the ones building the SSA from scratch
+/// would pick.
+///
+/// returns the locations that were renumbered.
+pub fn update_ssa<
+    'functions,
+    'disambiguator,
+    A: SSAValues + for<'mem> DecodeFrom<M>,
+    M: MemoryRange<A> + ?Sized,
+    U: ModifierCollection<A>,
+    LocSpec,
+    Disam: Disambiguator<A, LocSpec> + LocationAliasDescriptions<A>,
+    F: FunctionQuery<A::Address, Function=FunctionImpl<A::Location>>,
+>(
+    data: &M,
+    entry: A::Address,
+    basic_blocks: &ControlFlowGraph<A::Address>,
+    cfg: &GraphMap<A::Address, (), petgraph::Directed>,
+    ssa: &mut SSA<A>,
+    changes: &SSAChanges<A::Address, A::Location>,
+    value_modifiers: &U,
+    disambiguator: &'disambiguator Disam,
+    functions: &'functions F,
+) -> HashSet<A::Location> where
+    A::Location: 'static + AbiDefaults,
+    for<'a> &'a <A as Arch>::Instruction: LocIterator<'disambiguator, 'functions, A, A::Location, Disam, F, Item=(Option<A::Location>, Direction), LocSpec=LocSpec>,
+{
+    let idom = petgraph::algo::dominators::simple_fast(&cfg, entry);
+    let dominance_frontiers = compute_dominance_frontiers_from_idom(cfg, entry, &idom);
+
+    let reachable = |block: A::Address| {
+        cfg.contains_node(block) && (block == entry || idom.immediate_dominator(block).is_some())
+    };
+    // the block `addr` is in, if it's still in the function.
+    let owner = |addr: A::Address| {
+        let block = basic_blocks.get_block(addr);
+        if addr <= block.end && reachable(block.start) {
+            Some(block.start)
+        } else {
+            None
+        }
+    };
+    let depth = |mut block: A::Address| {
+        let mut depth = 0;
+        while let Some(parent) = idom.immediate_dominator(block) {
+            block = parent;
+            depth += 1;
+        }
+        depth
+    };
+
+    // changed blocks are renumbered after the blocks that dominate them, so what reaches them is
+    // already up to date.
+    let mut changed: Vec<A::Address> = changes.blocks.iter().cloned().filter(|block| reachable(*block)).collect();
+    changed.sort_by_key(|block| (depth(*block), *block));
+    let changed_blocks: HashSet<A::Address> = changed.iter().cloned().collect();
+
+    let mut replay: Vec<(A::Address, BlockSites<A>)> = Vec::new();
+    let mut present: HashSet<(A::Address, DefSource<A::Address>)> = HashSet::new();
+    for block in changed.iter() {
+        let block = basic_blocks.get_block(*block);
+        let mut sites = Vec::new();
+        let mut iter = A::instructions_spanning(data, block.start, block.end);
+        while let Some((address, instr)) = iter.next() {
+            sites.push(((address, DefSource::Modifier(modifier::Precedence::Before)), value_modifiers.before(address)));
+            sites.push(((address, DefSource::Instruction), instr.iter_locs(address, disambiguator, functions).collect()));
+            sites.push(((address, DefSource::Modifier(modifier::Precedence::After)), value_modifiers.after(address)));
+        }
+        for next in cfg.neighbors(block.start) {
+            sites.push(((block.start, DefSource::Between(next)), value_modifiers.between(block.start, next)));
+        }
+        present.extend(sites.iter().map(|(site, _)| *site));
+        replay.push((block.start, sites));
+    }
+
+    let mut affected = changes.locations.clone();
+
+    // locations changed blocks read or write differently than before,
+    for (_, sites) in replay.iter() {
+        for ((addr, source), items) in sites.iter() {
+            let now = accesses::<A>(items);
+            let before: HashSet<(A::Location, Direction)> = site_values(ssa, *addr, source)
+                .map(|values| values.keys().cloned().collect())
+                .unwrap_or_default();
+            affected.extend(now.symmetric_difference(&before).map(|(loc, _)| loc.clone()));
+        }
+    }
+
+    // or that were read or written somewhere that's not in the function anymore,
+    let gone = |addr: A::Address, source: &DefSource<A::Address>| {
+        match source {
+            DefSource::Between(to) => !reachable(addr) || !cfg.contains_edge(addr, *to),
+            _ => match owner(addr) {
+                Some(block) => changed_blocks.contains(&block) && !present.contains(&(addr, *source)),
+                None => true,
+            }
+        }
+    };
+    let mut gone_sites: Vec<(A::Address, DefSource<A::Address>)> = Vec::new();
+    gone_sites.extend(ssa.instruction_values.keys().map(|addr| (*addr, DefSource::Instruction)));
+    gone_sites.extend(ssa.modifier_values.keys().map(|(addr, precedence)| (*addr, DefSource::Modifier(*precedence))));
+    for (from, tos) in ssa.control_dependent_values.iter() {
+        gone_sites.extend(tos.keys().map(|to| (*from, DefSource::Between(*to))));
+    }
+    gone_sites.retain(|(addr, source)| gone(*addr, source));
+    for (addr, source) in gone_sites.iter() {
+        if let Some(values) = site_values(ssa, *addr, source) {
+            affected.extend(values.keys().map(|(loc, _)| loc.clone()));
+        }
+    }
+    for (block, block_phis) in ssa.phi.iter() {
+        if !reachable(*block) {
+            affected.extend(block_phis.keys().cloned());
+        }
+    }
+
+    // or whose phis in the changed blocks or their iterated dominance frontier moved.
+    let mut region = changed_blocks.clone();
+    let mut work = changed.clone();
+    while let Some(block) = work.pop() {
+        for frontier in dominance_frontiers.get(&block).into_iter().flatten() {
+            if region.insert(*frontier) {
+                work.push(*frontier);
+            }
+        }
+    }
+
+    let mut written: HashMap<A::Location, HashSet<A::Address>> = HashMap::new();
+    for (value, (addr, source)) in ssa.values.defs() {
+        let block = match source {
+            DefSource::Phi | DefSource::External => { continue; }
+            DefSource::Between(_) => *addr,
+            _ => match owner(*addr) {
+                Some(block) => block,
+                None => { continue; }
+            }
+        };
+        if changed_blocks.contains(&block) || gone(*addr, source) {
+            continue;
+        }
+        written.entry(ssa.value(value).location.clone()).or_default().insert(block);
+    }
+    for (block, sites) in replay.iter() {
+        for (_, items) in sites.iter() {
+            for (loc, _) in accesses::<A>(items).into_iter().filter(|(_, dir)| *dir == Direction::Write) {
+                written.entry(loc).or_default().insert(*block);
+            }
+        }
+    }
+
+    let mut placement: HashMap<A::Location, HashSet<A::Address>> = HashMap::new();
+    for (loc, blocks) in written.iter() {
+        let mut frontier: HashSet<A::Address> = HashSet::new();
+        let mut work: Vec<A::Address> = blocks.iter().cloned().collect();
+        while let Some(block) = work.pop() {
+            for next in dominance_frontiers.get(&block).into_iter().flatten() {
+                if frontier.insert(*next) {
+                    work.push(*next);
+                }
+            }
+        }
+        frontier.retain(|block| region.contains(block) && reachable(*block));
+        if !frontier.is_empty() {
+            placement.insert(loc.clone(), frontier);
+        }
+    }
+    for block in region.iter() {
+        let block_phis = ssa.phi.get(block);
+        for loc in block_phis.into_iter().flat_map(|block_phis| block_phis.keys()) {
+            if !placement.get(loc).map(|blocks| blocks.contains(block)).unwrap_or(false) {
+                affected.insert(loc.clone());
+            }
+        }
+        for (loc, blocks) in placement.iter() {
+            if blocks.contains(block) && !block_phis.map(|block_phis| block_phis.contains_key(loc)).unwrap_or(false) {
+                affected.insert(loc.clone());
+            }
+        }
+    }
+
+    // a write to one location writes all its aliases too, so they're numbered together.
+    let mut aliases: Vec<A::Location> = affected.iter().cloned().collect();
+    while let Some(loc) = aliases.pop() {
+        for alias in loc.aliases_of() {
+            if affected.insert(alias.clone()) {
+                aliases.push(alias);
+            }
+        }
+    }
+
+    // phis in changed blocks may have gained or lost predecessors, so their operands are worked
+    // out again even for locations that aren't renumbered.
+    let mut numbered = affected.clone();
+    for block in changed.iter() {
+        numbered.extend(ssa.phi.get(block).into_iter().flat_map(|block_phis| block_phis.keys().cloned()));
+    }
+
+    let mut reaching: HashMap<A::Location, Reaching<A>> = HashMap::new();
+    for loc in numbered.iter() {
+        reaching.insert(loc.clone(), Reaching {
+            entry,
+            idom: &idom,
+            location: loc.clone(),
+            defs: HashMap::new(),
+            edges: HashMap::new(),
+            phis: HashMap::new(),
+            input: ssa.external_defs.get(loc).cloned(),
+            next_version: 0,
+        });
+    }
+    for (value, (addr, source)) in ssa.values.defs() {
+        let (loc, version) = {
+            let value = ssa.value(value);
+            (value.location.clone(), value.version)
+        };
+        let numbering = match reaching.get_mut(&loc) {
+            Some(numbering) => numbering,
+            None => { continue; }
+        };
+        if let Some(version) = version {
+            numbering.next_version = std::cmp::max(numbering.next_version, version.saturating_add(1));
+        }
+        match source {
+            DefSource::Phi => { numbering.phis.insert(*addr, value); }
+            DefSource::Between(to) => { numbering.edges.insert((*addr, *to), value); }
+            DefSource::External => {}
+            _ => {
+                if let Some(block) = owner(*addr) {
+                    numbering.defs.entry(block).or_default().push((site_order(*addr, source), value));
+                }
+            }
+        }
+    }
+    for numbering in reaching.values_mut() {
+        for defs in numbering.defs.values_mut() {
+            defs.sort_by_key(|(order, _)| *order);
+        }
+    }
+    // values from before the function aren't defined anywhere, but are read.
+    let mut find_input = |value: &DFGRef<A>| {
+        let value_ref = ssa.value(*value);
+        if value_ref.version.is_none() {
+            if let Some(numbering) = reaching.get_mut(&value_ref.location) {
+                numbering.input.get_or_insert(*value);
+            }
+        }
+    };
+    for value in ssa.values.iter() {
+        if !ssa.uses_of(value).is_empty() {
+            find_input(&value);
+        }
+    }
+    for (site, loc) in ssa.overwrites.iter() {
+        if let Some((addr, source)) = def_site(site) {
+            if let Some(value) = site_values(ssa, addr, &source).and_then(|values| values.get(&(loc.clone(), Direction::Read))) {
+                find_input(value);
+            }
+        }
+    }
+
+    // whatever reached a block where something changed might not reach past it anymore.
+    let mut stale: ValueSet<A> = HashSet::new();
+    for loc in affected.iter() {
+        let numbering = reaching.get_mut(loc).expect("renumbered locations are numbered");
+        for block in region.iter().filter(|block| reachable(**block)) {
+            let value = numbering.at_start(&mut ssa.values, *block);
+            stale.insert(value);
+        }
+    }
+
+    // values being replaced, by where they're defined, so their replacements can take their place.
+    let mut prior: HashMap<SiteLocation<A>, DFGRef<A>> = HashMap::new();
+    // values whose `used` might not be right anymore.
+    let mut touched: ValueSet<A> = HashSet::new();
+
+    let mut strip = |ssa: &mut SSA<A>, addr: A::Address, source: DefSource<A::Address>, all: bool| {
+        let mut values = match take_site_values(ssa, addr, &source) {
+            Some(values) => values,
+            None => { return; }
+        };
+        let site = use_site(addr, &source).expect("values are only at use sites");
+        let block = match source {
+            DefSource::Between(_) => Some(addr),
+            _ => owner(addr),
+        };
+        values.retain(|(loc, dir), value| {
+            if !all && !affected.contains(loc) {
+                return true;
+            }
+            if *dir == Direction::Write {
+                ssa.values.clear_def_site(*value);
+                if let Some(numbering) = reaching.get_mut(loc) {
+                    numbering.forget(block, &source, value);
+                }
+                prior.insert((addr, source, loc.clone()), *value);
+            } else if !ssa.overwrites.remove(&(site.clone(), loc.clone())) {
+                remove_use(ssa, value, &site);
+            }
+            stale.insert(*value);
+            touched.insert(*value);
+            false
+        });
+        if !values.is_empty() {
+            put_site_values(ssa, addr, &source, values);
+        }
+    };
+    for (addr, source) in gone_sites.into_iter() {
+        strip(ssa, addr, source, true);
+    }
+    for (_, sites) in replay.iter() {
+        for ((addr, source), _) in sites.iter() {
+            strip(ssa, *addr, *source, false);
+        }
+    }
+
+    let mut blocks: Vec<A::Address> = ssa.phi.keys().cloned().collect();
+    blocks.retain(|block| !reachable(*block) || region.contains(block));
+    for block in blocks.into_iter() {
+        let mut block_phis = ssa.phi.remove(&block).expect("block has phis");
+        block_phis.retain(|loc, phi| {
+            if reachable(block) && !affected.contains(loc) {
+                return true;
+            }
+            ssa.values.clear_def_site(phi.out);
+            for value in phi.ins.iter() {
+                remove_use(ssa, value, &UseSite::Phi(block, loc.clone()));
+                touched.insert(*value);
+            }
+            if let Some(numbering) = reaching.get_mut(loc) {
+                numbering.phis.remove(&block);
+            }
+            prior.insert((block, DefSource::Phi, loc.clone()), phi.out);
+            stale.insert(phi.out);
+            touched.insert(phi.out);
+            false
+        });
+        if !block_phis.is_empty() {
+            ssa.phi.insert(block, block_phis);
+        }
+    }
+
+    let mut define = |ssa: &mut SSA<A>, numbering: &mut Reaching<A>, addr: A::Address, source: DefSource<A::Address>| {
+        let value = match prior.remove(&(addr, source, numbering.location.clone())) {
+            Some(value) => {
+                ssa.value_mut(value).data = None;
+                value
+            }
+            None => {
+                let version = numbering.next_version;
+                numbering.next_version += 1;
+                ssa.values.alloc(Value::new(numbering.location.clone(), Some(version)))
+            }
+        };
+        ssa.values.set_def_site(value, (addr, source));
+        value
+    };
+
+    // place phis again where they moved,
+    let mut recompute: HashSet<(A::Address, A::Location)> = HashSet::new();
+    for loc in affected.iter() {
+        let numbering = reaching.get_mut(loc).expect("renumbered locations are numbered");
+        for block in placement.get(loc).into_iter().flatten() {
+            let out = define(ssa, numbering, *block, DefSource::Phi);
+            numbering.phis.insert(*block, out);
+            touched.insert(out);
+            ssa.phi.entry(*block).or_default().insert(loc.clone(), PhiOp { out, ins: vec![] });
+            recompute.insert((*block, loc.clone()));
+        }
+    }
+    for block in changed.iter() {
+        for loc in ssa.phi.get(block).into_iter().flat_map(|block_phis| block_phis.keys()) {
+            recompute.insert((*block, loc.clone()));
+        }
+    }
+
+    // renumber changed blocks,
+    for (block, sites) in replay.into_iter() {
+        for ((addr, source), items) in sites.into_iter() {
+            let site = use_site(addr, &source).expect("changed blocks only have use sites");
+            let mut values = take_site_values(ssa, addr, &source).unwrap_or_default();
+            let mut writelog: Vec<(A::Location, DFGRef<A>)> = Vec::new();
+            let mut reads: HashSet<A::Location> = HashSet::new();
+            for (maybeloc, direction) in items.into_iter() {
+                let loc = match maybeloc {
+                    Some(loc) => loc,
+                    None => { continue; }
+                };
+                for loc in std::iter::once(loc.clone()).chain(loc.aliases_of()) {
+                    if !affected.contains(&loc) {
+                        continue;
+                    }
+                    let numbering = reaching.get_mut(&loc).expect("renumbered locations are numbered");
+                    // reads see the value from before this site, even if it also writes.
+                    let value = match source {
+                        DefSource::Between(_) => numbering.before(&mut ssa.values, block, None),
+                        _ => numbering.before(&mut ssa.values, block, Some(site_order(addr, &source))),
+                    };
+                    values.insert((loc.clone(), Direction::Read), value);
+                    if direction == Direction::Read {
+                        reads.insert(loc);
+                    } else if !writelog.iter().any(|(written, _)| *written == loc) {
+                        let value = define(ssa, numbering, addr, source);
+                        values.insert((loc.clone(), Direction::Write), value);
+                        writelog.push((loc, value));
+                    }
+                }
+            }
+            for ((loc, dir), value) in values.iter() {
+                if *dir != Direction::Read || !affected.contains(loc) {
+                    continue;
+                }
+                touched.insert(*value);
+                if writelog.iter().any(|(written, _)| written == loc) && !reads.contains(loc) {
+                    ssa.overwrites.insert((site.clone(), loc.clone()));
+                } else {
+                    add_use(ssa, value, site.clone());
+                }
+            }
+            for (loc, value) in writelog.into_iter() {
+                touched.insert(value);
+                let numbering = reaching.get_mut(&loc).expect("renumbered locations are numbered");
+                match source {
+                    DefSource::Between(to) => { numbering.edges.insert((block, to), value); }
+                    _ => {
+                        numbering.defs.entry(block).or_default().push((site_order(addr, &source), value));
+                    }
+                }
+            }
+            if !values.is_empty() {
+                put_site_values(ssa, addr, &source, values);
+            }
+        }
+    }
+
+    // and point reads elsewhere at whatever reaches them now.
+    let phi_operands = |ssa: &mut SSA<A>, reaching: &mut HashMap<A::Location, Reaching<A>>, touched: &mut ValueSet<A>, block: A::Address, loc: &A::Location| {
+        let numbering = reaching.get_mut(loc).expect("phis being renumbered are numbered");
+        let ins: Vec<DFGRef<A>> = cfg.neighbors_directed(block, petgraph::Incoming)
+            .filter(|pred| reachable(*pred))
+            .map(|pred| numbering.on_edge(&mut ssa.values, pred, block))
+            .collect();
+        let site = UseSite::Phi(block, loc.clone());
+        let phi = ssa.phi.get_mut(&block).and_then(|block_phis| block_phis.get_mut(loc)).expect("phi exists");
+        let old = std::mem::replace(&mut phi.ins, ins.clone());
+        for value in old.iter() {
+            remove_use(ssa, value, &site);
+            touched.insert(*value);
+        }
+        for value in ins.iter() {
+            add_use(ssa, value, site.clone());
+            touched.insert(*value);
+        }
+    };
+    for (block, loc) in recompute.iter() {
+        phi_operands(ssa, &mut reaching, &mut touched, *block, loc);
+    }
+
+    let mut reads = Vec::new();
+    for value in stale.iter() {
+        let loc = ssa.value(*value).location.clone();
+        for site in ssa.uses_of(*value).iter() {
+            reads.push((site.clone(), loc.clone()));
+        }
+    }
+    for (site, loc) in ssa.overwrites.iter() {
+        if !affected.contains(loc) {
+            continue;
+        }
+        if let Some((addr, source)) = def_site(site) {
+            let read = site_values(ssa, addr, &source).and_then(|values| values.get(&(loc.clone(), Direction::Read)));
+            if let Some(read) = read {
+                if stale.contains(read) {
+                    reads.push((site.clone(), loc.clone()));
+                }
+            }
+        }
+    }
+    for (site, loc) in reads.into_iter() {
+        if !affected.contains(&loc) {
+            continue;
+        }
+        let (addr, source) = match def_site(&site) {
+            Some(site) => site,
+            None => {
+                if let UseSite::Phi(block, loc) = site {
+                    if !recompute.contains(&(block, loc.clone())) {
+                        phi_operands(ssa, &mut reaching, &mut touched, block, &loc);
+                        recompute.insert((block, loc));
+                    }
+                }
+                continue;
+            }
+        };
+        let numbering = reaching.get_mut(&loc).expect("renumbered locations are numbered");
+        let value = match source {
+            DefSource::Between(_) => numbering.before(&mut ssa.values, addr, None),
+            _ => match owner(addr) {
+                Some(block) => numbering.before(&mut ssa.values, block, Some(site_order(addr, &source))),
+                None => { continue; }
+            }
+        };
+        let overwrite = ssa.overwrites.contains(&(site.clone(), loc.clone()));
+        let mut values = take_site_values(ssa, addr, &source).expect("reads are at sites with values");
+        let old = values.insert((loc.clone(), Direction::Read), value);
+        put_site_values(ssa, addr, &source, values);
+        if let Some(old) = old {
+            if old != value && !overwrite {
+                remove_use(ssa, &old, &site);
+                add_use(ssa, &value, site);
+            }
+            touched.insert(old);
+        }
+        touched.insert(value);
+    }
+
+    mark_used(ssa, touched);
+
+    // indirect values stay as long as the value they are does.
+    let graph = &ssa.values;
+    ssa.indirect_values.retain(|addr, locs| {
+        if owner(*addr).is_none() {
+            return false;
+        }
+        for (loc, values) in locs.iter_mut() {
+            if affected.contains(loc) {
+                values.retain(|_, value| {
+                    graph.value(*value).version.is_none() || graph.def_site(*value).is_some()
+                });
+            }
+        }
+        locs.retain(|_, values| !values.is_empty());
+        !locs.is_empty()
+    });
+
+    affected
+}
+
+/// values picked out by which value they are.
+type ValueSet<A> = HashSet<DFGRef<A>>;
+/// one location at one site, `(address, source, location)`.
+type SiteLocation<A> = (<A as Arch>::Address, DefSource<<A as Arch>::Address>, <A as ValueLocations>::Location);
+/// where in a block a site is, as `site_order` puts it.
+type SiteOrder<A> = (<A as Arch>::Address, u8);
+/// a value written in a block, and where in the block it's written.
+type BlockDef<A> = (SiteOrder<A>, DFGRef<A>);
+/// the sites in a block and what each reads and writes, in order.
+type BlockSites<A> = Vec<((<A as Arch>::Address, DefSource<<A as Arch>::Address>), Vec<(Option<<A as ValueLocations>::Location>, Direction)>)>;
+
+/// what's live where for one location, so it can be renumbered without going through the whole
+/// function.
+struct Reaching<'idom, A: SSAValues> {
+    entry: A::Address,
+    idom: &'idom petgraph::algo::dominators::Dominators<A::Address>,
+    location: A::Location,
+    /// values written in each block, in order. values written on edges out of the block aren't
+    /// here, they're in `edges`.
+    defs: HashMap<A::Address, Vec<BlockDef<A>>>,
+    edges: HashMap<(A::Address, A::Address), DFGRef<A>>,
+    phis: HashMap<A::Address, DFGRef<A>>,
+    /// the value from before the function, if it's been found yet.
+    input: Option<DFGRef<A>>,
+    next_version: u32,
+}
+
+impl<'idom, A: SSAValues> Reaching<'idom, A> {
+    fn input(&mut self, values: &mut ValueGraph<A>) -> DFGRef<A> {
+        let location = &self.location;
+        *self.input.get_or_insert_with(|| values.alloc(Value::new(location.clone(), None)))
+    }
+
+    /// the value at the start of `block`.
+    fn at_start(&mut self, values: &mut ValueGraph<A>, mut block: A::Address) -> DFGRef<A> {
+        loop {
+            if let Some(phi) = self.phis.get(&block) {
+                return *phi;
+            }
+            let parent = match self.idom.immediate_dominator(block) {
+                Some(parent) if block != self.entry => parent,
+                _ => { return self.input(values); }
+            };
+            if let Some(value) = self.edges.get(&(parent, block)) {
+                return *value;
+            }
+            if let Some((_, value)) = self.defs.get(&parent).and_then(|defs| defs.last()) {
+                return *value;
+            }
+            block = parent;
+        }
+    }
+
+    /// the value just before `order` in `block`, or at the end of `block` for `None`.
+    fn before(&mut self, values: &mut ValueGraph<A>, block: A::Address, order: Option<SiteOrder<A>>) -> DFGRef<A> {
+        let found = self.defs.get(&block).and_then(|defs| {
+            defs.iter().rev().find(|(at, _)| order.map(|order| *at < order).unwrap_or(true))
+        });
+        match found {
+            Some((_, value)) => *value,
+            None => self.at_start(values, block),
+        }
+    }
+
+    /// the value on the edge from `from` to `to`.
+    fn on_edge(&mut self, values: &mut ValueGraph<A>, from: A::Address, to: A::Address) -> DFGRef<A> {
+        match self.edges.get(&(from, to)) {
+            Some(value) => *value,
+            None => self.before(values, from, None),
+        }
+    }
+
+    fn forget(&mut self, block: Option<A::Address>, source: &DefSource<A::Address>, value: &DFGRef<A>) {
+        match (block, source) {
+            (Some(block), DefSource::Between(to)) => { self.edges.remove(&(block, *to)); }
+            (Some(block), _) => {
+                if let Some(defs) = self.defs.get_mut(&block) {
+                    defs.retain(|(_, def)| def != value);
+                }
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+/// where a site is in its block, to put reads and writes in order.
+fn site_order<Addr: Copy>(addr: Addr, source: &DefSource<Addr>) -> (Addr, u8) {
+    match source {
+        DefSource::Modifier(modifier::Precedence::Before) => (addr, 0),
+        DefSource::Modifier(modifier::Precedence::After) => (addr, 2),
+        _ => (addr, 1),
+    }
+}
+
+fn use_site<Addr: Copy, Loc>(addr: Addr, source: &DefSource<Addr>) -> Option<UseSite<Addr, Loc>> {
+    match source {
+        DefSource::Instruction => Some(UseSite::Instruction(addr)),
+        DefSource::Modifier(precedence) => Some(UseSite::Modifier(addr, *precedence)),
+        DefSource::Between(to) => Some(UseSite::Between(addr, *to)),
+        DefSource::Phi | DefSource::External => None,
+    }
+}
+
+fn def_site<Addr: Copy, Loc>(site: &UseSite<Addr, Loc>) -> Option<(Addr, DefSource<Addr>)> {
+    match site {
+        UseSite::Instruction(addr) => Some((*addr, DefSource::Instruction)),
+        UseSite::Modifier(addr, precedence) => Some((*addr, DefSource::Modifier(*precedence))),
+        UseSite::Between(from, to) => Some((*from, DefSource::Between(*to))),
+        UseSite::Phi(_, _) => None,
+    }
+}
+
+/// every location read or written by `items`, as they'd be in an `RWMap`.
+fn accesses<A: SSAValues>(items: &[(Option<A::Location>, Direction)]) -> HashSet<(A::Location, Direction)> {
+    let mut accesses = HashSet::new();
+    for (loc, direction) in items.iter() {
+        if let Some(loc) = loc {
+            for loc in std::iter::once(loc.clone()).chain(loc.aliases_of()) {
+                accesses.insert((loc.clone(), Direction::Read));
+                if *direction == Direction::Write {
+                    accesses.insert((loc, Direction::Write));
+                }
+            }
+        }
+    }
+    accesses
+}
+
+fn site_values<'ssa, A: SSAValues>(ssa: &'ssa SSA<A>, addr: A::Address, source: &DefSource<A::Address>) -> Option<&'ssa RWMap<A>> {
+    match source {
+        DefSource::Instruction => ssa.instruction_values.get(&addr),
+        DefSource::Modifier(precedence) => ssa.modifier_values.get(&(addr, *precedence)),
+        DefSource::Between(to) => ssa.control_dependent_values.get(&addr).and_then(|tos| tos.get(to)),
+        DefSource::Phi | DefSource::External => None,
+    }
+}
+
+fn take_site_values<A: SSAValues>(ssa: &mut SSA<A>, addr: A::Address, source: &DefSource<A::Address>) -> Option<RWMap<A>> {
+    match source {
+        DefSource::Instruction => ssa.instruction_values.remove(&addr),
+        DefSource::Modifier(precedence) => ssa.modifier_values.remove(&(addr, *precedence)),
+        DefSource::Between(to) => {
+            let tos = ssa.control_dependent_values.get_mut(&addr)?;
+            let values = tos.remove(to);
+            if tos.is_empty() {
+                ssa.control_dependent_values.remove(&addr);
+            }
+            values
+        }
+        DefSource::Phi | DefSource::External => None,
+    }
+}
+
+fn put_site_values<A: SSAValues>(ssa: &mut SSA<A>, addr: A::Address, source: &DefSource<A::Address>, values: RWMap<A>) {
+    match source {
+        DefSource::Instruction => { ssa.instruction_values.insert(addr, values); }
+        DefSource::Modifier(precedence) => { ssa.modifier_values.insert((addr, *precedence), values); }
+        DefSource::Between(to) => {
+            ssa.control_dependent_values.entry(addr).or_default().insert(*to, values);
+        }
+        DefSource::Phi | DefSource::External => {}
+    }
+}
+
+fn add_use<A: SSAValues>(ssa: &mut SSA<A>, value: &DFGRef<A>, site: UseSite<A::Address, A::Location>) {
+    ssa.values.add_use(*value, site);
+}
+
+fn remove_use<A: SSAValues>(ssa: &mut SSA<A>, value: &DFGRef<A>, site: &UseSite<A::Address, A::Location>) {
+    ssa.values.remove_use(*value, site);
+}
+
+/// work out `used` again for `values`, like `build_ssa` does: a value is used if something other
+/// than a phi reads it, or it's an operand of a used phi.
+fn mark_used<A: SSAValues>(ssa: &mut SSA<A>, mut values: ValueSet<A>) {
+    // a phi being used or not decides if its operands are, so they're worked out again too.
+    let mut phis: Vec<DFGRef<A>> = values.iter().cloned().collect();
+    while let Some(value) = phis.pop() {
+        if let Some((block, DefSource::Phi)) = ssa.try_get_def_site(value) {
+            let loc = ssa.value(value).location.clone();
+            if let Some(phi) = ssa.phi.get(block).and_then(|block_phis| block_phis.get(&loc)) {
+                for operand in phi.ins.iter() {
+                    if values.insert(*operand) {
+                        phis.push(*operand);
+                    }
+                }
+            }
+        }
+    }
+
+    for value in values.iter() {
+        ssa.value_mut(*value).used = false;
+    }
+    let phi_of = |block: &A::Address, loc: &A::Location| {
+        ssa.phi.get(block).and_then(|block_phis| block_phis.get(loc)).map(|phi| phi.out)
+    };
+    let mut work: Vec<DFGRef<A>> = Vec::new();
+    for value in values.iter() {
+        let read = ssa.uses_of(*value).iter().any(|site| match site {
+            UseSite::Phi(block, loc) => phi_of(block, loc)
+                .map(|out| !values.contains(&out) && ssa.value(out).used)
+                .unwrap_or(false),
+            _ => true,
+        });
+        if read {
+            work.push(*value);
+        }
+    }
+    while let Some(value) = work.pop() {
+        if ssa.value(value).used {
+            continue;
+        }
+        ssa.value_mut(value).used = true;
+        if let Some((block, DefSource::Phi)) = ssa.try_get_def_site(value).cloned() {
+            let loc = ssa.value(value).location.clone();
+            if let Some(phi) = ssa.phi.get(&block).and_then(|block_phis| block_phis.get(&loc)) {
+                work.extend(phi.ins.iter().cloned());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_update_ssa() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Data, Location, NoDisambiguation};
+    use data::modifier::{InstructionModifiers, ModifierExpression};
+
+    let data: Vec<u8> = vec![
+        0x48, 0xc7, 0xc1, 0x00, 0x00, 0x00, 0x00,   // 0x00: mov rcx, 0
+        0x48, 0xc7, 0xc0, 0x05, 0x00, 0x00, 0x00,   // 0x07: mov rax, 5
+        0x48, 0x85, 0xc9,                           // 0x0e: test rcx, rcx
+        0x74, 0x07,                                 // 0x11: je 0x1a
+        0x48, 0xc7, 0xc0, 0x07, 0x00, 0x00, 0x00,   // 0x13: mov rax, 7
+        0x48, 0x01, 0xc8,                           // 0x1a: add rax, rcx
+        0xc3,                                       // 0x1d: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+
+    let mut dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+
+    let five = dfg.get_def(0x07, Location::rax()).as_rc();
+    dfg.value_mut(five).name = Some("five".to_string());
+    let counter = dfg.get_def(0x00, Location::rcx()).as_rc();
+    dfg.value_mut(counter).name = Some("counter".to_string());
+    let indirect = (Data::Concrete(0, None), Direction::Write);
+    dfg.indirect_values.entry(0x00).or_default()
+        .entry(Location::rcx()).or_default()
+        .insert(indirect.clone(), counter);
+
+    // on the way to `mov rax, 7`, rcx is known to not be zero.
+    modifiers.add_edge_modifier(0x00, 0x13, Some(Location::rcx()), ModifierExpression::IsNot(0));
+    let changes = SSAChanges::new().block(0x00);
+    let renumbered = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron_update(&mut dfg, &changes);
+
+    assert!(renumbered.contains(&Location::rcx()));
+    assert!(!renumbered.contains(&Location::rax()));
+
+    // rax wasn't touched at all, and rcx is still defined at 0x00 by the same value.
+    assert_eq!(dfg.get_def(0x07, Location::rax()).as_rc(), five);
+    assert_eq!(dfg.get_def(0x00, Location::rcx()).as_rc(), counter);
+    assert_eq!(dfg.value(counter).name.as_ref().map(|s| s.as_str()), Some("counter"));
+    assert!(dfg.control_dependent_values[&0x00][&0x13].contains_key(&(Location::rcx(), Direction::Write)));
+    // so what's known about it from elsewhere still holds.
+    assert_eq!(dfg.indirect_values[&0x00][&Location::rcx()][&indirect], counter);
+
+    // otherwise, values are read and written just where they would be in SSA built from scratch.
+    let rebuilt = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+
+    assert_eq!(ssa_sites(&dfg), ssa_sites(&rebuilt));
+    assert_eq!(ssa_edges(&dfg), ssa_edges(&rebuilt));
+}
+
+/// every read and write in `ssa`, with where the value read or written is defined, so two SSAs can be
+/// compared without caring how their values are numbered.
+#[cfg(test)]
+fn ssa_sites<A: SSAValues>(ssa: &SSA<A>) -> HashSet<(String, String, usize, bool)> {
+    use yaxpeax_arch::AddressDisplay;
+
+    let mut accesses = HashSet::new();
+    let mut add = |site: String, value: &DFGRef<A>| {
+        let uses = ssa.uses_of(*value).len();
+        let (def, source) = ssa.get_def_site(*value);
+        let value = ssa.value(*value);
+        accesses.insert((format!("{} {:?}", site, value.location), format!("{} {}", def.show(), source), uses, value.used));
+    };
+    for (addr, values) in ssa.instruction_values.iter() {
+        for ((_, dir), value) in values.iter() {
+            add(format!("{} {:?}", addr.show(), dir), value);
+        }
+    }
+    for (from, tos) in ssa.control_dependent_values.iter() {
+        for (to, values) in tos.iter() {
+            for ((_, dir), value) in values.iter() {
+                add(format!("{} -> {} {:?}", from.show(), to.show(), dir), value);
+            }
+        }
+    }
+    for (block, block_phis) in ssa.phi.iter() {
+        for phi in block_phis.values() {
+            add(format!("phi {}", block.show()), &phi.out);
+            for value in phi.ins.iter() {
+                add(format!("phi {} in", block.show()), value);
+            }
+        }
+    }
+    accesses
+}
+
+/// how many values in `ssa` are defined somewhere, and how many are read somewhere.
+#[cfg(test)]
+fn ssa_edges<A: SSAValues>(ssa: &SSA<A>) -> (usize, usize) {
+    let defined = ssa.values.defs().count();
+    let read = ssa.values.iter().filter(|value| !ssa.uses_of(*value).is_empty()).count();
+    (defined, read)
+}
+
+#[test]
+fn test_update_ssa_moves_phis() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+
+    let mut data: Vec<u8> = vec![
+        0x48, 0xc7, 0xc1, 0x00, 0x00, 0x00, 0x00,   // 0x00: mov rcx, 0
+        0x48, 0xc7, 0xc0, 0x05, 0x00, 0x00, 0x00,   // 0x07: mov rax, 5
+        0x48, 0x85, 0xc9,                           // 0x0e: test rcx, rcx
+        0x74, 0x07,                                 // 0x11: je 0x1a
+        0x48, 0xc7, 0xc0, 0x07, 0x00, 0x00, 0x00,   // 0x13: mov rax, 7
+        0x48, 0x01, 0xc8,                           // 0x1a: add rax, rcx
+        0xc3,                                       // 0x1d: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+
+    let mut dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let five = dfg.get_def(0x07, Location::rax()).as_rc();
+    assert_eq!(dfg.get_def_site(dfg.get_use(0x1a, Location::rax()).as_rc()), (0x1a, DefSource::Phi));
+
+    // 0x13: mov rcx, 7
+    data[0x15] = 0xc1;
+    let changes = SSAChanges::new().block(0x13);
+    let renumbered = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron_update(&mut dfg, &changes);
+    assert!(renumbered.contains(&Location::rax()));
+    assert!(renumbered.contains(&Location::rcx()));
+
+    // rax doesn't need a phi anymore, but rcx does.
+    assert_eq!(dfg.get_use(0x1a, Location::rax()).as_rc(), five);
+    assert_eq!(dfg.get_def_site(dfg.get_use(0x1a, Location::rcx()).as_rc()), (0x1a, DefSource::Phi));
+
+    let rebuilt = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+    assert_eq!(ssa_sites(&dfg), ssa_sites(&rebuilt));
+    assert_eq!(ssa_edges(&dfg), ssa_edges(&rebuilt));
+}
+
+#[test]
+fn test_edge_values_stay_on_their_edge() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+    use data::modifier::{InstructionModifiers, ModifierExpression};
+
+    let data: Vec<u8> = vec![
+        0x48, 0x89, 0xf9,                           // 0x00: mov rcx, rdi
+        0x48, 0x85, 0xc9,                           // 0x03: test rcx, rcx
+        0x74, 0x04,                                 // 0x06: je 0x0c
+        0x48, 0x89, 0xc8,                           // 0x08: mov rax, rcx
+        0xc3,                                       // 0x0b: ret
+        0x48, 0x89, 0xca,                           // 0x0c: mov rdx, rcx
+        0xc3,                                       // 0x0f: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    // only when the branch isn't taken is rcx known to not be zero.
+    modifiers.add_edge_modifier(0x00, 0x08, Some(Location::rcx()), ModifierExpression::IsNot(0));
+
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+
+    let original = dfg.get_def(0x00, Location::rcx()).as_rc();
+    let refined = dfg.get_transient_def(0x00, 0x08, Location::rcx()).as_rc();
+    assert_eq!(dfg.get_use(0x08, Location::rcx()).as_rc(), refined);
+    // the other edge out of the block doesn't see the value defined on the first.
+    assert_eq!(dfg.get_use(0x0c, Location::rcx()).as_rc(), original);
+}
@@ -1,9 +1,7 @@
-use std::rc::Rc;
 use std::fmt::Debug;
 use std::fmt;
-use std::cell::RefCell;
-use std::hash::Hash;
-use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::collections::{HashMap, HashSet};
 
 use yaxpeax_arch::Arch;
 use yaxpeax_arch::AddressDisplay;
@@ -12,14 +10,20 @@ use data::types::Typed;
 use data::modifier;
 use data::ValueLocations;
 use data::Direction;
+use analyses::static_single_assignment::{DFGRef, ValueGraph};
 
 
 use num_traits::Zero;
 
-pub type DFGRef<A> = Rc<RefCell<Value<A>>>;
 pub type RWMap<A> = HashMap<(<A as ValueLocations>::Location, Direction), DFGRef<A>>;
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct PhiOp<A: SSAValues> { pub out: DFGRef<A>, pub ins: Vec<DFGRef<A>> }
+
+impl<A: SSAValues> Clone for PhiOp<A> {
+    fn clone(&self) -> Self {
+        PhiOp { out: self.out, ins: self.ins.clone() }
+    }
+}
 pub type PhiLocations<A> = HashMap<<A as ValueLocations>::Location, PhiOp<A>>;
 
 #[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
@@ -38,6 +42,17 @@ pub enum DefSource<A> {
     External,
 }
 
+/// somewhere a value is read. `Phi` is an operand of the phi for `Loc` at the start of the block
+/// at `Addr`, and `Between` is a read on the edge between two blocks, as recorded in
+/// `SSA::control_dependent_values`.
+#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+pub enum UseSite<Addr, Loc> {
+    Instruction(Addr),
+    Modifier(Addr, modifier::Precedence),
+    Phi(Addr, Loc),
+    Between(Addr, Addr),
+}
+
 impl <A: yaxpeax_arch::AddressDisplay> fmt::Display for DefSource<A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -51,19 +66,19 @@ impl <A: yaxpeax_arch::AddressDisplay> fmt::Display for DefSource<A> {
     }
 }
 
-// Look. Just rewrite this as a graph (one day). Vertices are DFGRef, edges ae data
-// dependences. Secondary graph with vertices (DFGRef | Address) where edges are Address -> DFGRef
-// (define) -> Address (use of DFGRef)
-//
-// in the mean time, DFGRef are growing an (Address, Location) field lol
+// values are vertices in `values`, with edges to the sites that write and read them. the maps
+// below are how to find a value from an address.
 #[derive(Debug)]
 pub struct SSA<A: Arch + SSAValues> where A::Location: Hash + Eq, A::Address: Hash + Eq {
-    // TODO: Fairly sure these Rc<RefCell<...>> could all just be raw pointers
-    // these aren't individually freed so Rc shouldn't be necessary?
+    // every value cytron numbered, where it's written, and where it's read. every `DFGRef` in the
+    // maps below is an index into this.
+    pub values: ValueGraph<A>,
     pub instruction_values: HashMap<A::Address, RWMap<A>>,
     pub modifier_values: HashMap<(A::Address, modifier::Precedence), RWMap<A>>,
     pub control_dependent_values: HashMap<A::Address, HashMap<A::Address, RWMap<A>>>,
-    pub defs: HashMap<HashedValue<DFGRef<A>>, (A::Address, DefSource<A::Address>)>,
+    // every write also has a read of the value it replaces, to link the two. these are the
+    // `(site, location)` of reads that only exist for that reason, and aren't uses.
+    pub overwrites: HashSet<(UseSite<A::Address, A::Location>, A::Location)>,
     pub phi: HashMap<A::Address, PhiLocations<A>>,
     pub indirect_values: HashMap<A::Address, HashMap<A::Location, HashMap<(A::Data, Direction), DFGRef<A>>>>,
     // invariant:
@@ -74,6 +89,23 @@ pub struct SSA<A: Arch + SSAValues> where A::Location: Hash + Eq, A::Address: Ha
     pub external_defs: HashMap<A::Location, DFGRef<A>>,
 }
 
+// by hand rather than derived, since a derive would want `A: Clone`, and not every architecture
+// is.
+impl<A: Arch + SSAValues> Clone for SSA<A> where A::Location: Hash + Eq, A::Address: Hash + Eq {
+    fn clone(&self) -> Self {
+        SSA {
+            values: self.values.clone(),
+            instruction_values: self.instruction_values.clone(),
+            modifier_values: self.modifier_values.clone(),
+            control_dependent_values: self.control_dependent_values.clone(),
+            overwrites: self.overwrites.clone(),
+            phi: self.phi.clone(),
+            indirect_values: self.indirect_values.clone(),
+            external_defs: self.external_defs.clone(),
+        }
+    }
+}
+
 pub struct SSAQuery<'a, A: Arch + SSAValues> where A::Location: Hash + Eq, A::Address: Hash + Eq {
     ssa: &'a SSA<A>,
     addr: A::Address
@@ -95,8 +127,8 @@ impl<'a, A: Arch + SSAValues> ValueDescriptionQuery<A::Location> for SSAQuery<'a
     fn modifier_name(&self, loc: A::Location, dir: Direction, precedence: modifier::Precedence) -> Option<String> {
         if let Some(rwmap) = self.ssa.modifier_values.get(&(self.addr, precedence)) {
             if let Some(entry) = rwmap.get(&(loc.clone(), dir)) {
-                entry.borrow().name.as_ref().map(|x| x.clone()).or_else(|| {
-                    let entry = entry.borrow();
+                let entry = self.ssa.value(*entry);
+                entry.name.clone().or_else(|| {
                     if let Some(version) = entry.version() {
                         Some(format!("{:?}_{}", entry.location, version))
                     } else {
@@ -114,8 +146,8 @@ impl<'a, A: Arch + SSAValues> ValueDescriptionQuery<A::Location> for SSAQuery<'a
     fn modifier_value(&self, loc: A::Location, dir: Direction, precedence: modifier::Precedence) -> Option<String> {
         if let Some(rwmap) = self.ssa.modifier_values.get(&(self.addr, precedence)) {
             if let Some(entry) = rwmap.get(&(loc.clone(), dir)) {
-                entry.borrow().name.as_ref().map(|x| x.clone()).or_else(|| {
-                    let entry = entry.borrow();
+                let entry = self.ssa.value(*entry);
+                entry.name.clone().or_else(|| {
                     if let Some(data) = entry.data.as_ref() {
                         Some(format!("{:?})", data))
                     } else {
@@ -183,6 +215,18 @@ impl <A: SSAValues> fmt::Display for Value<A> {
     }
 }
 
+impl <A: SSAValues> Clone for Value<A> {
+    fn clone(&self) -> Self {
+        Value {
+            name: self.name.clone(),
+            location: self.location.clone(),
+            version: self.version,
+            data: self.data.clone(),
+            used: self.used,
+        }
+    }
+}
+
 impl <A: SSAValues> Hash for Value<A> where A::Location: Hash, A::Data: Hash {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.location.hash(state);
@@ -191,49 +235,45 @@ impl <A: SSAValues> Hash for Value<A> where A::Location: Hash, A::Data: Hash {
     }
 }
 
-pub struct DFGLValue<A: SSAValues> {
+/// a value of an `SSA` and what's known about it, like `get_def` finds.
+pub struct DFGLValue<'ssa, A: SSAValues + Arch> where A::Location: Hash + Eq, A::Address: Hash + Eq {
+    ssa: &'ssa SSA<A>,
     pub value: DFGRef<A>
 }
 
-use std::cell::Ref;
-impl <A: SSAValues> DFGLValue<A> {
-    pub fn update(&self, new_data: A::Data) {
-        // TODO: check to see if the new value conflicts with what we're setting?
-        self.value.borrow_mut().data.replace(new_data);
-    }
-    pub fn replace(&self, new_data: Option<A::Data>) {
-        // TODO: check to see if the new value conflicts with what we're setting?
-        std::mem::drop(std::mem::replace(&mut self.value.borrow_mut().data, new_data));
-    }
-    pub fn clear(&self) {
-        self.value.borrow_mut().data.take();
-    }
-    pub fn get_data(&self) -> Ref<Option<A::Data>> {
-        Ref::map(self.value.borrow(), |v| &v.data)
+impl <'ssa, A: SSAValues> DFGLValue<'ssa, A> where A::Location: Hash + Eq, A::Address: Hash + Eq {
+    pub fn get_data(&self) -> &'ssa Option<A::Data> {
+        &self.ssa.value(self.value).data
     }
     pub fn as_rc(self) -> DFGRef<A> {
         self.value
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct HashedValue<A: Clone> {
-    pub value: A
+/// like `DFGLValue`, but from `get_def_mut` and friends, so what's known about the value can be
+/// changed.
+pub struct DFGLValueMut<'ssa, A: SSAValues + Arch> where A::Location: Hash + Eq, A::Address: Hash + Eq {
+    ssa: &'ssa mut SSA<A>,
+    pub value: DFGRef<A>
 }
 
-use std::hash::Hasher;
-impl <A: SSAValues> Hash for HashedValue<DFGRef<A>> where Value<A>: Hash {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-//        let v: &RefCell<Value<A>> = &*self.value;
-        (self.value.as_ptr()).hash(state);
+impl <'ssa, A: SSAValues> DFGLValueMut<'ssa, A> where A::Location: Hash + Eq, A::Address: Hash + Eq {
+    pub fn update(&mut self, new_data: A::Data) {
+        // TODO: check to see if the new value conflicts with what we're setting?
+        self.ssa.value_mut(self.value).data.replace(new_data);
     }
-}
-
-impl <A: SSAValues> Eq for HashedValue<DFGRef<A>> { }
-
-impl <A: SSAValues> PartialEq for HashedValue<DFGRef<A>> {
-    fn eq(&self, other: &HashedValue<DFGRef<A>>) -> bool {
-        Rc::ptr_eq(&self.value, &other.value)
+    pub fn replace(&mut self, new_data: Option<A::Data>) {
+        // TODO: check to see if the new value conflicts with what we're setting?
+        std::mem::drop(std::mem::replace(&mut self.ssa.value_mut(self.value).data, new_data));
+    }
+    pub fn clear(&mut self) {
+        self.ssa.value_mut(self.value).data.take();
+    }
+    pub fn get_data(&self) -> &Option<A::Data> {
+        &self.ssa.value(self.value).data
+    }
+    pub fn as_rc(self) -> DFGRef<A> {
+        self.value
     }
 }
 
@@ -367,7 +407,6 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
             println!("  {:?}, {:?} -> {:?}", addr, precedence, rwmap);
         }
         // pub control_dependent_values: HashMap<A::Address, HashMap<A::Address, RWMap<A>>>,
-        // pub defs: HashMap<HashedValue<DFGRef<A>>, (A::Address, DefSource<A::Address>)>,
         // pub phi: HashMap<A::Address, PhiLocations<A>>,
         // pub indirect_values: HashMap<A::Address, HashMap<A::Location, HashMap<(A::Data, Direction), DFGRef<A>>>>,
         println!("external_defs:");
@@ -391,8 +430,8 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
 
         for map in self.instruction_values.values() {
             for dfgref in map.values() {
-                let dfgref = dfgref.borrow();
-                if dfgref.version == None && dfgref.used {
+                let dfgref = self.value(*dfgref);
+                if dfgref.version.is_none() && dfgref.used {
                     undefineds.push(dfgref.location.clone());
                 }
             }
@@ -400,8 +439,8 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
 
         for map in self.modifier_values.values() {
             for dfgref in map.values() {
-                let dfgref = dfgref.borrow();
-                if dfgref.version == None && dfgref.used {
+                let dfgref = self.value(*dfgref);
+                if dfgref.version.is_none() && dfgref.used {
                     undefineds.push(dfgref.location.clone());
                 }
             }
@@ -410,8 +449,8 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
         for map in self.control_dependent_values.values() {
             for innermap in map.values() {
                 for dfgref in innermap.values() {
-                    let dfgref = dfgref.borrow();
-                    if dfgref.version == None && dfgref.used {
+                    let dfgref = self.value(*dfgref);
+                    if dfgref.version.is_none() && dfgref.used {
                         undefineds.push(dfgref.location.clone());
                     }
                 }
@@ -420,11 +459,11 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
 
         for map in self.phi.values() {
             for (loc, phiop) in map.iter() {
-                if !phiop.out.borrow().used {
+                if !self.value(phiop.out).used {
                     continue;
                 }
                 for inref in phiop.ins.iter() {
-                    if inref.borrow().version == None {
+                    if self.value(*inref).version.is_none() {
                         undefineds.push(loc.clone());
                     }
                 }
@@ -434,6 +473,14 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
         undefineds
     }
 
+    pub fn value(&self, value: DFGRef<A>) -> &Value<A> {
+        self.values.value(value)
+    }
+
+    pub fn value_mut(&mut self, value: DFGRef<A>) -> &mut Value<A> {
+        self.values.value_mut(value)
+    }
+
     pub fn query_at<'a>(&'a self, addr: A::Address) -> SSAQuery<'a, A> {
         SSAQuery {
             ssa: self,
@@ -443,13 +490,13 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
     pub fn get_value(&self, addr: A::Address, loc: A::Location, dir: Direction) -> Option<DFGRef<A>> {
         self.instruction_values.get(&addr)
             .and_then(|addr_values| addr_values.get(&(loc, dir)))
-            .map(|x| Rc::clone(x))
+            .cloned()
     }
     pub fn get_transient_value(&self, from: A::Address, to: A::Address, loc: A::Location, dir: Direction) -> Option<DFGRef<A>> {
         self.control_dependent_values.get(&from)
             .and_then(|dests| dests.get(&to))
             .and_then(|values| values.get(&(loc, dir)))
-            .map(|x| Rc::clone(x))
+            .cloned()
     }
     pub fn try_get_def(&self, addr: A::Address, loc: A::Location) -> Option<DFGRef<A>> {
         self.get_value(addr, loc, Direction::Write)
@@ -461,36 +508,114 @@ impl <A: SSAValues> SSA<A> where A::Address: Hash + Eq, A::Location: Hash + Eq {
     // unreachable_unchecked!() for the None case here.
     //
     // that flag should also remove the try_get_* variants
-    pub fn get_def(&self, addr: A::Address, loc: A::Location) -> DFGLValue<A> {
+    pub fn get_def(&self, addr: A::Address, loc: A::Location) -> DFGLValue<'_, A> {
         DFGLValue {
-            value: self.get_value(addr, loc.clone(), Direction::Write)
-                .unwrap_or_else(|| {
-                    panic!("Failed to get def of {:?} at {}", loc.clone(), addr.show())
-                })
+            ssa: self,
+            value: self.expect_value(addr, loc, Direction::Write),
         }
     }
-    pub fn get_use(&self, addr: A::Address, loc: A::Location) -> DFGLValue<A> {
+    pub fn get_use(&self, addr: A::Address, loc: A::Location) -> DFGLValue<'_, A> {
         DFGLValue {
-            value: self.get_value(addr, loc.clone(), Direction::Read)
-                .unwrap_or_else(|| {
-                    panic!("Failed to get use of {:?} at {}", loc.clone(), addr.show())
-                })
+            ssa: self,
+            value: self.expect_value(addr, loc, Direction::Read),
+        }
+    }
+    pub fn get_def_mut(&mut self, addr: A::Address, loc: A::Location) -> DFGLValueMut<'_, A> {
+        DFGLValueMut {
+            value: self.expect_value(addr, loc, Direction::Write),
+            ssa: self,
+        }
+    }
+    pub fn get_use_mut(&mut self, addr: A::Address, loc: A::Location) -> DFGLValueMut<'_, A> {
+        DFGLValueMut {
+            value: self.expect_value(addr, loc, Direction::Read),
+            ssa: self,
         }
     }
+    fn expect_value(&self, addr: A::Address, loc: A::Location, dir: Direction) -> DFGRef<A> {
+        self.get_value(addr, loc.clone(), dir)
+            .unwrap_or_else(|| {
+                let access = if dir == Direction::Write { "def" } else { "use" };
+                panic!("Failed to get {} of {:?} at {}", access, loc.clone(), addr.show())
+            })
+    }
 
-    pub fn get_transient_def(&self, from: A::Address, to: A::Address, loc: A::Location) -> DFGLValue<A> {
-        DFGLValue { value: self.get_transient_value(from, to, loc, Direction::Write).unwrap() }
+    pub fn get_transient_def(&self, from: A::Address, to: A::Address, loc: A::Location) -> DFGLValue<'_, A> {
+        DFGLValue { ssa: self, value: self.get_transient_value(from, to, loc, Direction::Write).unwrap() }
+    }
+    pub fn get_transient_use(&self, from: A::Address, to: A::Address, loc: A::Location) -> DFGLValue<'_, A> {
+        DFGLValue { ssa: self, value: self.get_transient_value(from, to, loc, Direction::Read).unwrap() }
     }
-    pub fn get_transient_use(&self, from: A::Address, to: A::Address, loc: A::Location) -> DFGLValue<A> {
-        DFGLValue { value: self.get_transient_value(from, to, loc, Direction::Read).unwrap() }
+    pub fn get_transient_def_mut(&mut self, from: A::Address, to: A::Address, loc: A::Location) -> DFGLValueMut<'_, A> {
+        let value = self.get_transient_value(from, to, loc, Direction::Write).unwrap();
+        DFGLValueMut { ssa: self, value }
+    }
+
+    /// what's known about some value of this SSA, like `get_def_mut` but for a value found some
+    /// other way.
+    pub fn lvalue_mut(&mut self, value: DFGRef<A>) -> DFGLValueMut<'_, A> {
+        DFGLValueMut { ssa: self, value }
     }
 
     pub fn try_get_def_site(&self, value: DFGRef<A>) -> Option<&(A::Address, DefSource<A::Address>)> {
-        self.defs.get(&HashedValue { value: Rc::clone(&value) })
+        self.values.def_site(value)
+    }
+
+    /// every place `value` is read, in no particular order.
+    pub fn uses_of(&self, value: DFGRef<A>) -> &[UseSite<A::Address, A::Location>] {
+        self.values.uses_of(value)
+    }
+
+    /// whether the read of `loc` at `site` is only there because `loc` is written at `site` too.
+    pub fn is_overwrite(&self, site: &UseSite<A::Address, A::Location>, loc: &A::Location) -> bool {
+        self.overwrites.contains(&(site.clone(), loc.clone()))
+    }
+
+    /// (re)build the uses in `values` from the reads in `instruction_values`, `modifier_values`,
+    /// `control_dependent_values`, and phi operands, except for reads in `overwrites`.
+    /// `generate_ssa` does this once values are numbered, but anything that edits those maps
+    /// afterward should call this again.
+    pub fn index_uses(&mut self) {
+        self.values.clear_uses();
+        let values = &mut self.values;
+        let mut add_use = |value: &DFGRef<A>, site: UseSite<A::Address, A::Location>| {
+            values.add_use(*value, site);
+        };
+
+        let overwrites = &self.overwrites;
+        let mut add_reads = |rwmap: &RWMap<A>, site: UseSite<A::Address, A::Location>| {
+            for ((loc, dir), value) in rwmap.iter() {
+                if *dir == Direction::Read && !overwrites.contains(&(site.clone(), loc.clone())) {
+                    add_use(value, site.clone());
+                }
+            }
+        };
+
+        for (addr, rwmap) in self.instruction_values.iter() {
+            add_reads(rwmap, UseSite::Instruction(*addr));
+        }
+
+        for ((addr, precedence), rwmap) in self.modifier_values.iter() {
+            add_reads(rwmap, UseSite::Modifier(*addr, *precedence));
+        }
+
+        for (from, dests) in self.control_dependent_values.iter() {
+            for (to, rwmap) in dests.iter() {
+                add_reads(rwmap, UseSite::Between(*from, *to));
+            }
+        }
+
+        for (addr, phis) in self.phi.iter() {
+            for (loc, phi) in phis.iter() {
+                for value in phi.ins.iter() {
+                    add_use(value, UseSite::Phi(*addr, loc.clone()));
+                }
+            }
+        }
     }
 
     pub fn get_def_site(&self, value: DFGRef<A>) -> (A::Address, DefSource<A::Address>) {
-        match self.defs.get(&HashedValue { value: Rc::clone(&value) }) {
+        match self.values.def_site(value) {
             Some(site) => *site,
             None => {
                 // This is a rather serious bug - we have a value but it was never defined.
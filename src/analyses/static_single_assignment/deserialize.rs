@@ -1,9 +1,8 @@
 use std::collections::HashMap;
 use std::fmt;
 use data::Direction;
-use std::rc::Rc;
-use std::cell::RefCell;
-use analyses::static_single_assignment::data::{HashedValue, Value, PhiOp, SSA, SSAValues};
+use analyses::static_single_assignment::{DFGRef, ValueGraph};
+use analyses::static_single_assignment::data::{DefSource, PhiOp, SSA, SSAValues, UseSite};
 use serialize::Memoable;
 use yaxpeax_arch::Arch;
 use serde::de::{self, Deserialize, Deserializer, Visitor, SeqAccess};
@@ -11,7 +10,7 @@ use serde::de::{self, Deserialize, Deserializer, Visitor, SeqAccess};
 struct DFGVisitor<A> { _marker: std::marker::PhantomData<A> }
 
 impl<'de, A: Arch + SSAValues + 'static> Visitor<'de> for DFGVisitor<A>
-where HashedValue<Rc<RefCell<Value<A>>>>: Memoable {
+where DFGRef<A>: Memoable<Context=ValueGraph<A>> {
     type Value = SSA<A>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -22,7 +21,7 @@ where HashedValue<Rc<RefCell<Value<A>>>>: Memoable {
     where
         V: SeqAccess<'de>,
     {
-        type Memoed<A> = <HashedValue<Rc<RefCell<Value<A>>>> as Memoable>::Out;
+        type Memoed<A> = <DFGRef<A> as Memoable>::Out;
 
         // indices are numbers to rebuild memos from
         let instruction_values: HashMap<A::Address, HashMap<(A::Location, Direction), u32>> = seq.next_element()?
@@ -38,39 +37,40 @@ where HashedValue<Rc<RefCell<Value<A>>>>: Memoable {
         let memos: Vec<Memoed<A>> = seq.next_element()?
             .ok_or_else(|| de::Error::invalid_length(2, &self))?;
 
+        let overwrites: Vec<(A::Address, A::Location)> = seq.next_element()?
+            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+
         // ok! we've read all the data out, now to turn it into something useful.
         //
         // first, rebuild memos into their original data
-        let mut values: HashMap<u32, HashedValue<Rc<RefCell<Value<A>>>>> = HashMap::new();
+        let mut graph: ValueGraph<A> = ValueGraph::new();
+        let mut values: HashMap<u32, DFGRef<A>> = HashMap::new();
 
         // TODO: why is it correct to ignore v?
         for (i, _v) in memos.iter().enumerate() {
             if values.contains_key(&(i as u32)) {
                 continue;
             }
-            let to_insert = <HashedValue<Rc<RefCell<Value<A>>>> as Memoable>::dememoize(i as u32, &memos, &mut values);
+            let to_insert = <DFGRef<A> as Memoable>::dememoize(i as u32, &memos, &mut values, &mut graph);
 
             // it is technically possible (in the case of reference cycles) that we just inserted
             // to_insert already, but a correct implementation would then also maintain the case
-            // that to_insert is an Rc to the same RefCell as the existing entry for to_insert's
+            // that to_insert is the same value as the existing entry for to_insert's
             // index, i. so correct implementations will never be made wrong by just inserting
             // to_insert again.
 
             values.insert(i as u32, to_insert);
         }
 
-        // TODO:
-        // type ValueEntry<A> = HashedValue<Rc<RefCell<Value<A>>>>;
-
         // we have all the values! on to the easy part of rebuilding maps.
         let mut dememoized_phis: HashMap<A::Address, HashMap<A::Location, PhiOp<A>>> = HashMap::new();
-        let mut dememoized_values: HashMap<A::Address, HashMap<(A::Location, Direction), Rc<RefCell<Value<A>>>>> = HashMap::new();
+        let mut dememoized_values: HashMap<A::Address, HashMap<(A::Location, Direction), DFGRef<A>>> = HashMap::new();
 
         for (addr, vmap) in phis.iter() {
             let mut dememoized_valuemap = HashMap::new();
             for (loc, (phi_ins, phi_out)) in vmap.iter() {
-                let dememoized_phi_ins: Vec<Rc<RefCell<Value<A>>>> = phi_ins.iter().map(|idx| values[idx].clone().value).collect();
-                let dememoized_phi_out: Rc<RefCell<Value<A>>> = values[phi_out].clone().value;
+                let dememoized_phi_ins: Vec<DFGRef<A>> = phi_ins.iter().map(|idx| values[idx]).collect();
+                let dememoized_phi_out: DFGRef<A> = values[phi_out];
                 dememoized_valuemap.insert(loc.to_owned(), PhiOp { out: dememoized_phi_out, ins: dememoized_phi_ins });
             }
             dememoized_phis.insert(*addr, dememoized_valuemap);
@@ -79,32 +79,51 @@ where HashedValue<Rc<RefCell<Value<A>>>>: Memoable {
         for (addr, locmap) in instruction_values.iter() {
             let mut value_locmap = HashMap::new();
             for (locdir, idx) in locmap.iter() {
-                value_locmap.insert(locdir.clone(), values[idx].clone().value);
+                value_locmap.insert(locdir.clone(), values[idx]);
             }
             dememoized_values.insert(*addr, value_locmap);
         }
 
-        Ok(SSA {
+        // def sites aren't serialized either, but writes at instructions and phis say where
+        // everything that was serialized is defined.
+        for (addr, vmap) in dememoized_values.iter() {
+            for ((_loc, dir), value) in vmap.iter() {
+                if *dir == Direction::Write {
+                    graph.set_def_site(*value, (*addr, DefSource::Instruction));
+                }
+            }
+        }
+        for (addr, phis) in dememoized_phis.iter() {
+            for phi in phis.values() {
+                graph.set_def_site(phi.out, (*addr, DefSource::Phi));
+            }
+        }
+
+        let mut ssa = SSA {
+            values: graph,
             instruction_values: dememoized_values,
             modifier_values: HashMap::new(),
             control_dependent_values: HashMap::new(),
-            defs: HashMap::new(),
+            overwrites: overwrites.into_iter().map(|(addr, loc)| (UseSite::Instruction(addr), loc)).collect(),
             phi: dememoized_phis,
             indirect_values: HashMap::new(), // TODO: serialize and deserialize
             external_defs: HashMap::new(), // TODO: serialize and deserialize
-        })
+        };
+        // uses aren't serialized, they're entirely derived from the maps above.
+        ssa.index_uses();
+        Ok(ssa)
     }
 }
 
 impl<'de, A: Arch + SSAValues + 'static> Deserialize<'de> for SSA<A>
 where
-    HashedValue<Rc<RefCell<Value<A>>>>: Memoable,
+    DFGRef<A>: Memoable<Context=ValueGraph<A>>,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        const FIELDS: &'static [&'static str] = &["instruction_values", "phis", "memos"];
+        const FIELDS: &'static [&'static str] = &["instruction_values", "phis", "memos", "overwrites"];
         let visitor: DFGVisitor<A> = DFGVisitor { _marker: std::marker::PhantomData };
         deserializer.deserialize_struct("SSA<A>", FIELDS, visitor)
     }
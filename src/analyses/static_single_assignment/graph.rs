@@ -0,0 +1,289 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use analyses::static_single_assignment::data::{DefSource, SSAValues, UseSite, Value};
+
+/// a value in an `SSA`, by its index into the SSA's `ValueGraph`. refs are plain indices: they're
+/// `Copy`, compare and hash by which value they are, and mean nothing to any other graph.
+pub struct DFGRef<A: SSAValues> {
+    index: u32,
+    _arch: PhantomData<fn() -> A>,
+}
+
+impl<A: SSAValues> DFGRef<A> {
+    pub fn index(&self) -> usize {
+        self.index as usize
+    }
+
+    /// the value at `index`, for code that numbered values some other way, like serialization.
+    /// nothing checks that `index` is in any particular graph.
+    pub fn from_index(index: usize) -> Self {
+        DFGRef {
+            index: index as u32,
+            _arch: PhantomData,
+        }
+    }
+}
+
+impl<A: SSAValues> Copy for DFGRef<A> {}
+
+impl<A: SSAValues> Clone for DFGRef<A> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A: SSAValues> PartialEq for DFGRef<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<A: SSAValues> Eq for DFGRef<A> {}
+
+impl<A: SSAValues> PartialOrd for DFGRef<A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<A: SSAValues> Ord for DFGRef<A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.index.cmp(&other.index)
+    }
+}
+
+impl<A: SSAValues> Hash for DFGRef<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+    }
+}
+
+impl<A: SSAValues> fmt::Debug for DFGRef<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.index)
+    }
+}
+
+impl<A: SSAValues> fmt::Display for DFGRef<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.index)
+    }
+}
+
+/// everything an `SSA` knows about one value: the value itself, where it's written, and where
+/// it's read.
+pub struct ValueNode<A: SSAValues> {
+    pub value: Value<A>,
+    /// where the value is written. values from outside the function have no def.
+    pub def: Option<(A::Address, DefSource<A::Address>)>,
+    pub uses: Vec<UseSite<A::Address, A::Location>>,
+}
+
+impl<A: SSAValues> fmt::Debug for ValueNode<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ValueNode")
+            .field("value", &self.value)
+            .field("def", &self.def)
+            .field("uses", &self.uses)
+            .finish()
+    }
+}
+
+impl<A: SSAValues> Clone for ValueNode<A> {
+    fn clone(&self) -> Self {
+        ValueNode {
+            value: self.value.clone(),
+            def: self.def,
+            uses: self.uses.clone(),
+        }
+    }
+}
+
+/// the values of an `SSA` and the def and use edges between them, as one array indexed by
+/// `DFGRef`. the graph owns every value in it, so cloning one is a copy of the array, and it's
+/// `Send` and `Sync` whenever the architecture's locations and data are.
+///
+/// values are never removed. values cytron stops using, like phis it takes back out, just have no
+/// def and no uses.
+pub struct ValueGraph<A: SSAValues> {
+    values: Vec<ValueNode<A>>,
+}
+
+impl<A: SSAValues> fmt::Debug for ValueGraph<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map()
+            .entries(self.values.iter().enumerate().map(|(idx, node)| (DFGRef::<A>::from_index(idx), node)))
+            .finish()
+    }
+}
+
+impl<A: SSAValues> Clone for ValueGraph<A> {
+    fn clone(&self) -> Self {
+        ValueGraph {
+            values: self.values.clone(),
+        }
+    }
+}
+
+impl<A: SSAValues> Default for ValueGraph<A> {
+    fn default() -> Self {
+        ValueGraph {
+            values: Vec::new(),
+        }
+    }
+}
+
+impl<A: SSAValues> ValueGraph<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn alloc(&mut self, value: Value<A>) -> DFGRef<A> {
+        let value_ref = DFGRef::from_index(self.values.len());
+        self.values.push(ValueNode {
+            value,
+            def: None,
+            uses: Vec::new(),
+        });
+        value_ref
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn contains(&self, value: DFGRef<A>) -> bool {
+        value.index() < self.values.len()
+    }
+
+    pub fn node(&self, value: DFGRef<A>) -> &ValueNode<A> {
+        &self.values[value.index()]
+    }
+
+    pub fn value(&self, value: DFGRef<A>) -> &Value<A> {
+        &self.values[value.index()].value
+    }
+
+    pub fn value_mut(&mut self, value: DFGRef<A>) -> &mut Value<A> {
+        &mut self.values[value.index()].value
+    }
+
+    pub fn def_site(&self, value: DFGRef<A>) -> Option<&(A::Address, DefSource<A::Address>)> {
+        self.values[value.index()].def.as_ref()
+    }
+
+    /// record that `value` is written at `site`, returning where it was written before, if
+    /// anywhere.
+    pub fn set_def_site(&mut self, value: DFGRef<A>, site: (A::Address, DefSource<A::Address>)) -> Option<(A::Address, DefSource<A::Address>)> {
+        self.values[value.index()].def.replace(site)
+    }
+
+    pub fn clear_def_site(&mut self, value: DFGRef<A>) -> Option<(A::Address, DefSource<A::Address>)> {
+        self.values[value.index()].def.take()
+    }
+
+    pub fn uses_of(&self, value: DFGRef<A>) -> &[UseSite<A::Address, A::Location>] {
+        &self.values[value.index()].uses
+    }
+
+    pub fn add_use(&mut self, value: DFGRef<A>, site: UseSite<A::Address, A::Location>) {
+        self.values[value.index()].uses.push(site);
+    }
+
+    /// forget one read of `value` at `site`, if there is one.
+    pub fn remove_use(&mut self, value: DFGRef<A>, site: &UseSite<A::Address, A::Location>) {
+        let uses = &mut self.values[value.index()].uses;
+        if let Some(idx) = uses.iter().position(|other| other == site) {
+            uses.swap_remove(idx);
+        }
+    }
+
+    /// forget every use of every value, like before uses are indexed again.
+    pub fn clear_uses(&mut self) {
+        for node in self.values.iter_mut() {
+            node.uses.clear();
+        }
+    }
+
+    /// every value in the graph, in the order they were allocated.
+    pub fn iter(&self) -> impl Iterator<Item=DFGRef<A>> {
+        (0..self.values.len()).map(DFGRef::from_index)
+    }
+
+    /// every value that's written somewhere, and where.
+    pub fn defs(&self) -> impl Iterator<Item=(DFGRef<A>, &(A::Address, DefSource<A::Address>))> {
+        self.values.iter().enumerate()
+            .filter_map(|(idx, node)| node.def.as_ref().map(|site| (DFGRef::from_index(idx), site)))
+    }
+}
+
+#[test]
+fn test_value_graph() {
+    use std::thread;
+
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::static_single_assignment::SSA;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Data, Location, NoDisambiguation};
+
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SSA<yaxpeax_x86::x86_64>>();
+    assert_send_sync::<SSA<yaxpeax_arm::armv7::ARMv7>>();
+    assert_send_sync::<SSA<yaxpeax_arm::armv8::a64::ARMv8>>();
+
+    let data: Vec<u8> = vec![
+        0x48, 0xc7, 0xc1, 0x00, 0x00, 0x00, 0x00,   // 0x00: mov rcx, 0
+        0x48, 0xc7, 0xc0, 0x05, 0x00, 0x00, 0x00,   // 0x07: mov rax, 5
+        0x48, 0x85, 0xc9,                           // 0x0e: test rcx, rcx
+        0x74, 0x07,                                 // 0x11: je 0x1a
+        0x48, 0xc7, 0xc0, 0x07, 0x00, 0x00, 0x00,   // 0x13: mov rax, 7
+        0x48, 0x83, 0xc0, 0x01,                     // 0x1a: add rax, 1
+        0xc3,                                       // 0x1e: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let mut dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    // the add reads a phi of both `mov rax`, and the edges agree with the phi.
+    let sum_in = dfg.get_use(0x1a, Location::rax()).as_rc();
+    assert_eq!(dfg.get_def_site(sum_in), (0x1a, DefSource::Phi));
+    let phi = &dfg.phi[&0x1a][&Location::rax()];
+    assert_eq!(phi.out, sum_in);
+    let mut ins = phi.ins.clone();
+    ins.sort();
+    let mut expected = vec![dfg.get_def(0x07, Location::rax()).as_rc(), dfg.get_def(0x13, Location::rax()).as_rc()];
+    expected.sort();
+    assert_eq!(ins, expected);
+    let first = dfg.get_def(0x07, Location::rax()).as_rc();
+    assert_eq!(dfg.uses_of(first), &[UseSite::Phi(0x1a, Location::rax())]);
+    assert_eq!(dfg.try_get_def(0x1a, Location::rcx()), None);
+    for (value, (addr, source)) in dfg.values.defs() {
+        if *source == DefSource::Instruction {
+            assert_eq!(dfg.try_get_def(*addr, dfg.value(value).location.clone()), Some(value));
+        }
+    }
+
+    // clones are independent of the original, and can go to another thread.
+    let original = dfg.clone();
+    dfg.get_def_mut(0x07, Location::rax()).update(Data::Concrete(5, None));
+    assert!(original.get_def(0x07, Location::rax()).get_data().is_none());
+    let found = thread::spawn(move || {
+        dfg.get_def(0x07, Location::rax()).get_data().clone()
+    }).join().unwrap();
+    assert_eq!(found, Some(Data::Concrete(5, None)));
+}
@@ -1,7 +1,9 @@
 pub mod cytron;
 mod data;
 mod deserialize;
+mod graph;
 mod serialize;
 
 pub use analyses::static_single_assignment::data::SSA;
-pub use analyses::static_single_assignment::data::{DataDisplay, DefSource, DFGRebase, DFGRef, RWMap, PhiLocations, NoValueDescriptions, Value, DFGLValue, HashedValue, SSAValues, ValueDescriptionQuery};
+pub use analyses::static_single_assignment::graph::{DFGRef, ValueGraph, ValueNode};
+pub use analyses::static_single_assignment::data::{DataDisplay, DefSource, DFGRebase, RWMap, PhiLocations, PhiOp, NoValueDescriptions, Value, DFGLValue, DFGLValueMut, SSAValues, UseSite, ValueDescriptionQuery};
@@ -1,12 +1,11 @@
 use std::collections::HashMap;
-use std::rc::Rc;
 use serde::{Serialize, Serializer};
 use serde::ser::{SerializeMap, SerializeStruct, SerializeSeq};
 use serialize::{Memoable, Memos, MemoizingSerializer};
-use analyses::static_single_assignment::{SSA, SSAValues, HashedValue, DFGRef, RWMap, PhiLocations};
+use analyses::static_single_assignment::{SSA, SSAValues, DFGRef, RWMap, PhiLocations, UseSite, ValueGraph};
 use yaxpeax_arch::Arch;
 
-impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, HashMap<A::Address, PhiLocations<A>>, HashedValue<DFGRef<A>>> {
+impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, HashMap<A::Address, PhiLocations<A>>, DFGRef<A>> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut phi_map = serializer.serialize_map(Some(self.inner.len()))?;
         for (addr, phis) in self.inner.iter() {
@@ -18,21 +17,21 @@ impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, Has
     }
 }
 
-impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, PhiLocations<A>, HashedValue<DFGRef<A>>> {
+impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, PhiLocations<A>, DFGRef<A>> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut location_phis_map = serializer.serialize_map(Some(self.inner.len()))?;
         for (loc, phispec) in self.inner.iter() {
             let new_phiargs: Vec<u32> = phispec.ins.iter().map(|v| {
-                self.id_of(HashedValue { value: Rc::clone(v) })
+                self.id_of(*v)
             }).collect();
-            let newvalue = (self.id_of(HashedValue { value: Rc::clone(&phispec.out) }), new_phiargs);
+            let newvalue = (self.id_of(phispec.out), new_phiargs);
             location_phis_map.serialize_entry(&format!("{:?}", loc), &newvalue)?;
         }
         location_phis_map.end()
     }
 }
 
-impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, HashMap<A::Address, RWMap<A>>, HashedValue<DFGRef<A>>> {
+impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, HashMap<A::Address, RWMap<A>>, DFGRef<A>> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut version_maps = serializer.serialize_map(Some(self.inner.len()))?;
         for (k, m) in self.inner.iter() {
@@ -44,24 +43,31 @@ impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, Has
     }
 }
 
-impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, RWMap<A>, HashedValue<DFGRef<A>>> {
+impl <'a, 'b, A: Arch + SSAValues> Serialize for MemoizingSerializer<'a, 'b, RWMap<A>, DFGRef<A>> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let mut version_map = serializer.serialize_map(Some(self.inner.len()))?;
         for ((loc, dir), v) in self.inner.iter() {
             let s = format!("loc={:?}:dir={:?}", loc, dir);
-            version_map.serialize_entry(&s, &self.id_of(HashedValue { value: Rc::clone(v) })).unwrap();
+            version_map.serialize_entry(&s, &self.id_of(*v)).unwrap();
         };
         version_map.end()
     }
 }
 
-impl <A: Arch + SSAValues> Serialize for Memos<HashedValue<DFGRef<A>>> where HashedValue<DFGRef<A>>: Memoable {
+/// memos of values, serialized out of the graph the values are in.
+struct ValueMemos<'a, A: SSAValues> {
+    memos: &'a Memos<DFGRef<A>>,
+    values: &'a ValueGraph<A>,
+}
+
+impl <'a, A: Arch + SSAValues> Serialize for ValueMemos<'a, A> where DFGRef<A>: Memoable<Context=ValueGraph<A>> {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut seq = serializer.serialize_seq(Some(self.node_ids.len()))?;
-        for i in 0..self.node_ids.len() {
-            for (k, v) in self.node_ids.iter() {
+        let node_ids = &self.memos.node_ids;
+        let mut seq = serializer.serialize_seq(Some(node_ids.len()))?;
+        for i in 0..node_ids.len() {
+            for (k, v) in node_ids.iter() {
                 if (i as u32) == *v {
-                    seq.serialize_element(&k.memoize(&self.node_ids))?;
+                    seq.serialize_element(&k.memoize(self.values, node_ids))?;
                 }
             }
         }
@@ -69,11 +75,11 @@ impl <A: Arch + SSAValues> Serialize for Memos<HashedValue<DFGRef<A>>> where Has
     }
 }
 
-impl <A: Arch + SSAValues + 'static> Serialize for SSA<A> where HashedValue<DFGRef<A>>: Memoable {
+impl <A: Arch + SSAValues + 'static> Serialize for SSA<A> where DFGRef<A>: Memoable<Context=ValueGraph<A>> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut memoizer: Memos<HashedValue<DFGRef<A>>> = Memos::new();
+        let mut memoizer: Memos<DFGRef<A>> = Memos::new();
 
-        let mut ssa_serializer = serializer.serialize_struct("SSA", 3)?;
+        let mut ssa_serializer = serializer.serialize_struct("SSA", 4)?;
 
         {
             let values = MemoizingSerializer::new(&mut memoizer, &self.instruction_values);
@@ -85,7 +91,17 @@ impl <A: Arch + SSAValues + 'static> Serialize for SSA<A> where HashedValue<DFGR
             ssa_serializer.serialize_field("phis", &phis)?;
         }
 
-        ssa_serializer.serialize_field("memos", &memoizer)?;
+        ssa_serializer.serialize_field("memos", &ValueMemos { memos: &memoizer, values: &self.values })?;
+
+        // only instruction values are serialized, so only overwrites at instructions are
+        // meaningful after a round trip.
+        let overwrites: Vec<(&A::Address, &A::Location)> = self.overwrites.iter().filter_map(|(site, loc)| {
+            match site {
+                UseSite::Instruction(addr) => Some((addr, loc)),
+                _ => None,
+            }
+        }).collect();
+        ssa_serializer.serialize_field("overwrites", &overwrites)?;
 
         ssa_serializer.end()
 
@@ -0,0 +1,508 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+
+use petgraph::Direction as EdgeDirection;
+
+use analyses::control_flow::ControlFlowGraph;
+use analyses::slicing::covers;
+use analyses::static_single_assignment::{DFGRef, RWMap, SSA, SSAValues, UseSite};
+use arch::{AbiDefaults, FunctionImpl, FunctionRepr};
+use data::Direction;
+use data::modifier::{InstructionModifiers, Precedence};
+
+/// where tainted data comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaintSource {
+    /// the value calls to `function` return, like for `getenv`.
+    Return { function: String },
+    /// the memory argument `argument` of `function` points to, once the call's done, like the
+    /// buffer for `recv`, `read`, or `fgets`. arguments count from zero.
+    ArgumentPointee { function: String, argument: usize },
+}
+
+impl TaintSource {
+    pub fn function(&self) -> &str {
+        match self {
+            TaintSource::Return { function } |
+            TaintSource::ArgumentPointee { function, .. } => function,
+        }
+    }
+}
+
+/// where tainted data shouldn't end up.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaintSink {
+    /// argument `argument` of `function`, like the length for `memcpy`.
+    Argument { function: String, argument: usize },
+    /// the memory argument `argument` of `function` points to, like the command for `system`.
+    ArgumentPointee { function: String, argument: usize },
+}
+
+impl TaintSink {
+    pub fn function(&self) -> &str {
+        match self {
+            TaintSink::Argument { function, .. } |
+            TaintSink::ArgumentPointee { function, .. } => function,
+        }
+    }
+}
+
+/// the sources and sinks to look for flows between. functions are matched by name, so these work
+/// for imports as long as something named them.
+#[derive(Debug, Clone, Default)]
+pub struct TaintRules {
+    pub sources: Vec<TaintSource>,
+    pub sinks: Vec<TaintSink>,
+}
+
+impl TaintRules {
+    pub fn new() -> Self {
+        TaintRules::default()
+    }
+
+    pub fn source_return(mut self, function: &str) -> Self {
+        self.sources.push(TaintSource::Return { function: function.to_string() });
+        self
+    }
+
+    pub fn source_pointee(mut self, function: &str, argument: usize) -> Self {
+        self.sources.push(TaintSource::ArgumentPointee { function: function.to_string(), argument });
+        self
+    }
+
+    pub fn sink(mut self, function: &str, argument: usize) -> Self {
+        self.sinks.push(TaintSink::Argument { function: function.to_string(), argument });
+        self
+    }
+
+    pub fn sink_pointee(mut self, function: &str, argument: usize) -> Self {
+        self.sinks.push(TaintSink::ArgumentPointee { function: function.to_string(), argument });
+        self
+    }
+}
+
+/// tainted data getting from a source to a sink.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaintFlow<Addr> {
+    pub source: TaintSource,
+    pub sink: TaintSink,
+    /// the call that is the source, each instruction the data went through, and the call that is
+    /// the sink.
+    pub path: Vec<Addr>,
+}
+
+/// some bytes of a region: offsets `start` through `end` from wherever the region starts. an `end`
+/// of `None` runs on as far as the region does, like the memory a pointer passed to a callee
+/// points to - nothing says how much of it the callee uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extent<R> {
+    pub region: R,
+    pub start: i64,
+    pub end: Option<i64>,
+}
+
+impl<R: Eq> Extent<R> {
+    /// all of `region`, for accesses at an offset that isn't known.
+    pub fn whole(region: R) -> Self {
+        Extent { region, start: i64::MIN, end: None }
+    }
+
+    /// `region` from `start` on.
+    pub fn from(region: R, start: i64) -> Self {
+        Extent { region, start, end: None }
+    }
+
+    pub fn overlaps(&self, other: &Extent<R>) -> bool {
+        self.region == other.region &&
+            self.end.map(|end| other.start <= end).unwrap_or(true) &&
+            other.end.map(|end| self.start <= end).unwrap_or(true)
+    }
+}
+
+/// how memory is described in an architecture's `SSA`, so taint can follow pointers.
+///
+/// a region is some memory a pointer points into, like what `MemoryLayout` finds as the base of
+/// accesses, and taint is tracked for `Extent`s of those. architectures that can't say leave these
+/// as `None`, and only registers are followed. accesses `MemoryLayout` couldn't resolve aren't
+/// followed either - taint would otherwise spread to everything read from memory after the first
+/// tainted store.
+pub trait TaintSemantics: SSAValues + Sized {
+    type Region: Hash + Eq + Clone;
+
+    /// the memory the address in `pointer` points to.
+    fn pointer_region(ssa: &SSA<Self>, pointer: &DFGRef<Self>) -> Option<Extent<Self::Region>>;
+    /// the memory a memory location covers, if it's one `MemoryLayout` resolved.
+    fn location_region(ssa: &SSA<Self>, loc: &Self::Location) -> Option<Extent<Self::Region>>;
+    /// the memory accessed at `address`, for keys of `SSA::indirect_values`.
+    fn access_region(ssa: &SSA<Self>, address: &Self::Data) -> Option<Extent<Self::Region>>;
+    /// whether an instruction writing `loc` writes something computed from what it read, rather
+    /// than just moving an address along - like the stack pointer in a `push`, which doesn't
+    /// become tainted because what's pushed is.
+    fn carries_data(loc: &Self::Location) -> bool;
+}
+
+/// find flows from `rules.sources` to `rules.sinks` in `ssa`, which must be built from `cfg` with
+/// `modifiers` so calls read their arguments and write their return values.
+///
+/// taint goes from a value to everything written by whatever reads it, except through calls, where
+/// it only goes from arguments to return values. reads and writes in `SSA::indirect_values` count
+/// as the instruction's own. flows are only reported for sinks that can run after their source,
+/// and only the first path to each sink is reported.
+pub fn find_taint_flows<A>(cfg: &ControlFlowGraph<A::Address>, ssa: &SSA<A>, modifiers: &InstructionModifiers<A>, rules: &TaintRules) -> Vec<TaintFlow<A::Address>> where
+    A: TaintSemantics,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults + std::fmt::Debug,
+{
+    let mut calls: Vec<(A::Address, FunctionImpl<A::Location>)> = modifiers.calls.keys()
+        .filter_map(|addr| modifiers.callee(*addr).map(|callee| (*addr, callee)))
+        .collect();
+    calls.sort_by_key(|(addr, _)| *addr);
+
+    let mut indirect_reads: HashMap<DFGRef<A>, Vec<A::Address>> = HashMap::new();
+    for (addr, locs) in ssa.indirect_values.iter() {
+        for ((_, dir), value) in locs.values().flat_map(|accesses| accesses.iter()) {
+            if *dir == Direction::Read {
+                indirect_reads.entry(*value)
+                    .or_insert_with(Vec::new)
+                    .push(*addr);
+            }
+        }
+    }
+
+    let mut flows = Vec::new();
+    for (source_addr, source_callee) in calls.iter() {
+        for source in rules.sources.iter().filter(|source| source.function() == source_callee.name()) {
+            let after = After::new(cfg, *source_addr);
+            let mut taint = Taint {
+                ssa,
+                modifiers,
+                indirect_reads: &indirect_reads,
+                from: HashMap::new(),
+                regions: Vec::new(),
+                work: VecDeque::new(),
+            };
+            match source {
+                TaintSource::Return { .. } => {
+                    let returns: Vec<A::Location> = source_callee.layout().returns.iter().filter_map(|loc| loc.clone()).collect();
+                    if let Some(rwmap) = ssa.modifier_values.get(&(*source_addr, Precedence::After)) {
+                        for ((loc, dir), value) in rwmap.iter() {
+                            if *dir == Direction::Write && covers(&returns, loc) {
+                                taint.taint(value, None, None, None);
+                            }
+                        }
+                    }
+                }
+                TaintSource::ArgumentPointee { argument, .. } => {
+                    let region = match argument_value(ssa, *source_addr, source_callee, *argument).and_then(|pointer| A::pointer_region(ssa, &pointer)) {
+                        Some(region) => region,
+                        None => { continue; }
+                    };
+                    taint.regions.push((region.clone(), None));
+                    // the call wrote this memory, but SSA doesn't know that, so later reads of it
+                    // are still of whatever was there before. taint what those reads write instead.
+                    for (addr, rwmap) in ssa.instruction_values.iter() {
+                        if !after.contains(cfg, *addr) {
+                            continue;
+                        }
+                        let site = UseSite::Instruction(*addr);
+                        let reads_region = rwmap.keys().any(|(loc, dir)| {
+                            *dir == Direction::Read && !ssa.is_overwrite(&site, loc) && A::location_region(ssa, loc).map(|read| read.overlaps(&region)).unwrap_or(false)
+                        });
+                        if reads_region {
+                            taint.taint_writes(*addr, None);
+                        }
+                    }
+                    for (addr, locs) in ssa.indirect_values.iter() {
+                        if !after.contains(cfg, *addr) {
+                            continue;
+                        }
+                        let reads_region = locs.values().flat_map(|accesses| accesses.keys()).any(|(address, dir)| {
+                            *dir == Direction::Read && A::access_region(ssa, address).map(|read| read.overlaps(&region)).unwrap_or(false)
+                        });
+                        if reads_region {
+                            taint.taint_writes(*addr, None);
+                        }
+                    }
+                }
+            }
+            taint.run();
+
+            for (sink_addr, sink_callee) in calls.iter() {
+                if !after.contains(cfg, *sink_addr) {
+                    continue;
+                }
+                for sink in rules.sinks.iter().filter(|sink| sink.function() == sink_callee.name()) {
+                    let path = match sink {
+                        TaintSink::Argument { argument, .. } => {
+                            argument_value(ssa, *sink_addr, sink_callee, *argument)
+                                .filter(|value| taint.is_tainted(value))
+                                .map(|value| taint.path(Some(&value)))
+                        }
+                        TaintSink::ArgumentPointee { argument, .. } => {
+                            argument_value(ssa, *sink_addr, sink_callee, *argument)
+                                .and_then(|pointer| A::pointer_region(ssa, &pointer))
+                                .and_then(|region| taint.tainted_memory(&region))
+                                .map(|value| taint.path(value.as_ref()))
+                        }
+                    };
+                    if let Some(mut path) = path {
+                        path.insert(0, *source_addr);
+                        path.push(*sink_addr);
+                        path.dedup();
+                        flows.push(TaintFlow {
+                            source: source.clone(),
+                            sink: sink.clone(),
+                            path,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    flows
+}
+
+/// the value of argument `argument` read by the modifier after the call at `addr`.
+fn argument_value<A: SSAValues>(ssa: &SSA<A>, addr: A::Address, callee: &FunctionImpl<A::Location>, argument: usize) -> Option<DFGRef<A>> where A::Location: AbiDefaults {
+    let loc = callee.layout().arguments.get(argument).cloned()??;
+    ssa.modifier_values.get(&(addr, Precedence::After))
+        .and_then(|rwmap| rwmap.get(&(loc, Direction::Read)))
+        .cloned()
+}
+
+/// the instructions that can run after the one at `addr`.
+struct After<Addr> {
+    addr: Addr,
+    block_end: Addr,
+    blocks: HashSet<Addr>,
+}
+
+impl<Addr: yaxpeax_arch::Address + std::fmt::Debug + petgraph::graphmap::NodeTrait> After<Addr> {
+    fn new(cfg: &ControlFlowGraph<Addr>, addr: Addr) -> Self {
+        let block = cfg.get_block(addr);
+        let mut blocks = HashSet::new();
+        let mut work = vec![block.start];
+        while let Some(next) = work.pop() {
+            for succ in cfg.graph.neighbors_directed(next, EdgeDirection::Outgoing) {
+                if blocks.insert(succ) {
+                    work.push(succ);
+                }
+            }
+        }
+        After { addr, block_end: block.end, blocks }
+    }
+
+    fn contains(&self, cfg: &ControlFlowGraph<Addr>, addr: Addr) -> bool {
+        (addr > self.addr && addr <= self.block_end) || self.blocks.contains(&cfg.get_block(addr).start)
+    }
+}
+
+/// some tainted memory, and a tainted value stored there. `None` is memory the source itself
+/// wrote.
+type TaintedMemory<A> = (Extent<<A as TaintSemantics>::Region>, Option<DFGRef<A>>);
+
+/// values tainted by one source so far, and how the taint got to each of them.
+struct Taint<'a, A: TaintSemantics> where A::Location: AbiDefaults {
+    ssa: &'a SSA<A>,
+    modifiers: &'a InstructionModifiers<A>,
+    indirect_reads: &'a HashMap<DFGRef<A>, Vec<A::Address>>,
+    /// for each tainted value, the tainted value it came from and the address where that
+    /// happened. values tainted directly by the source have neither.
+    from: HashMap<DFGRef<A>, (Option<DFGRef<A>>, Option<A::Address>)>,
+    regions: Vec<TaintedMemory<A>>,
+    work: VecDeque<DFGRef<A>>,
+}
+
+impl<'a, A: TaintSemantics> Taint<'a, A> where A::Location: AbiDefaults {
+    fn is_tainted(&self, value: &DFGRef<A>) -> bool {
+        self.from.contains_key(value)
+    }
+
+    /// the first tainted value stored anywhere in `region`, or `None` if it's memory the source
+    /// wrote. `None` at all if none of `region` is tainted.
+    fn tainted_memory(&self, region: &Extent<A::Region>) -> Option<Option<DFGRef<A>>> {
+        self.regions.iter()
+            .find(|(tainted, _)| tainted.overlaps(region))
+            .map(|(_, value)| *value)
+    }
+
+    fn taint(&mut self, value: &DFGRef<A>, from: Option<&DFGRef<A>>, addr: Option<A::Address>, region: Option<Extent<A::Region>>) {
+        if self.is_tainted(value) {
+            return;
+        }
+        let from = from.copied();
+        self.from.insert(*value, (from, addr));
+        let region = region.or_else(|| A::location_region(self.ssa, &self.ssa.value(*value).location));
+        if let Some(region) = region {
+            if !self.regions.iter().any(|(tainted, _)| *tainted == region) {
+                self.regions.push((region, Some(*value)));
+            }
+        }
+        self.work.push_back(*value);
+    }
+
+    /// taint the data written at `addr`, including through `indirect_values`.
+    fn taint_writes(&mut self, addr: A::Address, from: Option<&DFGRef<A>>) {
+        let ssa = self.ssa;
+        if let Some(rwmap) = ssa.instruction_values.get(&addr) {
+            for ((loc, dir), value) in rwmap.iter() {
+                if *dir == Direction::Write && A::carries_data(loc) {
+                    self.taint(value, from, Some(addr), None);
+                }
+            }
+        }
+        if let Some(locs) = ssa.indirect_values.get(&addr) {
+            for ((address, dir), value) in locs.values().flat_map(|accesses| accesses.iter()) {
+                if *dir == Direction::Write {
+                    self.taint(value, from, Some(addr), A::access_region(ssa, address));
+                }
+            }
+        }
+    }
+
+    fn taint_rwmap(&mut self, rwmap: Option<&'a RWMap<A>>, from: &DFGRef<A>, addr: Option<A::Address>, only: Option<&[A::Location]>) {
+        for ((loc, dir), value) in rwmap.into_iter().flat_map(|rwmap| rwmap.iter()) {
+            if *dir != Direction::Write {
+                continue;
+            }
+            if let Some(only) = only {
+                if !covers(only, loc) {
+                    continue;
+                }
+            }
+            self.taint(value, Some(from), addr, None);
+        }
+    }
+
+    fn run(&mut self) {
+        let ssa = self.ssa;
+        while let Some(value) = self.work.pop_front() {
+            let loc = ssa.value(value).location.clone();
+            for site in ssa.uses_of(value).iter() {
+                match site {
+                    UseSite::Instruction(addr) => {
+                        self.taint_writes(*addr, Some(&value));
+                    }
+                    UseSite::Modifier(addr, precedence) => {
+                        let rwmap = ssa.modifier_values.get(&(*addr, *precedence));
+                        let callee = if *precedence == Precedence::After {
+                            self.modifiers.callee(*addr)
+                        } else {
+                            None
+                        };
+                        match callee {
+                            Some(callee) => {
+                                let layout = callee.layout();
+                                let arguments: Vec<A::Location> = layout.arguments.iter().filter_map(|loc| loc.clone()).collect();
+                                if covers(&arguments, &loc) {
+                                    let returns: Vec<A::Location> = layout.returns.iter().filter_map(|loc| loc.clone()).collect();
+                                    self.taint_rwmap(rwmap, &value, Some(*addr), Some(&returns));
+                                } else {
+                                    self.taint_rwmap(rwmap, &value, Some(*addr), Some(std::slice::from_ref(&loc)));
+                                }
+                            }
+                            None => {
+                                self.taint_rwmap(rwmap, &value, Some(*addr), None);
+                            }
+                        }
+                    }
+                    UseSite::Phi(block, phi_loc) => {
+                        if let Some(phi) = ssa.phi.get(block).and_then(|phis| phis.get(phi_loc)) {
+                            self.taint(&phi.out, Some(&value), None, None);
+                        }
+                    }
+                    UseSite::Between(from, to) => {
+                        let rwmap = ssa.control_dependent_values.get(from).and_then(|edges| edges.get(to));
+                        self.taint_rwmap(rwmap, &value, None, None);
+                    }
+                }
+            }
+            let indirect_reads = self.indirect_reads.get(&value).cloned().unwrap_or_default();
+            for addr in indirect_reads {
+                self.taint_writes(addr, Some(&value));
+            }
+        }
+    }
+
+    /// addresses taint went through to get to `value`, or none for what the source wrote.
+    fn path(&self, value: Option<&DFGRef<A>>) -> Vec<A::Address> {
+        let mut path = Vec::new();
+        let mut next = value.copied();
+        while let Some(value) = next {
+            match self.from.get(&value) {
+                Some((from, addr)) => {
+                    if let Some(addr) = addr {
+                        path.push(*addr);
+                    }
+                    next = *from;
+                }
+                None => { break; }
+            }
+        }
+        path.reverse();
+        path.dedup();
+        path
+    }
+}
+
+#[test]
+fn test_taint() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::Parameter;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+
+    let data: Vec<u8> = vec![
+        0xe8, 0x1b, 0x00, 0x00, 0x00,   // 0x00: call getenv
+        0x48, 0x89, 0xc2,               // 0x05: mov rdx, rax
+        0x48, 0x83, 0xc2, 0x01,         // 0x08: add rdx, 1
+        0x48, 0x89, 0xcf,               // 0x0c: mov rdi, rcx
+        0xe8, 0x1c, 0x00, 0x00, 0x00,   // 0x0f: call memcpy
+        0xe8, 0x27, 0x00, 0x00, 0x00,   // 0x14: call system
+        0xc3,                           // 0x19: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+
+    let function = |name: &str, arguments: &[Location], returns: &[Location]| {
+        let mut f = FunctionImpl::new(name.to_string());
+        for (i, arg) in arguments.iter().enumerate() {
+            f.append_arg((Some(arg.clone()), Parameter::of(&format!("arg{}", i))));
+        }
+        f.layout_mut().returns.extend(returns.iter().cloned().map(Some));
+        f
+    };
+    {
+        let mut functions = x86_64_data.contexts.functions.borrow_mut();
+        functions.insert(0x20, function("getenv", &[Location::rdi()], &[Location::rax()]));
+        functions.insert(0x30, function("memcpy", &[Location::rdi(), Location::rsi(), Location::rdx()], &[Location::rax()]));
+        functions.insert(0x40, function("system", &[Location::rdi()], &[Location::rax()]));
+    }
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    modifiers.calls.insert(0x00, 0x20);
+    modifiers.calls.insert(0x0f, 0x30);
+    modifiers.calls.insert(0x14, 0x40);
+
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+
+    let rules = TaintRules::new()
+        .source_return("getenv")
+        .sink("memcpy", 2)
+        .sink("system", 0);
+    let flows = find_taint_flows(&cfg, &dfg, &modifiers, &rules);
+
+    // the length passed to `memcpy` comes from `getenv`, but `system`'s argument doesn't.
+    assert_eq!(flows, vec![TaintFlow {
+        source: TaintSource::Return { function: "getenv".to_string() },
+        sink: TaintSink::Argument { function: "memcpy".to_string(), argument: 2 },
+        path: vec![0x00, 0x05, 0x08, 0x0f],
+    }]);
+}
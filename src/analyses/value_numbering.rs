@@ -0,0 +1,586 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use analyses::{Expression, Item, ValueOrImmediate};
+use analyses::control_flow::ControlFlowGraph;
+use analyses::memory_layout::{Underlying, ValueData};
+use analyses::static_single_assignment::{DefSource, DFGRef, SSAValues};
+use data::modifier::Precedence;
+
+/// a class of values that always hold the same thing. numbers mean nothing outside the
+/// `ValueNumbering` they came from.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ValueNumber(u32);
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum Operator {
+    Add, Sub, Mul, Or, And, Xor, Shl, Shr, SignExtend,
+}
+
+impl Operator {
+    fn commutes(&self) -> bool {
+        match self {
+            Operator::Add | Operator::Mul | Operator::Or | Operator::And | Operator::Xor => true,
+            Operator::Sub | Operator::Shl | Operator::Shr | Operator::SignExtend => false,
+        }
+    }
+}
+
+/// what a value computes, with the values it computes from replaced by their numbers. values
+/// with the same term are congruent.
+#[derive(Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+enum Term {
+    Immediate(i64),
+    Number(usize),
+    Operation(Operator, Box<Term>, Box<Term>),
+}
+
+#[derive(Debug, Hash, PartialEq, Eq)]
+enum Key<Addr, Data> {
+    Expression(Term),
+    Data(Data),
+    Phi(Addr, Vec<usize>),
+}
+
+enum Resolution<Addr, Data> {
+    /// the value is a copy of some other value.
+    Same(usize),
+    Computed(Key<Addr, Data>),
+    /// nothing is known about the value, so it's only congruent with itself.
+    Opaque,
+}
+
+/// the congruence classes found by `number_values`.
+pub struct ValueNumbering<A: SSAValues> {
+    /// every value in the `SSA` that was numbered, inputs first and then by where they're defined.
+    values: Vec<DFGRef<A>>,
+    sites: Vec<(A::Address, DefSource<A::Address>)>,
+    indices: HashMap<DFGRef<A>, usize>,
+    /// for each value, the index of the first value in its class.
+    numbers: Vec<usize>,
+    /// whether each value is computed from other values, rather than a copy or unknown.
+    computed: Vec<bool>,
+}
+
+impl<A: SSAValues> fmt::Debug for ValueNumbering<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ValueNumbering")
+            .field("values", &self.values.len())
+            .field("classes", &self.numbers.iter().enumerate().filter(|(i, n)| i == *n).count())
+            .finish()
+    }
+}
+
+/// a value that's computed again when a congruent value is already available, because that
+/// value's definition dominates it.
+#[derive(Debug)]
+pub struct CommonSubexpression<A: SSAValues> {
+    pub value: DFGRef<A>,
+    pub available: DFGRef<A>,
+}
+
+impl<A: SSAValues> ValueNumbering<A> {
+    pub fn number(&self, value: &DFGRef<A>) -> Option<ValueNumber> {
+        self.indices.get(value)
+            .map(|idx| ValueNumber(self.numbers[*idx] as u32))
+    }
+
+    pub fn congruent(&self, left: &DFGRef<A>, right: &DFGRef<A>) -> bool {
+        left == right || match (self.number(left), self.number(right)) {
+            (Some(left), Some(right)) => left == right,
+            _ => false,
+        }
+    }
+
+    /// the first value in `number`'s class: an input if there is one, otherwise the one defined
+    /// at the lowest address.
+    pub fn leader(&self, number: ValueNumber) -> &DFGRef<A> {
+        &self.values[number.0 as usize]
+    }
+
+    /// the leader of `value`'s class, or `value` itself if it wasn't numbered.
+    pub fn leader_of(&self, value: &DFGRef<A>) -> DFGRef<A> {
+        match self.number(value) {
+            Some(number) => DFGRef::clone(self.leader(number)),
+            None => *value,
+        }
+    }
+
+    /// every value congruent to `number`'s leader, leader first.
+    pub fn members(&self, number: ValueNumber) -> Vec<DFGRef<A>> {
+        self.numbers.iter().enumerate()
+            .filter(|(_, n)| **n == number.0 as usize)
+            .map(|(idx, _)| self.values[idx])
+            .collect()
+    }
+
+    /// classes with more than one value in them.
+    pub fn classes(&self) -> Vec<ValueNumber> {
+        let mut sizes: HashMap<usize, usize> = HashMap::new();
+        for number in self.numbers.iter() {
+            *sizes.entry(*number).or_insert(0) += 1;
+        }
+        let mut classes: Vec<ValueNumber> = sizes.into_iter()
+            .filter(|(_, size)| *size > 1)
+            .map(|(number, _)| ValueNumber(number as u32))
+            .collect();
+        classes.sort();
+        classes
+    }
+}
+
+impl<A: SSAValues> ValueNumbering<A> where A::Data: Underlying<Arch=A> + Eq + fmt::Display {
+    /// `expr` with every value in it replaced by its class's leader. two congruent pointers
+    /// canonicalize to the same expression, so `MemoryLayout` can tell they point to the same
+    /// place.
+    pub fn canonical(&self, expr: &Arc<Item<ValueOrImmediate<A>>>) -> Arc<Item<ValueOrImmediate<A>>> {
+        let value = match &expr.value {
+            Expression::Unknown => Expression::Unknown,
+            Expression::Value(ValueOrImmediate::Immediate(i)) => Expression::Value(ValueOrImmediate::Immediate(*i)),
+            Expression::Value(ValueOrImmediate::Value(v)) => Expression::Value(ValueOrImmediate::Value(self.leader_of(v))),
+            Expression::Load { address, size } => Expression::Load { address: self.canonical(address), size: *size },
+            Expression::Add { left, right } => Expression::Add { left: self.canonical(left), right: self.canonical(right) },
+            Expression::Sub { left, right } => Expression::Sub { left: self.canonical(left), right: self.canonical(right) },
+            Expression::Mul { left, right } => Expression::Mul { left: self.canonical(left), right: self.canonical(right) },
+            Expression::Or { left, right } => Expression::Or { left: self.canonical(left), right: self.canonical(right) },
+            Expression::And { left, right } => Expression::And { left: self.canonical(left), right: self.canonical(right) },
+            Expression::Xor { left, right } => Expression::Xor { left: self.canonical(left), right: self.canonical(right) },
+            Expression::Shl { value, amount } => Expression::Shl { value: self.canonical(value), amount: self.canonical(amount) },
+            Expression::Shr { value, amount } => Expression::Shr { value: self.canonical(value), amount: self.canonical(amount) },
+            Expression::SignExtend { value, width } => Expression::SignExtend { value: self.canonical(value), width: *width },
+        };
+        Arc::new(Item { ty: expr.ty.clone(), value })
+    }
+
+    /// values an instruction computes that a dominating, congruent value already holds. each is
+    /// paired with the first such value, so replacing one with the other is always safe.
+    pub fn common_subexpressions(&self, cfg: &ControlFlowGraph<A::Address>) -> Vec<CommonSubexpression<A>> where A::Address: petgraph::graphmap::NodeTrait {
+        let dominators = petgraph::algo::dominators::simple_fast(&cfg.graph, cfg.entrypoint);
+        // where in the function a value becomes available: its block, and its place in that
+        // block. inputs are available everywhere.
+        let position = |idx: usize| -> Option<Option<(A::Address, (A::Address, u8))>> {
+            match self.sites[idx] {
+                (_, DefSource::External) => Some(None),
+                (addr, DefSource::Phi) => Some(Some((addr, (addr, 0)))),
+                (addr, DefSource::Modifier(Precedence::Before)) => Some(Some((cfg.get_block(addr).start, (addr, 1)))),
+                (addr, DefSource::Instruction) => Some(Some((cfg.get_block(addr).start, (addr, 2)))),
+                (addr, DefSource::Modifier(Precedence::After)) => Some(Some((cfg.get_block(addr).start, (addr, 3)))),
+                // only available on one edge, so nothing after it can rely on it.
+                (_, DefSource::Between(_)) => None,
+            }
+        };
+        let dominates = |dominator: Option<(A::Address, (A::Address, u8))>, node: (A::Address, (A::Address, u8))| -> bool {
+            match dominator {
+                None => true,
+                Some((block, offset)) if block == node.0 => offset < node.1,
+                Some((block, _)) => match dominators.dominators(node.0) {
+                    Some(mut doms) => doms.any(|dom| dom == block),
+                    None => false,
+                },
+            }
+        };
+
+        let mut found = Vec::new();
+        for (idx, value) in self.values.iter().enumerate() {
+            if !self.computed[idx] || self.sites[idx].1 != DefSource::Instruction {
+                continue;
+            }
+            let here = match position(idx) {
+                Some(Some(here)) => here,
+                _ => { continue; }
+            };
+            let available = (0..self.values.len())
+                .filter(|other| *other != idx && self.numbers[*other] == self.numbers[idx])
+                .find(|other| match position(*other) {
+                    Some(there) => dominates(there, here),
+                    None => false,
+                });
+            if let Some(available) = available {
+                found.push(CommonSubexpression {
+                    value: *value,
+                    available: self.values[available],
+                });
+            }
+        }
+        found
+    }
+
+    fn term(&self, numbers: &[usize], item: &Item<ValueOrImmediate<A>>) -> Option<Term> {
+        let (op, left, right) = match &item.value {
+            // memory may change between two loads of the same address, so loads are never
+            // congruent.
+            Expression::Unknown |
+            Expression::Load { .. } => { return None; }
+            Expression::Value(ValueOrImmediate::Immediate(i)) => { return Some(Term::Immediate(*i)); }
+            Expression::Value(ValueOrImmediate::Value(v)) => {
+                let idx = self.indices.get(v)?;
+                return Some(Term::Number(numbers[*idx]));
+            }
+            Expression::Add { left, right } => (Operator::Add, left, right),
+            Expression::Sub { left, right } => (Operator::Sub, left, right),
+            Expression::Mul { left, right } => (Operator::Mul, left, right),
+            Expression::Or { left, right } => (Operator::Or, left, right),
+            Expression::And { left, right } => (Operator::And, left, right),
+            Expression::Xor { left, right } => (Operator::Xor, left, right),
+            Expression::Shl { value, amount } => (Operator::Shl, value, amount),
+            Expression::Shr { value, amount } => (Operator::Shr, value, amount),
+            Expression::SignExtend { value, width } => {
+                let value = self.term(numbers, value)?;
+                return Some(Term::Operation(Operator::SignExtend, Box::new(value), Box::new(Term::Immediate(*width as i64))));
+            }
+        };
+        let mut left = self.term(numbers, left)?;
+        let mut right = self.term(numbers, right)?;
+        if op.commutes() && right < left {
+            std::mem::swap(&mut left, &mut right);
+        }
+        Some(Term::Operation(op, Box::new(left), Box::new(right)))
+    }
+
+    fn resolve<V: ValueData<A> + ?Sized>(&self, data: &V, numbers: &[usize], idx: usize) -> Resolution<A::Address, A::Data> {
+        let ssa = data.ssa();
+        let value = ssa.value(self.values[idx]);
+        if let Some(underlying) = data.underlying(self.values[idx]) {
+            return match self.indices.get(&underlying) {
+                Some(other) => Resolution::Same(numbers[*other]),
+                None => Resolution::Opaque,
+            };
+        }
+        if let Some(expr) = data.expression(self.values[idx]) {
+            return match self.term(numbers, &expr) {
+                Some(Term::Number(number)) => Resolution::Same(number),
+                Some(term) => Resolution::Computed(Key::Expression(term)),
+                None => Resolution::Opaque,
+            };
+        }
+        // two values in the same range aren't the same value, so only exact data numbers them.
+        if let Some(data) = value.data.as_ref().filter(|data| data.is_exact()) {
+            return Resolution::Computed(Key::Data(data.clone()));
+        }
+        if let (block, DefSource::Phi) = self.sites[idx] {
+            let phi = &ssa.phi[&block][&value.location];
+            let ins: Option<Vec<usize>> = phi.ins.iter()
+                .map(|value| self.indices.get(value).map(|other| numbers[*other]))
+                .collect();
+            return match ins {
+                Some(ins) => {
+                    if ins.iter().all(|number| *number == ins[0]) && !ins.is_empty() {
+                        Resolution::Same(ins[0])
+                    } else {
+                        Resolution::Computed(Key::Phi(block, ins))
+                    }
+                }
+                None => Resolution::Opaque,
+            };
+        }
+        Resolution::Opaque
+    }
+}
+
+/// find values in `data.ssa()` that always hold the same thing. `data` is where what values are
+/// is looked up: the `SSA` itself, or a `MemoryLayout` evaluated over it, which also knows the
+/// expressions it found.
+///
+/// values are congruent when they're aliases of each other, when their `Data` is the same exact
+/// thing, when their expressions are the same operations on congruent values, or when they're
+/// phis in the same block of congruent values. a phi of only one class of values is in that class too.
+///
+/// this is pessimistic: values start out distinct and are only merged when they're shown to be
+/// the same, so values that are only congruent through a loop, like two counters stepped in
+/// lockstep, are never found.
+pub fn number_values<A, V>(data: &V) -> ValueNumbering<A> where
+    A: SSAValues,
+    A::Data: Underlying<Arch=A> + Eq + fmt::Display,
+    V: ValueData<A> + ?Sized,
+{
+    let ssa = data.ssa();
+    let mut found: HashMap<DFGRef<A>, (A::Address, DefSource<A::Address>)> = HashMap::new();
+    for (value, site) in ssa.values.defs() {
+        found.insert(value, *site);
+    }
+    let mut note_input = |value: &DFGRef<A>| {
+        if ssa.value(*value).version.is_none() {
+            found.entry(*value)
+                .or_insert_with(|| ssa.get_def_site(*value));
+        }
+    };
+    let rwmaps = ssa.instruction_values.values()
+        .chain(ssa.modifier_values.values())
+        .chain(ssa.control_dependent_values.values().flat_map(|tos| tos.values()));
+    for rwmap in rwmaps {
+        rwmap.values().for_each(&mut note_input);
+    }
+    for phis in ssa.phi.values() {
+        for phi in phis.values() {
+            phi.ins.iter().for_each(&mut note_input);
+        }
+    }
+
+    let mut values: Vec<(DFGRef<A>, (A::Address, DefSource<A::Address>))> = found.into_iter().collect();
+    values.sort_by_cached_key(|(value, (addr, source))| {
+        let rank = match source {
+            DefSource::External => 0,
+            DefSource::Phi => 1,
+            DefSource::Between(_) => 2,
+            DefSource::Modifier(Precedence::Before) => 3,
+            DefSource::Instruction => 4,
+            DefSource::Modifier(Precedence::After) => 5,
+        };
+        let value = ssa.value(*value);
+        (rank != 0, *addr, rank, format!("{:?}", value.location), value.version)
+    });
+
+    let mut numbering = ValueNumbering {
+        indices: values.iter().enumerate().map(|(idx, (value, _))| (*value, idx)).collect(),
+        sites: values.iter().map(|(_, site)| *site).collect(),
+        values: values.into_iter().map(|(value, _)| value).collect(),
+        numbers: Vec::new(),
+        computed: Vec::new(),
+    };
+    let count = numbering.values.len();
+    let mut numbers: Vec<usize> = (0..count).collect();
+    let mut computed = vec![false; count];
+
+    // classes only ever merge, so this settles in at most one round per value.
+    for _ in 0..=count {
+        let mut table: HashMap<Key<A::Address, A::Data>, usize> = HashMap::new();
+        let mut next = numbers.clone();
+        for idx in 0..count {
+            let (number, is_computed) = match numbering.resolve(data, &numbers, idx) {
+                Resolution::Same(number) => (number, false),
+                Resolution::Computed(key) => (*table.entry(key).or_insert(idx), true),
+                Resolution::Opaque => (idx, false),
+            };
+            next[idx] = number;
+            computed[idx] = is_computed;
+        }
+        // a value may have been merged into a class whose leader was merged elsewhere in the
+        // same round, so follow that through to where it ends up. phis of each other in
+        // unreachable code can even merge in a circle, which is settled on its lowest index.
+        let merged: Vec<usize> = (0..count).map(|idx| {
+            let mut seen = vec![idx];
+            let mut number = next[idx];
+            while next[number] != number && !seen.contains(&number) {
+                seen.push(number);
+                number = next[number];
+            }
+            if next[number] != number {
+                let mut lowest = number;
+                let mut member = next[number];
+                while member != number {
+                    lowest = lowest.min(member);
+                    member = next[member];
+                }
+                number = lowest;
+            }
+            number
+        }).collect();
+        let next = merged;
+        if next == numbers {
+            break;
+        }
+        numbers = next;
+    }
+
+    numbering.numbers = numbers;
+    numbering.computed = computed;
+    numbering
+}
+
+#[test]
+fn test_value_numbering() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::memory_layout::MemoryLayout;
+    use arch::InstructionSpan;
+    use arch::x86_64::semantic;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+    use yaxpeax_x86::x86_64;
+    use yaxpeax_x86::long_mode::RegSpec;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x89, 0xfa,           // 0x00: mov rdx, rdi
+        0x48, 0x8d, 0x47, 0x10,     // 0x03: lea rax, [rdi + 0x10]
+        0x48, 0x85, 0xf6,           // 0x07: test rsi, rsi
+        0x74, 0x04,                 // 0x0a: je 0x10
+        0x48, 0x8d, 0x4a, 0x10,     // 0x0c: lea rcx, [rdx + 0x10]
+        0x4c, 0x8d, 0x47, 0x18,     // 0x10: lea r8, [rdi + 0x18]
+        0xc3,                       // 0x14: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let mut layout = MemoryLayout::new(&dfg);
+    for block in cfg.blocks() {
+        let block = cfg.get_block(block);
+        let mut iter = x86_64::instructions_spanning(&data, block.start, block.end);
+        while let Some((address, instr)) = iter.next() {
+            semantic::evaluate(address, &instr, &mut layout);
+        }
+    }
+
+    let numbering = number_values(&layout);
+    let def = |addr: u64, loc: Location| dfg.get_def(addr, loc).as_rc();
+
+    // `rdx` is a copy of `rdi`, so both `lea` of `0x10` past them are the same pointer.
+    assert!(numbering.congruent(&def(0x00, Location::rdx()), &dfg.get_use(0x00, Location::rdi()).as_rc()));
+    assert!(numbering.congruent(&def(0x03, Location::rax()), &def(0x0c, Location::rcx())));
+    assert_eq!(numbering.leader_of(&def(0x0c, Location::rcx())), def(0x03, Location::rax()));
+    assert!(!numbering.congruent(&def(0x03, Location::rax()), &def(0x10, Location::Register(RegSpec::r8()))));
+
+    let expression = |value: DFGRef<x86_64>| layout.expression(value).unwrap();
+    assert_eq!(
+        numbering.canonical(&expression(def(0x03, Location::rax()))),
+        numbering.canonical(&expression(def(0x0c, Location::rcx()))),
+    );
+
+    // and the second one is redundant, since the first is computed before the branch.
+    let redundant = numbering.common_subexpressions(&cfg);
+    assert_eq!(redundant.len(), 1);
+    assert_eq!(redundant[0].value, def(0x0c, Location::rcx()));
+    assert_eq!(redundant[0].available, def(0x03, Location::rax()));
+}
+
+#[test]
+fn test_value_numbering_locations() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::memory_layout::MemoryLayout;
+    use arch::InstructionSpan;
+    use arch::x86_64::semantic;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{ANY, ContextualDisambiguation, Location, NoDisambiguation};
+    use data::{Direction, Disambiguator, LocationAliasDescriptions};
+    use yaxpeax_arch::{Arch, Decoder, U8Reader};
+    use yaxpeax_x86::x86_64;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x85, 0xf6,           // 0x00: test rsi, rsi
+        0x74, 0x0a,                 // 0x03: je 0x0f
+        0x48, 0x8d, 0x47, 0x10,     // 0x05: lea rax, [rdi + 0x10]
+        0x48, 0x8d, 0x4f, 0x10,     // 0x09: lea rcx, [rdi + 0x10]
+        0xeb, 0x08,                 // 0x0d: jmp 0x17
+        0x48, 0x8d, 0x47, 0x10,     // 0x0f: lea rax, [rdi + 0x10]
+        0x48, 0x8d, 0x4f, 0x10,     // 0x13: lea rcx, [rdi + 0x10]
+        0x4c, 0x89, 0x00,           // 0x17: mov [rax], r8
+        0x4c, 0x8b, 0x09,           // 0x1a: mov r9, [rcx]
+        0xc3,                       // 0x1d: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let functions = x86_64_data.contexts.functions.borrow();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*functions,
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let evaluate = |layout: &mut MemoryLayout<x86_64>| {
+        for block in cfg.blocks() {
+            let block = cfg.get_block(block);
+            let mut iter = x86_64::instructions_spanning(&data, block.start, block.end);
+            while let Some((address, instr)) = iter.next() {
+                semantic::evaluate(address, instr, layout);
+            }
+        }
+    };
+    let mut layout = MemoryLayout::new(&dfg);
+    evaluate(&mut layout);
+    let numbering = number_values(&layout);
+    let mut numbered = MemoryLayout::new(&dfg).with_value_numbering(&numbering);
+    evaluate(&mut numbered);
+
+    let location = |layout: &MemoryLayout<x86_64>, addr: u64, operand: u8, direction: Direction| {
+        let instr = <x86_64 as Arch>::Decoder::default().decode(&mut U8Reader::new(&data[addr as usize..])).unwrap();
+        let disambiguation = ContextualDisambiguation {
+            dfg: &dfg,
+            memory_layout: Some(layout),
+        };
+        disambiguation.disambiguate(&instr, (Some(Location::Memory(ANY)), direction), (addr, operand, 0))
+            .expect("access has a location")
+    };
+
+    // `rax` and `rcx` are different phis at 0x17, so they look like different bases..
+    assert_ne!(location(&layout, 0x17, 1, Direction::Write), location(&layout, 0x1a, 2, Direction::Read));
+
+    // .. but both are `rdi + 0x10` whichever way the branch goes, so the store and load are one
+    // location once values are numbered.
+    let stored = location(&numbered, 0x17, 1, Direction::Write);
+    assert_eq!(stored, location(&numbered, 0x1a, 2, Direction::Read));
+
+    let mut disambiguation = ContextualDisambiguation {
+        dfg: &dfg,
+        memory_layout: Some(&numbered),
+    };
+    assert!(disambiguation.may_alias(&stored, &stored));
+    let refined = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*functions,
+        &mut disambiguation,
+    ).ssa_cytron_refining(&dfg);
+    // the refined `SSA` has its own values, so the store's location is in terms of those.
+    let stored = refined.instruction_values[&0x17].keys()
+        .find(|(loc, dir)| *dir == Direction::Write && matches!(loc, Location::MemoryLocation(_, _, Some(_))))
+        .map(|(loc, _)| loc.clone())
+        .expect("store has a location");
+    assert_eq!(
+        refined.get_use(0x1a, stored.clone()).as_rc(),
+        refined.get_def(0x17, stored).as_rc(),
+    );
+}
+
+#[test]
+fn test_value_numbering_inexact_data() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Data, Location, NoDisambiguation, ValueRange};
+
+    let data: Vec<u8> = vec![
+        0x8b, 0x07,                 // 0x00: mov eax, [rdi]
+        0x8b, 0x4f, 0x04,           // 0x02: mov ecx, [rdi + 4]
+        0xba, 0x05, 0x00, 0x00, 0x00, // 0x05: mov edx, 5
+        0xbe, 0x05, 0x00, 0x00, 0x00, // 0x0a: mov esi, 5
+        0xc3,                       // 0x0f: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let mut dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let eax = dfg.get_def(0x00, Location::rax()).as_rc();
+    let ecx = dfg.get_def(0x02, Location::rcx()).as_rc();
+    let edx = dfg.get_def(0x05, Location::rdx()).as_rc();
+    let esi = dfg.get_def(0x0a, Location::Register(yaxpeax_x86::long_mode::RegSpec::rsi())).as_rc();
+    let small = Data::ValueSet(vec![ValueRange::Between(Data::Concrete(0, None), Data::Concrete(10, None))]);
+    dfg.value_mut(eax).data = Some(small.clone());
+    dfg.value_mut(ecx).data = Some(small);
+    dfg.value_mut(edx).data = Some(Data::Concrete(5, None));
+    dfg.value_mut(esi).data = Some(Data::Concrete(5, None));
+
+    let numbering = number_values(&dfg);
+    // two loads known to be in the same range can still be different numbers..
+    assert!(!numbering.congruent(&eax, &ecx));
+    // .. but two fives are the same.
+    assert!(numbering.congruent(&edx, &esi));
+}
@@ -0,0 +1,717 @@
+use std::cell::Cell;
+use std::cmp::{max, min};
+use std::collections::{BTreeSet, HashMap};
+
+use yaxpeax_arch::{Address, AddressBase, AddressDiff, LengthedInstruction};
+
+use analyses::{CompletionStatus, DFG, OpaqueIndirection, Value, ValueRes};
+use analyses::constant_propagation::ConstantSemantics;
+use analyses::control_flow::ControlFlowGraph;
+use analyses::fixpoint::{self, Fixpoint, Lattice, Transfer};
+use analyses::static_single_assignment::{DFGRef, SSA};
+use arch::{AbiDefaults, DecodeFrom};
+use data::modifier::{InstructionModifiers, ModifierExpression};
+use memory::MemoryRange;
+
+const FULL_SIGNED: (i64, i64) = (i64::MIN, i64::MAX);
+const FULL_UNSIGNED: (u64, u64) = (0, u64::MAX);
+
+/// a range of 64-bit values, as bounds when the bits are read as signed and as unsigned. each view
+/// alone loses a lot - `[-1, 1]` is every unsigned value from `0xffff_ffff_ffff_ffff` around to
+/// `1` - so both are kept, and each tightens the other where it can.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Interval {
+    /// no values. either whatever defines this never executes, or nothing has reached it yet.
+    Empty,
+    /// values that are in both `signed` and `unsigned`. bounds are inclusive.
+    Range { signed: (i64, i64), unsigned: (u64, u64) },
+}
+
+/// the unsigned bounds of values in `(lo, hi)`. if the range crosses zero, it's everything from
+/// `0` to `u64::MAX`.
+fn as_unsigned((lo, hi): (i64, i64)) -> (u64, u64) {
+    if (lo < 0) == (hi < 0) {
+        (lo as u64, hi as u64)
+    } else {
+        FULL_UNSIGNED
+    }
+}
+
+/// the signed bounds of values in `(lo, hi)`. if the range crosses `1 << 63`, it's everything.
+fn as_signed((lo, hi): (u64, u64)) -> (i64, i64) {
+    if (lo as i64) <= (hi as i64) {
+        (lo as i64, hi as i64)
+    } else {
+        FULL_SIGNED
+    }
+}
+
+/// the smallest all-ones value at least `x`, which bounds anything `or`'d or `xor`'d from values
+/// no larger than `x`.
+pub(crate) fn ones_above(x: u64) -> u64 {
+    if x == 0 {
+        0
+    } else {
+        u64::MAX >> x.leading_zeros()
+    }
+}
+
+/// bounds from an operation applied to the low and high ends of two ranges. if it wrapped at one
+/// end and not the other, the results could be anything.
+fn wrapped<T: Ord>((lo, lo_wrapped): (T, bool), (hi, hi_wrapped): (T, bool), full: (T, T)) -> (T, T) {
+    if lo_wrapped == hi_wrapped && lo <= hi {
+        (lo, hi)
+    } else {
+        full
+    }
+}
+
+type Bounds = ((i64, i64), (u64, u64));
+
+impl Interval {
+    /// the values in both `signed` and `unsigned`.
+    pub fn new(signed: (i64, i64), unsigned: (u64, u64)) -> Interval {
+        let from_unsigned = as_signed(unsigned);
+        let signed = (max(signed.0, from_unsigned.0), min(signed.1, from_unsigned.1));
+        if signed.0 > signed.1 || unsigned.0 > unsigned.1 {
+            return Interval::Empty;
+        }
+        let from_signed = as_unsigned(signed);
+        let unsigned = (max(unsigned.0, from_signed.0), min(unsigned.1, from_signed.1));
+        if unsigned.0 > unsigned.1 {
+            return Interval::Empty;
+        }
+        let from_unsigned = as_signed(unsigned);
+        Interval::Range {
+            signed: (max(signed.0, from_unsigned.0), min(signed.1, from_unsigned.1)),
+            unsigned,
+        }
+    }
+
+    pub fn from_signed(lo: i64, hi: i64) -> Interval {
+        Interval::new((lo, hi), FULL_UNSIGNED)
+    }
+
+    pub fn from_unsigned(lo: u64, hi: u64) -> Interval {
+        Interval::new(FULL_SIGNED, (lo, hi))
+    }
+
+    /// `0` or `1`, unless `always` or `never` says which.
+    fn boolean(always: bool, never: bool) -> Interval {
+        if always {
+            Interval::from_const(1)
+        } else if never {
+            Interval::from_const(0)
+        } else {
+            Interval::from_unsigned(0, 1)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Interval::Empty
+    }
+
+    pub fn signed_bounds(&self) -> Option<(i64, i64)> {
+        self.bounds().map(|(signed, _)| signed)
+    }
+
+    pub fn unsigned_bounds(&self) -> Option<(u64, u64)> {
+        self.bounds().map(|(_, unsigned)| unsigned)
+    }
+
+    fn bounds(&self) -> Option<Bounds> {
+        match self {
+            Interval::Empty => None,
+            Interval::Range { signed, unsigned } => Some((*signed, *unsigned)),
+        }
+    }
+
+    fn pair(&self, other: &Interval) -> Option<(Bounds, Bounds)> {
+        Some((self.bounds()?, other.bounds()?))
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        match self {
+            Interval::Empty => false,
+            Interval::Range { signed, unsigned } => {
+                signed.0 <= x && x <= signed.1 && unsigned.0 <= x as u64 && x as u64 <= unsigned.1
+            }
+        }
+    }
+
+    /// can these values index something `len` items long? `Some(true)` if every value is less
+    /// than `len`, `Some(false)` if none are, and `None` if only some are. indexes are unsigned,
+    /// so negative values are never in bounds.
+    pub fn in_bounds(&self, len: u64) -> Option<bool> {
+        match self.unsigned_bounds() {
+            None => Some(true),
+            Some((_, hi)) if hi < len => Some(true),
+            Some((lo, _)) if lo >= len => Some(false),
+            Some(_) => None,
+        }
+    }
+
+    /// every value in either interval.
+    pub fn join(&self, other: &Interval) -> Interval {
+        match self.pair(other) {
+            Some(((ls, lu), (rs, ru))) => Interval::Range {
+                signed: (min(ls.0, rs.0), max(ls.1, rs.1)),
+                unsigned: (min(lu.0, ru.0), max(lu.1, ru.1)),
+            },
+            None => if self.is_empty() { *other } else { *self },
+        }
+    }
+
+    /// only values in both intervals.
+    pub fn meet(&self, other: &Interval) -> Interval {
+        match self.pair(other) {
+            Some(((ls, lu), (rs, ru))) => Interval::new(
+                (max(ls.0, rs.0), min(ls.1, rs.1)),
+                (max(lu.0, ru.0), min(lu.1, ru.1)),
+            ),
+            None => Interval::Empty,
+        }
+    }
+
+    /// `next`, with any bound that's moved past this interval's sent as far as it can go. a bound
+    /// can only be widened once, so a value that keeps growing around a loop stops changing.
+    pub fn widen(&self, next: &Interval) -> Interval {
+        match self.pair(next) {
+            Some(((ls, lu), (rs, ru))) => Interval::Range {
+                signed: (
+                    if rs.0 < ls.0 { i64::MIN } else { ls.0 },
+                    if rs.1 > ls.1 { i64::MAX } else { ls.1 },
+                ),
+                unsigned: (
+                    if ru.0 < lu.0 { 0 } else { lu.0 },
+                    if ru.1 > lu.1 { u64::MAX } else { lu.1 },
+                ),
+            },
+            None => self.join(next),
+        }
+    }
+
+    /// this interval, with any bound that was widened all the way taken from `next` instead.
+    /// this undoes the precision widening lost, without the risk of never settling.
+    pub fn narrow(&self, next: &Interval) -> Interval {
+        match self.pair(next) {
+            Some(((ls, lu), (rs, ru))) => Interval::new(
+                (
+                    if ls.0 == i64::MIN { rs.0 } else { ls.0 },
+                    if ls.1 == i64::MAX { rs.1 } else { ls.1 },
+                ),
+                (
+                    if lu.0 == 0 { ru.0 } else { lu.0 },
+                    if lu.1 == u64::MAX { ru.1 } else { lu.1 },
+                ),
+            ),
+            None => Interval::Empty,
+        }
+    }
+
+    /// these values, if they're stored in `width` bytes. values that don't fit could have been
+    /// truncated to anything.
+    pub fn truncate(&self, width: usize) -> Interval {
+        if width >= 8 {
+            return *self;
+        }
+        let mask = (1u64 << (width * 8)) - 1;
+        match self.unsigned_bounds() {
+            Some((_, hi)) if hi > mask => Interval::from_unsigned(0, mask),
+            _ => *self,
+        }
+    }
+
+    fn shift_amount(amt: &Interval) -> Option<u32> {
+        amt.to_const().filter(|amt| *amt >= 0 && *amt < 64).map(|amt| amt as u32)
+    }
+}
+
+impl<A: Address> From<AddressDiff<A>> for Interval {
+    fn from(diff: AddressDiff<A>) -> Self {
+        Interval::from_const(A::zero().wrapping_offset(diff).to_linear() as i64)
+    }
+}
+
+/// intervals are of 64-bit values, and wrap like them. like `Constant`, comparisons are signed
+/// and produce `0` or `1`, and `not` is a logical not.
+impl Value for Interval {
+    fn unknown() -> Self {
+        Interval::Range { signed: FULL_SIGNED, unsigned: FULL_UNSIGNED }
+    }
+
+    fn from_const(c: i64) -> Self {
+        Interval::Range { signed: (c, c), unsigned: (c as u64, c as u64) }
+    }
+
+    fn from_set(xs: &[Self]) -> Self {
+        xs.iter().fold(Interval::Empty, |acc, x| acc.join(x))
+    }
+
+    fn to_const(&self) -> Option<i64> {
+        match self.signed_bounds() {
+            Some((lo, hi)) if lo == hi => Some(lo),
+            _ => None,
+        }
+    }
+
+    fn add(&self, other: &Self) -> ValueRes<Self> {
+        let ((ls, lu), (rs, ru)) = match self.pair(other) {
+            Some(bounds) => bounds,
+            None => { return ValueRes { value: Interval::Empty, carry: Interval::Empty }; }
+        };
+        let signed = wrapped(ls.0.overflowing_add(rs.0), ls.1.overflowing_add(rs.1), FULL_SIGNED);
+        let unsigned = wrapped(lu.0.overflowing_add(ru.0), lu.1.overflowing_add(ru.1), FULL_UNSIGNED);
+        ValueRes {
+            value: Interval::new(signed, unsigned),
+            carry: Interval::boolean(lu.0.overflowing_add(ru.0).1, !lu.1.overflowing_add(ru.1).1),
+        }
+    }
+
+    fn sub(&self, other: &Self) -> ValueRes<Self> {
+        let ((ls, lu), (rs, ru)) = match self.pair(other) {
+            Some(bounds) => bounds,
+            None => { return ValueRes { value: Interval::Empty, carry: Interval::Empty }; }
+        };
+        let signed = wrapped(ls.0.overflowing_sub(rs.1), ls.1.overflowing_sub(rs.0), FULL_SIGNED);
+        let unsigned = wrapped(lu.0.overflowing_sub(ru.1), lu.1.overflowing_sub(ru.0), FULL_UNSIGNED);
+        ValueRes {
+            value: Interval::new(signed, unsigned),
+            carry: Interval::boolean(lu.1 < ru.0, lu.0 >= ru.1),
+        }
+    }
+
+    fn mul(&self, other: &Self) -> ValueRes<Self> {
+        let ((ls, lu), (rs, ru)) = match self.pair(other) {
+            Some(bounds) => bounds,
+            None => { return ValueRes { value: Interval::Empty, carry: Interval::Empty }; }
+        };
+        let corners = [
+            ls.0.checked_mul(rs.0),
+            ls.0.checked_mul(rs.1),
+            ls.1.checked_mul(rs.0),
+            ls.1.checked_mul(rs.1),
+        ];
+        let signed = if corners.iter().all(|c| c.is_some()) {
+            let corners = corners.iter().map(|c| c.unwrap());
+            (corners.clone().min().unwrap(), corners.max().unwrap())
+        } else {
+            FULL_SIGNED
+        };
+        let unsigned = match lu.1.checked_mul(ru.1) {
+            Some(hi) => (lu.0 * ru.0, hi),
+            None => FULL_UNSIGNED,
+        };
+        ValueRes {
+            value: Interval::new(signed, unsigned),
+            carry: Interval::unknown(),
+        }
+    }
+
+    fn or(&self, other: &Self) -> ValueRes<Self> {
+        let value = match (self.to_const(), other.to_const()) {
+            (Some(l), Some(r)) => Interval::from_const(l | r),
+            _ => match self.pair(other) {
+                Some(((_, lu), (_, ru))) => Interval::from_unsigned(max(lu.0, ru.0), ones_above(lu.1 | ru.1)),
+                None => Interval::Empty,
+            },
+        };
+        ValueRes::literal(value)
+    }
+
+    fn and(&self, other: &Self) -> ValueRes<Self> {
+        let value = match (self.to_const(), other.to_const()) {
+            (Some(l), Some(r)) => Interval::from_const(l & r),
+            _ => match self.pair(other) {
+                Some(((_, lu), (_, ru))) => Interval::from_unsigned(0, min(lu.1, ru.1)),
+                None => Interval::Empty,
+            },
+        };
+        ValueRes::literal(value)
+    }
+
+    fn xor(&self, other: &Self) -> ValueRes<Self> {
+        let value = match (self.to_const(), other.to_const()) {
+            (Some(l), Some(r)) => Interval::from_const(l ^ r),
+            _ => match self.pair(other) {
+                Some(((_, lu), (_, ru))) => Interval::from_unsigned(0, ones_above(lu.1 | ru.1)),
+                None => Interval::Empty,
+            },
+        };
+        ValueRes::literal(value)
+    }
+
+    fn modulo(&self, other: &Self) -> Self {
+        let ((ls, _), (rs, _)) = match self.pair(other) {
+            Some(bounds) => bounds,
+            None => { return Interval::Empty; }
+        };
+        match (self.to_const(), other.to_const()) {
+            (Some(l), Some(r)) => l.checked_rem(r).map(Interval::from_const).unwrap_or_else(Interval::unknown),
+            _ if ls.0 >= 0 && rs.0 > 0 => Interval::from_signed(0, min(ls.1, rs.1 - 1)),
+            _ => Interval::unknown(),
+        }
+    }
+
+    fn ne(&self, other: &Self) -> Self {
+        Value::eq(self, other).not()
+    }
+
+    fn le(&self, other: &Self) -> Self {
+        match self.pair(other) {
+            Some(((ls, _), (rs, _))) => Interval::boolean(ls.1 <= rs.0, ls.0 > rs.1),
+            None => Interval::Empty,
+        }
+    }
+
+    fn lt(&self, other: &Self) -> Self {
+        match self.pair(other) {
+            Some(((ls, _), (rs, _))) => Interval::boolean(ls.1 < rs.0, ls.0 >= rs.1),
+            None => Interval::Empty,
+        }
+    }
+
+    fn eq(&self, other: &Self) -> Self {
+        if self.pair(other).is_none() {
+            return Interval::Empty;
+        }
+        let same = self.to_const().is_some() && self.to_const() == other.to_const();
+        Interval::boolean(same, self.meet(other).is_empty())
+    }
+
+    /// semantics use `not` to negate conditions, so this is a logical not rather than a bitwise
+    /// one, as with `Constant`.
+    fn not(&self) -> Self {
+        if self.is_empty() {
+            return Interval::Empty;
+        }
+        Interval::boolean(self.to_const() == Some(0), !self.contains(0))
+    }
+
+    fn shr(&self, amt: &Self) -> Self {
+        match (self.unsigned_bounds(), Interval::shift_amount(amt)) {
+            (None, _) => Interval::Empty,
+            (Some((lo, hi)), Some(amt)) => Interval::from_unsigned(lo >> amt, hi >> amt),
+            _ => Interval::unknown(),
+        }
+    }
+
+    fn sar(&self, amt: &Self) -> Self {
+        match (self.signed_bounds(), Interval::shift_amount(amt)) {
+            (None, _) => Interval::Empty,
+            (Some((lo, hi)), Some(amt)) => Interval::from_signed(lo >> amt, hi >> amt),
+            _ => Interval::unknown(),
+        }
+    }
+
+    fn shl(&self, amt: &Self) -> Self {
+        let ((signed, unsigned), amt) = match (self.bounds(), Interval::shift_amount(amt)) {
+            (None, _) => { return Interval::Empty; }
+            (Some(bounds), Some(amt)) => (bounds, amt),
+            _ => { return Interval::unknown(); }
+        };
+        let signed = if (signed.0 << amt) >> amt == signed.0 && (signed.1 << amt) >> amt == signed.1 {
+            (signed.0 << amt, signed.1 << amt)
+        } else {
+            FULL_SIGNED
+        };
+        let unsigned = if unsigned.1.leading_zeros() >= amt {
+            (unsigned.0 << amt, unsigned.1 << amt)
+        } else {
+            FULL_UNSIGNED
+        };
+        Interval::new(signed, unsigned)
+    }
+
+    fn sal(&self, amt: &Self) -> Self {
+        self.shl(amt)
+    }
+}
+
+/// what an architecture needs to provide for `compute_intervals`, on top of what it provides
+/// for constant propagation: its `semantic::evaluate`, again, for `Interval`.
+pub trait IntervalSemantics: ConstantSemantics {
+    /// evaluate `instr` at `addr`. locations it doesn't write, but `SSA` says `instr` does, could
+    /// be anything.
+    fn evaluate_intervals<D: DFG<Interval, Self, Self::Address>>(addr: Self::Address, instr: &Self::Instruction, dfg: &mut D) -> CompletionStatus;
+}
+
+/// the results of `compute_intervals`.
+#[derive(Debug)]
+pub struct Intervals<A: IntervalSemantics> {
+    /// every value defined by an instruction, phi, or edge that was reached. values that aren't
+    /// here are `Empty` if they're defined somewhere that never executes, and could be anything
+    /// if they're from somewhere this doesn't look, like function inputs.
+    pub values: HashMap<DFGRef<A>, Interval>,
+    /// blocks reachable from the function's entry.
+    pub executable: BTreeSet<A::Address>,
+}
+
+impl<A: IntervalSemantics> Intervals<A> {
+    pub fn value(&self, value: &DFGRef<A>) -> Option<Interval> {
+        self.values.get(value).cloned()
+    }
+}
+
+/// an `SSA` read through the intervals found so far, at one instruction.
+struct Evaluation<'a, A: IntervalSemantics> where A::Location: AbiDefaults {
+    engine: &'a Fixpoint<'a, A, Interval>,
+    next: i64,
+    writes: Vec<(A::Location, Interval)>,
+    /// some input hasn't been reached yet, so anything computed is premature.
+    pending: Cell<bool>,
+    /// some input was narrower than the 64 bits semantics compute with.
+    narrow: Cell<bool>,
+}
+
+impl<'a, A: IntervalSemantics> DFG<Interval, A, A::Address> for Evaluation<'a, A> where A::Address: petgraph::graphmap::NodeTrait, A::Location: AbiDefaults {
+    type Indirect = OpaqueIndirection<Interval>;
+
+    fn read_loc(&self, when: A::Address, loc: A::Location) -> Interval {
+        if loc == A::program_counter() {
+            return Interval::from_const(self.next);
+        }
+        let value = match self.engine.ssa.try_get_use(when, loc.clone()) {
+            Some(value) => value,
+            None => { return Interval::unknown(); }
+        };
+        let interval = self.engine.current(&value);
+        if interval.is_empty() {
+            self.pending.set(true);
+        }
+        let defined_width = A::value_width(&self.engine.ssa.value(value).location);
+        match A::value_width(&loc) {
+            // a write to part of this location says nothing about the rest of it.
+            Some(width) if defined_width.map(|defined| defined < width).unwrap_or(false) => Interval::unknown(),
+            Some(width) if width < 8 => {
+                self.narrow.set(true);
+                interval.truncate(width)
+            }
+            Some(width) if width > 8 => Interval::unknown(),
+            _ => interval,
+        }
+    }
+
+    fn write_loc(&mut self, _when: A::Address, loc: A::Location, value: Interval) {
+        self.writes.push((loc, value));
+    }
+
+    fn indirect_loc(&self, _when: A::Address, _loc: A::Location) -> OpaqueIndirection<Interval> {
+        OpaqueIndirection::inst()
+    }
+}
+
+impl Lattice for Interval {
+    fn bottom() -> Self { Interval::Empty }
+    fn top() -> Self { Interval::unknown() }
+    fn join(&self, other: &Self) -> Self { Interval::join(self, other) }
+    fn meet(&self, other: &Self) -> Self { Interval::meet(self, other) }
+    fn widen(&self, next: &Self) -> Self { Interval::widen(self, next) }
+    fn narrow(&self, next: &Self) -> Self { Interval::narrow(self, next) }
+}
+
+struct Analysis<'a, A: IntervalSemantics> where A::Location: AbiDefaults {
+    engine: Fixpoint<'a, A, Interval>,
+}
+
+impl<'a, A> Transfer<'a, A> for Analysis<'a, A> where
+    A: IntervalSemantics,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+{
+    type Domain = Interval;
+
+    fn engine(&mut self) -> &mut Fixpoint<'a, A, Interval> {
+        &mut self.engine
+    }
+
+    fn visit_instruction(&mut self, addr: A::Address, instr: &A::Instruction) {
+        let next = addr.wrapping_offset(instr.len()).to_linear() as i64;
+        let (writes, pending, narrow) = {
+            let mut evaluation = Evaluation {
+                engine: &self.engine,
+                next,
+                writes: Vec::new(),
+                pending: Cell::new(false),
+                narrow: Cell::new(false),
+            };
+            A::evaluate_intervals(addr, instr, &mut evaluation);
+            (evaluation.writes, evaluation.pending.get(), evaluation.narrow.get())
+        };
+        if pending {
+            return;
+        }
+
+        let mut results: HashMap<A::Location, Interval> = HashMap::new();
+        for (loc, value) in writes.into_iter() {
+            let value = match A::value_width(&loc) {
+                Some(width) if width > 8 => Interval::unknown(),
+                Some(width) => value.truncate(width),
+                // flags, and other locations without a width, were computed from 64-bit
+                // operands. if an input was narrower, its sign was lost, and so were these.
+                None if narrow => Interval::unknown(),
+                None => value,
+            };
+            results.insert(loc, value);
+        }
+
+        self.engine.update_writes(addr, |loc| results.get(loc).cloned().unwrap_or_else(Interval::unknown));
+    }
+
+    /// this reads `ModifierExpression::Below(n)` as `value <= n` and `Above(n)` as `value >= n`,
+    /// both unsigned, as `ValueSetDomain` does. `SignedBelow` and `SignedAbove` are the same,
+    /// but signed.
+    fn bound(bounded: &Interval, expr: &ModifierExpression) -> Interval {
+        match expr {
+            ModifierExpression::Below(n) => bounded.meet(&Interval::from_unsigned(0, *n)),
+            ModifierExpression::Above(n) => bounded.meet(&Interval::from_unsigned(*n, u64::MAX)),
+            ModifierExpression::SignedBelow(n) => bounded.meet(&Interval::from_signed(i64::MIN, *n)),
+            ModifierExpression::SignedAbove(n) => bounded.meet(&Interval::from_signed(*n, i64::MAX)),
+            ModifierExpression::Is(n) => bounded.meet(&Interval::from_const(*n as i64)),
+            ModifierExpression::IsNot(n) => {
+                match bounded.unsigned_bounds() {
+                    Some((lo, hi)) if lo == *n && hi == *n => Interval::Empty,
+                    Some((lo, hi)) if lo == *n => bounded.meet(&Interval::from_unsigned(lo + 1, hi)),
+                    Some((lo, hi)) if hi == *n => bounded.meet(&Interval::from_unsigned(lo, hi - 1)),
+                    _ => *bounded,
+                }
+            }
+        }
+    }
+}
+
+/// find the range of every value in `ssa`, which should have been built from `cfg` - with
+/// `modifiers`, if there are any. `data` is where instructions in `cfg` are decoded from.
+///
+/// values are joined at phis until nothing changes, with phis at loop headers widened if they
+/// keep growing. the function is then re-evaluated a few times, narrowing widened bounds back to
+/// what the loop actually allows. bounds `modifiers` places on edges, like those
+/// `ConditionalBoundInference` adds for conditional branches, are where loops get those limits
+/// from. as with constant propagation, memory is never tracked, so loads could be anything.
+pub fn compute_intervals<A, M>(data: &M, cfg: &ControlFlowGraph<A::Address>, ssa: &SSA<A>, modifiers: Option<&InstructionModifiers<A>>) -> Intervals<A> where
+    A: IntervalSemantics + DecodeFrom<M>,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    M: MemoryRange<A>,
+{
+    let mut analysis = Analysis {
+        engine: Fixpoint::new(cfg, ssa, modifiers, |_, _| Interval::unknown()),
+    };
+    fixpoint::solve(&mut analysis, data);
+
+    let (values, executable) = analysis.engine.into_results();
+    Intervals {
+        values,
+        executable,
+    }
+}
+
+#[test]
+fn test_interval_arithmetic() {
+    let small = Interval::from_signed(-2, 3);
+    // crossing zero, the unsigned view is everything..
+    assert_eq!(small.unsigned_bounds(), Some((0, u64::MAX)));
+    // .. until it's known to be positive.
+    let positive = small.meet(&Interval::from_unsigned(0, 100));
+    assert_eq!(positive, Interval::Range { signed: (0, 3), unsigned: (0, 3) });
+    assert_eq!(positive.in_bounds(4), Some(true));
+    assert_eq!(small.in_bounds(4), None);
+    assert_eq!(Interval::from_const(-1).in_bounds(4), Some(false));
+
+    assert_eq!(small.add(&Interval::from_const(2)).value(), Interval::from_signed(0, 5));
+    assert_eq!(small.sub(&Interval::from_signed(1, 2)).value(), Interval::from_signed(-4, 2));
+    assert_eq!(small.mul(&Interval::from_const(-4)).value(), Interval::from_signed(-12, 8));
+    assert_eq!(positive.shl(&Interval::from_const(3)), Interval::from_unsigned(0, 24));
+    assert_eq!(Interval::from_unsigned(5, 9).or(&Interval::from_unsigned(0, 2)).value(), Interval::from_unsigned(5, 15));
+    assert_eq!(Interval::unknown().and(&Interval::from_const(0xff)).value(), Interval::from_unsigned(0, 0xff));
+    assert_eq!(Interval::unknown().modulo(&Interval::from_const(8)), Interval::unknown());
+    assert_eq!(positive.modulo(&Interval::from_const(2)), Interval::from_unsigned(0, 1));
+
+    // wrapping at both ends of an unsigned range still leaves a range..
+    let high = Interval::from_unsigned(u64::MAX - 1, u64::MAX);
+    assert_eq!(high.add(&Interval::from_const(2)).value(), Interval::from_unsigned(0, 1));
+    assert_eq!(high.add(&Interval::from_const(2)).carry, Interval::from_const(1));
+    // .. wrapping at just one doesn't, but the signed view can still say what happened.
+    assert_eq!(high.add(&Interval::from_unsigned(0, 1)).value(), Interval::from_signed(-2, 0));
+
+    assert_eq!(small.lt(&Interval::from_const(4)), Interval::from_const(1));
+    assert_eq!(small.lt(&Interval::from_const(0)), Interval::from_unsigned(0, 1));
+    assert_eq!(Value::eq(&positive, &Interval::from_const(7)), Interval::from_const(0));
+    assert_eq!(positive.add(&Interval::from_const(1)).value().not(), Interval::from_const(0));
+
+    let grown = positive.join(&Interval::from_signed(0, 4));
+    let widened = positive.widen(&grown);
+    assert_eq!(widened.signed_bounds(), Some((0, i64::MAX)));
+    assert_eq!(widened.narrow(&Interval::from_signed(0, 10)), Interval::from_signed(0, 10));
+    assert_eq!(Interval::from_unsigned(0, 0x1_0000).truncate(2), Interval::from_unsigned(0, 0xffff));
+}
+
+#[test]
+fn test_interval_loop() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+    use data::Direction;
+    use yaxpeax_x86::x86_64;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x31, 0xc9,                           // 0x00: xor rcx, rcx
+        0xeb, 0x08,                                 // 0x03: jmp 0x0d
+        0x48, 0x8b, 0x04, 0xcf,                     // 0x05: mov rax, [rdi + rcx * 8]
+        0x48, 0x83, 0xc1, 0x01,                     // 0x09: add rcx, 1
+        0x48, 0x83, 0xf9, 0x0a,                     // 0x0d: cmp rcx, 10
+        0x7c, 0xf2,                                 // 0x11: jl 0x05
+        0xc3,                                       // 0x13: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+
+    // with nothing bounding the loop, the counter could grow until it wraps around.
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+    let intervals = compute_intervals(&data, &cfg, &dfg, None);
+    let index = intervals.value(&dfg.get_use(0x05, Location::rcx()).as_rc()).unwrap();
+    assert_eq!(index, Interval::unknown());
+
+    // these are the bounds `ConditionalInference` places on the edges out of `jl`.
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    modifiers.add_edge_modifier(0x0d, 0x05, Some(Location::rcx()), ModifierExpression::SignedBelow(9));
+    modifiers.add_edge_modifier(0x0d, 0x13, Some(Location::rcx()), ModifierExpression::SignedAbove(10));
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+    let intervals = compute_intervals(&data, &cfg, &dfg, Some(&modifiers));
+
+    // each edge out of `jl` bounds the counter as it was at the end of the block, not as the
+    // other edge left it.
+    let counter = dfg.get_use(0x0d, Location::rcx()).as_rc();
+    for next in [0x05, 0x13].iter() {
+        let read = &dfg.control_dependent_values[&0x0d][next][&(Location::rcx(), Direction::Read)];
+        assert!(read == &counter);
+    }
+
+    // the bounds are exact, so the index is the `0..10` it really is, where widening alone left
+    // it unbounded.
+    let index = intervals.value(&dfg.get_use(0x05, Location::rcx()).as_rc()).unwrap();
+    assert_eq!(index, Interval::from_signed(0, 9));
+    assert_eq!(index.in_bounds(10), Some(true));
+    assert_eq!(intervals.value(&dfg.get_def(0x09, Location::rcx()).as_rc()), Some(Interval::from_signed(1, 10)));
+    assert_eq!(intervals.value(&dfg.get_use(0x0d, Location::rcx()).as_rc()), Some(Interval::from_signed(0, 10)));
+    let exit = &dfg.control_dependent_values[&0x0d][&0x13][&(Location::rcx(), Direction::Write)];
+    assert_eq!(intervals.value(exit), Some(Interval::from_const(10)));
+
+    // a signed bound doesn't say a value is positive, the way an unsigned one would.
+    let negative = Interval::from_signed(-5, 20);
+    assert_eq!(Analysis::<x86_64>::bound(&negative, &ModifierExpression::SignedBelow(9)), Interval::from_signed(-5, 9));
+    assert_eq!(Analysis::<x86_64>::bound(&negative, &ModifierExpression::Below(9)), Interval::from_signed(0, 9));
+}
@@ -4,6 +4,8 @@ use memory::MemoryRange;
 use analyses::control_flow::ControlFlowGraph;
 use analyses::static_single_assignment::{DefSource, SSA, SSAValues};
 
+pub mod interval;
+
 pub trait ConditionalBoundInference<A: Arch + SSAValues, U> {
     /// Finds the instruction responsible for the condition that `conditional_instr` branches on.
     /// For some architectures, this may be a ways earlier in the program. For others, this may
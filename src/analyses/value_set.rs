@@ -0,0 +1,1471 @@
+use std::cell::{Cell, RefCell};
+use std::cmp::{max, min};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use yaxpeax_arch::{Address, AddressBase, LengthedInstruction};
+
+use analyses::{CompletionStatus, DFG, Expression, IndirectQuery, Item, Value, ValueIndex, ValueOrImmediate, ValueRes};
+use analyses::constant_propagation::ConstantSemantics;
+use analyses::control_flow::ControlFlowGraph;
+use analyses::fixpoint::{self, Fixpoint, Lattice, Transfer};
+use analyses::stack_pointer::{Adjustment, StackCleanup, StackSemantics};
+use analyses::value_range::interval::ones_above;
+use analyses::static_single_assignment::{DFGRef, SSA, SSAValues};
+use arch::{AbiDefaults, DecodeFrom};
+use data::Direction;
+use data::modifier::{InstructionModifiers, ModifierExpression};
+use memory::MemoryRange;
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        let rem = a % b;
+        a = b;
+        b = rem;
+    }
+    a
+}
+
+/// how far `hi` is past `lo`, for `lo <= hi`. this can be more than `i64::MAX`.
+fn distance(lo: i64, hi: i64) -> u64 {
+    hi.wrapping_sub(lo) as u64
+}
+
+/// if `mask` clears some number of low bits and keeps the rest, like the `-16` in
+/// `and rsp, -16`, the low bits it clears.
+fn alignment(mask: i64) -> Option<i64> {
+    let low = !mask;
+    if mask < 0 && low & low.wrapping_add(1) == 0 {
+        Some(low)
+    } else {
+        None
+    }
+}
+
+/// values from `lo` to `hi`, `stride` apart: `lo`, `lo + stride`, `lo + 2 * stride`, up to `hi`,
+/// as signed 64-bit numbers. this is the shape of an index scaled into an array, where an `rcx`
+/// from `0` to `9` makes `rcx * 8` the strided interval `8[0, 0x48]`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StridedInterval {
+    Empty,
+    /// `stride` is `0` exactly when `lo` and `hi` are the same, and `hi` is always a whole number
+    /// of strides past `lo`.
+    Range { stride: u64, lo: i64, hi: i64 },
+}
+
+type Bounds = (u64, i64, i64);
+
+impl StridedInterval {
+    /// the values from `lo` to no further than `hi`, `stride` apart. a `stride` of `0` is just
+    /// `lo`.
+    pub fn new(stride: u64, lo: i64, hi: i64) -> StridedInterval {
+        if lo > hi {
+            return StridedInterval::Empty;
+        }
+        if stride == 0 || lo == hi {
+            return StridedInterval::constant(lo);
+        }
+        let hi = lo.wrapping_add((distance(lo, hi) / stride * stride) as i64);
+        if hi == lo {
+            return StridedInterval::constant(lo);
+        }
+        StridedInterval::Range { stride, lo, hi }
+    }
+
+    pub fn constant(c: i64) -> StridedInterval {
+        StridedInterval::Range { stride: 0, lo: c, hi: c }
+    }
+
+    /// every 64-bit value.
+    pub fn full() -> StridedInterval {
+        StridedInterval::Range { stride: 1, lo: i64::MIN, hi: i64::MAX }
+    }
+
+    /// bounds that were computed without wrapping. anything that doesn't fit in 64 bits could
+    /// have wrapped to anywhere.
+    fn checked(stride: u64, lo: i128, hi: i128) -> StridedInterval {
+        if lo < i64::MIN as i128 || hi > i64::MAX as i128 {
+            StridedInterval::full()
+        } else {
+            StridedInterval::new(stride, lo as i64, hi as i64)
+        }
+    }
+
+    /// `0` or `1`, unless `always` or `never` says which.
+    fn boolean(always: bool, never: bool) -> StridedInterval {
+        if always {
+            StridedInterval::constant(1)
+        } else if never {
+            StridedInterval::constant(0)
+        } else {
+            StridedInterval::new(1, 0, 1)
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == StridedInterval::Empty
+    }
+
+    pub fn bounds(&self) -> Option<(i64, i64)> {
+        self.parts().map(|(_, lo, hi)| (lo, hi))
+    }
+
+    pub fn stride(&self) -> Option<u64> {
+        self.parts().map(|(stride, _, _)| stride)
+    }
+
+    fn parts(&self) -> Option<Bounds> {
+        match self {
+            StridedInterval::Empty => None,
+            StridedInterval::Range { stride, lo, hi } => Some((*stride, *lo, *hi)),
+        }
+    }
+
+    fn pair(&self, other: &StridedInterval) -> Option<(Bounds, Bounds)> {
+        Some((self.parts()?, other.parts()?))
+    }
+
+    pub fn contains(&self, x: i64) -> bool {
+        match self.parts() {
+            None => false,
+            Some((stride, lo, hi)) => {
+                lo <= x && x <= hi && (x == lo || distance(lo, x).checked_rem(stride) == Some(0))
+            }
+        }
+    }
+
+    pub fn to_const(&self) -> Option<i64> {
+        match self.parts() {
+            Some((0, lo, _)) => Some(lo),
+            _ => None,
+        }
+    }
+
+    fn non_negative(&self) -> bool {
+        self.bounds().map(|(lo, _)| lo >= 0).unwrap_or(true)
+    }
+
+    /// every value in either. the stride is whatever both strides, and the distance between
+    /// their starts, are multiples of.
+    pub fn join(&self, other: &StridedInterval) -> StridedInterval {
+        match self.pair(other) {
+            Some(((ls, llo, lhi), (rs, rlo, rhi))) => {
+                let stride = gcd(gcd(ls, rs), distance(min(llo, rlo), max(llo, rlo)));
+                StridedInterval::new(stride, min(llo, rlo), max(lhi, rhi))
+            }
+            None => if self.is_empty() { *other } else { *self },
+        }
+    }
+
+    /// values in both. unless one is a constant or both have the same stride, this is the
+    /// values of the one with the larger stride that are within both's bounds, which may include
+    /// some that aren't in the other.
+    pub fn meet(&self, other: &StridedInterval) -> StridedInterval {
+        let ((ls, llo, lhi), (rs, rlo, rhi)) = match self.pair(other) {
+            Some(parts) => parts,
+            None => { return StridedInterval::Empty; }
+        };
+        if ls == 0 {
+            return if other.contains(llo) { *self } else { StridedInterval::Empty };
+        }
+        if rs == 0 {
+            return if self.contains(rlo) { *other } else { StridedInterval::Empty };
+        }
+        if ls == rs && distance(min(llo, rlo), max(llo, rlo)).checked_rem(ls) != Some(0) {
+            return StridedInterval::Empty;
+        }
+        let (stride, base) = if ls >= rs { (ls, llo) } else { (rs, rlo) };
+        let lo = max(llo, rlo) as i128;
+        let hi = min(lhi, rhi);
+        // round `lo` up to the next value `base` is some strides away from.
+        let lo = base as i128 + (lo - base as i128 + stride as i128 - 1).div_euclid(stride as i128) * stride as i128;
+        if lo > hi as i128 {
+            return StridedInterval::Empty;
+        }
+        StridedInterval::new(stride, lo as i64, hi)
+    }
+
+    /// `next`, with a bound that's moved past this one's sent as far as it goes. a bound can
+    /// only be widened once, so a value growing around a loop stops changing.
+    pub fn widen(&self, next: &StridedInterval) -> StridedInterval {
+        let joined = self.join(next);
+        match (self.bounds(), joined.parts()) {
+            (Some((lo, hi)), Some((stride, jlo, jhi))) if stride != 0 => {
+                let lo = if jlo < lo {
+                    // as far down as `jlo` can go and stay on its stride.
+                    jlo as i128 - (jlo as i128 - i64::MIN as i128) / stride as i128 * stride as i128
+                } else {
+                    jlo as i128
+                };
+                let hi = if jhi > hi { i64::MAX } else { jhi };
+                StridedInterval::new(stride, lo as i64, hi)
+            }
+            _ => joined,
+        }
+    }
+
+    /// this interval, with a bound that was widened all the way taken from `next` instead.
+    pub fn narrow(&self, next: &StridedInterval) -> StridedInterval {
+        match self.pair(next) {
+            Some(((stride, lo, hi), (_, nlo, nhi))) => {
+                let lo = if (lo as i128) - (stride as i128) < i64::MIN as i128 { nlo } else { lo };
+                let hi = if (hi as i128) + (stride as i128) > i64::MAX as i128 { nhi } else { hi };
+                self.meet(&StridedInterval::new(1, lo, hi))
+            }
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn add(&self, other: &StridedInterval) -> StridedInterval {
+        match self.pair(other) {
+            Some(((ls, llo, lhi), (rs, rlo, rhi))) => {
+                StridedInterval::checked(gcd(ls, rs), llo as i128 + rlo as i128, lhi as i128 + rhi as i128)
+            }
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn neg(&self) -> StridedInterval {
+        match self.parts() {
+            Some((stride, lo, hi)) => StridedInterval::checked(stride, -(hi as i128), -(lo as i128)),
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn sub(&self, other: &StridedInterval) -> StridedInterval {
+        self.add(&other.neg())
+    }
+
+    /// every value multiplied by `c`.
+    pub fn scale(&self, c: i64) -> StridedInterval {
+        let (stride, lo, hi) = match self.parts() {
+            Some(parts) => parts,
+            None => { return StridedInterval::Empty; }
+        };
+        let stride = match stride.checked_mul(c.unsigned_abs()) {
+            Some(stride) => stride,
+            None => { return StridedInterval::full(); }
+        };
+        let (lo, hi) = (lo as i128 * c as i128, hi as i128 * c as i128);
+        StridedInterval::checked(stride, min(lo, hi), max(lo, hi))
+    }
+
+    pub fn mul(&self, other: &StridedInterval) -> StridedInterval {
+        if let Some(c) = other.to_const() {
+            return self.scale(c);
+        }
+        if let Some(c) = self.to_const() {
+            return other.scale(c);
+        }
+        match self.pair(other) {
+            Some(((_, llo, lhi), (_, rlo, rhi))) => {
+                let corners = [
+                    llo as i128 * rlo as i128,
+                    llo as i128 * rhi as i128,
+                    lhi as i128 * rlo as i128,
+                    lhi as i128 * rhi as i128,
+                ];
+                StridedInterval::checked(1, *corners.iter().min().unwrap(), *corners.iter().max().unwrap())
+            }
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn shl(&self, amt: u32) -> StridedInterval {
+        if amt < 63 {
+            self.scale(1 << amt)
+        } else {
+            StridedInterval::full()
+        }
+    }
+
+    pub fn sar(&self, amt: u32) -> StridedInterval {
+        match self.parts() {
+            Some((stride, lo, hi)) if amt < 64 => {
+                // shifting out bits the stride doesn't touch keeps the values evenly spaced.
+                let stride = if stride.trailing_zeros() >= amt { stride >> amt } else { 1 };
+                StridedInterval::new(stride, lo >> amt, hi >> amt)
+            }
+            Some(_) => StridedInterval::full(),
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn shr(&self, amt: u32) -> StridedInterval {
+        if self.non_negative() || amt == 0 {
+            self.sar(amt)
+        } else if amt < 64 {
+            StridedInterval::new(1, 0, (u64::MAX >> amt) as i64)
+        } else {
+            StridedInterval::full()
+        }
+    }
+
+    /// these values, rounded down to a multiple of `low + 1`, which moves each by at most `low`.
+    /// where the values are offsets into a region, it isn't known what they're aligned to, so
+    /// this doesn't try to say which multiples they land on.
+    pub fn align_down(&self, low: i64) -> StridedInterval {
+        match self.bounds() {
+            Some((lo, hi)) => StridedInterval::checked(1, lo as i128 - low as i128, hi as i128),
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn and(&self, other: &StridedInterval) -> StridedInterval {
+        let ((_, llo, lhi), (_, rlo, rhi)) = match self.pair(other) {
+            Some(parts) => parts,
+            None => { return StridedInterval::Empty; }
+        };
+        if let (Some(l), Some(r)) = (self.to_const(), other.to_const()) {
+            return StridedInterval::constant(l & r);
+        }
+        if let Some(low) = other.to_const().and_then(alignment) {
+            return self.align_down(low);
+        }
+        if let Some(low) = self.to_const().and_then(alignment) {
+            return other.align_down(low);
+        }
+        match (llo >= 0, rlo >= 0) {
+            (true, true) => StridedInterval::new(1, 0, min(lhi, rhi)),
+            (true, false) => StridedInterval::new(1, 0, lhi),
+            (false, true) => StridedInterval::new(1, 0, rhi),
+            (false, false) => StridedInterval::full(),
+        }
+    }
+
+    pub fn or(&self, other: &StridedInterval) -> StridedInterval {
+        match (self.to_const(), other.to_const(), self.pair(other)) {
+            (_, _, None) => StridedInterval::Empty,
+            (Some(l), Some(r), _) => StridedInterval::constant(l | r),
+            (_, _, Some(((_, llo, lhi), (_, rlo, rhi)))) if llo >= 0 && rlo >= 0 => {
+                StridedInterval::new(1, max(llo, rlo), ones_above((lhi | rhi) as u64) as i64)
+            }
+            _ => StridedInterval::full(),
+        }
+    }
+
+    pub fn xor(&self, other: &StridedInterval) -> StridedInterval {
+        match (self.to_const(), other.to_const(), self.pair(other)) {
+            (_, _, None) => StridedInterval::Empty,
+            (Some(l), Some(r), _) => StridedInterval::constant(l ^ r),
+            (_, _, Some(((_, llo, lhi), (_, rlo, rhi)))) if llo >= 0 && rlo >= 0 => {
+                StridedInterval::new(1, 0, ones_above((lhi | rhi) as u64) as i64)
+            }
+            _ => StridedInterval::full(),
+        }
+    }
+
+    pub fn modulo(&self, other: &StridedInterval) -> StridedInterval {
+        match (self.to_const(), other.to_const(), self.pair(other)) {
+            (_, _, None) => StridedInterval::Empty,
+            (Some(l), Some(r), _) => l.checked_rem(r).map(StridedInterval::constant).unwrap_or_else(StridedInterval::full),
+            (_, _, Some(((_, llo, lhi), (_, rlo, rhi)))) if llo >= 0 && rlo > 0 => {
+                StridedInterval::new(1, 0, min(lhi, rhi - 1))
+            }
+            _ => StridedInterval::full(),
+        }
+    }
+
+    /// these values, if they're stored in `width` bytes. values that don't fit could have been
+    /// truncated to anything that does.
+    pub fn truncate(&self, width: usize) -> StridedInterval {
+        if width >= 8 {
+            return *self;
+        }
+        let mask = (1i64 << (width * 8)) - 1;
+        match self.bounds() {
+            Some((lo, hi)) if lo < 0 || hi > mask => StridedInterval::new(1, 0, mask),
+            _ => *self,
+        }
+    }
+
+    /// these values, with their low `bits` bits sign-extended to 64.
+    fn sign_extend(&self, bits: u32) -> StridedInterval {
+        if bits >= 64 {
+            return *self;
+        }
+        let limit = 1i64 << (bits - 1);
+        match self.bounds() {
+            Some((lo, hi)) if lo < -limit || hi >= limit => StridedInterval::new(1, -limit, limit - 1),
+            _ => *self,
+        }
+    }
+
+    pub fn le(&self, other: &StridedInterval) -> StridedInterval {
+        match self.pair(other) {
+            Some(((_, llo, lhi), (_, rlo, rhi))) => StridedInterval::boolean(lhi <= rlo, llo > rhi),
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn lt(&self, other: &StridedInterval) -> StridedInterval {
+        match self.pair(other) {
+            Some(((_, llo, lhi), (_, rlo, rhi))) => StridedInterval::boolean(lhi < rlo, llo >= rhi),
+            None => StridedInterval::Empty,
+        }
+    }
+
+    pub fn eq(&self, other: &StridedInterval) -> StridedInterval {
+        if self.pair(other).is_none() {
+            return StridedInterval::Empty;
+        }
+        let same = self.to_const().is_some() && self.to_const() == other.to_const();
+        StridedInterval::boolean(same, self.meet(other).is_empty())
+    }
+
+    /// could a `size`-byte access at any of these offsets overlap an `other_size`-byte access at
+    /// any of `other`'s? besides the bounds, this looks at strides: `16[0, 0x40]` and
+    /// `16[8, 0x48]` are the first and second fields of an array of two-`u64` structs, and
+    /// 8-byte accesses to them never overlap.
+    pub fn may_overlap(&self, size: u64, other: &StridedInterval, other_size: u64) -> bool {
+        let ((ls, llo, lhi), (rs, rlo, rhi)) = match self.pair(other) {
+            Some(parts) => parts,
+            None => { return false; }
+        };
+        let (size, other_size) = (size as i128, other_size as i128);
+        if lhi as i128 + size <= rlo as i128 || rhi as i128 + other_size <= llo as i128 {
+            return false;
+        }
+        let stride = gcd(ls, rs) as i128;
+        if stride == 0 {
+            return true;
+        }
+        // the accesses overlap if one starts less than `size` after the other, or less than
+        // `other_size` before it. starts are always `offset` plus some multiple of `stride`
+        // apart, so find the first such distance past `-other_size` and see if it's before
+        // `size`.
+        let offset = (llo as i128 - rlo as i128).rem_euclid(stride);
+        let first = offset + -(other_size - 1 + offset).div_euclid(stride) * stride;
+        first < size
+    }
+}
+
+/// some memory a pointer could point into. offsets into `Global` are addresses, and numbers that
+/// aren't pointers at all are offsets into it too.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Region<Addr> {
+    Global,
+    /// the stack frame of the function at this address. offsets are from where the stack pointer
+    /// was when the function was entered.
+    Stack(Addr),
+    /// memory returned by the allocator call at this address.
+    Heap(Addr),
+}
+
+/// a value-set in the sense of Balakrishnan and Reps' value-set analysis: for each region a value
+/// could point into, the offsets into it that it could be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueSet<Addr> {
+    /// anything, in any region.
+    Top,
+    /// a value with no regions has no values at all - either whatever defines it never executes,
+    /// or nothing has reached it yet. offsets are never `Empty`.
+    Regions(BTreeMap<Region<Addr>, StridedInterval>),
+}
+
+impl<Addr: Address> ValueSet<Addr> {
+    pub fn empty() -> ValueSet<Addr> {
+        ValueSet::Regions(BTreeMap::new())
+    }
+
+    pub fn in_region(region: Region<Addr>, offsets: StridedInterval) -> ValueSet<Addr> {
+        let mut regions = BTreeMap::new();
+        if !offsets.is_empty() {
+            regions.insert(region, offsets);
+        }
+        ValueSet::Regions(regions)
+    }
+
+    pub fn number(values: StridedInterval) -> ValueSet<Addr> {
+        ValueSet::in_region(Region::Global, values)
+    }
+
+    fn boolean() -> ValueSet<Addr> {
+        ValueSet::number(StridedInterval::new(1, 0, 1))
+    }
+
+    pub fn is_top(&self) -> bool {
+        *self == ValueSet::Top
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            ValueSet::Top => false,
+            ValueSet::Regions(regions) => regions.is_empty(),
+        }
+    }
+
+    pub fn regions(&self) -> Option<&BTreeMap<Region<Addr>, StridedInterval>> {
+        match self {
+            ValueSet::Top => None,
+            ValueSet::Regions(regions) => Some(regions),
+        }
+    }
+
+    /// offsets into `region` this could be, if it's known.
+    pub fn offsets(&self, region: &Region<Addr>) -> Option<StridedInterval> {
+        self.regions().map(|regions| regions.get(region).cloned().unwrap_or(StridedInterval::Empty))
+    }
+
+    /// the values this could be, if it's only a number and not a pointer into some region.
+    fn as_number(&self) -> Option<StridedInterval> {
+        let regions = self.regions()?;
+        if regions.keys().all(|region| *region == Region::Global) {
+            Some(regions.get(&Region::Global).cloned().unwrap_or(StridedInterval::Empty))
+        } else {
+            None
+        }
+    }
+
+    /// the one region this points into, other than `Global`, and the offsets into it.
+    fn as_pointer(&self) -> Option<(Region<Addr>, StridedInterval)> {
+        let regions = self.regions()?;
+        if regions.len() != 1 {
+            return None;
+        }
+        regions.iter()
+            .next()
+            .filter(|(region, _)| **region != Region::Global)
+            .map(|(region, offsets)| (*region, *offsets))
+    }
+
+    /// `f` of this and `other`, if both are numbers. arithmetic on pointers beyond adding offsets
+    /// says nothing about what they point to.
+    fn numeric<F: Fn(&StridedInterval, &StridedInterval) -> StridedInterval>(&self, other: &ValueSet<Addr>, f: F) -> ValueSet<Addr> {
+        if self.is_empty() || other.is_empty() {
+            return ValueSet::empty();
+        }
+        match (self.as_number(), other.as_number()) {
+            (Some(l), Some(r)) => ValueSet::number(f(&l, &r)),
+            _ => ValueSet::Top,
+        }
+    }
+
+    /// `f` of this number, if it is one.
+    fn map_number<F: Fn(&StridedInterval) -> StridedInterval>(&self, f: F) -> ValueSet<Addr> {
+        match self.as_number() {
+            Some(StridedInterval::Empty) => ValueSet::empty(),
+            Some(values) => ValueSet::number(f(&values)),
+            None => ValueSet::Top,
+        }
+    }
+
+    fn map_offsets<F: Fn(&StridedInterval) -> StridedInterval>(&self, f: F) -> ValueSet<Addr> {
+        self.map_regions(|_, offsets| f(offsets))
+    }
+
+    fn map_regions<F: Fn(&Region<Addr>, &StridedInterval) -> StridedInterval>(&self, f: F) -> ValueSet<Addr> {
+        match self {
+            ValueSet::Top => ValueSet::Top,
+            ValueSet::Regions(regions) => ValueSet::Regions(regions.iter()
+                .map(|(region, offsets)| (*region, f(region, offsets)))
+                .filter(|(_, offsets)| !offsets.is_empty())
+                .collect()),
+        }
+    }
+
+    /// combine offsets in regions both values are in with `both`, and keep the rest if `keep`.
+    fn combine<F: Fn(&StridedInterval, &StridedInterval) -> StridedInterval>(&self, other: &ValueSet<Addr>, keep: bool, both: F) -> ValueSet<Addr> {
+        let (left, right) = match (self, other) {
+            (ValueSet::Regions(left), ValueSet::Regions(right)) => (left, right),
+            _ => { return ValueSet::Top; }
+        };
+        let mut regions = BTreeMap::new();
+        for (region, offsets) in left.iter() {
+            let offsets = match right.get(region) {
+                Some(other) => both(offsets, other),
+                None if keep => *offsets,
+                None => StridedInterval::Empty,
+            };
+            if !offsets.is_empty() {
+                regions.insert(*region, offsets);
+            }
+        }
+        if keep {
+            for (region, offsets) in right.iter() {
+                regions.entry(*region).or_insert(*offsets);
+            }
+        }
+        ValueSet::Regions(regions)
+    }
+
+    pub fn join(&self, other: &ValueSet<Addr>) -> ValueSet<Addr> {
+        self.combine(other, true, StridedInterval::join)
+    }
+
+    pub fn meet(&self, other: &ValueSet<Addr>) -> ValueSet<Addr> {
+        match (self, other) {
+            (ValueSet::Top, _) => other.clone(),
+            (_, ValueSet::Top) => self.clone(),
+            _ => self.combine(other, false, StridedInterval::meet),
+        }
+    }
+
+    pub fn widen(&self, next: &ValueSet<Addr>) -> ValueSet<Addr> {
+        self.combine(next, true, StridedInterval::widen)
+    }
+
+    pub fn narrow(&self, next: &ValueSet<Addr>) -> ValueSet<Addr> {
+        match (self, next) {
+            (ValueSet::Top, _) => next.clone(),
+            (_, ValueSet::Top) => self.clone(),
+            _ => self.combine(next, false, StridedInterval::narrow),
+        }
+    }
+
+    /// only the values that, as numbers, are also in `bounds`. pointers are left alone, since
+    /// where regions are isn't known.
+    pub fn bound_number(&self, bounds: &StridedInterval) -> ValueSet<Addr> {
+        self.map_regions(|region, offsets| {
+            if *region == Region::Global { offsets.meet(bounds) } else { *offsets }
+        })
+    }
+
+    /// these values, if they're stored in `width` bytes. a truncated pointer doesn't point
+    /// anywhere known.
+    pub fn truncate(&self, width: usize) -> ValueSet<Addr> {
+        if width >= 8 {
+            return self.clone();
+        }
+        self.map_number(|values| values.truncate(width))
+    }
+
+    /// could a `size`-byte access through this pointer overlap an `other_size`-byte access
+    /// through `other`? distinct regions never overlap.
+    pub fn may_overlap(&self, size: u64, other: &ValueSet<Addr>, other_size: u64) -> bool {
+        match (self, other) {
+            (ValueSet::Regions(left), ValueSet::Regions(right)) => {
+                left.iter().any(|(region, offsets)| {
+                    right.get(region).map(|other| offsets.may_overlap(size, other, other_size)).unwrap_or(false)
+                })
+            }
+            _ => true,
+        }
+    }
+
+    fn shift_amount(amt: &ValueSet<Addr>) -> Option<u32> {
+        amt.to_const().filter(|amt| *amt >= 0 && *amt < 64).map(|amt| amt as u32)
+    }
+}
+
+/// value-sets are of 64-bit values. pointers can be offset by numbers, and subtracted from
+/// pointers into the same region; anything else computed from a pointer is `Top`. comparisons
+/// are signed and produce `0` or `1`, and `not` is a logical not, as with `Interval`.
+impl<Addr: Address> Value for ValueSet<Addr> {
+    fn unknown() -> Self {
+        ValueSet::Top
+    }
+
+    fn from_const(c: i64) -> Self {
+        ValueSet::number(StridedInterval::constant(c))
+    }
+
+    fn from_set(xs: &[Self]) -> Self {
+        xs.iter().fold(ValueSet::empty(), |acc, x| acc.join(x))
+    }
+
+    fn to_const(&self) -> Option<i64> {
+        self.as_number().and_then(|values| values.to_const())
+    }
+
+    fn add(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes { value: ValueSet::empty(), carry: ValueSet::empty() };
+        }
+        let value = match (self.as_number(), other.as_number()) {
+            (Some(l), _) => other.map_offsets(|offsets| offsets.add(&l)),
+            (_, Some(r)) => self.map_offsets(|offsets| offsets.add(&r)),
+            _ => ValueSet::Top,
+        };
+        ValueRes { value, carry: ValueSet::boolean() }
+    }
+
+    fn sub(&self, other: &Self) -> ValueRes<Self> {
+        if self.is_empty() || other.is_empty() {
+            return ValueRes { value: ValueSet::empty(), carry: ValueSet::empty() };
+        }
+        let value = match (other.as_number(), self.as_pointer(), other.as_pointer()) {
+            (Some(r), _, _) => self.map_offsets(|offsets| offsets.sub(&r)),
+            // how far apart two pointers into the same region are is a number.
+            (_, Some((l_region, l)), Some((r_region, r))) if l_region == r_region => ValueSet::number(l.sub(&r)),
+            _ => ValueSet::Top,
+        };
+        ValueRes { value, carry: ValueSet::boolean() }
+    }
+
+    fn mul(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes { value: self.numeric(other, StridedInterval::mul), carry: ValueSet::boolean() }
+    }
+
+    fn or(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.numeric(other, StridedInterval::or))
+    }
+
+    fn and(&self, other: &Self) -> ValueRes<Self> {
+        // aligning a pointer keeps it in its region, somewhere a little lower.
+        let mask = |v: &ValueSet<Addr>| v.to_const().and_then(alignment);
+        let value = match (self.as_number(), other.as_number(), mask(self), mask(other)) {
+            (Some(_), Some(_), _, _) => self.numeric(other, StridedInterval::and),
+            (_, _, _, Some(low)) => self.map_offsets(|offsets| offsets.align_down(low)),
+            (_, _, Some(low), _) => other.map_offsets(|offsets| offsets.align_down(low)),
+            _ => ValueSet::Top,
+        };
+        ValueRes::literal(value)
+    }
+
+    fn xor(&self, other: &Self) -> ValueRes<Self> {
+        ValueRes::literal(self.numeric(other, StridedInterval::xor))
+    }
+
+    fn modulo(&self, other: &Self) -> Self {
+        self.numeric(other, StridedInterval::modulo)
+    }
+
+    fn ne(&self, other: &Self) -> Self {
+        Value::eq(self, other).not()
+    }
+
+    fn le(&self, other: &Self) -> Self {
+        match self.numeric(other, StridedInterval::le) {
+            ValueSet::Top => ValueSet::boolean(),
+            value => value,
+        }
+    }
+
+    fn lt(&self, other: &Self) -> Self {
+        match self.numeric(other, StridedInterval::lt) {
+            ValueSet::Top => ValueSet::boolean(),
+            value => value,
+        }
+    }
+
+    fn eq(&self, other: &Self) -> Self {
+        match self.numeric(other, StridedInterval::eq) {
+            ValueSet::Top => ValueSet::boolean(),
+            value => value,
+        }
+    }
+
+    /// semantics use `not` to negate conditions, so this is a logical not rather than a bitwise
+    /// one.
+    fn not(&self) -> Self {
+        match self.as_number() {
+            Some(StridedInterval::Empty) => ValueSet::empty(),
+            Some(values) => ValueSet::number(StridedInterval::boolean(values.to_const() == Some(0), !values.contains(0))),
+            None => ValueSet::boolean(),
+        }
+    }
+
+    fn sxt(&self, width: &Self) -> Self {
+        match width.to_const() {
+            Some(bits) if bits > 0 => self.map_number(|values| values.sign_extend(bits as u32)),
+            _ => ValueSet::Top,
+        }
+    }
+
+    fn zxt(&self, width: &Self) -> Self {
+        match width.to_const() {
+            Some(bits) if bits > 0 && bits % 8 == 0 => self.map_number(|values| values.truncate(bits as usize / 8)),
+            _ => ValueSet::Top,
+        }
+    }
+
+    fn shr(&self, amt: &Self) -> Self {
+        match ValueSet::shift_amount(amt) {
+            Some(amt) => self.map_number(|values| values.shr(amt)),
+            None => ValueSet::Top,
+        }
+    }
+
+    fn sar(&self, amt: &Self) -> Self {
+        match ValueSet::shift_amount(amt) {
+            Some(amt) => self.map_number(|values| values.sar(amt)),
+            None => ValueSet::Top,
+        }
+    }
+
+    fn shl(&self, amt: &Self) -> Self {
+        match ValueSet::shift_amount(amt) {
+            Some(amt) => self.map_number(|values| values.shl(amt)),
+            None => ValueSet::Top,
+        }
+    }
+
+    fn sal(&self, amt: &Self) -> Self {
+        self.shl(amt)
+    }
+}
+
+/// what an architecture needs to provide for `compute_value_sets`, on top of what it provides for
+/// constant propagation and stack pointer analysis.
+pub trait ValueSetSemantics: ConstantSemantics + StackSemantics {
+    /// evaluate `instr` at `addr`. locations it doesn't write, but `SSA` says `instr` does, could
+    /// be anything. calls aren't evaluated this way, since what happens in the callee matters
+    /// more than what the instruction does.
+    fn evaluate_value_sets<D: DFG<ValueSet<Self::Address>, Self, Self::Address>>(addr: Self::Address, instr: &Self::Instruction, dfg: &mut D) -> CompletionStatus;
+
+    fn is_call(instr: &Self::Instruction) -> bool;
+
+    /// the function `instr` calls, if it's a call to somewhere known statically.
+    fn call_target(addr: Self::Address, instr: &Self::Instruction) -> Option<Self::Address>;
+
+    /// where called functions leave what they return.
+    fn return_location() -> Self::Location;
+}
+
+/// a load or store some instruction makes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryAccess<Addr> {
+    pub address: ValueSet<Addr>,
+    pub size: usize,
+    pub direction: Direction,
+}
+
+/// the results of `compute_value_sets`.
+#[derive(Debug)]
+pub struct ValueSets<A: SSAValues> {
+    /// every value defined by an instruction, phi, or edge that was reached, and function inputs
+    /// that were read. values that aren't here are empty if they're defined somewhere that never
+    /// executes, and `Top` otherwise.
+    pub values: HashMap<DFGRef<A>, ValueSet<A::Address>>,
+    /// the memory each reachable instruction accesses, in the order it accesses it.
+    pub accesses: BTreeMap<A::Address, Vec<MemoryAccess<A::Address>>>,
+    /// blocks reachable from the function's entry.
+    pub executable: BTreeSet<A::Address>,
+}
+
+impl<A: SSAValues> ValueSets<A> {
+    pub fn value(&self, value: &DFGRef<A>) -> Option<ValueSet<A::Address>> {
+        self.values.get(value).cloned()
+    }
+
+    /// where `expr` could point, for an expression over values this analysis looked at, like
+    /// the addresses `MemoryLayout` finds for accesses.
+    pub fn expression(&self, expr: &Item<ValueOrImmediate<A>>) -> ValueSet<A::Address> where A::Data: Eq + fmt::Display {
+        match &expr.value {
+            Expression::Value(ValueOrImmediate::Immediate(i)) => ValueSet::from_const(*i),
+            Expression::Value(ValueOrImmediate::Value(value)) => self.value(value).unwrap_or(ValueSet::Top),
+            Expression::Add { left, right } => self.expression(left).add(&self.expression(right)).value(),
+            Expression::Sub { left, right } => self.expression(left).sub(&self.expression(right)).value(),
+            Expression::Mul { left, right } => self.expression(left).mul(&self.expression(right)).value(),
+            Expression::Or { left, right } => self.expression(left).or(&self.expression(right)).value(),
+            Expression::And { left, right } => self.expression(left).and(&self.expression(right)).value(),
+            Expression::Xor { left, right } => self.expression(left).xor(&self.expression(right)).value(),
+            Expression::Shl { value, amount } => self.expression(value).shl(&self.expression(amount)),
+            Expression::Shr { value, amount } => self.expression(value).shr(&self.expression(amount)),
+            Expression::SignExtend { value, width } => self.expression(value).sxt(&ValueSet::from_const(*width as i64)),
+            // what memory held is only known while the function is being evaluated.
+            Expression::Load { .. } |
+            Expression::Unknown => ValueSet::Top,
+        }
+    }
+}
+
+/// what a location holds when `function` is entered: the stack pointer is at the start of its
+/// frame, and everything else could be anything.
+fn entry_value<A: ValueSetSemantics>(function: A::Address, loc: &A::Location) -> ValueSet<A::Address> {
+    if *loc == A::stack_pointer() {
+        ValueSet::in_region(Region::Stack(function), StridedInterval::constant(0))
+    } else {
+        ValueSet::Top
+    }
+}
+
+/// an abstract location: `size` bytes at a fixed offset into a region, like a spill slot in the
+/// stack frame or a global variable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct ALoc<Addr> {
+    region: Region<Addr>,
+    offset: i64,
+    size: usize,
+}
+
+/// what's known to be in memory at some point in a function. a-locs that aren't here could hold
+/// anything.
+type Memory<Addr> = BTreeMap<ALoc<Addr>, ValueSet<Addr>>;
+
+/// the a-loc a `size`-byte access to `address` is, if it's exactly one in `function`'s stack
+/// frame or the global region. memory from allocators isn't tracked.
+fn aloc_of<Addr: Address>(function: Addr, address: &ValueSet<Addr>, size: usize) -> Option<ALoc<Addr>> {
+    let regions = address.regions()?;
+    if regions.len() != 1 {
+        return None;
+    }
+    let (region, offsets) = regions.iter().next()?;
+    match region {
+        Region::Global => {}
+        Region::Stack(frame) if *frame == function => {}
+        _ => { return None; }
+    }
+    Some(ALoc {
+        region: *region,
+        offset: offsets.to_const()?,
+        size,
+    })
+}
+
+/// what's known at a point reached from each of `exits`: only what they all agree holds an a-loc.
+fn join_memory<'m, Addr: Address + 'm, I: Iterator<Item=&'m Memory<Addr>>>(mut exits: I) -> Memory<Addr> {
+    let mut joined = match exits.next() {
+        Some(memory) => memory.clone(),
+        None => { return Memory::new(); }
+    };
+    for memory in exits {
+        joined = joined.into_iter()
+            .filter_map(|(aloc, value)| memory.get(&aloc).map(|other| (aloc, value.join(other))))
+            .collect();
+    }
+    joined
+}
+
+/// records the loads and stores an instruction makes, and reads and writes a-locs in `memory`.
+struct RecordAccesses<'a, Addr: 'a> {
+    function: Addr,
+    accesses: &'a RefCell<Vec<MemoryAccess<Addr>>>,
+    memory: &'a RefCell<Memory<Addr>>,
+    /// set when a pointer into the stack frame is stored somewhere.
+    escaped: &'a Cell<bool>,
+}
+
+impl<'a, Addr: Address> RecordAccesses<'a, Addr> {
+    /// forget a-locs a `size`-byte store to `address` could overwrite. a store that's exactly
+    /// an a-loc doesn't overwrite itself.
+    fn clobber(&self, address: &ValueSet<Addr>, size: usize, exact: Option<ALoc<Addr>>) {
+        let mut memory = self.memory.borrow_mut();
+        let regions = match address.regions() {
+            Some(regions) => regions,
+            None => {
+                memory.clear();
+                return;
+            }
+        };
+        memory.retain(|aloc, _| {
+            if Some(*aloc) == exact {
+                return true;
+            }
+            match regions.get(&aloc.region) {
+                Some(offsets) => !offsets.may_overlap(size as u64, &StridedInterval::constant(aloc.offset), aloc.size as u64),
+                None => true,
+            }
+        });
+    }
+}
+
+impl<'a, Addr: Address> IndirectQuery<ValueSet<Addr>> for RecordAccesses<'a, Addr> {
+    fn load(&self, address: ValueIndex<ValueSet<Addr>>) -> ValueSet<Addr> {
+        self.accesses.borrow_mut().push(MemoryAccess {
+            address: address.base.clone(),
+            size: address.size,
+            direction: Direction::Read,
+        });
+        aloc_of(self.function, address.base, address.size)
+            .and_then(|aloc| self.memory.borrow().get(&aloc).cloned())
+            .unwrap_or(ValueSet::Top)
+    }
+
+    fn store(&self, address: ValueIndex<ValueSet<Addr>>, value: &ValueSet<Addr>) {
+        self.accesses.borrow_mut().push(MemoryAccess {
+            address: address.base.clone(),
+            size: address.size,
+            direction: Direction::Write,
+        });
+        if value.regions().map(|regions| regions.contains_key(&Region::Stack(self.function))).unwrap_or(false) {
+            self.escaped.set(true);
+        }
+        let aloc = aloc_of(self.function, address.base, address.size);
+        self.clobber(address.base, address.size, aloc);
+        if let Some(aloc) = aloc {
+            self.memory.borrow_mut().insert(aloc, value.clone());
+        }
+    }
+
+    fn try_get_load(&self, _address: ValueIndex<ValueSet<Addr>>) -> Option<ValueSet<Addr>> {
+        None
+    }
+
+    fn try_get_store(&self, _address: ValueIndex<ValueSet<Addr>>) -> Option<()> {
+        None
+    }
+}
+
+/// an `SSA` read through the value-sets found so far, at one instruction.
+struct Evaluation<'a, A: ValueSetSemantics> where A::Location: AbiDefaults {
+    engine: &'a Fixpoint<'a, A, ValueSet<A::Address>>,
+    next: i64,
+    writes: Vec<(A::Location, ValueSet<A::Address>)>,
+    accesses: &'a RefCell<Vec<MemoryAccess<A::Address>>>,
+    memory: &'a RefCell<Memory<A::Address>>,
+    escaped: &'a Cell<bool>,
+    /// function inputs that were read, to keep with the results.
+    inputs: RefCell<Vec<DFGRef<A>>>,
+    /// some input hasn't been reached yet, so anything computed is premature.
+    pending: Cell<bool>,
+    /// some input was narrower than the 64 bits semantics compute with.
+    narrow: Cell<bool>,
+}
+
+impl<'a, A: ValueSetSemantics> DFG<ValueSet<A::Address>, A, A::Address> for Evaluation<'a, A> where A::Address: petgraph::graphmap::NodeTrait, A::Location: AbiDefaults {
+    type Indirect = RecordAccesses<'a, A::Address>;
+
+    fn read_loc(&self, when: A::Address, loc: A::Location) -> ValueSet<A::Address> {
+        if loc == A::program_counter() {
+            return ValueSet::from_const(self.next);
+        }
+        let value = match self.engine.ssa.try_get_use(when, loc.clone()) {
+            Some(value) => value,
+            None => { return ValueSet::Top; }
+        };
+        if self.engine.ssa.try_get_def_site(value).is_none() {
+            self.inputs.borrow_mut().push(value);
+        }
+        let set = self.engine.current(&value);
+        if set.is_empty() {
+            self.pending.set(true);
+        }
+        let defined_width = A::value_width(&self.engine.ssa.value(value).location);
+        match A::value_width(&loc) {
+            // a write to part of this location says nothing about the rest of it.
+            Some(width) if defined_width.map(|defined| defined < width).unwrap_or(false) => ValueSet::Top,
+            Some(width) if width < 8 => {
+                self.narrow.set(true);
+                set.truncate(width)
+            }
+            Some(width) if width > 8 => ValueSet::Top,
+            _ => set,
+        }
+    }
+
+    fn write_loc(&mut self, _when: A::Address, loc: A::Location, value: ValueSet<A::Address>) {
+        self.writes.push((loc, value));
+    }
+
+    fn indirect_loc(&self, _when: A::Address, _loc: A::Location) -> RecordAccesses<'a, A::Address> {
+        RecordAccesses {
+            function: self.engine.cfg.entrypoint,
+            accesses: self.accesses,
+            memory: self.memory,
+            escaped: self.escaped,
+        }
+    }
+}
+
+impl<Addr: Address> Lattice for ValueSet<Addr> {
+    fn bottom() -> Self { ValueSet::empty() }
+    fn top() -> Self { ValueSet::Top }
+    fn join(&self, other: &Self) -> Self { ValueSet::join(self, other) }
+    fn meet(&self, other: &Self) -> Self { ValueSet::meet(self, other) }
+    fn widen(&self, next: &Self) -> Self { ValueSet::widen(self, next) }
+    fn narrow(&self, next: &Self) -> Self { ValueSet::narrow(self, next) }
+}
+
+struct Analysis<'a, A: ValueSetSemantics, C: StackCleanup<A::Address> + ?Sized> where A::Location: AbiDefaults {
+    engine: Fixpoint<'a, A, ValueSet<A::Address>>,
+    cleanup: &'a C,
+    allocators: &'a BTreeSet<A::Address>,
+    accesses: BTreeMap<A::Address, Vec<MemoryAccess<A::Address>>>,
+    /// accesses of the instruction being evaluated.
+    recorded: RefCell<Vec<MemoryAccess<A::Address>>>,
+    /// memory in the block being evaluated, as of the instruction being evaluated..
+    memory: RefCell<Memory<A::Address>>,
+    /// .. and as each block evaluated so far leaves it.
+    exits: HashMap<A::Address, Memory<A::Address>>,
+    /// a pointer into the stack frame has been stored or kept somewhere other than the stack
+    /// pointer, so calls may write to any of the frame.
+    escaped: Cell<bool>,
+}
+
+impl<'a, A, C> Analysis<'a, A, C> where
+    A: ValueSetSemantics,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    C: StackCleanup<A::Address> + ?Sized,
+{
+    /// the instruction's own writes describe a call going out, not what's left when it returns.
+    /// the stack pointer is where `StackSemantics` says the callee leaves it, what allocators
+    /// return is a new heap region, and everything else the call writes could be anything.
+    ///
+    /// the callee could write any global, and anything in the stack frame below the stack
+    /// pointer. the rest of the frame is assumed to be left alone, unless a pointer into it has
+    /// escaped.
+    fn visit_call(&mut self, addr: A::Address, instr: &A::Instruction) {
+        let function = self.engine.cfg.entrypoint;
+        let before = self.engine.ssa.try_get_use(addr, A::stack_pointer())
+            .map(|value| self.engine.current(&value))
+            .unwrap_or_else(|| entry_value::<A>(function, &A::stack_pointer()));
+        if before.is_empty() {
+            return;
+        }
+        let stack = match A::stack_effect(addr, instr, self.cleanup).stack {
+            Adjustment::Add(amount) => before.add(&ValueSet::from_const(amount)).value(),
+            _ => ValueSet::Top,
+        };
+        let bottom = match before.regions() {
+            Some(regions) if regions.len() == 1 && !self.escaped.get() => {
+                regions.get(&Region::Stack(function)).and_then(|offsets| offsets.to_const())
+            }
+            _ => None,
+        };
+        self.memory.borrow_mut().retain(|aloc, _| {
+            aloc.region != Region::Global && bottom.map(|bottom| aloc.offset >= bottom).unwrap_or(false)
+        });
+        let allocates = A::call_target(addr, instr)
+            .map(|target| self.allocators.contains(&target))
+            .unwrap_or(false);
+
+        self.engine.update_writes(addr, |loc| {
+            if *loc == A::stack_pointer() {
+                stack.clone()
+            } else if allocates && *loc == A::return_location() {
+                ValueSet::in_region(Region::Heap(addr), StridedInterval::constant(0))
+            } else {
+                ValueSet::Top
+            }
+        });
+    }
+}
+
+impl<'a, A, C> Transfer<'a, A> for Analysis<'a, A, C> where
+    A: ValueSetSemantics,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    C: StackCleanup<A::Address> + ?Sized,
+{
+    type Domain = ValueSet<A::Address>;
+
+    fn engine(&mut self) -> &mut Fixpoint<'a, A, ValueSet<A::Address>> {
+        &mut self.engine
+    }
+
+    fn visit_instruction(&mut self, addr: A::Address, instr: &A::Instruction) {
+        if A::is_call(instr) {
+            self.visit_call(addr, instr);
+            return;
+        }
+
+        let next = addr.wrapping_offset(instr.len()).to_linear() as i64;
+        let escaped = self.escaped.get();
+        let (writes, inputs, pending, narrow) = {
+            let mut evaluation = Evaluation {
+                engine: &self.engine,
+                next,
+                writes: Vec::new(),
+                accesses: &self.recorded,
+                memory: &self.memory,
+                escaped: &self.escaped,
+                inputs: RefCell::new(Vec::new()),
+                pending: Cell::new(false),
+                narrow: Cell::new(false),
+            };
+            A::evaluate_value_sets(addr, instr, &mut evaluation);
+            (evaluation.writes, evaluation.inputs.into_inner(), evaluation.pending.get(), evaluation.narrow.get())
+        };
+        let accesses = self.recorded.replace(Vec::new());
+
+        // setting up a frame pointer doesn't let the stack frame escape, but copying the stack
+        // pointer anywhere else might.
+        let frame = self.engine.cfg.entrypoint;
+        let sets_frame = A::stack_effect(addr, instr, self.cleanup).frame != Adjustment::Add(0);
+        if !sets_frame && writes.iter().any(|(loc, value)| {
+            *loc != A::stack_pointer() &&
+                value.regions().map(|regions| regions.contains_key(&Region::Stack(frame))).unwrap_or(false)
+        }) {
+            self.escaped.set(true);
+        }
+        if self.escaped.get() && !escaped {
+            // calls seen so far kept the frame, and shouldn't have.
+            let blocks: Vec<A::Address> = self.exits.keys().cloned().collect();
+            for block in blocks.into_iter() {
+                self.engine.revisit(block);
+            }
+        }
+
+        if pending {
+            return;
+        }
+        for input in inputs.into_iter() {
+            self.engine.record_input(input);
+        }
+        if !accesses.is_empty() {
+            self.accesses.insert(addr, accesses);
+        }
+
+        let mut results: HashMap<A::Location, ValueSet<A::Address>> = HashMap::new();
+        for (loc, value) in writes.into_iter() {
+            let value = match A::value_width(&loc) {
+                Some(width) if width > 8 => ValueSet::Top,
+                Some(width) => value.truncate(width),
+                // flags were computed from 64-bit operands, which lost their sign if they were
+                // narrower.
+                None if narrow => ValueSet::Top,
+                None => value,
+            };
+            results.insert(loc, value);
+        }
+
+        self.engine.update_writes(addr, |loc| results.get(loc).cloned().unwrap_or(ValueSet::Top));
+    }
+
+    /// reads `Below`, `Above` and their signed counterparts as `compute_intervals` does.
+    /// modifiers compare addresses, so they only bound values that are numbers.
+    fn bound(bounded: &ValueSet<A::Address>, expr: &ModifierExpression) -> ValueSet<A::Address> {
+        let bounds = match expr {
+            ModifierExpression::Below(n) => StridedInterval::new(1, 0, min(*n, i64::MAX as u64) as i64),
+            ModifierExpression::Above(n) if *n <= i64::MAX as u64 => StridedInterval::new(1, *n as i64, i64::MAX),
+            ModifierExpression::SignedBelow(n) => StridedInterval::new(1, i64::MIN, *n),
+            ModifierExpression::SignedAbove(n) => StridedInterval::new(1, *n, i64::MAX),
+            ModifierExpression::Is(n) => StridedInterval::constant(*n as i64),
+            _ => StridedInterval::full(),
+        };
+        bounded.bound_number(&bounds)
+    }
+
+    fn enter_block(&mut self, block: A::Address) {
+        let entry = if block == self.engine.cfg.entrypoint {
+            // the caller could have left anything in memory.
+            Memory::new()
+        } else {
+            let exits = &self.exits;
+            join_memory(self.engine.cfg.sources(block).iter().filter_map(|pred| exits.get(pred)))
+        };
+        self.memory.replace(entry);
+    }
+
+    fn leave_block(&mut self, block: A::Address) {
+        let memory = self.memory.replace(Memory::new());
+        if self.exits.get(&block) != Some(&memory) {
+            self.exits.insert(block, memory);
+            for next in self.engine.cfg.destinations(block) {
+                self.engine.revisit(next);
+            }
+        }
+    }
+}
+
+/// value-set analysis, after Balakrishnan and Reps: find where every value in `ssa` could point,
+/// as strided intervals of offsets into the global region, `cfg`'s stack frame, or memory from
+/// calls to `allocators`, and where every instruction's loads and stores could go. `ssa` should
+/// have been built from `cfg` - with `modifiers`, if there are any - and `cleanup` says how calls
+/// leave the stack.
+///
+/// this iterates like `compute_intervals`, widening at loop headers and narrowing after. memory
+/// is tracked as a-locs, fixed offsets into the stack frame or the global region, so a pointer
+/// spilled to the stack and reloaded is still known. a store that could be to more than one
+/// place forgets every a-loc it might overwrite, and calls forget globals and what's below the
+/// stack pointer. what this finds can be handed to `MemoryLayout` to tell apart accesses it
+/// can't find a common base for.
+pub fn compute_value_sets<A, M, C>(
+    data: &M,
+    cfg: &ControlFlowGraph<A::Address>,
+    ssa: &SSA<A>,
+    modifiers: Option<&InstructionModifiers<A>>,
+    cleanup: &C,
+    allocators: &BTreeSet<A::Address>,
+) -> ValueSets<A> where
+    A: ValueSetSemantics + DecodeFrom<M>,
+    A::Address: petgraph::graphmap::NodeTrait,
+    A::Location: AbiDefaults,
+    M: MemoryRange<A>,
+    C: StackCleanup<A::Address> + ?Sized,
+{
+    let mut analysis = Analysis {
+        engine: Fixpoint::new(cfg, ssa, modifiers, entry_value::<A>),
+        cleanup,
+        allocators,
+        accesses: BTreeMap::new(),
+        recorded: RefCell::new(Vec::new()),
+        memory: RefCell::new(Memory::new()),
+        exits: HashMap::new(),
+        escaped: Cell::new(false),
+    };
+    fixpoint::solve(&mut analysis, data);
+
+    let (values, executable) = analysis.engine.into_results();
+    ValueSets {
+        values,
+        accesses: analysis.accesses,
+        executable,
+    }
+}
+
+#[test]
+fn test_strided_intervals() {
+    let scaled = StridedInterval::new(1, 0, 9).scale(8);
+    assert_eq!(scaled, StridedInterval::Range { stride: 8, lo: 0, hi: 0x48 });
+    assert!(scaled.contains(0x10));
+    assert!(!scaled.contains(0x14));
+    assert_eq!(StridedInterval::new(8, 0, 0x4f), scaled);
+
+    // joining keeps whatever spacing both sides agree on.
+    assert_eq!(StridedInterval::constant(4).join(&StridedInterval::constant(12)), StridedInterval::new(8, 4, 12));
+    assert_eq!(scaled.join(&StridedInterval::constant(4)), StridedInterval::new(4, 0, 0x48));
+    assert_eq!(scaled.add(&StridedInterval::constant(-0x60)), StridedInterval::new(8, -0x60, -0x18));
+    assert_eq!(scaled.meet(&StridedInterval::new(1, 3, 20)), StridedInterval::new(8, 8, 16));
+    assert_eq!(scaled.meet(&StridedInterval::new(8, 4, 20)), StridedInterval::Empty);
+    assert_eq!(StridedInterval::new(16, 0, 0x40).sar(2), StridedInterval::new(4, 0, 0x10));
+    assert_eq!(StridedInterval::constant(i64::MAX).add(&StridedInterval::constant(1)), StridedInterval::full());
+
+    let grown = StridedInterval::new(8, 0, 0x10);
+    let widened = grown.widen(&StridedInterval::new(8, 0, 0x18));
+    assert_eq!(widened.bounds(), Some((0, i64::MAX - 7)));
+    assert_eq!(widened.stride(), Some(8));
+    assert_eq!(widened.narrow(&StridedInterval::new(8, 0, 0x48)), scaled);
+
+    // neighboring fields in an array of 16-byte structs never overlap..
+    let first = StridedInterval::new(16, 0, 0x40);
+    let second = StridedInterval::new(16, 8, 0x48);
+    assert!(!first.may_overlap(8, &second, 8));
+    assert!(!second.may_overlap(8, &first, 8));
+    // .. unless the access is wide enough to reach the next one.
+    assert!(first.may_overlap(16, &second, 8));
+    assert!(first.may_overlap(8, &StridedInterval::new(16, 4, 0x44), 8));
+    assert!(!first.may_overlap(8, &StridedInterval::constant(0x50), 8));
+
+    let stack: ValueSet<u64> = ValueSet::in_region(Region::Stack(0), StridedInterval::constant(-0x60));
+    let index = ValueSet::number(StridedInterval::new(1, 0, 4));
+    let element = stack.add(&index.shl(&ValueSet::from_const(4))).value();
+    assert_eq!(element.offsets(&Region::Stack(0)), Some(StridedInterval::new(16, -0x60, -0x20)));
+    assert_eq!(element.sub(&stack).value().to_const(), None);
+    assert_eq!(element.sub(&stack).value(), ValueSet::number(StridedInterval::new(16, 0, 0x40)));
+    // aligning the stack pointer keeps it in the stack frame.
+    assert_eq!(stack.and(&ValueSet::from_const(-16)).value().offsets(&Region::Stack(0)), Some(StridedInterval::new(1, -0x6f, -0x60)));
+    assert!(stack.mul(&index).value().is_top());
+    assert!(!stack.may_overlap(8, &ValueSet::from_const(-0x60), 8));
+}
+
+#[test]
+fn test_value_set_analysis() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::memory_layout::MemoryLayout;
+    use analyses::stack_pointer::NoCleanup;
+    use arch::InstructionSpan;
+    use arch::x86_64::semantic;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{ANY, ContextualDisambiguation, Location, NoDisambiguation};
+    use data::{Disambiguator, LocationAliasDescriptions};
+    use yaxpeax_arch::{Arch, Decoder, U8Reader};
+    use yaxpeax_x86::x86_64;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x83, 0xec, 0x68,                     // 0x00: sub rsp, 0x68
+        0x48, 0x31, 0xc9,                           // 0x04: xor rcx, rcx
+        0xeb, 0x14,                                 // 0x07: jmp 0x1d
+        0x48, 0x89, 0xca,                           // 0x09: mov rdx, rcx
+        0x48, 0xc1, 0xe2, 0x04,                     // 0x0c: shl rdx, 4
+        0x48, 0x89, 0x04, 0x14,                     // 0x10: mov [rsp + rdx], rax
+        0x48, 0x8b, 0x74, 0x14, 0x08,               // 0x14: mov rsi, [rsp + rdx + 8]
+        0x48, 0x83, 0xc1, 0x01,                     // 0x19: add rcx, 1
+        0x48, 0x83, 0xf9, 0x05,                     // 0x1d: cmp rcx, 5
+        0x7c, 0xe6,                                 // 0x21: jl 0x09
+        0x8b, 0x04, 0x25, 0x40, 0x10, 0x60, 0x00,   // 0x23: mov eax, [0x601040]
+        0x48, 0x83, 0xc4, 0x68,                     // 0x2a: add rsp, 0x68
+        0xc3,                                       // 0x2e: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    modifiers.add_edge_modifier(0x1d, 0x09, Some(Location::rcx()), ModifierExpression::SignedBelow(4));
+    modifiers.add_edge_modifier(0x1d, 0x23, Some(Location::rcx()), ModifierExpression::SignedAbove(5));
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+    let value_sets = compute_value_sets(&data, &cfg, &dfg, Some(&modifiers), &NoCleanup, &BTreeSet::new());
+
+    let frame = Region::Stack(0);
+    let access = |addr: u64| value_sets.accesses[&addr][0].clone();
+    // each element is 16 bytes, the first field written and the second read.
+    let store = access(0x10);
+    assert_eq!(store.direction, Direction::Write);
+    assert_eq!(store.size, 8);
+    assert_eq!(store.address, ValueSet::in_region(frame, StridedInterval::new(16, -0x68, -0x28)));
+    let load = access(0x14);
+    assert_eq!(load.direction, Direction::Read);
+    assert_eq!(load.address, ValueSet::in_region(frame, StridedInterval::new(16, -0x60, -0x20)));
+    assert!(!store.address.may_overlap(8, &load.address, 8));
+    assert_eq!(access(0x23).address, ValueSet::from_const(0x601040));
+    assert_eq!(access(0x23).size, 4);
+    assert_eq!(
+        value_sets.value(&dfg.get_def(0x2a, Location::rsp()).as_rc()),
+        Some(ValueSet::in_region(frame, StridedInterval::constant(0))),
+    );
+
+    // `MemoryLayout` finds no value as a base for the second field or the global, but with value
+    // sets those accesses still get locations, which don't alias each other or the first field.
+    let mut layout = MemoryLayout::new(&dfg).with_value_sets(&value_sets);
+    for block in cfg.blocks() {
+        let block = cfg.get_block(block);
+        let mut iter = x86_64::instructions_spanning(&data, block.start, block.end);
+        while let Some((address, instr)) = iter.next() {
+            semantic::evaluate(address, instr, &mut layout);
+        }
+    }
+    let disambiguation = ContextualDisambiguation {
+        dfg: &dfg,
+        memory_layout: Some(&layout),
+    };
+    let location = |addr: u64, bytes: &[u8], operand: u8, direction: Direction| {
+        let instr = <x86_64 as Arch>::Decoder::default().decode(&mut U8Reader::new(bytes)).unwrap();
+        disambiguation.disambiguate(&instr, (Some(Location::Memory(ANY)), direction), (addr, operand, 0))
+            .expect("access has a location")
+    };
+    let first = location(0x10, &data[0x10..0x14], 1, Direction::Write);
+    let second = location(0x14, &data[0x14..0x19], 2, Direction::Read);
+    let global = location(0x23, &data[0x23..0x2a], 2, Direction::Read);
+    assert!(!disambiguation.may_alias(&first, &second));
+    assert!(!disambiguation.may_alias(&second, &global));
+    assert!(disambiguation.may_alias(&second, &second));
+}
+
+#[test]
+fn test_value_set_spills() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::stack_pointer::NoCleanup;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::{Location, NoDisambiguation};
+    use yaxpeax_x86::long_mode::RegSpec;
+
+    let mut data: Vec<u8> = vec![
+        0x53,                                       // 0x00: push rbx
+        0x48, 0x83, 0xec, 0x10,                     // 0x01: sub rsp, 0x10
+        0x48, 0xc7, 0xc0, 0x00, 0x10, 0x60, 0x00,   // 0x05: mov rax, 0x601000
+        0x48, 0x89, 0x44, 0x24, 0x08,               // 0x0c: mov [rsp + 8], rax
+        0x48, 0x89, 0x04, 0x25, 0x40, 0x10, 0x60, 0x00, // 0x11: mov [0x601040], rax
+        0xe8, 0x32, 0x00, 0x00, 0x00,               // 0x19: call 0x50
+        0x48, 0x8b, 0x4c, 0x24, 0x08,               // 0x1e: mov rcx, [rsp + 8]
+        0x48, 0x8b, 0x34, 0x25, 0x40, 0x10, 0x60, 0x00, // 0x23: mov rsi, [0x601040]
+        0x48, 0x89, 0x51, 0x08,                     // 0x2b: mov [rcx + 8], rdx
+        0x48, 0x89, 0x3a,                           // 0x2f: mov [rdx], rdi
+        0x4c, 0x8b, 0x44, 0x24, 0x08,               // 0x32: mov r8, [rsp + 8]
+        0x48, 0x83, 0xc4, 0x10,                     // 0x37: add rsp, 0x10
+        0x5b,                                       // 0x3b: pop rbx
+        0xc3,                                       // 0x3c: ret
+    ];
+    data.resize(0x50, 0xcc);
+    data.push(0xc3);                                // 0x50: ret
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+    let value_sets = compute_value_sets(&data, &cfg, &dfg, None, &NoCleanup, &BTreeSet::new());
+    let def = |addr: u64, loc: Location| value_sets.value(&dfg.get_def(addr, loc).as_rc());
+
+    // the pointer is spilled to the stack across a call, which can't reach the slot it's in..
+    assert_eq!(def(0x1e, Location::rcx()), Some(ValueSet::from_const(0x601000)));
+    assert_eq!(value_sets.accesses[&0x2b][0].address, ValueSet::from_const(0x601008));
+    // .. but could have written the global it was also kept in.
+    assert_eq!(def(0x23, Location::Register(RegSpec::rsi())), Some(ValueSet::Top));
+    // and a store through a pointer that could be anything could have overwritten the slot.
+    assert_eq!(def(0x32, Location::Register(RegSpec::r8())), Some(ValueSet::Top));
+}
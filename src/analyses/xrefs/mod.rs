@@ -73,6 +73,19 @@ impl <A: Address + NodeTrait> XRefCollection<A> {
         );
     }
 
+    /// delete every reference from code in `[start, end]`, like when that code is found to not be
+    /// code after all.
+    pub fn delete_all_from_code_in(&mut self, start: A, end: A) {
+        let sources: Vec<(A, RefType, RefAction)> = self.xrefs.nodes()
+            .filter(|(addr, tpe, action)| {
+                *tpe == RefType::Code && *action == RefAction::Referrer && start <= *addr && *addr <= end
+            })
+            .collect();
+        for source in sources.into_iter() {
+            self.xrefs.remove_node(source);
+        }
+    }
+
     pub fn code_references_to(&self, tpe: RefType, action: RefAction, to: A) -> Vec<A> {
         let mut result = Vec::new();
 
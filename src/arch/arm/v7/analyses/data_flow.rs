@@ -1,19 +1,21 @@
 use arch::{FunctionImpl, FunctionQuery};
 use arch::{AbiDefaults, FunctionAbiReference};
-use analyses::static_single_assignment::{DFGRef, SSAValues, Value};
+use analyses::static_single_assignment::{DFGRef, SSA, SSAValues, Value, ValueGraph};
+use analyses::{CompletionStatus, DFG};
+use analyses::constant_propagation::{Constant, ConstantSemantics};
+use analyses::stack_pointer::{Adjustment, StackCleanup, StackEffect, StackSemantics};
+use analyses::taint::{Extent, TaintSemantics};
+use analyses::value_range::interval::{Interval, IntervalSemantics};
 
-use std::rc::Rc;
 use std::fmt;
-use std::cell::RefCell;
 
 use std::collections::HashMap;
-use analyses::static_single_assignment::HashedValue;
 use serialize::Memoable;
 use data::{Direction, Disambiguator, ValueLocations};
 use data::types::{TypeAtlas, TypeSpec, Typed};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde::de::{self, Visitor, Unexpected};
-use yaxpeax_arm::armv7::{ARMv7, Operand};
+use yaxpeax_arm::armv7::{ARMv7, Instruction, Opcode, Operand};
 use analyses::data_flow::Use;
 use std::hash::{Hasher, Hash};
 
@@ -157,7 +159,7 @@ impl AliasInfo for Location {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Data {
     Concrete(u32),
-    Alias(Rc<RefCell<Value<ARMv7>>>),
+    Alias(DFGRef<ARMv7>),
 }
 
 use crate::ColorSettings;
@@ -177,7 +179,7 @@ impl Hash for Data {
             }
             Data::Alias(ref value) => {
                 state.write_u8(2);
-                value.borrow().hash(state);
+                value.hash(state);
             }
         }
     }
@@ -202,18 +204,19 @@ impl Typed for Data {
     }
 }
 
-impl Memoable for HashedValue<Rc<RefCell<Value<ARMv7>>>> {
+impl Memoable for DFGRef<ARMv7> {
     type Out = ValueMemo;
+    type Context = ValueGraph<ARMv7>;
 
-    fn memoize(&self, memos: &HashMap<Self, u32>) -> Self::Out {
-        fn memoize_data(data: &Data, memos: &HashMap<HashedValue<DFGRef<ARMv7>>, u32>) -> DataMemo {
+    fn memoize(&self, values: &ValueGraph<ARMv7>, memos: &HashMap<Self, u32>) -> Self::Out {
+        fn memoize_data(data: &Data, memos: &HashMap<DFGRef<ARMv7>, u32>) -> DataMemo {
             match data {
                 Data::Concrete(v) => DataMemo::Concrete(*v),
-                Data::Alias(ptr) => DataMemo::Alias(memos[&HashedValue { value: Rc::clone(ptr) }])
+                Data::Alias(ptr) => DataMemo::Alias(memos[ptr])
             }
         }
 
-        let selfref: &Value<ARMv7> = &*(&*self.value.borrow());
+        let selfref: &Value<ARMv7> = values.value(*self);
         let newdata = selfref.data.as_ref().map(|data| memoize_data(data, memos));
 
         ValueMemo {
@@ -223,7 +226,7 @@ impl Memoable for HashedValue<Rc<RefCell<Value<ARMv7>>>> {
         }
     }
 
-    fn dememoize(_idx: u32, _memos: &[Self::Out], _dememoized: &mut HashMap<u32, Self>) -> Self {
+    fn dememoize(_idx: u32, _memos: &[Self::Out], _dememoized: &mut HashMap<u32, Self>, _values: &mut ValueGraph<ARMv7>) -> Self {
         unimplemented!("data_flow::Memoable::dememoize");
     }
 }
@@ -232,6 +235,172 @@ impl SSAValues for ARMv7 {
     type Data = Data;
 }
 
+impl ConstantSemantics for ARMv7 {
+    fn program_counter() -> Location {
+        Location::pc()
+    }
+
+    fn value_width(loc: &Location) -> Option<usize> {
+        match loc {
+            Location::Register(_) => Some(4),
+            _ => None,
+        }
+    }
+
+    fn evaluate<D: DFG<Constant, ARMv7, u32>>(addr: u32, instr: &<ARMv7 as yaxpeax_arch::Arch>::Instruction, dfg: &mut D) -> CompletionStatus {
+        crate::arch::arm::v7::semantic::evaluate(addr, instr, dfg)
+    }
+
+    fn constant_data(_loc: &Location, value: i64) -> Data {
+        Data::Concrete(value as u32)
+    }
+}
+
+impl IntervalSemantics for ARMv7 {
+    fn evaluate_intervals<D: DFG<Interval, ARMv7, u32>>(addr: u32, instr: &<ARMv7 as yaxpeax_arch::Arch>::Instruction, dfg: &mut D) -> CompletionStatus {
+        crate::arch::arm::v7::semantic::evaluate(addr, instr, dfg)
+    }
+}
+
+// there's no `MemoryLayout` for ARMv7 yet, so taint only follows registers.
+impl TaintSemantics for ARMv7 {
+    type Region = ();
+
+    fn pointer_region(_ssa: &SSA<ARMv7>, _pointer: &DFGRef<ARMv7>) -> Option<Extent<()>> {
+        None
+    }
+
+    fn location_region(_ssa: &SSA<ARMv7>, _loc: &Location) -> Option<Extent<()>> {
+        None
+    }
+
+    fn access_region(_ssa: &SSA<ARMv7>, _address: &Data) -> Option<Extent<()>> {
+        None
+    }
+
+    fn carries_data(loc: &Location) -> bool {
+        // memory is all one location here, so following it would taint every later load.
+        *loc != Location::Memory && *loc != Location::sp()
+    }
+}
+
+impl StackSemantics for ARMv7 {
+    fn stack_pointer() -> Location {
+        Location::sp()
+    }
+
+    // AAPCS callees don't pop their caller's arguments, so calls are left alone.
+    fn stack_effect<C: StackCleanup<u32> + ?Sized>(_addr: u32, instr: &Instruction, _cleanup: &C) -> StackEffect {
+        const SP: u8 = 13;
+        // thumb code keeps its frame pointer in r7, arm code in r11.
+        let fp: u8 = if instr.thumb() { 7 } else { 11 };
+        let is_reg = |op: &Operand, num: u8| if let Operand::Reg(reg) = op { reg.number() == num } else { false };
+        let immediate = |op: &Operand| match op {
+            Operand::Imm12(imm) => Some(*imm as i64),
+            Operand::Imm32(imm) => Some(*imm as i64),
+            _ => None,
+        };
+        let list_effect = |list: u16, stack: Adjustment| {
+            let mut effect = StackEffect::none().with_stack(stack);
+            if list & (1 << SP) != 0 {
+                effect.stack = Adjustment::Unknown;
+            }
+            if list & (1 << fp) != 0 {
+                effect.frame = Adjustment::Unknown;
+            }
+            effect
+        };
+
+        match (instr.opcode, &instr.operands) {
+            (Opcode::PUSH, [Operand::RegList(list), ..]) => {
+                return StackEffect::adjust(-4 * list.count_ones() as i64);
+            }
+            (Opcode::POP, [Operand::RegList(list), ..]) => {
+                return list_effect(*list, Adjustment::Add(4 * list.count_ones() as i64));
+            }
+            (Opcode::STM(add, _, _, _), [Operand::RegWBack(base, true), Operand::RegList(list), ..]) if base.number() == SP => {
+                let size = 4 * list.count_ones() as i64;
+                return StackEffect::adjust(if add { size } else { -size });
+            }
+            (Opcode::LDM(add, _, _, _), [Operand::RegWBack(base, wback), Operand::RegList(list), ..]) => {
+                let size = 4 * list.count_ones() as i64;
+                let stack = if base.number() == SP && *wback {
+                    Adjustment::Add(if add { size } else { -size })
+                } else {
+                    Adjustment::Add(0)
+                };
+                return list_effect(*list, stack);
+            }
+            (Opcode::CMP, _) | (Opcode::CMN, _) | (Opcode::TST, _) | (Opcode::TEQ, _) => {
+                return StackEffect::none();
+            }
+            _ => {}
+        }
+
+        // loads and stores that write back to the stack pointer, like `str r4, [sp, #-4]!`.
+        let mut stack = Adjustment::Add(0);
+        for op in instr.operands.iter() {
+            match op {
+                Operand::RegDerefPostindexOffset(base, offset, add, _) |
+                Operand::RegDerefPreindexOffset(base, offset, add, true) if base.number() == SP => {
+                    let offset = *offset as i64;
+                    stack = Adjustment::Add(if *add { offset } else { -offset });
+                }
+                Operand::RegDerefPostindexReg(base, _, _, _) |
+                Operand::RegDerefPreindexReg(base, _, _, true) |
+                Operand::RegDerefPostindexRegShift(base, _, _, _) |
+                Operand::RegDerefPreindexRegShift(base, _, _, true) if base.number() == SP => {
+                    stack = Adjustment::Unknown;
+                }
+                _ => {}
+            }
+        }
+        let writes_dest = match instr.opcode {
+            Opcode::STR | Opcode::STRB | Opcode::STRH | Opcode::STRD | Opcode::STRT |
+            Opcode::STRBT | Opcode::STRHT | Opcode::B | Opcode::BL | Opcode::BX | Opcode::BLX => false,
+            _ => true,
+        };
+        if !writes_dest {
+            return StackEffect::none().with_stack(stack);
+        }
+
+        // `add sp, sp, #8`, `sub sp, r11, #4`, and the two-operand thumb `add sp, #8`.
+        let offset = match (&instr.operands[1], &instr.operands[2], &instr.operands[3]) {
+            (Operand::Reg(base), imm, Operand::Nothing) => immediate(imm).and_then(|imm| {
+                if base.number() == SP {
+                    Some((Adjustment::FromStack(0), imm))
+                } else if base.number() == fp {
+                    Some((Adjustment::FromFrame(0), imm))
+                } else {
+                    None
+                }
+            }),
+            (imm, Operand::Nothing, Operand::Nothing) => immediate(imm).map(|imm| (Adjustment::Add(0), imm)),
+            _ => None,
+        };
+        let with_offset = |relative: Adjustment, offset: i64| match relative {
+            Adjustment::FromStack(_) => Adjustment::FromStack(offset),
+            Adjustment::FromFrame(_) => Adjustment::FromFrame(offset),
+            _ => Adjustment::Add(offset),
+        };
+        let adjustment = match (instr.opcode, &instr.operands[1], &instr.operands[2]) {
+            (Opcode::MOV, op, Operand::Nothing) if is_reg(op, SP) => Some(Adjustment::FromStack(0)),
+            (Opcode::MOV, op, Operand::Nothing) if is_reg(op, fp) => Some(Adjustment::FromFrame(0)),
+            (Opcode::ADD, _, _) => offset.map(|(relative, imm)| with_offset(relative, imm)),
+            (Opcode::SUB, _, _) => offset.map(|(relative, imm)| with_offset(relative, -imm)),
+            _ => None,
+        };
+
+        if is_reg(&instr.operands[0], SP) {
+            StackEffect::none().with_stack(adjustment.unwrap_or(Adjustment::Unknown))
+        } else if is_reg(&instr.operands[0], fp) {
+            StackEffect::none().with_stack(stack).with_frame(adjustment.unwrap_or(Adjustment::Unknown))
+        } else {
+            StackEffect::none().with_stack(stack)
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct NoDisambiguation {}
 impl Disambiguator<yaxpeax_arm::armv7::ARMv7, (u8, u8)> for NoDisambiguation {
@@ -9,7 +9,7 @@ use arch::SymbolQuery;
 use std::fmt;
 
 use analyses::data_flow::Use;
-use analyses::static_single_assignment::{DFGRef, SSA};
+use analyses::static_single_assignment::{SSA, Value};
 use analyses::control_flow::ControlFlowGraph;
 use arch::AddressNamer;
 use arch::CommentQuery;
@@ -25,7 +25,6 @@ use std::marker::PhantomData;
 
 use termion::color;
 
-use std::rc::Rc;
 
 use tracing::{event, Level};
 
@@ -96,7 +95,7 @@ impl <'a, 'b, 'c, 'd, 'e, Context: AddressNamer<<ARMv7 as Arch>::Address>, Highl
     ) -> impl fmt::Display {
         let text = self.ssa.map(|ssa| {
             let num = ssa.get_value(self.addr, Location::Register(reg), direction)
-                .map(|data| data.borrow().version());
+                .map(|value| ssa.value(value).version());
             format!("{}_{}",
                 reg,
                 num.map(|n| n.map(|v| v.to_string()).unwrap_or("input".to_string())).unwrap_or_else(|| {
@@ -111,7 +110,7 @@ impl <'a, 'b, 'c, 'd, 'e, Context: AddressNamer<<ARMv7 as Arch>::Address>, Highl
 
 pub struct RegValueDisplay<'a, 'b, 'c, Y: YaxColors> {
     pub reg: &'a u8,
-    pub value: &'b Option<DFGRef<ARMv7>>,
+    pub value: &'b Option<&'b Value<ARMv7>>,
     pub colors: &'c Y,
 }
 
@@ -126,13 +125,13 @@ impl <'a, 'b, 'c, Y: YaxColors> fmt::Display for RegValueDisplay<'a, 'b, 'c, Y>
                         format!(
                             "r{}_{}",
                             self.reg,
-                            value.borrow().version()
+                            value.version()
                                 .map(|ver| ver.to_string())
                                 .unwrap_or_else(|| "input".to_string())
                         )
                     )
                 )?;
-                if let Some(data) = value.borrow().data.as_ref() {
+                if let Some(data) = value.data.as_ref() {
                     write!(fmt, " (= {})", DataDisplay { data: &data, colors: self.colors })?;
                 }
                 Ok(())
@@ -175,15 +174,8 @@ impl <'a, 'b, Y: YaxColors> fmt::Display for DataDisplay<'a, 'b, Y> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self.data {
             Data::Alias(alias) => {
-                if let Location::Register(alias_reg) = alias.borrow().location {
-                    write!(fmt, "{}", RegValueDisplay {
-                        reg: &alias_reg,
-                        value: &Some(Rc::clone(alias)),
-                        colors: self.colors,
-                    })?;
-                } else {
-                    unreachable!("Register alias must be another register");
-                }
+                // what `alias` refers to is in an `SSA` this doesn't have, so name it by reference.
+                write!(fmt, "{}", alias)?;
             },
             Data::Concrete(v) => {
                 write!(fmt, "{:#x}", v)?;
@@ -761,5 +753,6 @@ pub fn show_function<'a, 'b, 'c, 'd, 'e, M: MemoryRepr<ARMv7> + MemoryRange<ARMv
         colors,
         highlight_instrs: Vec::new(),
         highlight_locs: Vec::new(),
+        dimmed_instrs: Vec::new(),
     }
 }
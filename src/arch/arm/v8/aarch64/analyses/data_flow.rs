@@ -1,20 +1,22 @@
 use yaxpeax_arm::armv8::a64::ARMv8;
 use analyses::static_single_assignment::DFGRef;
-use analyses::static_single_assignment::SSAValues;
-use analyses::static_single_assignment::HashedValue;
+use analyses::static_single_assignment::{SSA, SSAValues};
 use std::collections::HashMap;
-use std::cell::RefCell;
-use std::rc::Rc;
 use std::hash::{Hash, Hasher};
 use arch::FunctionAbiReference;
 use arch::AbiDefaults;
-use analyses::static_single_assignment::Value;
+use analyses::static_single_assignment::{Value, ValueGraph};
+use analyses::{CompletionStatus, DFG};
+use analyses::constant_propagation::{Constant, ConstantSemantics};
+use analyses::stack_pointer::{Adjustment, StackCleanup, StackEffect, StackSemantics};
+use analyses::taint::{Extent, TaintSemantics};
+use analyses::value_range::interval::{Interval, IntervalSemantics};
 use data::ValueLocations;
 use data::Direction;
 use data::types::{Typed, TypeAtlas, TypeSpec};
 use serialize::Memoable;
 use analyses::data_flow::Use;
-use yaxpeax_arm::armv8::a64::Operand;
+use yaxpeax_arm::armv8::a64::{Instruction, Opcode, Operand};
 use data::Disambiguator;
 use arch::FunctionQuery;
 use arch::FunctionImpl;
@@ -313,6 +315,161 @@ impl SSAValues for ARMv8 {
     type Data = Data;
 }
 
+impl ConstantSemantics for ARMv8 {
+    fn program_counter() -> Location {
+        Location::PC
+    }
+
+    fn value_width(loc: &Location) -> Option<usize> {
+        match loc {
+            Location::Register(_) | Location::PC | Location::SP => Some(8),
+            _ => None,
+        }
+    }
+
+    fn evaluate<D: DFG<Constant, ARMv8, u64>>(addr: u64, instr: &<ARMv8 as yaxpeax_arch::Arch>::Instruction, dfg: &mut D) -> CompletionStatus {
+        // conditional branches aren't described yet, and `semantic::evaluate` panics on them.
+        // without a write to `pc`, every successor is possible.
+        if let yaxpeax_arm::armv8::a64::Opcode::Bcc(_) = instr.opcode {
+            return CompletionStatus::Incomplete;
+        }
+        crate::arch::arm::v8::aarch64::semantic::evaluate(addr, instr, dfg)
+    }
+
+    fn constant_data(_loc: &Location, value: i64) -> Data {
+        Data::Concrete(value as u64)
+    }
+}
+
+impl IntervalSemantics for ARMv8 {
+    fn evaluate_intervals<D: DFG<Interval, ARMv8, u64>>(addr: u64, instr: &<ARMv8 as yaxpeax_arch::Arch>::Instruction, dfg: &mut D) -> CompletionStatus {
+        // `semantic::evaluate` panics on conditional branches, as it does for constants.
+        if let yaxpeax_arm::armv8::a64::Opcode::Bcc(_) = instr.opcode {
+            return CompletionStatus::Incomplete;
+        }
+        crate::arch::arm::v8::aarch64::semantic::evaluate(addr, instr, dfg)
+    }
+}
+
+// there's no `MemoryLayout` for ARMv8 yet, so taint only follows registers.
+impl TaintSemantics for ARMv8 {
+    type Region = ();
+
+    fn pointer_region(_ssa: &SSA<ARMv8>, _pointer: &DFGRef<ARMv8>) -> Option<Extent<()>> {
+        None
+    }
+
+    fn location_region(_ssa: &SSA<ARMv8>, _loc: &Location) -> Option<Extent<()>> {
+        None
+    }
+
+    fn access_region(_ssa: &SSA<ARMv8>, _address: &Data) -> Option<Extent<()>> {
+        None
+    }
+
+    fn carries_data(loc: &Location) -> bool {
+        // memory is all one location here, so following it would taint every later load.
+        *loc != Location::Memory && *loc != Location::SP
+    }
+}
+
+impl StackSemantics for ARMv8 {
+    fn stack_pointer() -> Location {
+        Location::SP
+    }
+
+    fn stack_effect<C: StackCleanup<u64> + ?Sized>(addr: u64, instr: &Instruction, cleanup: &C) -> StackEffect {
+        // register 31 is `sp` where `RegisterOrSP` says so and as a base address, and `xzr`
+        // everywhere else. the frame pointer is `x29`.
+        fn is_stack(op: &Operand) -> bool {
+            if let Operand::RegisterOrSP(_, 31) = op { true } else { false }
+        }
+        fn is_frame(op: &Operand) -> bool {
+            match op {
+                Operand::Register(_, 29) | Operand::RegisterOrSP(_, 29) => true,
+                _ => false,
+            }
+        }
+        fn immediate(op: &Operand) -> Option<i64> {
+            match op {
+                Operand::Immediate(imm) => Some(*imm as i64),
+                Operand::ImmShift(imm, shift) => Some((*imm as i64) << shift),
+                _ => None,
+            }
+        }
+
+        match instr.opcode {
+            Opcode::BL => {
+                let callee_cleanup = match instr.operands[0] {
+                    Operand::Offset(offset) => {
+                        // a 26-bit word offset, sign-extended.
+                        let offset = ((offset << 4) as i32 >> 4) as i64;
+                        cleanup.stack_cleanup(addr.wrapping_add(offset as u64))
+                    }
+                    _ => 0,
+                };
+                return StackEffect::adjust(callee_cleanup as i64);
+            }
+            Opcode::BLR => {
+                return StackEffect::none();
+            }
+            _ => {}
+        }
+
+        // `stp x29, x30, [sp, #-0x10]!`, `ldr x19, [sp], #0x10`, and so on.
+        let mut effect = StackEffect::none();
+        for op in instr.operands.iter() {
+            match op {
+                Operand::RegPreIndex(31, offset) |
+                Operand::RegPostIndex(31, offset) => {
+                    effect.stack = Adjustment::Add(*offset as i64);
+                }
+                _ => {}
+            }
+        }
+
+        let (writes_first, writes_second) = match instr.opcode {
+            Opcode::STLR | Opcode::STLRB | Opcode::STLRH | Opcode::STLXP | Opcode::STLXR |
+            Opcode::STLXRB | Opcode::STLXRH | Opcode::STP | Opcode::STR | Opcode::STTR |
+            Opcode::STTRB | Opcode::STTRH | Opcode::STRB | Opcode::STRH | Opcode::STRW |
+            Opcode::STUR | Opcode::STURB | Opcode::STURH | Opcode::STXP | Opcode::STXR |
+            Opcode::STXRB | Opcode::STXRH | Opcode::TBZ | Opcode::TBNZ | Opcode::CBZ |
+            Opcode::CBNZ | Opcode::B | Opcode::BR | Opcode::Bcc(_) | Opcode::RET => (false, false),
+            Opcode::LDP | Opcode::LDPSW | Opcode::LDXP | Opcode::LDAXP => (true, true),
+            _ => (true, false),
+        };
+        if writes_second && is_frame(&instr.operands[1]) {
+            effect.frame = Adjustment::Unknown;
+        }
+        if !writes_first {
+            return effect;
+        }
+
+        // `add x29, sp, #0x10`, `sub sp, sp, #0x20`, `mov sp, x29`.
+        let offset = match (instr.opcode, immediate(&instr.operands[2])) {
+            (Opcode::ADD, Some(imm)) => Some(imm),
+            (Opcode::SUB, Some(imm)) => Some(-imm),
+            _ => None,
+        };
+        let adjustment = offset.and_then(|offset| {
+            if is_stack(&instr.operands[1]) {
+                Some(Adjustment::FromStack(offset))
+            } else if is_frame(&instr.operands[1]) {
+                Some(Adjustment::FromFrame(offset))
+            } else {
+                None
+            }
+        });
+
+        if is_stack(&instr.operands[0]) {
+            effect.stack = adjustment.unwrap_or(Adjustment::Unknown);
+        } else if is_frame(&instr.operands[0]) {
+            effect.frame = adjustment.unwrap_or(Adjustment::Unknown);
+        }
+        effect
+    }
+}
+
 #[derive(Default)]
 pub struct NoDisambiguation {}
 impl Disambiguator<ARMv8, (u8, u8)> for NoDisambiguation {
@@ -345,7 +502,7 @@ impl AliasInfo for Location {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Data {
     Concrete(u64),
-    Alias(Rc<RefCell<Value<ARMv8>>>),
+    Alias(DFGRef<ARMv8>),
 }
 
 use crate::ColorSettings;
@@ -365,7 +522,7 @@ impl Hash for Data {
             }
             Data::Alias(value) => {
                 state.write_u8(2);
-                value.borrow().hash(state);
+                value.hash(state);
             }
         }
     }
@@ -398,18 +555,19 @@ impl Typed for Data {
     }
 }
 
-impl Memoable for HashedValue<Rc<RefCell<Value<ARMv8>>>> {
+impl Memoable for DFGRef<ARMv8> {
     type Out = ValueMemo;
+    type Context = ValueGraph<ARMv8>;
 
-    fn memoize(&self, memos: &HashMap<Self, u32>) -> Self::Out {
-        fn memoize_data(data: &Data, memos: &HashMap<HashedValue<DFGRef<ARMv8>>, u32>) -> DataMemo {
+    fn memoize(&self, values: &ValueGraph<ARMv8>, memos: &HashMap<Self, u32>) -> Self::Out {
+        fn memoize_data(data: &Data, memos: &HashMap<DFGRef<ARMv8>, u32>) -> DataMemo {
             match data {
                 Data::Concrete(v) => DataMemo::Concrete(*v),
-                Data::Alias(ptr) => DataMemo::Alias(memos[&HashedValue { value: Rc::clone(ptr) }])
+                Data::Alias(ptr) => DataMemo::Alias(memos[ptr])
             }
         }
 
-        let selfref: &Value<ARMv8> = &*(&*self.value.borrow());
+        let selfref: &Value<ARMv8> = values.value(*self);
         let newdata = selfref.data.as_ref().map(|data| memoize_data(data, memos));
 
         ValueMemo {
@@ -419,7 +577,7 @@ impl Memoable for HashedValue<Rc<RefCell<Value<ARMv8>>>> {
         }
     }
 
-    fn dememoize(_idx: u32, _memos: &[Self::Out], _dememoized: &mut HashMap<u32, Self>) -> Self {
+    fn dememoize(_idx: u32, _memos: &[Self::Out], _dememoized: &mut HashMap<u32, Self>, _values: &mut ValueGraph<ARMv8>) -> Self {
         unimplemented!("data_flow::Memoable::dememoize");
     }
 }
@@ -175,5 +175,6 @@ pub fn show_function<'a, 'b, 'c, 'd, 'e, M: MemoryRepr<ARMv8> + MemoryRange<ARMv
         colors,
         highlight_instrs: Vec::new(),
         highlight_locs: Vec::new(),
+        dimmed_instrs: Vec::new(),
     }
 }
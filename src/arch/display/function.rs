@@ -45,6 +45,8 @@ pub struct FunctionView<
     pub colors: Option<&'e ColorSettings>,
     pub highlight_instrs: Vec<A::Address>,
     pub highlight_locs: Vec<(A::Address, A::Location, Direction)>,
+    // shown faint, like instructions `analyses::dead_code` found nothing needs.
+    pub dimmed_instrs: Vec<A::Address>,
 }
 
 pub trait FunctionDisplay<A: Arch + SSAValues> {
@@ -54,6 +56,8 @@ pub trait FunctionDisplay<A: Arch + SSAValues> {
     fn add_highlight_loc(&mut self, loc: (A::Address, A::Location, Direction));
     fn reset_highlight_instrs(&mut self);
     fn reset_highlight_locs(&mut self);
+    fn add_dimmed_instr(&mut self, addr: A::Address);
+    fn reset_dimmed_instrs(&mut self);
     fn view_between(&self, start: Option<A::Address>, end: Option<A::Address>) -> Vec<(A::Address, Vec<String>)>;
 }
 
@@ -106,6 +110,12 @@ impl <
     fn reset_highlight_locs(&mut self) {
         self.highlight_locs.clear();
     }
+    fn add_dimmed_instr(&mut self, addr: A::Address) {
+        self.dimmed_instrs.push(addr);
+    }
+    fn reset_dimmed_instrs(&mut self) {
+        self.dimmed_instrs.clear();
+    }
 
     fn view_between(&self, start: Option<A::Address>, end: Option<A::Address>) -> Vec<(A::Address, Vec<String>)> {
         let mut text: Vec<(A::Address, Vec<String>)> = Vec::new();
@@ -143,11 +153,11 @@ impl <
                             for ((_loc, dir), value) in modifications.iter() {
                                 match dir {
                                     Direction::Read => {
-                                        strings.push(format!("read: {}", value.borrow().display(false, None)));
+                                        strings.push(format!("read: {}", ssa.value(*value).display(false, None)));
                                         strings.push(format!("  via edge {} -> {}", source.show(), block.start.show()));
                                     }
                                     Direction::Write => {
-                                        strings.push(format!("write: {}", value.borrow().display(false, None)));
+                                        strings.push(format!("write: {}", ssa.value(*value).display(false, None)));
                                         strings.push(format!("  via edge {} -> {}", source.show(), block.start.show()));
                                     }
                                 }
@@ -160,18 +170,18 @@ impl <
                         let frame = format!("{}:                                 : | |", block.start.show());
                         let mut hiddens: Vec<A::Location> = Vec::new();
                         for (_, phi_op) in phis.iter() {
-                            let out = phi_op.out.borrow();
+                            let out = ssa.value(phi_op.out);
                             if !out.used {
                                 hiddens.push(out.location.clone());
                                 continue;
                             }
-                            let mut phi_line = format!("{} {} <- phi(", frame, phi_op.out.borrow().display(false, None));
+                            let mut phi_line = format!("{} {} <- phi(", frame, out.display(false, None));
                             let mut in_iter = phi_op.ins.iter();
                             if let Some(phi_in) = in_iter.next() {
-                                write!(phi_line, "{}", phi_in.borrow().display(false, None)).unwrap();
+                                write!(phi_line, "{}", ssa.value(*phi_in).display(false, None)).unwrap();
                             }
                             while let Some(phi_in) = in_iter.next() {
-                                write!(phi_line, ", {}", phi_in.borrow().display(false, None)).unwrap();
+                                write!(phi_line, ", {}", ssa.value(*phi_in).display(false, None)).unwrap();
                             }
                             phi_line.push(')');
                             strings.push(phi_line);
@@ -215,6 +225,9 @@ impl <
                 if self.highlight_instrs.contains(&address) {
                     write!(instr_string, "{}", termion::style::Invert).unwrap();
                 }
+                if self.dimmed_instrs.contains(&address) {
+                    write!(instr_string, "{}", termion::style::Faint).unwrap();
+                }
                 let highlights: Vec<(A::Location, Direction)> = self.highlight_locs.iter().filter_map(|(highlight_addr, loc, dir)| {
                     Some((loc.clone(), *dir)).filter(|_| highlight_addr == &address)
                 }).collect();
@@ -238,6 +251,9 @@ impl <
                         }
                     ).unwrap();
                 }
+                if self.dimmed_instrs.contains(&address) {
+                    write!(instr_string, "{}", termion::style::NoFaint).unwrap();
+                }
                 if self.highlight_instrs.contains(&address) {
                     write!(instr_string, "{}", termion::style::NoInvert).unwrap();
                 }
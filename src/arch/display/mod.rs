@@ -55,6 +55,35 @@ pub fn show_block<M: MemoryRange<A>, A, F, Contexts, Y: YaxColors>(
     }
 }
 
+/// the disassembly of `block`, one instruction per line, rendered as `show_block` shows it. this
+/// is meant for labels in exported graphs, so pass `NoColors` unless the label will end up on a
+/// terminal. `render_frame` may color comments and declarations either way; the exporters in
+/// `control_flow::export` strip those escapes out.
+pub fn render_block<M: MemoryRange<A>, A, F, Contexts, Y: YaxColors>(
+    data: &M,
+    ctx: &Contexts,
+    block: &BasicBlock<A::Address>,
+    colors: &Y
+) -> String where
+    A: Arch + for<'mem> DecodeFrom<M> + BaseDisplay<F, Contexts>,
+    A::Address: std::hash::Hash + petgraph::graphmap::NodeTrait,
+    A::Instruction: ShowContextual<A::Address, Contexts, String, Y> {
+    let mut text = String::new();
+    let mut iter = A::instructions_spanning(data, block.start, block.end);
+    while let Some((address, instr)) = iter.next() {
+        A::render_frame(
+            &mut text,
+            address,
+            instr,
+            &mut data.range(address..(address.wrapping_offset(instr.len()))).unwrap(),
+            Some(ctx),
+        ).unwrap();
+        instr.contextualize(colors, address, Some(ctx), &mut text).unwrap();
+        text.push('\n');
+    }
+    text
+}
+
 pub fn show_instruction<M: MemoryRange<A>, A, F, Contexts, Y: YaxColors>(
     data: &M,
     ctx: &Contexts,
@@ -119,6 +119,15 @@ pub struct Function {
 pub struct FunctionImpl<Loc: AbiDefaults> {
     names: Function,
     layout: Rc<RefCell<FunctionLayout<Loc>>>,
+    /// set when no call to this function returns to its caller - `exit`, `abort`, and
+    /// everything that only ever ends up calling them.
+    #[serde(default)]
+    noreturn: bool,
+    /// bytes of arguments the function pops off the stack as it returns, beyond its return
+    /// address - `0x10` for a function ending in `ret 0x10`, like a 32-bit stdcall function with
+    /// four arguments.
+    #[serde(default)]
+    stack_cleanup: u64,
 }
 
 pub struct FunctionImplDescription<'a, Loc: AbiDefaults, V: ValueDescriptionQuery<Loc>> {
@@ -250,14 +259,34 @@ impl <Loc: AbiDefaults + PartialEq> FunctionImpl<Loc> {
     pub fn append_ret(&mut self, new_ret: (Option<Loc>, Parameter)) {
         self.names.returns.push(Some(new_ret.1));
         let ret_idx = self.names.returns.len() - 1;
-        let ret = new_ret.0.or_else(|| { self.layout.borrow_mut().return_at(ret_idx) });
-        self.layout.borrow_mut().returns[ret_idx] = ret;
+        let mut layout_mut = self.layout.borrow_mut();
+        let ret = new_ret.0.or_else(|| { layout_mut.return_at(ret_idx) });
+        while ret_idx >= layout_mut.returns.len() {
+            layout_mut.returns.push(None);
+        }
+        layout_mut.returns[ret_idx] = ret;
     }
 
     pub fn layout(&self) -> Ref<FunctionLayout<Loc>> {
         self.layout.borrow()
     }
 
+    pub fn is_noreturn(&self) -> bool {
+        self.noreturn
+    }
+
+    pub fn set_noreturn(&mut self, noreturn: bool) {
+        self.noreturn = noreturn;
+    }
+
+    pub fn stack_cleanup(&self) -> u64 {
+        self.stack_cleanup
+    }
+
+    pub fn set_stack_cleanup(&mut self, bytes: u64) {
+        self.stack_cleanup = bytes;
+    }
+
     pub fn layout_mut(&self) -> RefMut<FunctionLayout<Loc>> {
         self.layout.borrow_mut()
     }
@@ -385,7 +414,9 @@ impl Function {
     fn implement_for<Loc: AbiDefaults>(self, layout: FunctionLayout<Loc>) -> FunctionImpl<Loc> {
         FunctionImpl {
             names: self,
-            layout: Rc::new(RefCell::new(layout))
+            layout: Rc::new(RefCell::new(layout)),
+            noreturn: false,
+            stack_cleanup: 0,
         }
     }
 
@@ -56,7 +56,7 @@ impl <T> SyntaxedSSARender<MSP430, T, Function> for yaxpeax_msp430::Instruction
                 format!("{}_{}",
                     register_name(reg),
                     match ssa.get_value(address, msp430::Location::Register(reg), direction) {
-                        Some(data) => data.borrow().version().map(|v| v.to_string()).unwrap_or("input".to_string()),
+                        Some(value) => ssa.value(value).version().map(|v| v.to_string()).unwrap_or("input".to_string()),
                         None => format!("ERR_{:?}", direction)
                     }
                 )
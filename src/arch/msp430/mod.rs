@@ -22,7 +22,7 @@ use arch::CommentQuery;
 
 use analyses::control_flow;
 use analyses::static_single_assignment::SSA;
-use analyses::static_single_assignment::{DFGRef, HashedValue, Value};
+use analyses::static_single_assignment::{DFGRef, Value, ValueGraph};
 use analyses::xrefs;
 use memory::MemoryRepr;
 use memory::repr::ReadCursor;
@@ -361,21 +361,21 @@ impl AliasInfo for Location {
     }
 }
 
-impl Memoable for HashedValue<DFGRef<MSP430>> {
+impl Memoable for DFGRef<MSP430> {
     type Out = u32;
+    type Context = ValueGraph<MSP430>;
 
-    fn memoize(&self, memos: &HashMap<Self, u32>) -> Self::Out {
+    fn memoize(&self, _values: &ValueGraph<MSP430>, memos: &HashMap<Self, u32>) -> Self::Out {
         memos[self]
     }
-    fn dememoize(_idx: u32, _memos: &[Self::Out], _dememoized: &mut HashMap<u32, Self>) -> Self {
-        use std::cell::RefCell;
-        HashedValue { value: Rc::new(RefCell::new(Value {
+    fn dememoize(_idx: u32, _memos: &[Self::Out], _dememoized: &mut HashMap<u32, Self>, values: &mut ValueGraph<MSP430>) -> Self {
+        values.alloc(Value {
             name: None,
             used: true,
             location: Location::MemoryAny,
             version: Some(0),
             data: None,
-        })) }
+        })
     }
 }
 
@@ -587,3 +587,72 @@ impl ValueLocations for MSP430 {
         }
     }
 }
+
+use analyses::stack_pointer::{Adjustment, StackCleanup, StackEffect, StackSemantics};
+impl StackSemantics for MSP430 {
+    fn stack_pointer() -> Location {
+        SP
+    }
+
+    // mspgcc keeps its frame pointer in r4.
+    fn stack_effect<C: StackCleanup<u16> + ?Sized>(_addr: u16, instr: &Self::Instruction, cleanup: &C) -> StackEffect {
+        fn immediate(op: &Operand) -> Option<i64> {
+            match op {
+                Operand::Immediate(imm) => Some(*imm as i16 as i64),
+                Operand::Const0 => Some(0),
+                Operand::Const1 => Some(1),
+                Operand::Const2 => Some(2),
+                Operand::Const4 => Some(4),
+                Operand::Const8 => Some(8),
+                Operand::ConstNeg1 => Some(-1),
+                _ => None,
+            }
+        }
+
+        match instr.opcode {
+            Opcode::PUSH => { return StackEffect::adjust(-2); }
+            Opcode::CALL => {
+                let callee_cleanup = match instr.operands[0] {
+                    Operand::Immediate(target) => cleanup.stack_cleanup(target),
+                    _ => 0,
+                };
+                return StackEffect::adjust(callee_cleanup as i64);
+            }
+            // pops `sr` and then `pc`.
+            Opcode::RETI => { return StackEffect::adjust(4); }
+            _ => {}
+        }
+
+        let mut effect = StackEffect::none();
+        // `@sp+`, as in `pop r10` (really `mov @sp+, r10`) and `ret` (`mov @sp+, pc`).
+        if let Operand::IndirectAutoinc(1) = instr.operands[0] {
+            effect.stack = Adjustment::Add(2);
+        }
+
+        let dest = match instr.opcode {
+            Opcode::RRC | Opcode::SWPB | Opcode::RRA | Opcode::SXT => instr.operands[0],
+            Opcode::MOV | Opcode::ADD | Opcode::ADDC | Opcode::SUBC | Opcode::SUB |
+            Opcode::DADD | Opcode::BIC | Opcode::BIS | Opcode::XOR | Opcode::AND => instr.operands[1],
+            _ => { return effect; }
+        };
+        let source = instr.operands[0];
+        let adjustment = match (instr.opcode, source) {
+            (Opcode::ADD, source) => immediate(&source).map(Adjustment::Add),
+            (Opcode::SUB, source) => immediate(&source).map(|imm| Adjustment::Add(-imm)),
+            (Opcode::MOV, Operand::Register(1)) => Some(Adjustment::FromStack(0)),
+            (Opcode::MOV, Operand::Register(4)) => Some(Adjustment::FromFrame(0)),
+            _ => None,
+        };
+
+        match dest {
+            Operand::Register(1) => {
+                effect.stack = adjustment.unwrap_or(Adjustment::Unknown);
+            }
+            Operand::Register(4) => {
+                effect.frame = adjustment.unwrap_or(Adjustment::Unknown);
+            }
+            _ => {}
+        }
+        effect
+    }
+}
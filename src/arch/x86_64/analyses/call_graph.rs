@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+
+use yaxpeax_arch::{AddressBase, AddressDiff, Arch, LengthedInstruction};
+use yaxpeax_x86::long_mode::{Instruction, Opcode, Operand, RegSpec};
+use yaxpeax_x86::x86_64;
+
+use analyses::call_graph::{CallGraph, CallKind};
+use analyses::control_flow::{ControlFlowGraph, Effect};
+use arch::{DecodeFrom, InstructionSpan};
+use arch::x86_64::MergedContextTable;
+use arch::x86_64::analyses::find_function_hints;
+use arch::x86_64::analyses::jump_tables::read_le;
+use memory::MemoryRange;
+
+/// where an indirect call reads its destination from, if that's a fixed address.
+fn call_slot(addr: <x86_64 as Arch>::Address, instr: &Instruction) -> Option<<x86_64 as Arch>::Address> {
+    match instr.operand(0) {
+        Operand::RegDisp(RegSpec::RIP, disp) => {
+            Some(addr.wrapping_offset(instr.len()).wrapping_offset(AddressDiff::from_const(disp as i64 as u64)))
+        }
+        Operand::DisplacementU32(disp) => Some(disp as u64),
+        Operand::DisplacementU64(disp) => Some(disp),
+        _ => None,
+    }
+}
+
+/// figure out what `instr`, a call at `addr`, calls.
+fn resolve_call<M: MemoryRange<x86_64> + ?Sized>(
+    data: &M,
+    addr: <x86_64 as Arch>::Address,
+    instr: &Instruction,
+    starts: &HashMap<<x86_64 as Arch>::Address, ()>,
+    contexts: &MergedContextTable,
+) -> (Option<<x86_64 as Arch>::Address>, CallKind) {
+    // direct calls are exactly what becomes a function hint, so don't duplicate deciding that.
+    if let Some((dest, _)) = find_function_hints(instr, addr, &Effect::cont(), contexts).into_iter().next() {
+        return (Some(dest), CallKind::Direct);
+    }
+
+    if let Some(slot) = call_slot(addr, instr) {
+        // imports are recorded as functions at the slot they're called through.
+        if contexts.functions.borrow().contains_key(&slot) {
+            return (Some(slot), CallKind::IndirectResolved);
+        }
+        // otherwise, the slot may just hold a pointer to a function we know of.
+        if let Some(dest) = read_le(data, slot, 8) {
+            if starts.contains_key(&dest) {
+                return (Some(dest), CallKind::IndirectResolved);
+            }
+        }
+    }
+
+    (None, CallKind::Unresolved)
+}
+
+/// build a call graph for every known or hinted function in `cfg`. call sites are found by
+/// walking each function's blocks as `ControlFlowGraph::get_function` would, and tail calls come
+/// from `cfg.tail_calls`.
+pub fn build_call_graph<M: MemoryRange<x86_64> + ?Sized>(
+    data: &M,
+    cfg: &ControlFlowGraph<<x86_64 as Arch>::Address>,
+    contexts: &MergedContextTable,
+) -> CallGraph<<x86_64 as Arch>::Address> where x86_64: DecodeFrom<M> {
+    let mut starts: HashMap<<x86_64 as Arch>::Address, ()> = contexts.functions.borrow().keys()
+        .map(|addr| (*addr, ()))
+        .collect();
+    for hint in contexts.function_hints.iter() {
+        starts.insert(*hint, ());
+    }
+
+    let mut entries: Vec<<x86_64 as Arch>::Address> = starts.keys()
+        .filter(|start| cfg.blocks.contains_key(*start))
+        .cloned()
+        .collect();
+    entries.sort();
+
+    let mut call_graph = CallGraph::new();
+    for entry in entries.into_iter() {
+        call_graph.add_function(entry);
+        let function = cfg.get_function(entry, &starts);
+        for block in function.blocks.values() {
+            let mut iter = x86_64::instructions_spanning(data, block.start, block.end);
+            while let Some((addr, instr)) = iter.next() {
+                if instr.opcode() == Opcode::CALL {
+                    let (callee, kind) = resolve_call(data, addr, instr, &starts, contexts);
+                    call_graph.add_call(entry, addr, callee, kind);
+                }
+            }
+        }
+        for (at, callee) in function.tail_calls.iter() {
+            call_graph.add_call(entry, *at, Some(*callee), CallKind::TailCall);
+        }
+    }
+
+    call_graph
+}
+
+#[test]
+fn test_build_call_graph() {
+    use analyses::control_flow;
+    use arch::{BaseUpdate, Function};
+    use arch::x86_64::x86_64Data;
+    use ContextWrite;
+
+    let mut data: Vec<u8> = vec![
+        0xe8, 0x1b, 0x00, 0x00, 0x00,               // 0x00: call 0x20
+        0xff, 0x15, 0x35, 0x00, 0x00, 0x00,         // 0x05: call [rip + 0x35]
+        0xff, 0xd0,                                 // 0x0b: call rax
+        0xc3,                                       // 0x0d: ret
+        0xcc, 0xcc,                                 // 0x0e: padding
+        0xff, 0x15, 0x32, 0x00, 0x00, 0x00,         // 0x10: call [rip + 0x32]
+        0xc3,                                       // 0x16: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x17: padding
+        0xcc, 0xcc,
+        0xe8, 0xdb, 0xff, 0xff, 0xff,               // 0x20: call 0x00
+        0xc3,                                       // 0x25: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x26: padding
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+    ];
+    // 0x40: a function pointer to 0x20, and 0x48: an import slot.
+    data.extend_from_slice(&0x20u64.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+
+    let mut x86_64_data = x86_64Data::default();
+    for addr in [0x00u64, 0x10, 0x20].iter() {
+        x86_64_data.contexts.put(*addr, BaseUpdate::DefineFunction(Function::of(format!("fn_{:x}", addr), vec![], vec![])));
+    }
+    x86_64_data.contexts.put(0x48, BaseUpdate::DefineFunction(Function::of("imported".to_string(), vec![], vec![])));
+
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00, 0x10, 0x20])
+        .evaluate();
+
+    let call_graph = build_call_graph(&data, &cfg, &x86_64_data.contexts);
+    let kinds: Vec<(u64, Option<u64>, CallKind)> = call_graph.calls_from(0x00).iter()
+        .map(|site| (site.at, site.callee, site.kind))
+        .collect();
+    assert_eq!(kinds, vec![
+        (0x00, Some(0x20), CallKind::Direct),
+        (0x05, Some(0x20), CallKind::IndirectResolved),
+        (0x0b, None, CallKind::Unresolved),
+    ]);
+    assert_eq!(call_graph.calls_from(0x10)[0].callee, Some(0x48));
+    assert_eq!(call_graph.calls_from(0x10)[0].kind, CallKind::IndirectResolved);
+
+    assert_eq!(call_graph.callers(0x20), vec![0x00]);
+    assert!(call_graph.is_recursive(0x00));
+    assert!(!call_graph.is_recursive(0x10));
+    let order = call_graph.reverse_topological_order();
+    assert!(order.iter().position(|f| *f == 0x48) < order.iter().position(|f| *f == 0x10));
+}
@@ -84,3 +84,96 @@ fn test_x86_determinant() {
         control_flow::Target::Relative(AddressDiff::from_const(0x14))
     ));
 }
+
+#[test]
+fn test_overlapping_instructions() {
+    use analyses::control_flow::Diagnostic;
+    use arch::x86_64::x86_64Data;
+
+    let data: Vec<u8> = vec![
+        0xeb, 0xff,                                 // 0x00: jmp 0x01
+        0xc0,                                       // 0x01: (inc eax, from 0xff at 0x01)
+        0xc3,                                       // 0x03: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00])
+        .evaluate();
+
+    // the jmp lands on its own second byte. that must not split the jmp's block...
+    assert_eq!(cfg.get_block(0x00).end, 0x01);
+    assert_eq!(cfg.destinations(0x00), Vec::<u64>::new());
+    assert_eq!(cfg.diagnostics, vec![
+        Diagnostic::IntoInstruction { from: Some(0x00), target: 0x01, instruction: 0x00 },
+    ]);
+    // ... and the other reading of those bytes is explored on its own.
+    let stream = &cfg.overlapping_streams[&0x01];
+    assert_eq!(stream.get_block(0x01).start, 0x01);
+    assert_eq!(stream.get_block(0x01).end, 0x03);
+
+    // the order entrypoints are given in doesn't change which reading is which.
+    for starts in [vec![0x00, 0x01], vec![0x01, 0x00]].iter() {
+        let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+            .with_entrypoints(starts.clone())
+            .evaluate();
+        assert_eq!(cfg.get_block(0x00).end, 0x01);
+        assert_eq!(cfg.diagnostics, vec![
+            Diagnostic::IntoInstruction { from: Some(0x00), target: 0x01, instruction: 0x00 },
+            Diagnostic::IntoInstruction { from: None, target: 0x01, instruction: 0x00 },
+        ]);
+        assert_eq!(cfg.overlapping_streams.keys().collect::<Vec<_>>(), vec![&0x01]);
+        assert_eq!(cfg.overlapping_streams[&0x01].get_block(0x01).end, 0x03);
+    }
+}
+
+#[test]
+fn test_overlapping_instruction_decoded_late() {
+    use analyses::control_flow::Diagnostic;
+    use arch::x86_64::x86_64Data;
+
+    let data: Vec<u8> = vec![
+        0x74, 0x02,                                 // 0x00: jz 0x04
+        0x90,                                       // 0x02: nop
+        0xb8, 0xc3, 0x90, 0x90, 0x90,               // 0x03: mov eax, 0x909090c3
+        0xc3,                                       // 0x08: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00])
+        .evaluate();
+
+    // the jz's fallthrough is decoded first, so the jz's target is only found to be partway
+    // through the mov after the jz was followed there...
+    assert_eq!(cfg.diagnostics, vec![
+        Diagnostic::IntoInstruction { from: Some(0x00), target: 0x04, instruction: 0x03 },
+    ]);
+    assert_eq!(cfg.destinations(0x00), vec![0x02]);
+    assert_eq!(cfg.get_block(0x02).end, 0x08);
+    // ... but it ends up the same as if the mov had been decoded first.
+    let stream = &cfg.overlapping_streams[&0x04];
+    assert_eq!(stream.get_block(0x04).start, 0x04);
+    assert_eq!(stream.get_block(0x04).end, 0x04);
+
+    let data: Vec<u8> = vec![
+        0xeb, 0x04,                                 // 0x00: jmp 0x06
+        0xcc,                                       // 0x02: int3
+        0xb8, 0x90, 0x90, 0xc3, 0x90,               // 0x03: mov eax, 0x90c39090
+        0xc3,                                       // 0x08: ret
+    ];
+
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00, 0x03])
+        .evaluate();
+
+    // the ret at 0x06 is decoded from the jmp before the mov it's the tail of...
+    assert_eq!(cfg.diagnostics, vec![
+        Diagnostic::OverlappingInstruction { instruction: 0x03, other: 0x06 },
+    ]);
+    assert_eq!(cfg.get_block(0x06).start, 0x06);
+    // ... and the mov is explored on its own all the same.
+    let stream = &cfg.overlapping_streams[&0x03];
+    assert_eq!(stream.get_block(0x03).start, 0x03);
+    assert_eq!(stream.get_block(0x03).end, 0x08);
+}
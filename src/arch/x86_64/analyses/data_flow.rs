@@ -12,20 +12,29 @@
 /// this should be optimistic or pessimistic, I'm .. ignoring it :)
 
 use arch::Symbol;
+use arch::x86_64::analyses::noreturn::branch_target;
 use arch::{AbiDefaults, FunctionAbiReference};
 use analyses::static_single_assignment::{DFGRef, SSAValues};
+use analyses::{CompletionStatus, DFG};
+use analyses::constant_propagation::{Constant, ConstantSemantics};
+use analyses::memory_layout::MemoryAccessBaseInference;
+use analyses::stack_pointer::{Adjustment, StackCleanup, StackEffect, StackSemantics};
+use analyses::taint::{Extent, TaintSemantics};
+use analyses::value_range::interval::{Interval, IntervalSemantics};
+use analyses::value_set::{ValueSet, ValueSetSemantics};
+use analyses::{Expression, Item, ValueOrImmediate};
 use analyses::static_single_assignment::{DataDisplay as SSADataDisplay};
 use data::types::{Typed, TypeSpec, TypeAtlas};
-use yaxpeax_x86::long_mode::{register_class, ConditionCode, RegSpec};
+use yaxpeax_x86::long_mode::{register_class, ConditionCode, Instruction, Opcode, Operand, RegSpec};
 use yaxpeax_x86::x86_64;
 
 use std::rc::Rc;
+use std::sync::Arc;
 use std::fmt;
 // use std::cell::RefCell;
 use std::hash::{Hash, Hasher};
 
 // use std::collections::HashMap;
-use analyses::static_single_assignment::HashedValue;
 // use serialize::Memoable;
 use serde::{Serialize, Deserialize};
 use serde::de::{self, Deserializer, Visitor, Unexpected};
@@ -105,7 +114,6 @@ impl Location {
 }
 
 use analyses::static_single_assignment::{DFGRebase, SSA};
-use analyses::static_single_assignment::DefSource;
 impl DFGRebase<yaxpeax_x86::x86_64> for Data {
     fn rebase_references(&self, old_dfg: &SSA<yaxpeax_x86::x86_64>, new_dfg: &SSA<yaxpeax_x86::x86_64>) -> Self {
         match self {
@@ -113,23 +121,13 @@ impl DFGRebase<yaxpeax_x86::x86_64> for Data {
                 Data::Expression(expr.rebase_references(old_dfg, new_dfg))
             },
             Data::Alias(dfg_ref) => {
-                if !new_dfg.defs.contains_key(&HashedValue { value: Rc::clone(dfg_ref) }) {
-                    let (_old_ref_addr, old_ref_source) = old_dfg.get_def_site(Rc::clone(dfg_ref));
-                    // "External" might mean f.ex `rsp_input`.
-                    match old_ref_source {
-                        DefSource::External => {
-                            Data::Alias(Rc::clone(new_dfg.external_defs.get(&dfg_ref.borrow().location).expect("external def has matching new def")))
-                        }
-                        DefSource::Instruction => {
-//                            self.to_owned()
-                            panic!("instruction def source");
-                        }
-                        other => {
-                            panic!("unhandled def source: {:?}", other);
-                        }
+                // an alias is found again in `new_dfg` the same way as any value in an expression.
+                let rebased = Item::value(ValueOrImmediate::Value(*dfg_ref)).rebase_references(old_dfg, new_dfg);
+                match &rebased.value {
+                    Expression::Value(ValueOrImmediate::Value(new_ref)) => Data::Alias(*new_ref),
+                    other => {
+                        panic!("rebased alias is not a value: {:?}", other);
                     }
-                } else {
-                    Data::Alias(Rc::clone(dfg_ref))
                 }
             },
             Data::ValueSet(value_ranges) => Data::ValueSet(value_ranges.to_owned()),
@@ -532,7 +530,7 @@ pub enum SymbolicExpression {
 fn test_expr_fields() {
     use data::types::KPCR;
     let type_atlas = &TypeAtlas::new();
-    let expr = SymbolicExpression::Opaque(TypeSpec::PointerTo(Rc::new(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(KPCR))))));
+    let expr = SymbolicExpression::Opaque(TypeSpec::PointerTo(Arc::new(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(KPCR))))));
     println!("expr: {}", expr.show(type_atlas));
     println!("  ty: {:?}", expr.type_of(type_atlas));
     let access = SymbolicExpression::Deref(Box::new(expr.clone()));
@@ -604,7 +602,7 @@ impl Typed for SymbolicExpression {
             SymbolicExpression::Opaque(spec) => spec.clone(),
             SymbolicExpression::Add(expr, offset) => {
                 if let Some(field) = type_atlas.get_field(&expr.type_of(type_atlas), *offset as u32) {
-                    TypeSpec::PointerTo(Rc::new(field.type_of()))
+                    TypeSpec::PointerTo(Arc::new(field.type_of()))
                 } else {
                     TypeSpec::Unknown
                 }
@@ -640,7 +638,7 @@ pub enum ValueRange {
 pub enum Data {
     Concrete(u64, Option<TypeSpec>),
     Str(String),
-    Expression(Rc<crate::analyses::Item<crate::analyses::ValueOrImmediate<x86_64>>>),
+    Expression(Arc<crate::analyses::Item<crate::analyses::ValueOrImmediate<x86_64>>>),
     Alias(DFGRef<x86_64>),
     // This is a disjunction of "ranges" that may be a range of a single element
     // eg this is a set of ranges/specific values
@@ -658,17 +656,12 @@ pub enum Data {
 impl crate::analyses::memory_layout::Underlying for Data {
     type Arch = x86_64;
 
-    fn underlying(&self) -> Option<DFGRef<Self::Arch>> {
+    fn underlying(&self, ssa: &SSA<Self::Arch>) -> Option<DFGRef<Self::Arch>> {
         match self {
             Data::Alias(alias) => {
-                let mut underlying = Rc::clone(alias);
-                loop {
-                    let next = if let Some(Data::Alias(inner)) = &underlying.borrow().data {
-                        Rc::clone(inner)
-                    } else {
-                        break;
-                    };
-                    underlying = next;
+                let mut underlying = *alias;
+                while let Some(Data::Alias(inner)) = &ssa.value(underlying).data {
+                    underlying = *inner;
                 }
                 Some(underlying)
             }
@@ -676,13 +669,17 @@ impl crate::analyses::memory_layout::Underlying for Data {
         }
     }
 
-    fn expression(&self) -> Option<Rc<crate::analyses::Item<crate::analyses::ValueOrImmediate<x86_64>>>> {
+    fn expression(&self) -> Option<Arc<crate::analyses::Item<crate::analyses::ValueOrImmediate<x86_64>>>> {
         if let Data::Expression(expr) = self {
-            Some(Rc::clone(expr))
+            Some(Arc::clone(expr))
         } else {
             None
         }
     }
+
+    fn is_exact(&self) -> bool {
+        matches!(self, Data::Concrete(..) | Data::Str(_))
+    }
 }
 
 impl fmt::Display for Data {
@@ -703,11 +700,10 @@ impl<'data, 'colors> crate::analyses::static_single_assignment::DataDisplay<'dat
 }
 
 impl Data {
-    pub fn underlying(&self) -> Option<Data> {
+    pub fn underlying(&self, ssa: &SSA<x86_64>) -> Option<Data> {
         let mut curr = self.to_owned();
         while let Data::Alias(alias) = &curr {
-            let alias = Rc::clone(alias);
-            let referent = alias.borrow().data.clone();
+            let referent = ssa.value(*alias).data.clone();
             match referent {
                 Some(aliased) => {
                     curr = aliased;
@@ -810,12 +806,13 @@ impl Data {
 }
 
 impl Typed for Data {
-    fn type_of(&self, type_atlas: &TypeAtlas) -> TypeSpec {
+    fn type_of(&self, _type_atlas: &TypeAtlas) -> TypeSpec {
         match self {
             Data::Concrete(_, ref t) => t.to_owned().unwrap_or(TypeSpec::Bottom),
             Data::Str(_) => TypeSpec::Bottom,
             Data::Expression(expr) => expr.ty.as_ref().map(|x| x.clone()).unwrap_or(TypeSpec::Bottom),
-            Data::Alias(ptr) => ptr.borrow().data.to_owned().map(|d| d.type_of(type_atlas)).unwrap_or(TypeSpec::Unknown),
+            // the aliased value lives in an `SSA` we don't have here.
+            Data::Alias(_) => TypeSpec::Unknown,
             // TODO: technically not valid - we could see if there's a shared type among
             // all the elements of the value set.
             Data::ValueSet(_) => TypeSpec::Bottom,
@@ -847,16 +844,17 @@ pub struct ValueMemo {
     pub data: Option<DataMemo>
 }
 
-impl Memoable for HashedValue<Rc<RefCell<Value<x86_64>>>> {
+impl Memoable for DFGRef<x86_64> {
     type Out = ValueMemo;
+    type Context = ValueGraph<x86_64>;
 
-    fn memoize(&self, memos: &HashMap<Self, u32>) -> Self::Out {
-        fn memoize_data(data: &Data, memos: &HashMap<HashedValue<DFGRef<x86_64>>, u32>) -> DataMemo {
+    fn memoize(&self, values: &ValueGraph<x86_64>, memos: &HashMap<Self, u32>) -> Self::Out {
+        fn memoize_data(data: &Data, memos: &HashMap<DFGRef<x86_64>, u32>) -> DataMemo {
             match data {
                 Data::Concrete(v, ty) => DataMemo::Concrete(*v, ty.to_owned()),
                 Data::Str(string) => DataMemo::Str(string.to_owned()),
                 Data::Expression(expr) => DataMemo::Expression(expr.to_owned()),
-                Data::Alias(ptr) => DataMemo::Alias(memos[&HashedValue { value: Rc::clone(ptr) }]),
+                Data::Alias(ptr) => DataMemo::Alias(memos[ptr]),
                 Data::ValueSet(values) => {
                     let mut memoized_values: Vec<ValueRangeMemo> = vec![];
                     for value in values {
@@ -875,7 +873,7 @@ impl Memoable for HashedValue<Rc<RefCell<Value<x86_64>>>> {
             }
         }
 
-        let selfref: &Value<x86_64> = &*(&*self.value.borrow());
+        let selfref: &Value<x86_64> = values.value(*self);
         let newdata = selfref.data.as_ref().map(|data| memoize_data(data, memos));
 
         ValueMemo {
@@ -884,24 +882,24 @@ impl Memoable for HashedValue<Rc<RefCell<Value<x86_64>>>> {
             data: newdata
         }
     }
-    fn dememoize(idx: u32, memos: &[Self::Out], dememoized: &mut HashMap<u32, Self>) -> Self {
-        fn dememoize_data(data: &DataMemo, memos: &[ValueMemo], dememoized: &mut HashMap<u32, HashedValue<Rc<RefCell<Value<x86_64>>>>>) -> Data {
+    fn dememoize(idx: u32, memos: &[Self::Out], dememoized: &mut HashMap<u32, Self>, values: &mut ValueGraph<x86_64>) -> Self {
+        fn dememoize_data(data: &DataMemo, memos: &[ValueMemo], dememoized: &mut HashMap<u32, DFGRef<x86_64>>, values: &mut ValueGraph<x86_64>) -> Data {
             match data {
                 DataMemo::Concrete(v, ty) => Data::Concrete(*v, ty.to_owned()),
                 DataMemo::Str(string) => Data::Str(string.to_owned()),
                 DataMemo::Expression(expr) => Data::Expression(expr.to_owned()),
                 DataMemo::Alias(idx) => {
-                    Data::Alias(<HashedValue<Rc<RefCell<Value<x86_64>>>> as Memoable>::dememoize(*idx, memos, dememoized).value)
+                    Data::Alias(<DFGRef<x86_64> as Memoable>::dememoize(*idx, memos, dememoized, values))
                 }
                 DataMemo::ValueSet(values) => {
                     let mut memoized_values: Vec<ValueRange> = vec![];
                     for value in values {
                         let memoized_value = match value {
                             ValueRangeMemo::Between(start, end) => {
-                                ValueRange::Between(dememoize_data(start, memos, dememoized), dememoize_data(end, memos, dememoized))
+                                ValueRange::Between(dememoize_data(start, memos, dememoized, values), dememoize_data(end, memos, dememoized, values))
                             }
                             ValueRangeMemo::Precisely(v) => {
-                                ValueRange::Precisely(dememoize_data(v, memos, dememoized))
+                                ValueRange::Precisely(dememoize_data(v, memos, dememoized, values))
                             }
                         };
                         memoized_values.push(memoized_value)
@@ -918,27 +916,27 @@ impl Memoable for HashedValue<Rc<RefCell<Value<x86_64>>>> {
             Entry::Occupied(v) => {
                 // something already caused us to dememoize this value. it's part of a
                 // cycle or something.
-                HashedValue { value: Rc::clone(&v.get().value) }
+                *v.get()
             }
             Entry::Vacant(e) => {
                 let loc = memo.location;
                 let version = memo.version;
 
-                let dememo = Rc::new(RefCell::new(Value {
+                let dememo = values.alloc(Value {
                     name: None,
                     used: true,
                     location: loc,
                     version: version,
                     data: None
-                }));
+                });
 
-                e.insert(HashedValue { value: Rc::clone(&dememo) });
+                e.insert(dememo);
 
                 if let Some(data) = &memo.data {
-                    (&mut *dememo.borrow_mut()).data = Some(dememoize_data(data, memos, dememoized));
+                    values.value_mut(dememo).data = Some(dememoize_data(data, memos, dememoized, values));
                 }
 
-                HashedValue { value: dememo }
+                dememo
             }
         }
     }
@@ -962,7 +960,7 @@ impl Hash for Data {
             }
             Data::Alias(ref value) => {
                 state.write_u8(3);
-                value.borrow().hash(state);
+                value.hash(state);
             }
             Data::ValueSet(ref values) => {
                 state.write_u8(4);
@@ -978,6 +976,268 @@ impl SSAValues for x86_64 {
     type Data = Data;
 }
 
+impl ConstantSemantics for x86_64 {
+    fn program_counter() -> Location {
+        Location::RIP
+    }
+
+    fn value_width(loc: &Location) -> Option<usize> {
+        match loc {
+            Location::Register(reg) => Some(reg.width() as usize),
+            _ => None,
+        }
+    }
+
+    fn evaluate<D: DFG<Constant, x86_64, u64>>(addr: u64, instr: &yaxpeax_x86::long_mode::Instruction, dfg: &mut D) -> CompletionStatus {
+        crate::arch::x86_64::semantic::evaluate(addr, instr, dfg)
+    }
+
+    fn constant_data(_loc: &Location, value: i64) -> Data {
+        Data::Concrete(value as u64, None)
+    }
+}
+
+impl IntervalSemantics for x86_64 {
+    fn evaluate_intervals<D: DFG<Interval, x86_64, u64>>(addr: u64, instr: &yaxpeax_x86::long_mode::Instruction, dfg: &mut D) -> CompletionStatus {
+        crate::arch::x86_64::semantic::evaluate(addr, instr, dfg)
+    }
+}
+
+impl ValueSetSemantics for x86_64 {
+    fn evaluate_value_sets<D: DFG<ValueSet<u64>, x86_64, u64>>(addr: u64, instr: &Instruction, dfg: &mut D) -> CompletionStatus {
+        crate::arch::x86_64::semantic::evaluate(addr, instr, dfg)
+    }
+
+    fn is_call(instr: &Instruction) -> bool {
+        instr.opcode() == Opcode::CALL
+    }
+
+    fn call_target(addr: u64, instr: &Instruction) -> Option<u64> {
+        branch_target(addr, instr)
+    }
+
+    fn return_location() -> Location {
+        Location::rax()
+    }
+}
+
+impl TaintSemantics for x86_64 {
+    type Region = ValueOrImmediate<x86_64>;
+
+    fn pointer_region(ssa: &SSA<x86_64>, pointer: &DFGRef<x86_64>) -> Option<Extent<Self::Region>> {
+        address_extent(ssa, &Item::value(ValueOrImmediate::Value(*pointer)), None)
+    }
+
+    fn location_region(ssa: &SSA<x86_64>, loc: &Location) -> Option<Extent<Self::Region>> {
+        match loc {
+            Location::MemoryLocation(_, size, Some((base, addend))) => {
+                // the base may have been narrowed down since this location was disambiguated.
+                match (location_address(base, addend), base) {
+                    (Some(address), _) => address_extent(ssa, &address, Some(*size)),
+                    (None, Data::Expression(base)) => {
+                        let (base, _) = base.infer_base_and_addend(ssa)?;
+                        base_region(&base).map(Extent::whole)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn access_region(ssa: &SSA<x86_64>, address: &Data) -> Option<Extent<Self::Region>> {
+        match address {
+            Data::Expression(expr) => address_extent(ssa, expr, None),
+            _ => None,
+        }
+    }
+
+    fn carries_data(loc: &Location) -> bool {
+        match loc {
+            // `push`, `pop`, `call` and `ret` move the stack pointer whatever they move through it.
+            Location::Register(reg) => {
+                !(reg.num() == 4 && [register_class::Q, register_class::D, register_class::W, register_class::RB].contains(&reg.class()))
+            }
+            // memory `MemoryLayout` couldn't resolve isn't followed, or every later read of memory
+            // would be tainted.
+            Location::Memory(_) => false,
+            _ => true,
+        }
+    }
+}
+
+/// the address a `MemoryLocation` refers to, as one expression.
+fn location_address(base: &Data, addend: &Data) -> Option<Arc<Item<ValueOrImmediate<x86_64>>>> {
+    match (base, addend) {
+        (Data::Expression(base), Data::Expression(addend)) => {
+            Some(Item::untyped(Expression::Add { left: Arc::clone(base), right: Arc::clone(addend) }))
+        }
+        _ => None,
+    }
+}
+
+/// regions are the base `MemoryLayout` finds for an access, which is only useful if it's a value.
+fn base_region(base: &Arc<Item<ValueOrImmediate<x86_64>>>) -> Option<ValueOrImmediate<x86_64>> {
+    match &base.value {
+        Expression::Value(value) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+/// the `size` bytes at `address`, as offsets from the base `MemoryLayout` finds for it. without a
+/// `size`, or if the offset isn't constant, this runs on to the end of the region.
+fn address_extent(ssa: &SSA<x86_64>, address: &Arc<Item<ValueOrImmediate<x86_64>>>, size: Option<u8>) -> Option<Extent<ValueOrImmediate<x86_64>>> {
+    let (base, addend) = address.infer_base_and_addend(ssa)?;
+    let region = base_region(&base)?;
+    match addend.value {
+        Expression::Value(ValueOrImmediate::Immediate(start)) => {
+            let end = size.filter(|size| *size > 0).map(|size| start.wrapping_add(size as i64 - 1));
+            Some(Extent { region, start, end })
+        }
+        _ => Some(Extent::whole(region)),
+    }
+}
+
+impl StackSemantics for x86_64 {
+    fn stack_pointer() -> Location {
+        Location::rsp()
+    }
+
+    fn stack_effect<C: StackCleanup<u64> + ?Sized>(addr: u64, instr: &Instruction, cleanup: &C) -> StackEffect {
+        fn is_reg(op: &Operand, num: u8) -> bool {
+            match op {
+                Operand::Register(reg) => {
+                    reg.num() == num && [register_class::Q, register_class::D, register_class::W, register_class::RB].contains(&reg.class())
+                }
+                _ => false,
+            }
+        }
+        fn is_stack(op: &Operand) -> bool { is_reg(op, 4) }
+        fn is_frame(op: &Operand) -> bool { is_reg(op, 5) }
+        fn immediate(op: &Operand) -> Option<i64> {
+            match op {
+                Operand::ImmediateI8(imm) => Some(*imm as i64),
+                Operand::ImmediateU8(imm) => Some(*imm as i64),
+                Operand::ImmediateI16(imm) => Some(*imm as i64),
+                Operand::ImmediateU16(imm) => Some(*imm as i64),
+                Operand::ImmediateI32(imm) => Some(*imm as i64),
+                Operand::ImmediateU32(imm) => Some(*imm as i64),
+                Operand::ImmediateI64(imm) => Some(*imm),
+                Operand::ImmediateU64(imm) => Some(*imm as i64),
+                _ => None,
+            }
+        }
+        // where `lea` points relative to the stack and frame pointers.
+        fn stack_relative(op: &Operand) -> Option<Adjustment> {
+            let (base, disp) = match op {
+                Operand::RegDeref(base) => (*base, 0),
+                Operand::RegDisp(base, disp) => (*base, *disp as i64),
+                _ => { return None; }
+            };
+            if base == RegSpec::rsp() {
+                Some(Adjustment::FromStack(disp))
+            } else if base == RegSpec::rbp() {
+                Some(Adjustment::FromFrame(disp))
+            } else {
+                None
+            }
+        }
+        // `push ax` and `pop ax` only move the stack by two bytes.
+        fn width(op: &Operand) -> i64 {
+            match op {
+                Operand::Register(reg) if reg.class() == register_class::W => 2,
+                _ => 8,
+            }
+        }
+
+        let opcode = instr.opcode();
+        match opcode {
+            Opcode::PUSH => {
+                return StackEffect::adjust(-width(&instr.operand(0)));
+            }
+            Opcode::POP => {
+                let dest = instr.operand(0);
+                let effect = StackEffect::adjust(width(&dest));
+                if is_stack(&dest) {
+                    return effect.with_stack(Adjustment::Unknown);
+                } else if is_frame(&dest) {
+                    return effect.with_frame(Adjustment::Unknown);
+                }
+                return effect;
+            }
+            Opcode::PUSHF => { return StackEffect::adjust(-8); }
+            Opcode::POPF => { return StackEffect::adjust(8); }
+            Opcode::CALL => {
+                // the callee pops its return address, and maybe some of its arguments too.
+                let callee_cleanup = branch_target(addr, instr)
+                    .map(|target| cleanup.stack_cleanup(target))
+                    .unwrap_or(0);
+                return StackEffect::adjust(callee_cleanup as i64);
+            }
+            Opcode::RETURN => {
+                let popped = if instr.operand_count() > 0 {
+                    immediate(&instr.operand(0)).unwrap_or(0)
+                } else {
+                    0
+                };
+                return StackEffect::adjust(8 + popped);
+            }
+            Opcode::ENTER => {
+                // `enter size, 0` is `push rbp; mov rbp, rsp; sub rsp, size`. with a nesting
+                // level it copies frame pointers from enclosing frames too, which nothing uses.
+                return match (immediate(&instr.operand(0)), immediate(&instr.operand(1))) {
+                    (Some(size), Some(0)) => {
+                        StackEffect::adjust(-8 - size).with_frame(Adjustment::FromStack(-8))
+                    }
+                    _ => StackEffect::none().with_stack(Adjustment::Unknown).with_frame(Adjustment::Unknown),
+                };
+            }
+            Opcode::LEAVE => {
+                return StackEffect::none()
+                    .with_stack(Adjustment::FromFrame(8))
+                    .with_frame(Adjustment::Unknown);
+            }
+            Opcode::CMP | Opcode::TEST | Opcode::BT => {
+                return StackEffect::none();
+            }
+            _ => {}
+        }
+
+        if instr.operand_count() == 0 {
+            return StackEffect::none();
+        }
+
+        let dest = instr.operand(0);
+        let source = if instr.operand_count() > 1 { Some(instr.operand(1)) } else { None };
+        if opcode == Opcode::XCHG && source.as_ref().map(|source| is_stack(source) || is_frame(source)).unwrap_or(false) {
+            return StackEffect::none().with_stack(Adjustment::Unknown).with_frame(Adjustment::Unknown);
+        }
+
+        let adjustment = match (opcode, source.as_ref()) {
+            (Opcode::ADD, Some(source)) => immediate(source).map(Adjustment::Add),
+            (Opcode::SUB, Some(source)) => immediate(source).map(|imm| Adjustment::Add(imm.wrapping_neg())),
+            (Opcode::MOV, Some(source)) if is_stack(source) => Some(Adjustment::FromStack(0)),
+            (Opcode::MOV, Some(source)) if is_frame(source) => Some(Adjustment::FromFrame(0)),
+            (Opcode::LEA, Some(source)) => stack_relative(source),
+            _ => None,
+        };
+
+        if dest == Operand::Register(RegSpec::rsp()) {
+            StackEffect::none().with_stack(adjustment.unwrap_or(Adjustment::Unknown))
+        } else if dest == Operand::Register(RegSpec::rbp()) {
+            StackEffect::none().with_frame(adjustment.unwrap_or(Adjustment::Unknown))
+        } else if is_stack(&dest) {
+            // a write to `esp` or `sp` leaves the rest of `rsp` zeroed or untouched, and either
+            // way it's not somewhere useful.
+            StackEffect::none().with_stack(Adjustment::Unknown)
+        } else if is_frame(&dest) {
+            StackEffect::none().with_frame(Adjustment::Unknown)
+        } else {
+            StackEffect::none()
+        }
+    }
+}
+
 pub(crate) fn cond_to_flags(cond: ConditionCode) -> &'static [(Option<Location>, Direction)] {
     match cond {
         ConditionCode::O => {
@@ -1077,8 +1337,25 @@ pub struct ContextualDisambiguation<'dfg, 'mem> {
 impl <'dfg, 'mem> LocationAliasDescriptions<x86_64> for ContextualDisambiguation<'dfg, 'mem> {
     fn may_alias(&self, left: &Location, right: &Location) -> bool {
         match (left, right) {
-            (Location::MemoryLocation(_, _l_size, Some((l_base, l_addend))), Location::MemoryLocation(_, _r_size, Some((r_base, r_addend)))) => {
+            (Location::MemoryLocation(_, l_size, Some((l_base, l_addend))), Location::MemoryLocation(_, r_size, Some((r_base, r_addend)))) => {
                 if let Some(memory_layout) = self.memory_layout {
+                    // a pointer computed more than once is still one base, if values were numbered.
+                    let canonical = |data: &Data| match data {
+                        Data::Expression(expr) => Data::Expression(memory_layout.canonical(expr)),
+                        other => other.clone(),
+                    };
+                    let l_base = &canonical(l_base);
+                    let l_addend = &canonical(l_addend);
+                    let r_base = &canonical(r_base);
+                    let r_addend = &canonical(r_addend);
+
+                    // value-set analysis knows about accesses that don't share a base, too.
+                    if let (Some(left), Some(right)) = (location_address(l_base, l_addend), location_address(r_base, r_addend)) {
+                        if let Some(overlap) = memory_layout.may_overlap(&left, *l_size as u64, &right, *r_size as u64) {
+                            return overlap;
+                        }
+                    }
+
                     let segments = memory_layout.segments.borrow();
 
                     use analyses::Expression;
@@ -1219,9 +1496,12 @@ impl <'dfg, 'mem> Disambiguator<x86_64, (<x86_64 as crate::Arch>::Address, u8, u
                 } else {
                     q.effective_address(&instr.operand(spec.1 - 1))
                 };
+                // name the access by leaders of congruent values, so the same pointer computed
+                // in two places is one location.
+                let access = memory.canonical(&access);
                 use analyses::memory_layout::MemoryAccessBaseInference;
                 use analyses::Expression;
-                if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&access) {
+                if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&access, memory) {
                     if base.value != Expression::Unknown && addend.value != Expression::Unknown {
                         use analyses::IntoValueIndex;
                         println!("base {}, addend {}, instr {}", base, addend, instr);
@@ -1257,6 +1537,14 @@ impl <'dfg, 'mem> Disambiguator<x86_64, (<x86_64 as crate::Arch>::Address, u8, u
                 } else {
                     eprintln!("could not infer base/addend for {}, operand={}", instr, access);
                 }
+
+                // there's no base `MemoryLayout` knows this access by, but value-set analysis may
+                // still know what region it's in and where, like for an absolute address or a
+                // field of an array element.
+                if let (Some(_), Some(size)) = (memory.value_set(&access), instr.mem_size().and_then(|sz| sz.bytes_size())) {
+                    let addend = Item::untyped(Expression::Value(ValueOrImmediate::Immediate(0)));
+                    return Some(Location::MemoryLocation(ANY, size, Some((Data::Expression(access), Data::Expression(addend)))));
+                }
             }
         }
         None
@@ -1326,3 +1614,55 @@ impl ValueLocations for x86_64 {
         crate::arch::x86_64::semantic::specialized::data_flow::decompose_locations(instr)
     }
 }
+
+#[test]
+fn test_ssa_use_sites() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::static_single_assignment::{DefSource, UseSite};
+    use arch::x86_64::x86_64Data;
+
+    let data: Vec<u8> = vec![
+        0x48, 0xc7, 0xc0, 0x01, 0x00, 0x00, 0x00,   // 0x00: mov rax, 1
+        0x48, 0x85, 0xc9,                           // 0x07: test rcx, rcx
+        0x74, 0x07,                                 // 0x0a: je 0x13
+        0x48, 0xc7, 0xc0, 0x02, 0x00, 0x00, 0x00,   // 0x0c: mov rax, 2
+        0x48, 0x01, 0xc8,                           // 0x13: add rax, rcx
+        0xc3,                                       // 0x16: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    // both writes of rax are only read by the phi where the paths join. `mov rax, 2` replaces
+    // the first, but that isn't a use of it..
+    for def in [0x00u64, 0x0c].iter() {
+        let value = dfg.get_def(*def, Location::rax()).as_rc();
+        assert_eq!(dfg.uses_of(value), &[UseSite::Phi(0x13, Location::rax())]);
+    }
+    assert!(dfg.is_overwrite(&UseSite::Instruction(0x0c), &Location::rax()));
+
+    // .. which is read by the add.
+    let joined = dfg.get_use(0x13, Location::rax()).as_rc();
+    assert_eq!(dfg.get_def_site(joined), (0x13, DefSource::Phi));
+    assert!(dfg.uses_of(joined).contains(&UseSite::Instruction(0x13)));
+
+    // rcx is never written, so every read is of the same input value.
+    let rcx = dfg.get_use(0x07, Location::rcx()).as_rc();
+    let mut readers: Vec<u64> = dfg.uses_of(rcx).iter().filter_map(|site| {
+        if let UseSite::Instruction(addr) = site { Some(*addr) } else { None }
+    }).collect();
+    readers.sort();
+    readers.dedup();
+    assert_eq!(readers, vec![0x07, 0x13]);
+
+    // and the def of the add's result isn't read by anything.
+    assert!(dfg.uses_of(dfg.get_def(0x13, Location::rax()).as_rc()).is_empty());
+}
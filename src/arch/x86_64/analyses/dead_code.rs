@@ -0,0 +1,153 @@
+use yaxpeax_x86::long_mode::{register_class, Opcode};
+use yaxpeax_x86::x86_64;
+
+use analyses::control_flow::ControlFlowGraph;
+use analyses::dead_code::{self, DeadCode, Visibility};
+use analyses::static_single_assignment::SSA;
+use arch::DecodeFrom;
+use arch::x86_64::analyses::data_flow::Location;
+use memory::MemoryRange;
+
+/// who can see writes to `loc`. arithmetic flags are only for this function, but `DF` has to be
+/// clear when returning, and `TF`, `IF` and `IOPL` change how the processor runs.
+pub fn visibility(loc: &Location) -> Visibility {
+    match loc {
+        Location::CF |
+        Location::PF |
+        Location::AF |
+        Location::ZF |
+        Location::SF |
+        Location::OF => Visibility::Local,
+        Location::DF => Visibility::Exit,
+        Location::TF |
+        Location::IF |
+        Location::IOPL |
+        Location::RIP |
+        Location::Memory(_) |
+        Location::MemoryLocation(..) => Visibility::Observable,
+        Location::Register(reg) => {
+            // rflags is written as an alias of every flag, and reading it reads them all, so it
+            // doesn't need to say more than the flags do.
+            if reg.class() == register_class::RFLAGS {
+                Visibility::Local
+            } else {
+                Visibility::Exit
+            }
+        }
+    }
+}
+
+/// whether `loc` is one of the arithmetic flags most instructions write and few read.
+pub fn is_arithmetic_flag(loc: &Location) -> bool {
+    match loc {
+        Location::CF |
+        Location::PF |
+        Location::AF |
+        Location::ZF |
+        Location::SF |
+        Location::OF => true,
+        _ => false,
+    }
+}
+
+/// whether the instruction at `addr` is a call. `SSA` doesn't say what a callee reads from its
+/// caller, even for a function with a known ABI, so any register may be an argument to any call.
+pub fn is_call<M: MemoryRange<x86_64> + ?Sized>(data: &M, addr: u64) -> bool {
+    match data.range_from(addr).and_then(|range| x86_64::decode_from(&range).ok()) {
+        Some(instr) => instr.opcode() == Opcode::CALL || instr.opcode() == Opcode::CALLF,
+        None => false,
+    }
+}
+
+pub fn find_dead_code<M: MemoryRange<x86_64> + ?Sized>(data: &M, cfg: &ControlFlowGraph<u64>, ssa: &SSA<x86_64>) -> DeadCode<x86_64> {
+    dead_code::find_dead_code(cfg, ssa, visibility, |addr| is_call(data, addr))
+}
+
+#[test]
+fn test_dead_flags() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::NoDisambiguation;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x85, 0xff,                           // 0x00: test rdi, rdi
+        0x48, 0x01, 0xf7,                           // 0x03: add rdi, rsi
+        0x74, 0x02,                                 // 0x06: je 0x0a
+        0x31, 0xc0,                                 // 0x08: xor eax, eax
+        0x89, 0x0f,                                 // 0x0a: mov [rdi], ecx
+        0x48, 0x89, 0xf9,                           // 0x0c: mov rcx, rdi
+        0x48, 0x89, 0xd1,                           // 0x0f: mov rcx, rdx
+        0xc3,                                       // 0x12: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let dead = find_dead_code(&data, &cfg, &dfg);
+
+    // `add` overwrites every flag `test` wrote before `je` can read them..
+    assert!(dead.is_dead_instruction(0x00));
+    // .. and `je` only reads ZF of what `add` writes.
+    assert!(!dead.is_dead_instruction(0x03));
+    let mut add_flags: Vec<Location> = dead.dead_writes(&dfg, 0x03).into_iter()
+        .filter(is_arithmetic_flag)
+        .collect();
+    add_flags.sort_by_key(|loc| format!("{:?}", loc));
+    assert_eq!(add_flags, vec![Location::AF, Location::CF, Location::OF, Location::PF, Location::SF]);
+    assert!(!dead.is_dead(&dfg.get_def(0x03, Location::Register(yaxpeax_x86::long_mode::RegSpec::rdi())).as_rc()));
+
+    // `eax` may be the return value, and `xor` flags are never read.
+    assert!(!dead.is_dead_instruction(0x08));
+    assert!(dead.dead_writes(&dfg, 0x08).contains(&Location::ZF));
+    // stores are never dead.
+    assert!(!dead.is_dead_instruction(0x0a));
+    // the first write to rcx is overwritten before anything reads it, the second isn't.
+    assert!(dead.is_dead_instruction(0x0c));
+    assert!(!dead.is_dead_instruction(0x0f));
+    // and `ret` is needed to return.
+    assert!(!dead.is_dead_instruction(0x12));
+}
+
+#[test]
+fn test_live_across_calls() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::NoDisambiguation;
+
+    let data: Vec<u8> = vec![
+        0xbf, 0x05, 0x00, 0x00, 0x00,               // 0x00: mov edi, 5
+        0xbe, 0x01, 0x00, 0x00, 0x00,               // 0x05: mov esi, 1
+        0xe8, 0x0b, 0x00, 0x00, 0x00,               // 0x0a: call 0x1a
+        0xbf, 0x00, 0x00, 0x00, 0x00,               // 0x0f: mov edi, 0
+        0xbe, 0x02, 0x00, 0x00, 0x00,               // 0x14: mov esi, 2
+        0xc3,                                       // 0x19: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let dead = find_dead_code(&data, &cfg, &dfg);
+
+    // nothing says what the callee takes, so `edi` and `esi` may well be its arguments.
+    assert!(!dead.is_dead_instruction(0x00));
+    assert!(!dead.is_dead_instruction(0x05));
+    // what's written after the call can still be seen once the function returns.
+    assert!(!dead.is_dead_instruction(0x0f));
+    assert!(!dead.is_dead_instruction(0x14));
+}
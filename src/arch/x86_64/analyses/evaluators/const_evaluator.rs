@@ -112,30 +112,32 @@ fn effective_address(instr: &Instruction, mem_op: &Operand, addr: <x86_64 as Arc
 }
 
 impl ConstEvaluator<x86_64, (), ConcreteDomain> for x86_64 {
-    fn apply_transient(from: <x86_64 as Arch>::Address, to: <x86_64 as Arch>::Address, location: Option<<x86_64 as ValueLocations>::Location>, exprs: &Vec<ModifierExpression>, dfg: &SSA<x86_64>, _contexts: &()) {
+    fn apply_transient(from: <x86_64 as Arch>::Address, to: <x86_64 as Arch>::Address, location: Option<<x86_64 as ValueLocations>::Location>, exprs: &Vec<ModifierExpression>, dfg: &mut SSA<x86_64>, _contexts: &()) {
         for expr in exprs {
             match expr {
                 ModifierExpression::IsNot(_) |
                 ModifierExpression::Below(_) |
-                ModifierExpression::Above(_) => { }
+                ModifierExpression::Above(_) |
+                ModifierExpression::SignedBelow(_) |
+                ModifierExpression::SignedAbove(_) => { }
                 ModifierExpression::Is(v) => {
                     if let Some(loc) = &location {
                         println!("Applying bound {:?} to location {:?}", expr, loc);
                         println!("The corresponding def version is: {:?}", dfg.get_transient_def(from, to, loc.clone()).as_rc());
-                        dfg.get_transient_def(from, to, loc.clone()).update(Data::Concrete(*v, None));
+                        dfg.get_transient_def_mut(from, to, loc.clone()).update(Data::Concrete(*v, None));
                     }
                 }
             }
         }
     }
 
-    fn evaluate_instruction<U: MemoryRange<x86_64>>(instr: &<x86_64 as Arch>::Instruction, addr: <x86_64 as Arch>::Address, dfg: &SSA<x86_64>, _contexts: &(), _data: &U) {
+    fn evaluate_instruction<U: MemoryRange<x86_64>>(instr: &<x86_64 as Arch>::Instruction, addr: <x86_64 as Arch>::Address, dfg: &mut SSA<x86_64>, _contexts: &(), _data: &U) {
         //TODO: handle prefixes like at all
         match instr.opcode() {
             Opcode::XOR => {
                 if let (Operand::Register(l), Operand::Register(r)) = (instr.operand(0), instr.operand(1)) {
                     if l == r {
-                        dfg.get_def(addr, Location::Register(r)).update(Data::Concrete(0, None));
+                        dfg.get_def_mut(addr, Location::Register(r)).update(Data::Concrete(0, None));
                     }
                 }
             },
@@ -145,14 +147,15 @@ impl ConstEvaluator<x86_64, (), ConcreteDomain> for x86_64 {
                     let r_value = dfg.get_use(addr, Location::Register(r));
                     match (l_value.get_data().as_ref(), r_value.get_data().as_ref()) {
                         (Some(Data::Concrete(l_v, _)), Some(Data::Concrete(r_v, _))) => {
-                            let def_site = if l.class() == register_class::D {
+                            let sum = l_v.wrapping_add(*r_v);
+                            let mut def_site = if l.class() == register_class::D {
                                 // this assigns to Q instead of D
-                                dfg.get_def(addr, Location::Register(RegSpec::q(l.num())))
+                                dfg.get_def_mut(addr, Location::Register(RegSpec::q(l.num())))
                             } else {
-                                dfg.get_def(addr, Location::Register(l))
+                                dfg.get_def_mut(addr, Location::Register(l))
                             };
                             def_site.update(
-                                Data::Concrete(l_v.wrapping_add(*r_v), None)
+                                Data::Concrete(sum, None)
                             );
                         }
                         _ => {}
@@ -162,7 +165,7 @@ impl ConstEvaluator<x86_64, (), ConcreteDomain> for x86_64 {
             Opcode::SUB => {
                 if let (Operand::Register(l), Operand::Register(r)) = (instr.operand(0), instr.operand(1)) {
                     if l == r {
-                        dfg.get_def(addr, Location::Register(r)).update(Data::Concrete(0, None));
+                        dfg.get_def_mut(addr, Location::Register(r)).update(Data::Concrete(0, None));
                     }
                 }
             },
@@ -171,19 +174,18 @@ impl ConstEvaluator<x86_64, (), ConcreteDomain> for x86_64 {
                 let def_site = if let Operand::Register(l) = instr.operand(0) {
                     if l.class() == register_class::D {
                         // this assigns to Q instead of D
-                        dfg.get_def(addr, Location::Register(RegSpec::q(l.num())))
+                        dfg.get_def(addr, Location::Register(RegSpec::q(l.num()))).as_rc()
                     } else {
-                        dfg.get_def(addr, Location::Register(l))
+                        dfg.get_def(addr, Location::Register(l)).as_rc()
                     }
                 } else {
                     // memory operand of some kind
                     if let Some(src) = crate::arch::x86_64::analyses::evaluators::symbolizer::referent(instr, &instr.operand(0), addr, dfg, _contexts) {
                         use analyses::memory_layout::MemoryAccessBaseInference;
-                        use analyses::static_single_assignment::DFGLValue;
                         use crate::arch::x86_64::analyses::data_flow::ANY;
-                        if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&src) {
+                        if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&src, &*dfg) {
                             if let Some(def) = dfg.try_get_def(addr, Location::MemoryLocation(ANY, 8 /* l.width() */, Some((Data::Expression(base), Data::Expression(addend))))) {
-                                DFGLValue { value: def }
+                                def
                             } else {
                                 return;
                             }
@@ -195,11 +197,16 @@ impl ConstEvaluator<x86_64, (), ConcreteDomain> for x86_64 {
                     }
                 };
 
+                let source = match instr.operand(1) {
+                    Operand::Register(r) => Some(dfg.get_use(addr, Location::Register(r)).as_rc()),
+                    _ => None,
+                };
+                let mut def_site = dfg.lvalue_mut(def_site);
                 match instr.operand(1) {
-                    Operand::Register(r) => {
-                        def_site.update(
-                            Data::Alias(dfg.get_use(addr, Location::Register(r)).as_rc())
-                        );
+                    Operand::Register(_) => {
+                        if let Some(source) = source {
+                            def_site.update(Data::Alias(source));
+                        }
                     },
                     Operand::ImmediateI8(imm) => {
                         def_site.update(Data::Concrete(imm as i64 as u64, None));
@@ -231,7 +238,7 @@ impl ConstEvaluator<x86_64, (), ConcreteDomain> for x86_64 {
             Opcode::LEA => {
                 if let (Operand::Register(l), mem_op) = (instr.operand(0), instr.operand(1)) {
                     effective_address(instr, &mem_op, addr, dfg, _contexts).map(|ea| {
-                        dfg.get_def(addr, Location::Register(l)).update(Data::Concrete(ea, None));
+                        dfg.get_def_mut(addr, Location::Register(l)).update(Data::Concrete(ea, None));
                     });
                 }
             },
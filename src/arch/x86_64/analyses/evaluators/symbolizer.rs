@@ -30,8 +30,8 @@ impl Domain for SymbolicDomain {
     }
 }
 
-use std::rc::Rc;
-pub(crate) fn referent(instr: &Instruction, mem_op: &Operand, addr: <x86_64 as Arch>::Address, dfg: &SSA<x86_64>, _contexts: &()) -> Option<Rc<Item<ValueOrImmediate<x86_64>>>> {
+use std::sync::Arc;
+pub(crate) fn referent(instr: &Instruction, mem_op: &Operand, addr: <x86_64 as Arch>::Address, dfg: &SSA<x86_64>, _contexts: &()) -> Option<Arc<Item<ValueOrImmediate<x86_64>>>> {
     match mem_op {
         Operand::DisplacementU32(disp) => {
             if instr.prefixes.gs() {
@@ -72,7 +72,7 @@ pub(crate) fn referent(instr: &Instruction, mem_op: &Operand, addr: <x86_64 as A
             } else if addr == 0x1402a3148 {
                 // its that global struct referenced in ntoskrnl:0x1402a9387
                 // stored in the KPCR, MAYBE??
-                Some(Item::opaque(TypeSpec::PointerTo(Rc::new(TypeSpec::Unknown)).pointer_to()))
+                Some(Item::opaque(TypeSpec::PointerTo(Arc::new(TypeSpec::Unknown)).pointer_to()))
             } else {
                 None
             }
@@ -138,10 +138,10 @@ pub(crate) fn referent(instr: &Instruction, mem_op: &Operand, addr: <x86_64 as A
 }
 
 impl ConstEvaluator<x86_64, (), SymbolicDomain> for x86_64 {
-    fn apply_transient(_from: <x86_64 as Arch>::Address, _to: <x86_64 as Arch>::Address, _location: Option<<x86_64 as ValueLocations>::Location>, _exprs: &Vec<<SymbolicDomain as Domain>::Modifier>, _dfg: &SSA<x86_64>, _contexts: &()) {
+    fn apply_transient(_from: <x86_64 as Arch>::Address, _to: <x86_64 as Arch>::Address, _location: Option<<x86_64 as ValueLocations>::Location>, _exprs: &Vec<<SymbolicDomain as Domain>::Modifier>, _dfg: &mut SSA<x86_64>, _contexts: &()) {
 
     }
-    fn evaluate_instruction<U: MemoryRange<x86_64>>(instr: &<x86_64 as Arch>::Instruction, addr: <x86_64 as Arch>::Address, dfg: &SSA<x86_64>, contexts: &(), _data: &U) {
+    fn evaluate_instruction<U: MemoryRange<x86_64>>(instr: &<x86_64 as Arch>::Instruction, addr: <x86_64 as Arch>::Address, dfg: &mut SSA<x86_64>, contexts: &(), _data: &U) {
         //TODO: handle prefixes like at all
         match instr.opcode() {
             Opcode::MOV => {
@@ -156,26 +156,26 @@ impl ConstEvaluator<x86_64, (), SymbolicDomain> for x86_64 {
                             if let Some(src) = referent(instr, &op, addr, dfg, contexts) {
                                 use analyses::memory_layout::MemoryAccessBaseInference;
                                 use crate::arch::x86_64::analyses::data_flow::ANY;
-                                let def = dfg.get_def(addr, Location::Register(l));
+                                let def = dfg.get_def(addr, Location::Register(l)).as_rc();
 
-                                if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&src) {
+                                if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&src, &*dfg) {
                                     println!("inferred base and addend {:?}, {:?}", base, addend);
-                                    println!("  def is {:?}={:p}", def.value, def.value.as_ptr());
+                                    println!("  def is {:?}", def);
                                     if let Some(memory) = dfg.try_get_use(addr, Location::MemoryLocation(ANY, 8 /* l.width() */, Some((Data::Expression(base), Data::Expression(addend))))) {
-                                        println!("    AND aliasing to {:?}, was {:?}", memory, def.get_data());
-                                        def.update(Data::Alias(Rc::clone(&memory)));
-                                        println!("    DONE aliasing to {:?}, is {:?}", memory, dfg.get_def(addr, Location::Register(l)).value);
+                                        println!("    AND aliasing to {:?}, was {:?}", memory, dfg.value(def).data);
+                                        dfg.lvalue_mut(def).update(Data::Alias(memory));
+                                        println!("    DONE aliasing to {:?}, is {:?}", memory, dfg.value(def));
                                         return;
                                     } else {
-                                        if def.get_data().is_none() {
-                                            def.update(
+                                        if dfg.value(def).data.is_none() {
+                                            dfg.lvalue_mut(def).update(
                                                 Data::Expression(Item::load(&src, l.width()))
                                             )
                                         }
                                     }
                                 } else {
-                                    if def.get_data().is_none() {
-                                        def.update(
+                                    if dfg.value(def).data.is_none() {
+                                        dfg.lvalue_mut(def).update(
                                             Data::Expression(Item::load(&src, l.width()))
                                         )
                                     }
@@ -192,12 +192,12 @@ impl ConstEvaluator<x86_64, (), SymbolicDomain> for x86_64 {
                             if let Some(src) = referent(instr, &op, addr, dfg, contexts) {
                                 use analyses::memory_layout::MemoryAccessBaseInference;
                                 use crate::arch::x86_64::analyses::data_flow::ANY;
-                                let usage = dfg.get_use(addr, Location::Register(r));
+                                let usage = dfg.get_use(addr, Location::Register(r)).as_rc();
 
-                                if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&src) {
+                                if let Some((base, addend)) = MemoryAccessBaseInference::infer_base_and_addend(&src, &*dfg) {
                                     println!("inferred base and addend {:?}, {:?}", base, addend);
                                     if let Some(def) = dfg.try_get_def(addr, Location::MemoryLocation(ANY, 8 /* l.width() */, Some((Data::Expression(base), Data::Expression(addend))))) {
-                                        def.borrow_mut().data.replace(Data::Alias(Rc::clone(&usage.value)));
+                                        dfg.lvalue_mut(def).update(Data::Alias(usage));
                                     }
                                 }
                             }
@@ -221,12 +221,13 @@ impl ConstEvaluator<x86_64, (), SymbolicDomain> for x86_64 {
                             }
                         };
                         let use_val = dfg.get_use(addr, Location::Register(l));
-                        let def_val = dfg.get_def(addr, Location::Register(l));
+                        let def_val = dfg.get_def(addr, Location::Register(l)).as_rc();
         //                println!("Symbolizing use {:?} + {:#x} = ...", use_val.get_data(), i);
                         match use_val.get_data().as_ref() {
                             Some(Data::Expression(expr)) => {
         //                        println!("  = {:?}", expr.clone().offset(*i as i64 as u64));
-                                def_val.update(Data::Expression(expr.add(&Item::immediate(offset))));
+                                let sum = expr.add(&Item::immediate(offset));
+                                dfg.lvalue_mut(def_val).update(Data::Expression(sum));
                             }
                             _ => { }
                         };
@@ -75,7 +75,7 @@ fn referent(_instr: &Instruction, mem_op: &Operand, addr: <x86_64 as Arch>::Addr
         }
         Operand::RegScaleDisp(base, scale, disp) => {
 //            let _data = dfg.get_use(addr, Location::Register(*base)).get_data().as_ref().and_then(|x| Data::underlying(x));
-            let referent = match dfg.get_use(addr, Location::Register(*base)).get_data().as_ref().and_then(|x| Data::underlying(x)) {
+            let referent = match dfg.get_use(addr, Location::Register(*base)).get_data().as_ref().and_then(|x| x.underlying(dfg)) {
                 Some(Data::ValueSet(base_values)) => {
                     if let Some(scaled) = Data::mul(&Data::ValueSet(base_values.clone()), &Data::Concrete(*scale as u64, None)) {
                         Data::add(&scaled, &Data::Concrete(*disp as i64 as u64, None))
@@ -200,7 +200,7 @@ impl ConstEvaluator<x86_64, (), ValueSetDomain> for x86_64 {
     /// v_2 = transient ModifierExpression::Below(20) .
     ///       transient ModifierExpression::Above(5) . v_1
     /// -> v_2 == Data::ValueSet(vec![ValueRange::Between(5, 20)])
-    fn apply_transient(from: <x86_64 as Arch>::Address, to: <x86_64 as Arch>::Address, location: Option<<x86_64 as ValueLocations>::Location>, exprs: &Vec<<ValueSetDomain as Domain>::Modifier>, dfg: &SSA<x86_64>, _contexts: &()) {
+    fn apply_transient(from: <x86_64 as Arch>::Address, to: <x86_64 as Arch>::Address, location: Option<<x86_64 as ValueLocations>::Location>, exprs: &Vec<<ValueSetDomain as Domain>::Modifier>, dfg: &mut SSA<x86_64>, _contexts: &()) {
         if let Some(loc) = location {
             for expr in exprs {
                 let new_value = dfg.get_transient_value(from, to, loc.clone(), Direction::Read).and_then(|_lvalue| {
@@ -228,16 +228,19 @@ impl ConstEvaluator<x86_64, (), ValueSetDomain> for x86_64 {
                         ModifierExpression::Is(v) => {
                             Some(Data::Concrete(*v, None))
                         }
-                        ModifierExpression::IsNot(_) => {
+                        // signed bounds around zero aren't one unsigned range, so don't try.
+                        ModifierExpression::IsNot(_) |
+                        ModifierExpression::SignedBelow(_) |
+                        ModifierExpression::SignedAbove(_) => {
                             None
                         }
                     }
                 });
 
                 if let Some(new_value) = new_value {
-                    dfg.get_transient_def(from, to, loc.clone()).update(new_value);
+                    dfg.get_transient_def_mut(from, to, loc.clone()).update(new_value);
                 } else {
-                    dfg.get_transient_def(from, to, loc.clone()).clear();
+                    dfg.get_transient_def_mut(from, to, loc.clone()).clear();
                 }
             }
         } else {
@@ -246,16 +249,15 @@ impl ConstEvaluator<x86_64, (), ValueSetDomain> for x86_64 {
         }
     }
 
-    fn evaluate_instruction<U: MemoryRange<x86_64>>(instr: &<x86_64 as Arch>::Instruction, addr: <x86_64 as Arch>::Address, dfg: &SSA<x86_64>, contexts: &(), data: &U) {
+    fn evaluate_instruction<U: MemoryRange<x86_64>>(instr: &<x86_64 as Arch>::Instruction, addr: <x86_64 as Arch>::Address, dfg: &mut SSA<x86_64>, contexts: &(), data: &U) {
         //TODO: handle prefixes like at all
         match instr.opcode() {
             Opcode::MOVSXD => {
                 match (instr.operand(0), instr.operand(1)) {
                     (Operand::Register(l), Operand::Register(r)) => {
                         // TODO: respect the sign extendy bits of movsxd
-                        dfg.get_def(addr, Location::Register(l)).replace(
-                            dfg.get_use(addr, Location::Register(r)).get_data().clone()
-                        );
+                        let data = dfg.get_use(addr, Location::Register(r)).get_data().clone();
+                        dfg.get_def_mut(addr, Location::Register(l)).replace(data);
                     }
                     _ => {}
                 }
@@ -273,7 +275,7 @@ impl ConstEvaluator<x86_64, (), ValueSetDomain> for x86_64 {
                                         Data::ValueSet(values).display(false, None),
                                         Data::ValueSet(read_values.clone()).display(false, None),
                                     );
-                                    dfg.get_def(addr, Location::Register(l)).update(
+                                    dfg.get_def_mut(addr, Location::Register(l)).update(
                                         Data::ValueSet(read_values)
                                     );
                                 } else {
@@ -314,7 +316,7 @@ impl ConstEvaluator<x86_64, (), ValueSetDomain> for x86_64 {
                                         Data::ValueSet(values).display(false, None),
                                         Data::ValueSet(read_values.clone()).display(false, None),
                                     );
-                                    dfg.get_def(addr, Location::Register(l)).update(
+                                    dfg.get_def_mut(addr, Location::Register(l)).update(
                                         Data::ValueSet(read_values)
                                     );
                                 } else {
@@ -354,13 +356,12 @@ impl ConstEvaluator<x86_64, (), ValueSetDomain> for x86_64 {
             Opcode::ADD => {
                 match (instr.operand(0), instr.operand(1)) {
                     (Operand::Register(l), Operand::Register(r)) => {
-                        dfg.get_def(addr, Location::Register(l)).replace(
-                            dfg.get_use(addr, Location::Register(l)).get_data().as_ref().and_then(|ldata| {
-                                dfg.get_use(addr, Location::Register(r)).get_data().as_ref().and_then(|rdata| {
-                                    Data::add(&ldata, &rdata)
-                                })
+                        let sum = dfg.get_use(addr, Location::Register(l)).get_data().as_ref().and_then(|ldata| {
+                            dfg.get_use(addr, Location::Register(r)).get_data().as_ref().and_then(|rdata| {
+                                Data::add(&ldata, &rdata)
                             })
-                        );
+                        });
+                        dfg.get_def_mut(addr, Location::Register(l)).replace(sum);
                     }
                     _ => {}
                 }
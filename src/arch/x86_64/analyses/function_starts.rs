@@ -0,0 +1,26 @@
+use yaxpeax_x86::x86_64;
+
+use analyses::function_starts::{self, PrologueScanner};
+use arch::interface::Data;
+use memory::repr::process::ModuleData;
+
+/// a prologue scanner for common x86_64 function starts, at the 16-byte alignment compilers
+/// usually give functions.
+pub fn prologue_scanner() -> PrologueScanner {
+    PrologueScanner::new()
+        // endbr64
+        .with_pattern(&[Some(0xf3), Some(0x0f), Some(0x1e), Some(0xfa)])
+        // push rbp; mov rbp, rsp
+        .with_pattern(&[Some(0x55), Some(0x48), Some(0x89), Some(0xe5)])
+        .with_pattern(&[Some(0x55), Some(0x48), Some(0x8b), Some(0xec)])
+        // sub rsp, imm8
+        .with_pattern(&[Some(0x48), Some(0x83), Some(0xec), None])
+        .with_alignment(16)
+}
+
+/// function hints for `module` from unwind info and `prologue_scanner()`. these are for starts
+/// nothing calls directly - functions only reached through pointers - which exploring from the
+/// entrypoint won't find.
+pub fn function_hints(module: &ModuleData) -> Vec<Data<x86_64>> {
+    function_starts::function_hints(module, &prologue_scanner())
+}
@@ -0,0 +1,375 @@
+use yaxpeax_arch::{AddressBase, AddressDiff, Arch, LengthedInstruction};
+use yaxpeax_x86::long_mode::{register_class, Instruction, Opcode, Operand, RegSpec};
+use yaxpeax_x86::x86_64;
+
+use analyses::control_flow::{BasicBlock, ControlFlowGraph};
+use analyses::control_flow::jump_tables::{JumpTable, JumpTableResolver};
+use arch::DecodeFrom;
+use arch::InstructionSpan;
+use arch::x86_64::analyses::data_flow::Location;
+use data::{Direction, ValueLocations};
+use data::modifier::{InstructionModifiers, ModifierExpression};
+use memory::MemoryRange;
+
+/// tables with more entries than this are assumed to be the result of a bad bound, rather than
+/// a real switch.
+const MAX_TABLE_ENTRIES: u64 = 0x1000;
+
+/// blocks longer than this aren't worth decoding to look for a dispatch at the end.
+const MAX_BLOCK_INSTRUCTIONS: usize = 0x400;
+
+pub struct JumpTableInference;
+
+/// how a table's entries are turned into destinations.
+enum TableShape {
+    /// `jmp [table + idx * 8]`: entries are absolute addresses.
+    Absolute,
+    /// `movsxd reg, [table + idx * 4]; add reg, table; jmp reg`: entries are 32-bit offsets from
+    /// the start of the table. this is what position-independent code gets.
+    Relative,
+}
+
+/// does `reg` overlap `other`? this only considers the general purpose registers - `eax` aliases
+/// `rax`, `ah` doesn't matter here.
+fn aliases(reg: RegSpec, other: RegSpec) -> bool {
+    fn is_gpr(reg: RegSpec) -> bool {
+        match reg.class() {
+            register_class::Q |
+            register_class::D |
+            register_class::W |
+            register_class::B => true,
+            _ => false,
+        }
+    }
+
+    is_gpr(reg) && is_gpr(other) && reg.num() == other.num()
+}
+
+fn writes_reg(instr: &Instruction, reg: RegSpec) -> bool {
+    <x86_64 as ValueLocations>::decompose(instr).into_iter().any(|(loc, dir)| {
+        match (loc, dir) {
+            (Some(Location::Register(written)), Direction::Write) => aliases(written, reg),
+            _ => false,
+        }
+    })
+}
+
+/// find the value `reg` holds at the end of `instrs` if it's set by a `lea reg, [rip + disp]` that
+/// is not later overwritten.
+fn rip_relative_base(instrs: &[(<x86_64 as Arch>::Address, Instruction)], reg: RegSpec) -> Option<<x86_64 as Arch>::Address> {
+    for (addr, instr) in instrs.iter().rev() {
+        if instr.opcode() == Opcode::LEA && instr.operand(0) == Operand::Register(reg) {
+            if let Operand::RegDisp(RegSpec::RIP, disp) = instr.operand(1) {
+                return Some(
+                    addr.wrapping_offset(instr.len())
+                        .wrapping_offset(AddressDiff::from_const(disp as i64 as u64))
+                );
+            }
+            return None;
+        }
+        if writes_reg(instr, reg) {
+            return None;
+        }
+    }
+    None
+}
+
+/// find the largest value `index` can have when entering `block`, from bounds placed on edges into
+/// it. every incoming edge must bound the index; any unbounded edge means any index could reach
+/// the dispatch.
+///
+/// this reads `ModifierExpression::Below(n)` as `index <= n`, as `ValueSetDomain` does. bounds
+/// from strict conditions are already pulled in by one, so both `cmp idx, n; ja default` and
+/// `cmp idx, n; jb dispatch` are exact.
+fn index_bound(block: &BasicBlock<<x86_64 as Arch>::Address>, cfg: &ControlFlowGraph<<x86_64 as Arch>::Address>, modifiers: &InstructionModifiers<x86_64>, index: RegSpec) -> Option<u64> {
+    let sources = cfg.sources(block.start);
+    if sources.is_empty() {
+        return None;
+    }
+
+    let mut bound: Option<u64> = None;
+    for source in sources {
+        let edge_modifiers = modifiers.modifiers_between(source, block.start)?;
+        let mut edge_bound: Option<u64> = None;
+        for (loc, exprs) in edge_modifiers.iter() {
+            match loc {
+                Some(Location::Register(reg)) if aliases(*reg, index) => {
+                    for expr in exprs {
+                        if let ModifierExpression::Below(n) = expr {
+                            edge_bound = Some(edge_bound.map(|b| std::cmp::min(b, *n)).unwrap_or(*n));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let edge_bound = edge_bound?;
+        bound = Some(bound.map(|b| std::cmp::max(b, edge_bound)).unwrap_or(edge_bound));
+    }
+    bound
+}
+
+pub(crate) fn read_le<M: MemoryRange<x86_64> + ?Sized>(data: &M, addr: <x86_64 as Arch>::Address, size: u8) -> Option<u64> {
+    let mut value = 0u64;
+    for i in 0..size {
+        value |= (data.read(addr.wrapping_add(i as u64))? as u64) << (8 * i);
+    }
+    Some(value)
+}
+
+impl JumpTableResolver<x86_64, InstructionModifiers<x86_64>> for JumpTableInference {
+    fn resolve_jump_table<M: MemoryRange<x86_64> + ?Sized>(block: &BasicBlock<<x86_64 as Arch>::Address>, cfg: &ControlFlowGraph<<x86_64 as Arch>::Address>, data: &M, modifiers: &InstructionModifiers<x86_64>) -> Option<JumpTable<<x86_64 as Arch>::Address>>
+        where x86_64: DecodeFrom<M>,
+    {
+        let mut instrs: Vec<(<x86_64 as Arch>::Address, Instruction)> = Vec::new();
+        let mut iter = x86_64::instructions_spanning(data, block.start, block.end);
+        while let Some((addr, instr)) = iter.next() {
+            if instrs.len() >= MAX_BLOCK_INSTRUCTIONS {
+                return None;
+            }
+            instrs.push((addr, *instr));
+        }
+
+        let (dispatch, jmp) = instrs.pop()?;
+        if jmp.opcode() != Opcode::JMP {
+            return None;
+        }
+        // the dispatch must actually be what ends the block, rather than the block running off the
+        // end of decodable memory.
+        if dispatch.wrapping_offset(jmp.len()) != block.end.wrapping_add(1) {
+            return None;
+        }
+
+        // `index_use` is how many instructions in the block run before the index is used.
+        let (table, index, index_use, shape) = match jmp.operand(0) {
+            Operand::RegScaleDisp(index, 8, disp) => {
+                (disp as i64 as u64, index, instrs.len(), TableShape::Absolute)
+            }
+            Operand::RegIndexBaseScale(base, index, 8) => {
+                (rip_relative_base(&instrs, base)?, index, instrs.len(), TableShape::Absolute)
+            }
+            Operand::RegIndexBaseScaleDisp(base, index, 8, disp) => {
+                (rip_relative_base(&instrs, base)?.wrapping_add(disp as i64 as u64), index, instrs.len(), TableShape::Absolute)
+            }
+            Operand::Register(target) => {
+                // walk back looking for the `add target, base` and `movsxd target, [base + idx * 4]`
+                // that produce `target`.
+                let add_idx = instrs.iter().rposition(|(_, instr)| writes_reg(instr, target))?;
+                let (_, add) = &instrs[add_idx];
+                let base = match (add.opcode(), add.operand(0), add.operand(1)) {
+                    (Opcode::ADD, Operand::Register(dest), Operand::Register(base)) if dest == target => base,
+                    _ => { return None; }
+                };
+                let load_idx = instrs[..add_idx].iter().rposition(|(_, instr)| writes_reg(instr, target))?;
+                let (_, load) = &instrs[load_idx];
+                let index = match (load.opcode(), load.operand(1)) {
+                    (Opcode::MOVSXD, Operand::RegIndexBaseScale(load_base, index, 4)) if load_base == base => index,
+                    _ => { return None; }
+                };
+                let table = rip_relative_base(&instrs[..load_idx], base)?;
+                // and `base` had better still be the table when it's added back in.
+                if instrs[load_idx..add_idx].iter().any(|(_, instr)| writes_reg(instr, base)) {
+                    return None;
+                }
+                (table, index, load_idx, TableShape::Relative)
+            }
+            _ => {
+                return None;
+            }
+        };
+
+        // the index must reach the dispatch unchanged from where it was bounded.
+        if instrs[..index_use].iter().any(|(_, instr)| writes_reg(instr, index)) {
+            return None;
+        }
+
+        let bound = index_bound(block, cfg, modifiers, index)?;
+        if bound >= MAX_TABLE_ENTRIES {
+            return None;
+        }
+
+        let mut entries = Vec::new();
+        for i in 0..=bound {
+            let entry = match shape {
+                TableShape::Absolute => {
+                    read_le(data, table.wrapping_add(i * 8), 8)?
+                }
+                TableShape::Relative => {
+                    let offset = read_le(data, table.wrapping_add(i * 4), 4)? as u32 as i32;
+                    table.wrapping_add(offset as i64 as u64)
+                }
+            };
+            // an entry that isn't even in the program means we've misread the table.
+            data.read(entry)?;
+            entries.push(entry);
+        }
+
+        Some(JumpTable {
+            dispatch,
+            table,
+            entries,
+        })
+    }
+}
+
+#[test]
+fn test_absolute_jump_table() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::value_range::ConditionalBoundInference;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::NoDisambiguation;
+    use arch::x86_64::analyses::value_range::conditional_inference::ConditionalInference;
+
+    let mut data: Vec<u8> = vec![
+        0x83, 0xf8, 0x03,                           // 0x00: cmp eax, 3
+        0x77, 0x0b,                                 // 0x03: ja 0x10
+        0xff, 0x24, 0xc5, 0x18, 0x00, 0x00, 0x00,   // 0x05: jmp [rax * 8 + 0x18]
+        0xc3,                                       // 0x0c: ret
+        0xc3,                                       // 0x0d: ret
+        0xc3,                                       // 0x0e: ret
+        0xc3,                                       // 0x0f: ret
+        0xc3,                                       // 0x10: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x11: padding
+    ];
+    // the default case looks like a plausible fifth entry, but `ja` rules it out.
+    for case in [0x0cu64, 0x0d, 0x0e, 0x0f, 0x10].iter() {
+        data.extend_from_slice(&case.to_le_bytes());
+    }
+
+    let mut x86_64_data = x86_64Data::default();
+    let mut cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    // nothing knows where the jmp goes yet.
+    assert_eq!(cfg.destinations(0x05), Vec::<u64>::new());
+
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    let ja = x86_64::decode_from(&data.range_from(0x03).unwrap()).unwrap();
+    assert!(ConditionalInference::add_conditional_bounds(0x00, 0x03, &ja, &cfg, &dfg, &data, &mut modifiers));
+
+    let tables = JumpTableInference::recover_jump_tables(&data, &mut x86_64_data.contexts, &mut cfg, &modifiers);
+    assert_eq!(tables, vec![JumpTable {
+        dispatch: 0x05,
+        table: 0x18,
+        entries: vec![0x0c, 0x0d, 0x0e, 0x0f],
+    }]);
+
+    let mut dests = cfg.destinations(0x05);
+    dests.sort();
+    assert_eq!(dests, vec![0x0c, 0x0d, 0x0e, 0x0f]);
+    for case in [0x0cu64, 0x0d, 0x0e, 0x0f].iter() {
+        assert_eq!(cfg.get_block(*case).start, *case);
+        assert_eq!(cfg.get_block(*case).end, *case);
+    }
+}
+
+#[test]
+fn test_strict_jump_table_bound() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::value_range::ConditionalBoundInference;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::NoDisambiguation;
+    use arch::x86_64::analyses::value_range::conditional_inference::ConditionalInference;
+
+    let mut data: Vec<u8> = vec![
+        0xeb, 0x07,                                 // 0x00: jmp 0x09
+        0xff, 0x24, 0xc5, 0x20, 0x00, 0x00, 0x00,   // 0x02: jmp [rax * 8 + 0x20]
+        0x83, 0xf8, 0x04,                           // 0x09: cmp eax, 4
+        0x72, 0xf4,                                 // 0x0c: jb 0x02
+        0xc3,                                       // 0x0e: ret
+        0xc3,                                       // 0x0f: ret
+        0xc3,                                       // 0x10: ret
+        0xc3,                                       // 0x11: ret
+        0xc3,                                       // 0x12: ret
+        0xc3,                                       // 0x13: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,         // 0x14: padding
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,         // 0x1a: padding
+    ];
+    // `jb` is strict, so index 4 never reaches the dispatch even though its entry looks fine.
+    for case in [0x0fu64, 0x10, 0x11, 0x12, 0x13].iter() {
+        data.extend_from_slice(&case.to_le_bytes());
+    }
+
+    let mut x86_64_data = x86_64Data::default();
+    let mut cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    let jb = x86_64::decode_from(&data.range_from(0x0c).unwrap()).unwrap();
+    assert!(ConditionalInference::add_conditional_bounds(0x09, 0x0c, &jb, &cfg, &dfg, &data, &mut modifiers));
+
+    let tables = JumpTableInference::recover_jump_tables(&data, &mut x86_64_data.contexts, &mut cfg, &modifiers);
+    assert_eq!(tables, vec![JumpTable {
+        dispatch: 0x02,
+        table: 0x20,
+        entries: vec![0x0f, 0x10, 0x11, 0x12],
+    }]);
+}
+
+#[test]
+fn test_relative_jump_table() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use analyses::value_range::ConditionalBoundInference;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::NoDisambiguation;
+    use arch::x86_64::analyses::value_range::conditional_inference::ConditionalInference;
+
+    let mut data: Vec<u8> = vec![
+        0x83, 0xf8, 0x02,                           // 0x00: cmp eax, 2
+        0x77, 0x13,                                 // 0x03: ja 0x18
+        0x48, 0x8d, 0x15, 0x14, 0x00, 0x00, 0x00,   // 0x05: lea rdx, [rip + 0x14]
+        0x48, 0x63, 0x04, 0x82,                     // 0x0c: movsxd rax, dword [rdx + rax * 4]
+        0x48, 0x01, 0xd0,                           // 0x10: add rax, rdx
+        0xff, 0xe0,                                 // 0x13: jmp rax
+        0xc3,                                       // 0x15: ret
+        0xc3,                                       // 0x16: ret
+        0xc3,                                       // 0x17: ret
+        0xc3,                                       // 0x18: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x19: padding
+    ];
+    for offset in [-0x0bi32, -0x0a, -0x09].iter() {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+
+    let mut x86_64_data = x86_64Data::default();
+    let mut cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+
+    let dfg = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    let ja = x86_64::decode_from(&data.range_from(0x03).unwrap()).unwrap();
+    assert!(ConditionalInference::add_conditional_bounds(0x00, 0x03, &ja, &cfg, &dfg, &data, &mut modifiers));
+
+    let tables = JumpTableInference::recover_jump_tables(&data, &mut x86_64_data.contexts, &mut cfg, &modifiers);
+    assert_eq!(tables, vec![JumpTable {
+        dispatch: 0x13,
+        table: 0x20,
+        entries: vec![0x15, 0x16, 0x17],
+    }]);
+
+    let mut dests = cfg.destinations(0x05);
+    dests.sort();
+    assert_eq!(dests, vec![0x15, 0x16, 0x17]);
+}
@@ -8,9 +8,16 @@ use arch::BaseUpdate;
 use analyses::xrefs::{RefType, RefAction};
 use tracing::{event, Level};
 
+pub mod call_graph;
 pub mod control_flow;
 pub mod data_flow;
+pub mod dead_code;
 pub mod evaluators;
+pub mod function_starts;
+pub mod jump_tables;
+pub mod noreturn;
+pub mod smt;
+pub mod tail_calls;
 pub mod value_range;
 
 pub fn all_instruction_analyses(
@@ -0,0 +1,249 @@
+use std::collections::HashSet;
+
+use yaxpeax_arch::{AddressBase, AddressDiff, Arch, LengthedInstruction};
+use yaxpeax_x86::long_mode::{Instruction, Opcode, Operand, RegSpec};
+use yaxpeax_x86::x86_64;
+
+use analyses::control_flow::{ControlFlowGraph, Effect};
+use analyses::noreturn::{is_noreturn_name, noreturn_imports, propagate_noreturn};
+use arch::{DecodeFrom, FunctionImpl, InstructionSpan, Symbol};
+use arch::x86_64::MergedContextTable;
+use memory::MemoryRange;
+
+/// where a `call` or `jmp` goes, as far as the instruction alone says. for branches through
+/// `[rip + disp]` or an absolute address, this is the address of the pointer that is branched
+/// through - for imports that's the IAT or .got slot, and that's where imports are recorded as
+/// functions.
+pub(crate) fn branch_target(addr: <x86_64 as Arch>::Address, instr: &Instruction) -> Option<<x86_64 as Arch>::Address> {
+    let next = addr.wrapping_offset(instr.len());
+    match instr.operand(0) {
+        Operand::ImmediateI8(rel) => Some(next.wrapping_offset(AddressDiff::from_const(rel as i64 as u64))),
+        Operand::ImmediateI32(rel) => Some(next.wrapping_offset(AddressDiff::from_const(rel as i64 as u64))),
+        Operand::RegDisp(RegSpec::RIP, disp) => Some(next.wrapping_offset(AddressDiff::from_const(disp as i64 as u64))),
+        Operand::DisplacementU32(disp) => Some(disp as u64),
+        Operand::DisplacementU64(disp) => Some(disp),
+        _ => None,
+    }
+}
+
+/// an effect refinement for `control_flow::AnalysisBuilder`: calls to functions marked noreturn
+/// end control flow rather than continuing after the call.
+pub fn stop_at_noreturn_calls(instr: &Instruction, addr: <x86_64 as Arch>::Address, effect: Effect<<x86_64 as Arch>::Address>, contexts: &MergedContextTable) -> Effect<<x86_64 as Arch>::Address> {
+    if instr.opcode() != Opcode::CALL {
+        return effect;
+    }
+
+    let noreturn = branch_target(addr, instr)
+        .and_then(|target| contexts.functions.borrow().get(&target).map(|f| f.is_noreturn()))
+        .unwrap_or(false);
+
+    if noreturn {
+        Effect::stop()
+    } else {
+        effect
+    }
+}
+
+/// is there any path from `entry` to a return, if calls to `noreturn` functions don't come back?
+/// anything this can't see through - indirect branches with no known destinations, code that
+/// doesn't decode, jumps into functions that may return - counts as returning.
+fn may_return<M: MemoryRange<x86_64> + ?Sized>(
+    data: &M,
+    cfg: &ControlFlowGraph<<x86_64 as Arch>::Address>,
+    entry: <x86_64 as Arch>::Address,
+    functions: &HashSet<<x86_64 as Arch>::Address>,
+    noreturn: &HashSet<<x86_64 as Arch>::Address>,
+) -> bool where x86_64: DecodeFrom<M> {
+    // blocks with no edges in or out don't show up in `cfg.graph`, so `cfg.blocks` is what says
+    // if `entry` was explored at all.
+    if !cfg.blocks.contains_key(&entry) {
+        return true;
+    }
+
+    let mut visited: HashSet<<x86_64 as Arch>::Address> = HashSet::new();
+    visited.insert(entry);
+    let mut worklist = vec![entry];
+
+    while let Some(start) = worklist.pop() {
+        let block = cfg.get_block(start);
+        let mut decoded_any = false;
+        let mut path_ends = false;
+
+        let mut iter = x86_64::instructions_spanning(data, block.start, block.end);
+        while let Some((addr, instr)) = iter.next() {
+            decoded_any = true;
+            match instr.opcode() {
+                Opcode::RETURN |
+                Opcode::RETF |
+                Opcode::IRET |
+                Opcode::IRETD |
+                Opcode::IRETQ => {
+                    return true;
+                }
+                Opcode::HLT |
+                Opcode::UD2 => {
+                    path_ends = true;
+                }
+                // a call to a noreturn function, or a tail call to one (which is how plt stubs
+                // end up looking).
+                Opcode::CALL |
+                Opcode::JMP if branch_target(addr, instr).map(|target| noreturn.contains(&target)).unwrap_or(false) => {
+                    path_ends = true;
+                }
+                _ => {}
+            }
+            if path_ends {
+                break;
+            }
+        }
+
+        if !decoded_any {
+            return true;
+        }
+        if path_ends {
+            continue;
+        }
+
+        let dests = cfg.destinations(block.start);
+        if dests.is_empty() {
+            return true;
+        }
+        for dest in dests.into_iter() {
+            if dest != entry && functions.contains(&dest) {
+                // control flow continues in another function, which returns for us if it returns
+                // at all.
+                if !noreturn.contains(&dest) {
+                    return true;
+                }
+            } else if visited.insert(dest) {
+                worklist.push(dest);
+            }
+        }
+    }
+
+    false
+}
+
+fn mark_noreturn(contexts: &MergedContextTable, addr: <x86_64 as Arch>::Address, name: &str) {
+    contexts.functions.borrow_mut()
+        .entry(addr)
+        .or_insert_with(|| FunctionImpl::new(name.to_string()))
+        .set_noreturn(true);
+}
+
+/// find functions in `cfg` that never return, and mark them noreturn in `contexts.functions`.
+///
+/// this starts from imports of `data`'s module and symbols in `contexts` with names from
+/// `analyses::noreturn::NORETURN_FUNCTIONS`, as well as any function already marked noreturn,
+/// then looks through every function in `cfg` for ones where no path returns. a function that
+/// can only end in `hlt`, `ud2`, or calls to noreturn functions is noreturn too, which may make
+/// more functions noreturn, so this repeats until nothing changes.
+///
+/// `cfg` itself is not changed. it still falls through after calls to functions found here, and
+/// probably includes some junk from doing so; rebuilding it with `stop_at_noreturn_calls` as an
+/// effect refinement gives a graph that stops there.
+pub fn infer_noreturn<M: MemoryRange<x86_64> + ?Sized>(
+    data: &M,
+    cfg: &ControlFlowGraph<<x86_64 as Arch>::Address>,
+    contexts: &mut MergedContextTable,
+) -> HashSet<<x86_64 as Arch>::Address> where x86_64: DecodeFrom<M> {
+    if let Some(module) = data.module_info() {
+        for (addr, name) in noreturn_imports(module).into_iter() {
+            mark_noreturn(contexts, addr, &name);
+        }
+    }
+    for (addr, Symbol(_, name)) in contexts.symbols.iter() {
+        if is_noreturn_name(name) {
+            mark_noreturn(contexts, *addr, name);
+        }
+    }
+
+    let (known, functions): (HashSet<<x86_64 as Arch>::Address>, HashSet<<x86_64 as Arch>::Address>) = {
+        let table = contexts.functions.borrow();
+        (
+            table.iter().filter(|(_, f)| f.is_noreturn()).map(|(addr, _)| *addr).collect(),
+            table.keys().cloned().collect(),
+        )
+    };
+
+    let mut candidates: Vec<<x86_64 as Arch>::Address> = functions.iter()
+        .filter(|addr| cfg.blocks.contains_key(*addr))
+        .cloned()
+        .collect();
+    candidates.sort();
+
+    let noreturn = propagate_noreturn(&candidates, known, |entry, noreturn| {
+        may_return(data, cfg, entry, &functions, noreturn)
+    });
+
+    let mut table = contexts.functions.borrow_mut();
+    for addr in noreturn.iter() {
+        if let Some(f) = table.get_mut(addr) {
+            f.set_noreturn(true);
+        }
+    }
+
+    noreturn
+}
+
+#[test]
+fn test_noreturn_inference() {
+    use analyses::control_flow;
+    use arch::{BaseUpdate, Function, Library};
+    use arch::x86_64::x86_64Data;
+    use ContextWrite;
+
+    let data: Vec<u8> = vec![
+        0xe8, 0x1b, 0x00, 0x00, 0x00,               // 0x00: call 0x20
+        0x48, 0x01, 0xc0,                           // 0x05: add rax, rax
+        0xc3,                                       // 0x08: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x09: padding
+        0xb8, 0x01, 0x00, 0x00, 0x00,               // 0x10: mov eax, 1
+        0x0f, 0x0b,                                 // 0x15: ud2
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x17: padding
+        0xcc, 0xcc,
+        0xe8, 0xeb, 0xff, 0xff, 0xff,               // 0x20: call 0x10
+        0xc3,                                       // 0x25: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x26: padding
+        0xcc, 0xcc, 0xcc,
+        0x85, 0xc0,                                 // 0x30: test eax, eax
+        0x74, 0x01,                                 // 0x32: jz 0x35
+        0xc3,                                       // 0x33: ret
+        0x0f, 0x0b,                                 // 0x35: ud2
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x37: padding
+        0xcc, 0xcc,
+        0xff, 0x15, 0x3a, 0x00, 0x00, 0x00,         // 0x40: call [rip + 0x3a]
+        0xc3,                                       // 0x46: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    for addr in [0x00u64, 0x10, 0x20, 0x30, 0x40].iter() {
+        x86_64_data.contexts.put(*addr, BaseUpdate::DefineFunction(Function::of(format!("fn_{:x}", addr), vec![], vec![])));
+    }
+    // 0x80 is where `exit` would be imported to.
+    x86_64_data.contexts.put(0x80, BaseUpdate::DefineSymbol(Symbol(Library::Name("libc.so.6".to_string()), "exit".to_string())));
+
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00, 0x10, 0x20, 0x30, 0x40])
+        .evaluate();
+    // without knowing better, the call at 0x00 falls through.
+    assert_eq!(cfg.get_block(0x05).start, 0x00);
+
+    let noreturn = infer_noreturn(&data, &cfg, &mut x86_64_data.contexts);
+    let expected: HashSet<u64> = [0x00, 0x10, 0x20, 0x40, 0x80].iter().cloned().collect();
+    assert_eq!(noreturn, expected);
+    {
+        let functions = x86_64_data.contexts.functions.borrow();
+        assert!(functions[&0x10].is_noreturn());
+        assert!(functions[&0x80].is_noreturn());
+        assert!(!functions[&0x30].is_noreturn());
+    }
+
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00])
+        .with_effect_refinement(stop_at_noreturn_calls)
+        .evaluate();
+    assert_eq!(cfg.get_block(0x00).end, 0x04);
+    assert_eq!(cfg.destinations(0x00), Vec::<u64>::new());
+    assert!(!cfg.graph.contains_node(0x05));
+}
@@ -0,0 +1,630 @@
+//! turning a path through a function into an SMT-LIB2 script, for questions like "can this branch
+//! be taken with `rax == 0`" that are for a solver to answer.
+//!
+//! every SSA value along the path is a constant in the script, defined by what the instruction
+//! writing it computes. general purpose registers and flags are 64-bit bitvectors, and memory is
+//! an array from 64-bit addresses to bytes. each conditional branch along the path is asserted
+//! to go to the next block of the path.
+//!
+//! memory is only modeled as one array if the SSA was built with all of memory as one location,
+//! as `NoDisambiguation` does.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+use petgraph;
+
+use yaxpeax_arch::{AddressBase, LengthedInstruction};
+use yaxpeax_x86::long_mode::{Arch as amd64};
+use yaxpeax_x86::long_mode::{register_class, Opcode, RegSpec};
+use yaxpeax_x86::x86_64;
+
+use analyses::{DFG, IndirectQuery, Value, ValueIndex};
+use analyses::control_flow::ControlFlowGraph;
+use analyses::liveness::phi_operand;
+use analyses::smt::{SmtScript, Sort, Term};
+use analyses::static_single_assignment::{DFGRef, DefSource, SSA};
+use arch::InstructionSpan;
+use arch::x86_64::analyses::data_flow::{Location, ANY};
+use arch::x86_64::semantic;
+use data::Direction;
+use memory::MemoryRange;
+
+/// where `reg` is in a general purpose register: the number of the full register, and the bit
+/// offset and width of `reg` in it.
+fn gpr_slice(reg: RegSpec) -> Option<(u8, i64, i64)> {
+    match reg.class() {
+        register_class::Q => Some((reg.num(), 0, 64)),
+        register_class::D => Some((reg.num(), 0, 32)),
+        register_class::W => Some((reg.num(), 0, 16)),
+        register_class::RB => Some((reg.num(), 0, 8)),
+        register_class::B if reg.num() < 4 => Some((reg.num(), 0, 8)),
+        // `ah`, `ch`, `dh`, and `bh`.
+        register_class::B => Some((reg.num() & 3, 8, 8)),
+        _ => None,
+    }
+}
+
+/// the `width` bits of `full` starting at bit `offset`.
+fn slice(full: &Rc<Term>, offset: i64, width: i64) -> Rc<Term> {
+    let shifted = if offset == 0 {
+        Rc::clone(full)
+    } else {
+        full.shr(&Rc::<Term>::from_const(offset))
+    };
+    if width >= 64 {
+        shifted
+    } else {
+        shifted.and(&Rc::<Term>::from_const((1 << width) - 1)).value()
+    }
+}
+
+fn sort(loc: &Location) -> Sort {
+    match loc {
+        Location::Memory(_) |
+        Location::MemoryLocation(..) => Sort::Memory,
+        _ => Sort::BitVec,
+    }
+}
+
+/// the name of `value` in a script, before telling apart different times it's defined.
+fn base_name(ssa: &SSA<amd64>, value: &DFGRef<amd64>) -> String {
+    let value = ssa.value(*value);
+    let location = match value.location {
+        Location::Memory(_) |
+        Location::MemoryLocation(..) => "mem".to_string(),
+        ref other => other.to_string(),
+    };
+    match value.version {
+        Some(version) => format!("{}_{}", location, version),
+        None => format!("{}_input", location),
+    }
+}
+
+/// `term`, with each term in `defined` replaced by the name it was defined as.
+fn named(term: &Rc<Term>, defined: &[(Rc<Term>, String)]) -> Rc<Term> {
+    if let Some((_, name)) = defined.iter().find(|(other, _)| Rc::ptr_eq(other, term)) {
+        return Term::symbol(name.clone());
+    }
+    match &**term {
+        Term::Apply(op, args) => {
+            Rc::new(Term::Apply(op.clone(), args.iter().map(|arg| named(arg, defined)).collect()))
+        }
+        _ => Rc::clone(term),
+    }
+}
+
+/// the `Bool`-sorted `condition`, negated.
+fn negated(condition: &Rc<Term>) -> Rc<Term> {
+    match &**condition {
+        Term::Apply(op, args) if op == "distinct" && args.len() == 2 => {
+            Term::apply("=", &[&args[0], &args[1]])
+        }
+        Term::Apply(op, args) if op == "not" && args.len() == 1 => Rc::clone(&args[0]),
+        _ => Term::apply("not", &[condition]),
+    }
+}
+
+/// the script so far, and what its constants are for.
+#[derive(Default)]
+struct PathState {
+    script: SmtScript,
+    /// the constant for each SSA value, as of its latest definition along the path.
+    names: HashMap<DFGRef<amd64>, String>,
+    /// how many times each name has been used. a block visited twice defines its values again,
+    /// under new names.
+    defined: HashMap<String, usize>,
+    /// values written by the instruction being evaluated, to be defined once it's done.
+    pending: Vec<(DFGRef<amd64>, Rc<Term>)>,
+    /// where the instruction being evaluated goes next, if it writes `rip`.
+    rip: Option<Rc<Term>>,
+}
+
+impl PathState {
+    fn fresh_name(&mut self, ssa: &SSA<amd64>, value: &DFGRef<amd64>) -> String {
+        let base = base_name(ssa, value);
+        let count = self.defined.entry(base.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 {
+            base
+        } else {
+            format!("{}~{}", base, count)
+        }
+    }
+
+    /// the term for `value` as of here. values not defined along the path so far are new
+    /// constants, except that parts of an input register are still parts of that register.
+    fn term_of(&mut self, ssa: &SSA<amd64>, value: &DFGRef<amd64>) -> Rc<Term> {
+        let key = *value;
+        if let Some(name) = self.names.get(&key) {
+            return Term::symbol(name.clone());
+        }
+
+        let location = ssa.value(*value).location.clone();
+        let is_input = match ssa.try_get_def_site(*value) {
+            Some((_, DefSource::External)) | None => true,
+            _ => false,
+        };
+        if is_input {
+            if let Location::Register(reg) = location {
+                if let Some((num, offset, width)) = gpr_slice(reg) {
+                    let full = ssa.external_defs.get(&Location::Register(RegSpec::q(num))).cloned();
+                    if let Some(full) = full.filter(|_| width < 64) {
+                        let full = self.term_of(ssa, &full);
+                        self.define(ssa, value, &slice(&full, offset, width));
+                        return Term::symbol(self.names[&key].clone());
+                    }
+                }
+            }
+        }
+
+        let name = self.fresh_name(ssa, value);
+        self.script.declare(&name, sort(&location));
+        self.names.insert(key, name.clone());
+        Term::symbol(name)
+    }
+
+    fn define(&mut self, ssa: &SSA<amd64>, value: &DFGRef<amd64>, term: &Rc<Term>) -> String {
+        let name = self.fresh_name(ssa, value);
+        let sort = sort(&ssa.value(*value).location);
+        if **term == Term::Unknown {
+            self.script.declare(&name, sort);
+        } else {
+            self.script.define(&name, sort, term);
+        }
+        self.names.insert(*value, name.clone());
+        name
+    }
+
+    /// `value` was defined again, but not to anything in particular.
+    fn forget(&mut self, value: &DFGRef<amd64>) {
+        self.names.remove(value);
+    }
+
+    fn pending(&self, value: &DFGRef<amd64>) -> Option<Rc<Term>> {
+        self.pending.iter()
+            .find(|(def, _)| def == value)
+            .map(|(_, term)| Rc::clone(term))
+    }
+
+    fn set_pending(&mut self, value: DFGRef<amd64>, term: Rc<Term>) {
+        match self.pending.iter_mut().find(|(def, _)| def == &value) {
+            Some(entry) => { entry.1 = term; }
+            None => { self.pending.push((value, term)); }
+        }
+    }
+
+    /// define everything the instruction at `when` wrote.
+    fn finish(&mut self, ssa: &SSA<amd64>, when: u64) {
+        let pending = std::mem::take(&mut self.pending);
+        if let Some(rwmap) = ssa.instruction_values.get(&when) {
+            for ((_, dir), value) in rwmap.iter() {
+                if *dir == Direction::Write && !pending.iter().any(|(def, _)| def == value) {
+                    self.forget(value);
+                }
+            }
+        }
+        // registers first, so flags and memory computed from the same terms can name them.
+        let mut pending = pending;
+        pending.sort_by_key(|(value, _)| match ssa.value(*value).location {
+            Location::Register(_) => 0,
+            _ => 1,
+        });
+        let mut defined: Vec<(Rc<Term>, String)> = Vec::new();
+        for (value, term) in pending.iter() {
+            let name = self.define(ssa, value, &named(term, &defined));
+            defined.push((Rc::clone(term), name));
+        }
+    }
+
+    /// assert that the branch just evaluated goes to `next`.
+    fn take_branch(&mut self, next: u64) {
+        let rip = match self.rip.take() {
+            Some(rip) => rip,
+            None => { return; }
+        };
+        if rip.to_const() == Some(next as i64) {
+            return;
+        }
+
+        let mut condition = None;
+        if let Term::Apply(op, args) = &*rip {
+            if op == "ite" && args.len() == 3 {
+                let next = Some(next as i64);
+                let (taken, not_taken) = (args[1].to_const(), args[2].to_const());
+                if taken == next && not_taken != next {
+                    condition = Some(Rc::clone(&args[0]));
+                } else if not_taken == next && taken != next {
+                    condition = Some(negated(&args[0]));
+                }
+            }
+        }
+        let condition = condition.unwrap_or_else(|| {
+            Value::eq(&rip, &Rc::<Term>::from_const(next as i64)).truth(true)
+        });
+        self.script.assert(&condition);
+    }
+}
+
+/// an `SSA` evaluated along a path, one instruction at a time.
+struct PathEvaluation<'ssa> {
+    ssa: &'ssa SSA<amd64>,
+    state: Rc<RefCell<PathState>>,
+    next: u64,
+}
+
+impl<'ssa> PathEvaluation<'ssa> {
+    fn write_def(&mut self, when: u64, loc: Location, value: Rc<Term>) {
+        if let Some(def) = self.ssa.try_get_def(when, loc) {
+            self.state.borrow_mut().set_pending(def, value);
+        }
+    }
+
+    /// a write to part of general purpose register `num` changes every register that overlaps
+    /// it, so the whole register is computed first, and each overlapping register is a slice of
+    /// it.
+    fn write_gpr(&mut self, when: u64, num: u8, offset: i64, width: i64, value: Rc<Term>) {
+        let full = match width {
+            64 => value,
+            // writes to 32-bit registers clear the upper half, narrower writes leave it alone.
+            32 => value.and(&Rc::<Term>::from_const(0xffff_ffff)).value(),
+            _ => {
+                let mask = ((1i64 << width) - 1) << offset;
+                let old = self.read_loc(when, Location::Register(RegSpec::q(num)));
+                let shifted = if offset == 0 {
+                    value
+                } else {
+                    value.shl(&Rc::<Term>::from_const(offset))
+                };
+                let kept = old.and(&Rc::<Term>::from_const(!mask)).value();
+                kept.or(&shifted.and(&Rc::<Term>::from_const(mask)).value()).value()
+            }
+        };
+
+        let mut overlapping: Vec<(i64, i64, DFGRef<amd64>)> = Vec::new();
+        if let Some(rwmap) = self.ssa.instruction_values.get(&when) {
+            for ((loc, dir), def) in rwmap.iter() {
+                if let (Location::Register(reg), Direction::Write) = (loc, dir) {
+                    match gpr_slice(*reg) {
+                        Some((n, offset, width)) if n == num => {
+                            overlapping.push((offset, width, *def));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        // widest first, so definitions come out in the same order every time.
+        overlapping.sort_by_key(|(offset, width, _)| (-width, *offset));
+        let mut state = self.state.borrow_mut();
+        for (offset, width, def) in overlapping.into_iter() {
+            state.set_pending(def, slice(&full, offset, width));
+        }
+    }
+}
+
+impl<'ssa> DFG<Rc<Term>, amd64, u64> for PathEvaluation<'ssa> {
+    type Indirect = PathMemory<'ssa>;
+
+    fn read_loc(&self, when: u64, loc: Location) -> Rc<Term> {
+        if loc == Location::RIP {
+            return Rc::<Term>::from_const(self.next as i64);
+        }
+        // a write earlier in the same instruction is what later reads see.
+        if let Some(def) = self.ssa.try_get_def(when, loc.clone()) {
+            if let Some(term) = self.state.borrow().pending(&def) {
+                return term;
+            }
+        }
+        match self.ssa.try_get_use(when, loc) {
+            Some(value) => self.state.borrow_mut().term_of(self.ssa, &value),
+            None => Rc::<Term>::unknown(),
+        }
+    }
+
+    fn write_loc(&mut self, when: u64, loc: Location, value: Rc<Term>) {
+        match loc {
+            Location::RIP => {
+                self.state.borrow_mut().rip = Some(value);
+            }
+            Location::Register(reg) => match gpr_slice(reg) {
+                Some((num, offset, width)) => self.write_gpr(when, num, offset, width, value),
+                None => self.write_def(when, loc, value),
+            },
+            other => self.write_def(when, other, value),
+        }
+    }
+
+    fn indirect_loc(&self, when: u64, _loc: Location) -> PathMemory<'ssa> {
+        PathMemory {
+            ssa: self.ssa,
+            state: Rc::clone(&self.state),
+            when,
+        }
+    }
+}
+
+/// memory at one instruction, as a byte array.
+struct PathMemory<'ssa> {
+    ssa: &'ssa SSA<amd64>,
+    state: Rc<RefCell<PathState>>,
+    when: u64,
+}
+
+impl<'ssa> PathMemory<'ssa> {
+    fn def(&self) -> Option<DFGRef<amd64>> {
+        self.ssa.try_get_def(self.when, Location::Memory(ANY))
+    }
+
+    fn current(&self) -> Rc<Term> {
+        let mut state = self.state.borrow_mut();
+        if let Some(term) = self.def().and_then(|def| state.pending(&def)) {
+            return term;
+        }
+        match self.ssa.try_get_use(self.when, Location::Memory(ANY)) {
+            Some(value) => state.term_of(self.ssa, &value),
+            None => Term::symbol(state.script.fresh(Sort::Memory)),
+        }
+    }
+}
+
+fn byte_address(base: &Rc<Term>, i: usize) -> Rc<Term> {
+    if i == 0 {
+        Rc::clone(base)
+    } else {
+        base.add(&Rc::<Term>::from_const(i as i64)).value()
+    }
+}
+
+impl<'ssa> IndirectQuery<Rc<Term>> for PathMemory<'ssa> {
+    fn load(&self, index: ValueIndex<Rc<Term>>) -> Rc<Term> {
+        if index.size == 0 || index.size > 8 {
+            return Rc::<Term>::unknown();
+        }
+        let memory = self.current();
+        // little-endian, so each byte goes above the ones before it.
+        let mut loaded: Option<Rc<Term>> = None;
+        for i in 0..index.size {
+            let byte = Term::apply("select", &[&memory, &byte_address(index.base, i)]);
+            loaded = Some(match loaded {
+                Some(low) => Term::apply("concat", &[&byte, &low]),
+                None => byte,
+            });
+        }
+        let loaded = loaded.expect("at least one byte is loaded");
+        if index.size == 8 {
+            loaded
+        } else {
+            Term::apply(&format!("(_ zero_extend {})", 64 - index.size * 8), &[&loaded])
+        }
+    }
+
+    fn store(&self, index: ValueIndex<Rc<Term>>, value: &Rc<Term>) {
+        let def = match self.def() {
+            Some(def) => def,
+            None => { return; }
+        };
+        let stored = if index.size == 0 || index.size > 8 {
+            Term::symbol(self.state.borrow_mut().script.fresh(Sort::Memory))
+        } else {
+            let mut memory = self.current();
+            for i in 0..index.size {
+                let byte = Term::apply(&format!("(_ extract {} {})", i * 8 + 7, i * 8), &[value]);
+                memory = Term::apply("store", &[&memory, &byte_address(index.base, i), &byte]);
+            }
+            memory
+        };
+        self.state.borrow_mut().set_pending(def, stored);
+    }
+
+    fn try_get_load(&self, _index: ValueIndex<Rc<Term>>) -> Option<Rc<Term>> {
+        None
+    }
+
+    fn try_get_store(&self, _index: ValueIndex<Rc<Term>>) -> Option<()> {
+        None
+    }
+}
+
+/// an SMT-LIB2 script that is satisfiable if a path through a function can be taken.
+pub struct PathFormula<'ssa> {
+    ssa: &'ssa SSA<amd64>,
+    state: PathState,
+}
+
+impl<'ssa> PathFormula<'ssa> {
+    /// the term for `value` as of the end of the path, to build more assertions with. values
+    /// the path doesn't define are unconstrained, like function inputs.
+    pub fn value(&mut self, value: &DFGRef<amd64>) -> Rc<Term> {
+        self.state.term_of(self.ssa, value)
+    }
+
+    /// also assert that `condition` is nonzero.
+    pub fn assume(&mut self, condition: &Rc<Term>) {
+        self.state.script.assert(&condition.truth(true));
+    }
+
+    pub fn script(&self) -> &SmtScript {
+        &self.state.script
+    }
+}
+
+impl<'ssa> fmt::Display for PathFormula<'ssa> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.state.script)
+    }
+}
+
+/// define the values that `block` starts with when entered from `pred`: those that take a new
+/// version along the edge, and then `block`'s phis.
+fn enter_block(
+    cfg: &ControlFlowGraph<u64>,
+    ssa: &SSA<amd64>,
+    dominators: &petgraph::algo::dominators::Dominators<u64>,
+    state: &mut PathState,
+    pred: u64,
+    block: u64,
+) {
+    let mut entries: Vec<(String, DFGRef<amd64>, Option<DFGRef<amd64>>)> = Vec::new();
+    if let Some(rwmap) = ssa.control_dependent_values.get(&pred).and_then(|to| to.get(&block)) {
+        for ((loc, dir), value) in rwmap.iter() {
+            if *dir == Direction::Write {
+                let read = rwmap.get(&(loc.clone(), Direction::Read)).cloned();
+                entries.push((loc.to_string(), *value, read));
+            }
+        }
+    }
+    entries.sort_by(|l, r| l.0.cmp(&r.0));
+    define_entries(ssa, state, entries);
+
+    let mut entries = Vec::new();
+    if let Some(phis) = ssa.phi.get(&block) {
+        for (loc, phi) in phis.iter() {
+            let operand = phi_operand(cfg, ssa, dominators, phi, pred).cloned();
+            entries.push((loc.to_string(), phi.out, operand));
+        }
+    }
+    entries.sort_by(|l, r| l.0.cmp(&r.0));
+    define_entries(ssa, state, entries);
+}
+
+/// define each value from its source, all at once: sources are read before any of the values
+/// are defined.
+fn define_entries(ssa: &SSA<amd64>, state: &mut PathState, entries: Vec<(String, DFGRef<amd64>, Option<DFGRef<amd64>>)>) {
+    let terms: Vec<Option<Rc<Term>>> = entries.iter()
+        .map(|(_, _, source)| source.as_ref().map(|source| state.term_of(ssa, source)))
+        .collect();
+    for ((_, value, _), term) in entries.iter().zip(terms) {
+        match term {
+            Some(term) => { state.define(ssa, value, &term); }
+            None => state.forget(value),
+        }
+    }
+}
+
+/// write out the path through `cfg` visiting each block in `path`, in order, as an SMT-LIB2
+/// script. `ssa` should have been built with `NoDisambiguation`, so all of memory is one array.
+///
+/// errors if a block on the path is not a block of `cfg`, or isn't a successor of the block
+/// before it.
+pub fn export_path<'ssa, M: MemoryRange<amd64>>(
+    data: &M,
+    cfg: &ControlFlowGraph<u64>,
+    ssa: &'ssa SSA<amd64>,
+    path: &[u64],
+) -> Result<PathFormula<'ssa>, String> {
+    let dominators = petgraph::algo::dominators::simple_fast(&cfg.graph, cfg.entrypoint);
+    let state = Rc::new(RefCell::new(PathState::default()));
+
+    for (i, start) in path.iter().enumerate() {
+        if !cfg.graph.contains_node(*start) {
+            return Err(format!("{:#x} is not the start of a basic block", start));
+        }
+        state.borrow_mut().script.comment(&format!("block {:#x}", start));
+        if i > 0 {
+            let pred = path[i - 1];
+            if !cfg.graph.contains_edge(pred, *start) {
+                return Err(format!("{:#x} does not follow {:#x}", start, pred));
+            }
+            enter_block(cfg, ssa, &dominators, &mut state.borrow_mut(), pred, *start);
+        }
+
+        let block = cfg.get_block(*start);
+        let mut last = None;
+        let mut iter = x86_64::instructions_spanning(data, block.start, block.end);
+        while let Some((addr, instr)) = iter.next() {
+            state.borrow_mut().rip = None;
+            let mut evaluation = PathEvaluation {
+                ssa,
+                state: Rc::clone(&state),
+                next: addr.wrapping_offset(instr.len()),
+            };
+            semantic::evaluate(addr, instr, &mut evaluation);
+            state.borrow_mut().finish(ssa, addr);
+            last = Some(instr.opcode());
+        }
+
+        // calls come back to the next block, wherever they go in the meantime.
+        if let (Some(next), false) = (path.get(i + 1), last == Some(Opcode::CALL)) {
+            state.borrow_mut().take_branch(*next);
+        }
+    }
+
+    let state = match Rc::try_unwrap(state) {
+        Ok(state) => state.into_inner(),
+        Err(_) => unreachable!("evaluations are done with the path state"),
+    };
+    Ok(PathFormula { ssa, state })
+}
+
+#[test]
+fn test_export_path() {
+    use analyses::control_flow;
+    use analyses::data_flow;
+    use arch::x86_64::x86_64Data;
+    use arch::x86_64::analyses::data_flow::NoDisambiguation;
+
+    let data: Vec<u8> = vec![
+        0x48, 0x89, 0x7c, 0x24, 0xf8,                               // 0x00: mov [rsp - 0x8], rdi
+        0x48, 0x85, 0xff,                                           // 0x05: test rdi, rdi
+        0x74, 0x09,                                                 // 0x08: je 0x13
+        0x48, 0x8b, 0x44, 0x24, 0xf8,                               // 0x0a: mov rax, [rsp - 0x8]
+        0x83, 0xc0, 0x01,                                           // 0x0f: add eax, 1
+        0xc3,                                                       // 0x12: ret
+        0x31, 0xc0,                                                 // 0x13: xor eax, eax
+        0xc3,                                                       // 0x15: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .evaluate();
+    let ssa = data_flow::AnalysisBuilder::new(
+        &data,
+        &cfg,
+        &*x86_64_data.contexts.functions.borrow(),
+        &mut NoDisambiguation::default(),
+    ).ssa_cytron();
+
+    assert!(export_path(&data, &cfg, &ssa, &[0x00, 0x00]).is_err());
+
+    let mut formula = export_path(&data, &cfg, &ssa, &[0x00, 0x0a]).unwrap();
+    // can `eax` be zero after the increment?
+    let rax = formula.value(&ssa.get_def(0x0f, Location::rax()).as_rc());
+    formula.assume(&Value::eq(&rax, &Rc::<Term>::from_const(0)));
+    let text = formula.to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    let expected = [
+        "(set-logic QF_ABV)",
+        "; block 0x0",
+        "(declare-const |rdi_input| (_ BitVec 64))",
+        "(declare-const |rsp_input| (_ BitVec 64))",
+        "(declare-const |mem_input| (Array (_ BitVec 64) (_ BitVec 8)))",
+        // `je` is not taken..
+        "(assert (= |zf_0| #x0000000000000000))",
+        "; block 0xa",
+        // .. and the low half of `rax` is all `add` reads and writes.
+        "(define-fun |eax_0| () (_ BitVec 64) (bvand |rax_0| #x00000000ffffffff))",
+        "(define-fun |rax_1| () (_ BitVec 64) (bvand (bvadd |eax_0| #x0000000000000001) #x00000000ffffffff))",
+        "(define-fun |rsp_0| () (_ BitVec 64) (bvadd |rsp_input| #x0000000000000008))",
+        "(assert (= |rax_1| #x0000000000000000))",
+        "(check-sat)",
+    ];
+    let mut remaining = lines.iter();
+    for line in expected.iter() {
+        assert!(remaining.any(|l| l == line), "missing or out of order: {}\n{}", line, text);
+    }
+    assert!(text.contains(
+        "(define-fun |zf_0| () (_ BitVec 64) (ite (= (bvand |rdi_input| |rdi_input|) #x0000000000000000) #x0000000000000001 #x0000000000000000))"
+    ));
+    // `rdi` is stored a byte at a time, and loaded the same way.
+    assert!(text.contains(
+        "(store |mem_input| (bvadd |rsp_input| #xfffffffffffffff8) ((_ extract 7 0) |rdi_input|))"
+    ));
+    assert!(text.contains("(select |mem_0| (bvadd |rsp_input| #xfffffffffffffff8))"));
+
+    // the other way, the branch is taken.
+    let taken = export_path(&data, &cfg, &ssa, &[0x00, 0x13]).unwrap().to_string();
+    assert!(taken.contains("(assert (distinct |zf_0| #x0000000000000000))"));
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use yaxpeax_arch::{AddressBase, AddressDiff, Arch, LengthedInstruction};
+use yaxpeax_x86::long_mode::{Instruction, Opcode, Operand};
+use yaxpeax_x86::x86_64;
+
+use analyses::control_flow::ControlFlowGraph;
+use analyses::stack_pointer::{stack_deltas, StackCleanup, StackDelta, StackDeltas};
+use arch::{DecodeFrom, InstructionSpan};
+use arch::x86_64::MergedContextTable;
+use memory::MemoryRange;
+
+fn jump_target(addr: <x86_64 as Arch>::Address, instr: &Instruction) -> Option<<x86_64 as Arch>::Address> {
+    if instr.opcode() != Opcode::JMP {
+        return None;
+    }
+    let next = addr.wrapping_offset(instr.len());
+    match instr.operand(0) {
+        Operand::ImmediateI8(rel) => Some(next.wrapping_offset(AddressDiff::from_const(rel as i64 as u64))),
+        Operand::ImmediateI32(rel) => Some(next.wrapping_offset(AddressDiff::from_const(rel as i64 as u64))),
+        _ => None,
+    }
+}
+
+/// tail calls out of the function at `entry`, as `(address of the jmp, callee)`.
+fn tail_calls_in<M: MemoryRange<x86_64>, C: StackCleanup<<x86_64 as Arch>::Address> + ?Sized>(
+    data: &M,
+    cfg: &ControlFlowGraph<<x86_64 as Arch>::Address>,
+    entry: <x86_64 as Arch>::Address,
+    starts: &HashMap<<x86_64 as Arch>::Address, ()>,
+    cleanup: &C,
+) -> Vec<(<x86_64 as Arch>::Address, <x86_64 as Arch>::Address)> where x86_64: DecodeFrom<M> {
+    // first find the stack height at each instruction in the function, without walking into
+    // other functions.
+    let function = cfg.get_function(entry, starts);
+    let deltas: StackDeltas<x86_64> = stack_deltas(data, &function, cleanup);
+
+    // then look for jumps to other functions with the stack back where it was at entry.
+    let mut candidates: Vec<(<x86_64 as Arch>::Address, <x86_64 as Arch>::Address, <x86_64 as Arch>::Address)> = Vec::new();
+    for block in function.blocks.values() {
+        let mut iter = x86_64::instructions_spanning(data, block.start, block.end);
+        while let Some((addr, instr)) = iter.next() {
+            if let Some(target) = jump_target(addr, instr) {
+                if target != entry && starts.contains_key(&target) && deltas.at(addr) == Some(StackDelta::Known(0)) {
+                    candidates.push((block.start, addr, target));
+                }
+            }
+        }
+    }
+
+    // and finally, a callee that's also reached some other way from inside the function (falling
+    // through into it, a conditional branch, a jump with the stack elsewhere) is probably a
+    // misplaced function start rather than a separate function. leave those alone.
+    let mut result = Vec::new();
+    for (_, at, target) in candidates.iter() {
+        let reached_otherwise = function.blocks.keys().any(|block| {
+            cfg.destinations(*block).contains(target) &&
+                !candidates.iter().any(|(from, _, other)| from == block && other == target)
+        });
+        if !reached_otherwise {
+            result.push((*at, *target));
+        }
+    }
+    result.sort();
+    result
+}
+
+/// find jumps in `cfg` that are tail calls, and mark them as such. a `jmp` is a tail call when it
+/// goes to the start of a known or hinted function other than the one it's in, the stack pointer
+/// is back at its height from function entry, and nothing else in the function reaches the
+/// destination.
+///
+/// this returns the tail calls found, as `(address of the jmp, callee)`.
+pub fn find_tail_calls<M: MemoryRange<x86_64>>(
+    data: &M,
+    cfg: &mut ControlFlowGraph<<x86_64 as Arch>::Address>,
+    contexts: &MergedContextTable,
+) -> Vec<(<x86_64 as Arch>::Address, <x86_64 as Arch>::Address)> where x86_64: DecodeFrom<M> {
+    let functions = contexts.functions.borrow();
+    let mut starts: HashMap<<x86_64 as Arch>::Address, ()> = functions.keys().map(|start| (*start, ())).collect();
+    starts.extend(contexts.function_hints.iter().map(|start| (*start, ())));
+
+    let mut entries: Vec<<x86_64 as Arch>::Address> = starts.keys()
+        .filter(|start| cfg.blocks.contains_key(*start))
+        .cloned()
+        .collect();
+    entries.sort();
+
+    let mut found = Vec::new();
+    for entry in entries.into_iter() {
+        found.extend(tail_calls_in(data, cfg, entry, &starts, &*functions));
+    }
+
+    for (at, callee) in found.iter() {
+        cfg.mark_tail_call(*at, *callee);
+    }
+
+    found
+}
+
+#[test]
+fn test_tail_calls() {
+    use analyses::control_flow;
+    use analyses::control_flow::EdgeKind;
+    use arch::{BaseUpdate, Function};
+    use arch::x86_64::{x86_64Data, x86Update};
+    use ContextWrite;
+
+    let data: Vec<u8> = vec![
+        0x53,                                       // 0x00: push rbx
+        0x85, 0xff,                                 // 0x01: test edi, edi
+        0x74, 0x03,                                 // 0x03: jz 0x08
+        0x5b,                                       // 0x05: pop rbx
+        0xeb, 0x18,                                 // 0x06: jmp 0x20
+        0x85, 0xf6,                                 // 0x08: test esi, esi
+        0x74, 0x02,                                 // 0x0a: jz 0x0e
+        0xeb, 0x22,                                 // 0x0c: jmp 0x30
+        0x5b,                                       // 0x0e: pop rbx
+        0xc3,                                       // 0x0f: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x10: padding
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+        0xcc, 0xcc,
+        0x31, 0xc0,                                 // 0x20: xor eax, eax
+        0xc3,                                       // 0x22: ret
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,   // 0x23: padding
+        0xcc, 0xcc, 0xcc, 0xcc, 0xcc, 0xcc,
+        0x85, 0xff,                                 // 0x30: test edi, edi
+        0x74, 0x02,                                 // 0x32: jz 0x36
+        0xeb, 0x02,                                 // 0x34: jmp 0x38
+        0x31, 0xc0,                                 // 0x36: xor eax, eax
+        0xc3,                                       // 0x38: ret
+    ];
+
+    let mut x86_64_data = x86_64Data::default();
+    for addr in [0x00u64, 0x30].iter() {
+        x86_64_data.contexts.put(*addr, BaseUpdate::DefineFunction(Function::of(format!("fn_{:x}", addr), vec![], vec![])));
+    }
+    // 0x20 is only hinted, and 0x38 is hinted but is really just the end of 0x30.
+    x86_64_data.contexts.put(0x20, BaseUpdate::Specialized(x86Update::FunctionHint));
+    x86_64_data.contexts.put(0x38, BaseUpdate::Specialized(x86Update::FunctionHint));
+
+    let mut cfg = control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00, 0x20, 0x30])
+        .evaluate();
+    {
+        let functions = x86_64_data.contexts.functions.borrow();
+        assert!(cfg.get_function(0x00, &*functions).blocks.contains_key(&0x20));
+    }
+
+    let tail_calls = find_tail_calls(&data, &mut cfg, &x86_64_data.contexts);
+    // the jmp at 0x0c has rbx still pushed, so it's not a tail call. the jmp at 0x34 goes to a
+    // block 0x30 also falls into.
+    assert_eq!(tail_calls, vec![(0x06, 0x20)]);
+    assert_eq!(cfg.edge_kind(0x05, 0x20), Some(EdgeKind::TailCall));
+    assert_eq!(cfg.edge_kind(0x0c, 0x30), Some(EdgeKind::Branch));
+    assert_eq!(cfg.edge_kind(0x34, 0x38), Some(EdgeKind::Branch));
+
+    {
+        let functions = x86_64_data.contexts.functions.borrow();
+        let function = cfg.get_function(0x00, &*functions);
+        assert!(!function.blocks.contains_key(&0x20));
+        assert_eq!(function.tail_calls_from(0x05), vec![0x20]);
+    }
+
+    // exploring the jump again doesn't glue it back in.
+    control_flow::AnalysisBuilder::new(&data, &mut x86_64_data.contexts)
+        .with_entrypoints(vec![0x00])
+        .evaluate_into(&mut cfg);
+    assert_eq!(cfg.edge_kind(0x05, 0x20), Some(EdgeKind::TailCall));
+}
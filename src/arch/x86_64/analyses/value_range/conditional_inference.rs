@@ -207,8 +207,13 @@ impl ConditionalBoundInference<x86_64, InstructionModifiers<x86_64>> for Conditi
                                 _ => { return false; } // TODO: support non-immediate sources
                             };
 
-                            aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Above(imm_src));
-                            aux_data.add_edge_modifier(curr_block, negated_bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Below(imm_src));
+                            // `SignedAbove` and `SignedBelow` are inclusive, and `g` is a strict
+                            // comparison.
+                            let imm_src = imm_src as i64;
+                            if let Some(above) = imm_src.checked_add(1) {
+                                aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::SignedAbove(above));
+                            }
+                            aux_data.add_edge_modifier(curr_block, negated_bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::SignedBelow(imm_src));
                             true
                         }
                         Opcode::TEST => {
@@ -250,8 +255,13 @@ impl ConditionalBoundInference<x86_64, InstructionModifiers<x86_64>> for Conditi
                                 _ => { return false; } // TODO: support non-immediate sources
                             };
 
-                            aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Below(imm_src));
-                            aux_data.add_edge_modifier(curr_block, negated_bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Above(imm_src));
+                            // `SignedAbove` and `SignedBelow` are inclusive, and `l` is a strict
+                            // comparison.
+                            let imm_src = imm_src as i64;
+                            if let Some(below) = imm_src.checked_sub(1) {
+                                aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::SignedBelow(below));
+                            }
+                            aux_data.add_edge_modifier(curr_block, negated_bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::SignedAbove(imm_src));
                             true
                         }
                         Opcode::TEST => {
@@ -294,7 +304,10 @@ impl ConditionalBoundInference<x86_64, InstructionModifiers<x86_64>> for Conditi
                                 _ => { return false; } // TODO: support non-immediate sources
                             };
 
-                            aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Above(imm_src));
+                            // `Above` and `Below` are inclusive, and `a` is a strict comparison.
+                            if let Some(above) = imm_src.checked_add(1) {
+                                aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Above(above));
+                            }
                             eprintln!("adding negated bound dest, between blocks {:#x} and {:#x}, {} is {:?}", curr_block, negated_bound_dest, dest_reg, ModifierExpression::Below(imm_src));
                             aux_data.add_edge_modifier(curr_block, negated_bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Below(imm_src));
                             true
@@ -339,7 +352,10 @@ impl ConditionalBoundInference<x86_64, InstructionModifiers<x86_64>> for Conditi
                                 _ => { return false; } // TODO: support non-immediate sources
                             };
 
-                            aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Below(imm_src));
+                            // `Above` and `Below` are inclusive, and `b` is a strict comparison.
+                            if let Some(below) = imm_src.checked_sub(1) {
+                                aux_data.add_edge_modifier(curr_block, bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Below(below));
+                            }
                             aux_data.add_edge_modifier(curr_block, negated_bound_dest, Some(Location::Register(dest_reg)), ModifierExpression::Above(imm_src));
                             true
                         }
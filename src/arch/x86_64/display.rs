@@ -1,5 +1,4 @@
 use std::fmt::Display;
-use std::rc::Rc;
 
 use termion::color;
 
@@ -24,7 +23,7 @@ use yaxpeax_x86::long_mode::{Instruction, Opcode, Operand};
 use yaxpeax_x86::long_mode::{RegSpec, Segment};
 use yaxpeax_x86::x86_64 as x86_64Arch;
 use analyses::control_flow::{BasicBlock, ControlFlowGraph};
-use analyses::static_single_assignment::{DFGRef, SSA};
+use analyses::static_single_assignment::{SSA, Value};
 use data::Direction;
 use data::types::{TypeAtlas, TypeSpec};
 use display::location::{LocationHighlighter, NoHighlights, StyledDisplay};
@@ -108,7 +107,7 @@ impl <'a, 'b, 'c, 'd, 'e, Context: AddressNamer<<x86_64Arch as Arch>::Address>,
     ) -> impl fmt::Display {
         let text = self.ssa.map(|ssa| {
             let num = ssa.get_value(self.addr, Location::Register(reg), direction)
-                .map(|data| data.borrow().version());
+                .map(|value| ssa.value(value).version());
             format!("{}_{}",
                 reg,
                 num.map(|n| n.map(|v| v.to_string()).unwrap_or("input".to_string())).unwrap_or_else(|| {
@@ -123,7 +122,7 @@ impl <'a, 'b, 'c, 'd, 'e, Context: AddressNamer<<x86_64Arch as Arch>::Address>,
 
 pub struct RegValueDisplay<'a, 'b, 'c> {
     pub reg: &'a RegSpec,
-    pub value: &'b Option<DFGRef<x86_64Arch>>,
+    pub value: &'b Option<&'b Value<x86_64Arch>>,
     pub colors: Option<&'c ColorSettings>,
 }
 
@@ -138,13 +137,13 @@ impl <'a, 'b, 'c> fmt::Display for RegValueDisplay<'a, 'b, 'c> {
                         format!(
                             "{}_{}",
                             self.reg,
-                            value.borrow().version()
+                            value.version()
                                 .map(|ver| ver.to_string())
                                 .unwrap_or_else(|| "input".to_string())
                         )
                     )
                 )?;
-                if let Some(data) = value.borrow().data.as_ref() {
+                if let Some(data) = value.data.as_ref() {
                     write!(fmt, " (= {})", data.display(false, self.colors))?;
                 }
                 Ok(())
@@ -216,21 +215,8 @@ impl <'a, 'b> fmt::Display for DataDisplay<'a, 'b> {
         let _type_atlas = TypeAtlas::new();
         match self.data {
             Data::Alias(alias) => {
-                if let Location::Register(alias_reg) = alias.borrow().location {
-                    write!(fmt, "{}", RegValueDisplay {
-                        reg: &alias_reg,
-                        value: &Some(Rc::clone(alias)),
-                        colors: self.colors
-                    })?;
-                } else if let Location::MemoryLocation(region, size, Some((base, addend))) = &alias.borrow().location {
-                    if let Some(data) = &alias.borrow().data {
-                        write!(fmt, "{}[{} + {}, {}] (= {})", region, base, addend, size, data.display(false, self.colors))?;
-                    } else {
-                        write!(fmt, "{}[{} + {}, {}] (= unknown)", region, base, addend, size)?;
-                    }
-                } else {
-                    unreachable!("impossible register alias, either not a register or not bounded memory");
-                }
+                // what `alias` refers to is in an `SSA` this doesn't have, so name it by reference.
+                write!(fmt, "{}", alias)?;
             },
             Data::Str(string) => { write!(fmt, "\"{}\"", string)?; }
             Data::Expression(expr) => {
@@ -869,7 +855,7 @@ impl <
                     match usage {
                         Use::Read => {
                             let value = ctx.ssa.map(|ssa| {
-                                ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc()
+                                ssa.value(ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc())
                             });
                             write!(fmt, "{}", ctx.highlight.location(
                                 &(Location::Register(*spec), Direction::Read),
@@ -882,7 +868,7 @@ impl <
                         },
                         Use::Write => {
                             let value = ctx.ssa.map(|ssa| {
-                                ssa.get_def(ctx.addr, Location::Register(*spec)).as_rc()
+                                ssa.value(ssa.get_def(ctx.addr, Location::Register(*spec)).as_rc())
                             });
                             write!(fmt, "{}", ctx.highlight.location(
                                 &(Location::Register(*spec), Direction::Write),
@@ -895,10 +881,10 @@ impl <
                         },
                         Use::ReadWrite => {
                             let read = ctx.ssa.map(|ssa| {
-                                ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc()
+                                ssa.value(ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc())
                             });
                             let write = ctx.ssa.map(|ssa| {
-                                ssa.get_def(ctx.addr, Location::Register(*spec)).as_rc()
+                                ssa.value(ssa.get_def(ctx.addr, Location::Register(*spec)).as_rc())
                             });
                             write!(fmt, "{} (-> {})",
                                 ctx.highlight.location(
@@ -989,7 +975,7 @@ impl <
                     if let Some(prefix) = ctx.instr.segment_override_for_op(op_idx) {
                         write!(fmt, "{}", ctx.colors.address(format!("{}:", prefix)))?;
                     }
-                    let value = ctx.ssa.map(|ssa| ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc());
+                    let value = ctx.ssa.map(|ssa| ssa.value(ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc()));
                     let reg_disp = RegValueDisplay {
                         reg: spec,
                         value: &value,
@@ -1069,7 +1055,7 @@ impl <
                     if let Some(prefix) = ctx.instr.segment_override_for_op(op_idx) {
                         write!(fmt, "{}", ctx.colors.address(format!("{}:", prefix)))?;
                     }
-                    // let value = ctx.ssa.map(|ssa| ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc());
+                    // let value = ctx.ssa.map(|ssa| ssa.value(ssa.get_use(ctx.addr, Location::Register(*spec)).as_rc()));
                     let reg_text = ctx.numbered_register_name(*spec, Direction::Read);
                     let reg = ctx.highlight.location(
                         &(Location::Register(*spec), Direction::Read),
@@ -1799,5 +1785,6 @@ pub fn show_function<'a, 'b, 'c, 'd, 'e, M: MemoryRepr<x86_64Arch> + MemoryRange
         colors,
         highlight_instrs: Vec::new(),
         highlight_locs: Vec::new(),
+        dimmed_instrs: Vec::new(),
     }
 }
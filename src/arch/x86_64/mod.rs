@@ -1,14 +1,13 @@
 use yaxpeax_arch::Arch;
 use yaxpeax_arch::Decoder;
 use arch::DecodeFrom;
+use analyses::call_graph::CallGraph;
 use analyses::control_flow;
 use analyses::static_single_assignment::SSA;
 use analyses::xrefs;
 use memory::MemoryRepr;
 use self::analyses::data_flow::DefaultCallingConvention;
 
-use petgraph::graphmap::GraphMap;
-
 use std::collections::HashMap;
 use std::collections::HashSet;
 use yaxpeax_x86::x86_64;
@@ -152,13 +151,31 @@ impl Default for x86_64Data {
     }
 }
 
+impl x86_64Data {
+    /// throw out everything computed from parts of `cfg` that `change` says are different now:
+    /// SSA for any function with an affected block, and xrefs and calls from removed code. this
+    /// returns the functions whose SSA was dropped, to be recomputed if they're still wanted.
+    pub fn invalidate(&mut self, change: &control_flow::Invalidation<<x86_64 as Arch>::Address>) -> Vec<<x86_64 as Arch>::Address> {
+        let mut stale: Vec<<x86_64 as Arch>::Address> = self.ssa.iter()
+            .filter(|(_, (function_cfg, _))| function_cfg.blocks.values().any(|block| change.affects(block)))
+            .map(|(addr, _)| *addr)
+            .collect();
+        stale.sort();
+        for addr in stale.iter() {
+            self.ssa.remove(addr);
+        }
+        self.contexts.invalidate(change);
+        stale
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct MergedContextTable {
     pub user_contexts: HashMap<<x86_64 as Arch>::Address, Rc<()>>,
     pub computed_contexts: HashMap<<x86_64 as Arch>::Address, Rc<()>>,
     pub comments: HashMap<<x86_64 as Arch>::Address, String>,
     #[serde(skip)]
-    pub call_graph: GraphMap<<x86_64 as Arch>::Address, (), petgraph::Directed>,
+    pub call_graph: CallGraph<<x86_64 as Arch>::Address>,
     #[serde(skip)]
     pub xrefs: xrefs::XRefCollection<<x86_64 as Arch>::Address>,
     pub symbols: HashMap<<x86_64 as Arch>::Address, Symbol>,
@@ -192,7 +209,7 @@ impl MergedContextTable {
             user_contexts: HashMap::new(),
             computed_contexts: HashMap::new(),
             comments: HashMap::new(),
-            call_graph: GraphMap::new(),
+            call_graph: CallGraph::new(),
             xrefs: xrefs::XRefCollection::new(),
             functions: Rc::new(RefCell::new(HashMap::new())),
             function_hints: Vec::new(),
@@ -204,6 +221,14 @@ impl MergedContextTable {
         }
     }
 
+    /// drop xrefs and calls from code that `change` removed.
+    pub fn invalidate(&mut self, change: &control_flow::Invalidation<<x86_64 as Arch>::Address>) {
+        for (start, end) in change.removed.iter() {
+            self.xrefs.delete_all_from_code_in(*start, *end);
+            self.call_graph.remove_calls_in(*start, *end);
+        }
+    }
+
     pub fn display_ctx(&self) -> DisplayCtx {
         DisplayCtx {
             functions: self.functions.borrow(),
@@ -233,7 +233,9 @@ pub trait DFGAccessExtMut<V: Value + std::fmt::Debug> where Self: DFGLocationQue
                 antiresult(self)
             }
             None => {
-                V::from_set(&[result(self), antiresult(self)])
+                let result = result(self);
+                let antiresult = antiresult(self);
+                condition.select(result, antiresult)
             }
         };
         self.write(&dest(), res);
@@ -253,7 +255,9 @@ pub trait DFGAccessExtMut<V: Value + std::fmt::Debug> where Self: DFGLocationQue
                 antiresult(self)
             }
             None => {
-                V::from_set(&[result(self), antiresult(self)])
+                let result = result(self);
+                let antiresult = antiresult(self);
+                condition.select(result, antiresult)
             }
         };
         self.write_operand(instr, &dest(), res);
@@ -315,6 +319,22 @@ pub fn evaluate<K: Copy, V: Value + std::fmt::Debug, D: DFG<V, amd64, K>>(when:
             let value = dfg.read_operand(instr, &instr.operand(1));
             dfg.write_operand(instr, &instr.operand(0), value);
         }
+        Opcode::MOVSX |
+        Opcode::MOVSXD => {
+            let width = match instr.operand(1) {
+                Operand::Register(reg) => Some(reg.width()),
+                _ => instr.mem_size().and_then(|size| size.bytes_size()),
+            };
+            let value = dfg.read_operand(instr, &instr.operand(1));
+            match width {
+                Some(width) => {
+                    dfg.write_operand(instr, &instr.operand(0), value.sxt(&V::from_const(width as i64 * 8)));
+                }
+                None => {
+                    dfg.write_operand(instr, &instr.operand(0), V::unknown());
+                }
+            }
+        }
         Opcode::PREFETCHW => {
             dfg.read_operand(instr, &instr.operand(0));
         }
@@ -407,8 +427,9 @@ pub fn evaluate<K: Copy, V: Value + std::fmt::Debug, D: DFG<V, amd64, K>>(when:
             dfg.write_bitwise_result(instr, instr.operand(0), src.or(&dest));
         }
         Opcode::NOT => {
+            // `Value::not` is a logical not, this is bitwise. `not` doesn't touch flags, either.
             let dest = dfg.read_operand(instr, &instr.operand(0));
-            dfg.write_operand(&instr, &instr.operand(0), dest.not());
+            dfg.write_operand(&instr, &instr.operand(0), dest.xor(&V::from_const(-1)).value());
         }
         Opcode::RETURN => {
             let ra = dfg.pop();
@@ -427,10 +448,14 @@ pub fn evaluate<K: Copy, V: Value + std::fmt::Debug, D: DFG<V, amd64, K>>(when:
             return CompletionStatus::Incomplete;
         },
         Opcode::CALL => {
+            let rsp = dfg.read(&Location::rsp());
             let ra = dfg.read(&Location::RIP);
             dfg.push(ra);
             let jump_target = dfg.read_operand(instr, &instr.operand(0));
             dfg.write(&Location::RIP, jump_target);
+            // the callee pops the return address before anything after the call runs, so that's
+            // the `rsp` the rest of the caller sees.
+            dfg.write(&Location::rsp(), rsp);
         },
         Opcode::CALLF => {
             dfg.write(&Location::RIP, V::unknown());
@@ -447,7 +472,7 @@ pub fn evaluate<K: Copy, V: Value + std::fmt::Debug, D: DFG<V, amd64, K>>(when:
         Opcode::IMUL => {
         },
         Opcode::JO => {
-            let of = dfg.read(&Location::OF).eq(&V::from_const(0));
+            let of = dfg.read(&Location::OF);
             let condition = of;
             dfg.conditional_loc_write(
                 condition,
@@ -594,7 +619,7 @@ pub fn evaluate<K: Copy, V: Value + std::fmt::Debug, D: DFG<V, amd64, K>>(when:
             let zf = dfg.read(&Location::ZF);
             let sf = dfg.read(&Location::SF);
             let of = dfg.read(&Location::OF);
-            let condition = zf.not().and(&sf.eq(&of)).value();
+            let condition = zf.or(&sf.ne(&of)).value();
             dfg.conditional_loc_write(
                 condition,
                 || Location::RIP,
@@ -10,7 +10,7 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::rc::Rc;
 
-#[derive(Hash, Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Hash, Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord)]
 pub enum Precedence {
     Before,
     After
@@ -56,10 +56,15 @@ pub enum ModifierExpression {
     Is(analyses::data_flow::Data),
     IsNot(analyses::data_flow::Data)
     */
+    /// the value is at most this, compared unsigned..
     Below(u64),
+    /// .. or at least this.
     Above(u64),
     Is(u64),
     IsNot(u64),
+    /// like `Below` and `Above`, but for signed comparisons, like after `jl` or `jg`.
+    SignedBelow(i64),
+    SignedAbove(i64),
 }
 
 /// The `Vec<ModifierExpression>` are _conjunctions_. This differs from `ValueSet`, which uses a
@@ -86,6 +91,12 @@ impl<A: Arch + ValueLocations> InstructionModifiers<A> where A::Location: AbiDef
         }
     }
 
+    /// the function called at `addr`, if `addr` is in `calls` and the target is a known function.
+    pub fn callee(&self, addr: A::Address) -> Option<FunctionImpl<A::Location>> {
+        let target = self.calls.get(&addr)?;
+        self.fn_query.borrow().function_at(*target).cloned()
+    }
+
     pub fn modifiers_between(&self, from: A::Address, to: A::Address) -> Option<&HashMap<Option<A::Location>, Vec<ModifierExpression>>> {
         self.between.get(&from).and_then(|tos| tos.get(&to))
     }
@@ -107,7 +118,9 @@ impl<A: Arch + ValueLocations> ModifierCollection<A> for InstructionModifiers<A>
                     match v {
                         ModifierExpression::IsNot(_) |
                         ModifierExpression::Below(_) |
-                        ModifierExpression::Above(_) => {
+                        ModifierExpression::Above(_) |
+                        ModifierExpression::SignedBelow(_) |
+                        ModifierExpression::SignedAbove(_) => {
                             res.push((k.to_owned(), Direction::Read));
                             res.push((k.to_owned(), Direction::Write));
                         }
@@ -129,7 +142,9 @@ impl<A: Arch + ValueLocations> ModifierCollection<A> for InstructionModifiers<A>
                     match v {
                         ModifierExpression::IsNot(_) |
                         ModifierExpression::Below(_) |
-                        ModifierExpression::Above(_) => {
+                        ModifierExpression::Above(_) |
+                        ModifierExpression::SignedBelow(_) |
+                        ModifierExpression::SignedAbove(_) => {
                             res.push((k.to_owned(), Direction::Read));
                             res.push((k.to_owned(), Direction::Write));
                         }
@@ -150,7 +165,9 @@ impl<A: Arch + ValueLocations> ModifierCollection<A> for InstructionModifiers<A>
                     match v {
                         ModifierExpression::IsNot(_) |
                         ModifierExpression::Below(_) |
-                        ModifierExpression::Above(_) => {
+                        ModifierExpression::Above(_) |
+                        ModifierExpression::SignedBelow(_) |
+                        ModifierExpression::SignedAbove(_) => {
                             res.push((k.to_owned(), Direction::Read));
                             res.push((k.to_owned(), Direction::Write));
                         }
@@ -1,6 +1,6 @@
 #![allow(non_snake_case, non_upper_case_globals)]
 
-use std::rc::Rc;
+use std::sync::Arc;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Field {
@@ -238,24 +238,24 @@ impl TypeAtlas {
                 // 0x0004
                 Field { size: 8, ty: Some(TypeSpec::LayoutId(I64)), name: Some("Rsp0".to_string()) },
                 // 0x000c
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Rsp1".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Rsp1".to_string()) },
                 // 0x0014
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Rsp2".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Rsp2".to_string()) },
                 // 0x001c
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[0]".to_string()) },
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[1]".to_string()) },
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[2]".to_string()) },
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[3]".to_string()) },
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[4]".to_string()) },
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[5]".to_string()) },
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[6]".to_string()) },
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[7]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[0]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[1]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[2]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[3]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[4]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[5]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[6]".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Ist[7]".to_string()) },
                 // 0x005c
-                Field { size: 8, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I64)))), name: Some("Reserved1".to_string()) },
+                Field { size: 8, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I64)))), name: Some("Reserved1".to_string()) },
                 // 0x0064
-                Field { size: 2, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I16)))), name: Some("Reserved2".to_string()) },
+                Field { size: 2, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I16)))), name: Some("Reserved2".to_string()) },
                 // 0x0066
-                Field { size: 2, ty: Some(TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(I16)))), name: Some("IoMapBase".to_string()) },
+                Field { size: 2, ty: Some(TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(I16)))), name: Some("IoMapBase".to_string()) },
 
             ]
         );
@@ -301,17 +301,17 @@ impl TypeAtlas {
 pub enum TypeSpec {
     Top,
     LayoutId(usize),
-    PointerTo(Rc<TypeSpec>),
+    PointerTo(Arc<TypeSpec>),
     Unknown,
     Bottom,
 }
 
 impl TypeSpec {
     pub fn struct_pointer(id: usize) -> Self {
-        TypeSpec::PointerTo(Rc::new(TypeSpec::LayoutId(id)))
+        TypeSpec::PointerTo(Arc::new(TypeSpec::LayoutId(id)))
     }
     pub fn pointer_to(self) -> Self {
-        TypeSpec::PointerTo(Rc::new(self))
+        TypeSpec::PointerTo(Arc::new(self))
     }
 }
 
@@ -123,7 +123,8 @@ pub struct ELFExport {
 pub struct Segment {
     start: usize,
     data: Vec<u8>,
-    name: String
+    name: String,
+    executable: bool,
 }
 
 impl Segment {
@@ -143,6 +144,13 @@ impl Segment {
     pub fn end(&self) -> usize {
         self.start + self.data.len()
     }
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+    /// is this segment mapped executable, as far as the program or section headers say?
+    pub fn is_executable(&self) -> bool {
+        self.executable
+    }
 }
 
 impl Named for Segment {
@@ -543,7 +551,7 @@ impl ModuleInfo {
                 ))
             }
             Object::Elf(elf) => {
-                let imports: Vec<ELFImport> = Vec::new();
+                let mut imports: Vec<ELFImport> = Vec::new();
                 let mut exports: Vec<ELFExport> = Vec::new();
                 let mut syms: Vec<ELFSymbol> = Vec::new();
                 let mut sections: Vec<ELFSection> = Vec::new();
@@ -588,6 +596,21 @@ impl ModuleInfo {
                     }
                 }
 
+                for reloc in elf.pltrelocs.iter() {
+                    // calls to imported functions go through a plt stub that jumps through the
+                    // .got slot this relocation fills in, so the slot is as good a place as any
+                    // to say where the import "is".
+                    if let Some(dynsym) = elf.dynsyms.get(reloc.r_sym) {
+                        if let Some(Ok(name)) = elf.dynstrtab.get(dynsym.st_name) {
+                            imports.push(ELFImport {
+                                name: name.to_string(),
+                                section_index: dynsym.st_shndx,
+                                value: reloc.r_offset
+                            });
+                        }
+                    }
+                }
+
                 let isa = map_elf_machine(elf.header.e_machine);
 
     // PE(ISAHint, goblin::pe::header::Header, Vec<goblin::pe::section_table::SectionTable>, Vec<PEReloc>, Vec<PEImport>, Vec<PEExport>),
@@ -673,6 +696,7 @@ impl ModuleData {
                         start: section.p_vaddr as usize,
                         data: section_data,
                         name: elf::program_header::type_to_str(elf.header.e_machine, section.p_type),
+                        executable: section.p_flags & goblin::elf::program_header::PF_X != 0,
                     };
                     println!("mapped section {} to [{}, {})",
                         i,
@@ -723,7 +747,8 @@ impl ModuleData {
                     let new_section = Segment {
                         start: section.virtual_address as usize + pe.header.optional_header.map(|x| x.windows_fields.image_base as usize).unwrap_or(0x400000),
                         data: section_data,
-                        name: std::str::from_utf8(&section.name[..]).unwrap().to_string()
+                        name: std::str::from_utf8(&section.name[..]).unwrap().to_string(),
+                        executable: section.characteristics & goblin::pe::section_table::IMAGE_SCN_MEM_EXECUTE != 0,
                     };
                     println!("mapped {} to [{}, {})",
                         std::str::from_utf8(&section.name[..]).unwrap(),
@@ -758,6 +783,12 @@ impl ModuleData {
             }
         }
     }
+    /// the `len` bytes starting at `addr`, if they're all in one segment.
+    pub fn bytes(&self, addr: u64, len: u64) -> Option<&[u8]> {
+        let segment = self.segment_for(addr)?;
+        let offset = (addr - segment.start as u64) as usize;
+        segment.data.get(offset..offset.checked_add(len as usize)?)
+    }
     fn segment_for<A: Address>(&self, addr: A) -> Option<&Segment> {
         for segment in self.segments.iter() {
             if segment.contains(addr) {
@@ -136,9 +136,12 @@ impl<'de, A: Address + Hash> Deserialize<'de> for GraphDeserializer<A> {
 
 pub trait Memoable: Sized {
     type Out: Sized + Serialize + for<'de> Deserialize<'de>;
+    /// where what `Self` refers to lives. a `DFGRef` is only an index, so its value is memoized
+    /// out of, and dememoized into, the `ValueGraph` it indexes.
+    type Context;
 
-    fn memoize(&self, memos: &HashMap<Self, u32>) -> Self::Out;
-    fn dememoize(idx: u32, memos: &[Self::Out], dememoized: &mut HashMap<u32, Self>) -> Self;
+    fn memoize(&self, context: &Self::Context, memos: &HashMap<Self, u32>) -> Self::Out;
+    fn dememoize(idx: u32, memos: &[Self::Out], dememoized: &mut HashMap<u32, Self>, context: &mut Self::Context) -> Self;
 }
 
 pub struct Memos<T: Hash + PartialEq + Eq> {
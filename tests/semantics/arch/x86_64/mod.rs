@@ -1,6 +1,3 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-
 use petgraph::visit::Bfs;
 
 use yaxpeax_arch::Arch;
@@ -26,7 +23,7 @@ fn do_analyses<'memory, 'dfg: 'layout, 'layout>(data: &'memory [u8], memory_layo
     let cfg = control_flow::AnalysisBuilder::new(data, &mut x86_64_data.contexts)
         .evaluate();
 
-    let dfg = if let Some((prior_dfg, layout)) = memory_layout {
+    let mut dfg = if let Some((prior_dfg, layout)) = memory_layout {
         let mut disambiguator = yaxpeax_core::arch::x86_64::analyses::data_flow::ContextualDisambiguation {
             dfg: prior_dfg,
             memory_layout: Some(layout),
@@ -50,7 +47,7 @@ fn do_analyses<'memory, 'dfg: 'layout, 'layout>(data: &'memory [u8], memory_layo
             .ssa_cytron()
     };
 
-    Evaluator::new(&data.to_vec(), &cfg, &dfg).full_function_iterate();
+    Evaluator::new(&data.to_vec(), &cfg, &mut dfg).full_function_iterate();
 
     (cfg, dfg, x86_64_data)
 }
@@ -58,10 +55,7 @@ fn do_analyses<'memory, 'dfg: 'layout, 'layout>(data: &'memory [u8], memory_layo
 fn do_memory_analyses<'memory, 'dfg: 'layout, 'layout>(data: &'memory [u8]) -> (ControlFlowGraph<<x86_64 as Arch>::Address>, SSA<x86_64>, x86_64Data) {
     let (cfg, dfg, _) = do_analyses(data, None);
 
-    let mut mem_analysis = MemoryLayout {
-        ssa: &dfg,
-        segments: RefCell::new(HashMap::new()),
-    };
+    let mut mem_analysis = MemoryLayout::new(&dfg);
 
     let instvec = data.to_vec();
 
@@ -171,16 +165,15 @@ fn test_known_stack_alias() {
         8,
         Some((
             Data::Expression(Item::value(ValueOrImmediate::Value(dfg.get_use(0, Location::Register(RegSpec::rsp())).value))),
-            Data::Expression(std::rc::Rc::<Item<ValueOrImmediate<_>>>::from_const(4))
+            Data::Expression(std::sync::Arc::<Item<ValueOrImmediate<_>>>::from_const(4))
         ))
     );
 
     let initial_const_value = dfg.get_def(0, rsp_access.clone()).as_rc();
-    let initial_const_data = initial_const_value.borrow();
-    let initial_const = initial_const_data.data.as_ref();
+    let initial_const = dfg.value(initial_const_value).data.as_ref();
     assert_eq!(initial_const, Some(&Data::Concrete(0xffffffff_efbeadde, None)));
-    assert_eq!(initial_const, dfg.get_def(12, Location::Register(RegSpec::rax())).get_data().as_ref().unwrap().underlying().as_ref());
-    assert_eq!(initial_const, dfg.get_def(16, Location::Register(RegSpec::rcx())).get_data().as_ref().unwrap().underlying().as_ref());
+    assert_eq!(initial_const, dfg.get_def(12, Location::Register(RegSpec::rax())).get_data().as_ref().unwrap().underlying(&dfg).as_ref());
+    assert_eq!(initial_const, dfg.get_def(16, Location::Register(RegSpec::rcx())).get_data().as_ref().unwrap().underlying(&dfg).as_ref());
 }
 
 #[test]
@@ -201,10 +194,9 @@ fn test_function_argument_inference() {
 fn print_memory_layouts(instvec: &Vec<u8>, cfg: &ControlFlowGraph<<x86_64 as Arch>::Address>, dfg: &SSA<x86_64>, mem_analysis: &MemoryLayout<x86_64>) {
 //    println!("{:?}", mem_analysis.segments);
     println!("\n");
-use yaxpeax_core::analyses::static_single_assignment::HashedValue;
 use yaxpeax_core::arch::x86_64::analyses::data_flow::ANY;
 use yaxpeax_core::analyses::ValueOrImmediate;
-use std::rc::Rc;
+use std::sync::Arc;
 
     let mut bfs = Bfs::new(&cfg.graph, cfg.entrypoint);
     while let Some(k) = bfs.next(&cfg.graph) {
@@ -218,12 +210,12 @@ use std::rc::Rc;
 
             if let Some(mem_read) = dfg.try_get_use(address, Location::Memory(ANY)) {
                 println!("getting segment for {:?}", mem_read);
-                println!("{:x}", Rc::clone(&mem_read).as_ptr() as u64);
+                println!("{}", mem_read);
                 print!("segments: ");
                 for key in segments.keys() {
                     if let ValueOrImmediate::Value(v) = key {
-                        print!("{:x} - ", Rc::clone(&v).as_ptr() as u64);
-                        print!("{}_{}, ", v.borrow().location, v.borrow().version.map(|x| x.to_string()).unwrap_or_else(||"input".to_string()));
+                        print!("{} - ", v);
+                        print!("{}_{}, ", dfg.value(*v).location, dfg.value(*v).version.map(|x| x.to_string()).unwrap_or_else(||"input".to_string()));
                     } else {
                         panic!("memory analysis key is an immediate. this is not actually impossible (x86 offset addressing exists) but is unhandled in this test");
                     }
@@ -251,10 +243,10 @@ use std::rc::Rc;
                         println!("memory region is constant offset: {:?}", value);
                         continue;
                     };
-                    let value = value.borrow();
+                    let value = dfg.value(*value);
                     if value.version == None && value.location == Location::Register(yaxpeax_x86::long_mode::RegSpec::rsp()) {
                         // this is the stack, show it off!
-                        let mut keys: Vec<Rc<Item<ValueOrImmediate<yaxpeax_x86::x86_64>>>> = region.accesses.keys().cloned().collect();
+                        let mut keys: Vec<Arc<Item<ValueOrImmediate<yaxpeax_x86::x86_64>>>> = region.accesses.keys().cloned().collect();
                         use yaxpeax_core::analyses::Expression;
                         use std::cmp::Ordering;
                         keys.sort_unstable_by(|a, b| {
@@ -365,10 +357,7 @@ fn test_stack_inference()  {
 
     let (cfg, dfg, _) = do_analyses(instructions, None);
 
-    let mut mem_analysis = MemoryLayout {
-        ssa: &dfg,
-        segments: RefCell::new(HashMap::new()),
-    };
+    let mut mem_analysis = MemoryLayout::new(&dfg);
 
     let instvec = instructions.to_vec();
 
@@ -383,11 +372,10 @@ fn test_stack_inference()  {
     }
 
     println!("{:?}", mem_analysis.segments);
-use yaxpeax_core::analyses::static_single_assignment::HashedValue;
 use yaxpeax_core::arch::x86_64::analyses::data_flow::ANY;
 use yaxpeax_core::analyses::Expression;
 use yaxpeax_core::analyses::ValueOrImmediate;
-use std::rc::Rc;
+use yaxpeax_core::analyses::static_single_assignment::DFGRef;
 
     let mut bfs = Bfs::new(&cfg.graph, cfg.entrypoint);
     while let Some(k) = bfs.next(&cfg.graph) {
@@ -396,12 +384,12 @@ use std::rc::Rc;
         while let Some((address, instr)) = iter.next() {
             let segments = mem_analysis.segments.borrow();
             if let Some(mem_read) = dfg.try_get_use(address, Location::Memory(ANY)) {
-                let mem_read = ValueOrImmediate::Value(Rc::clone(&mem_read));
+                let mem_read = ValueOrImmediate::Value(mem_read);
                 let segment = segments.get(&mem_read).unwrap();
                 println!("{} read : {:?}", address.show(), segment);
             }
             if let Some(mem_write) = dfg.try_get_def(address, Location::Memory(ANY)) {
-                let mem_write = ValueOrImmediate::Value(Rc::clone(&mem_write));
+                let mem_write = ValueOrImmediate::Value(mem_write);
                 let segment = segments.get(&mem_write).unwrap();
                 println!("{} write: {:?}", address.show(), segment);
             }
@@ -464,6 +452,212 @@ fn test_cmp_const() {
     ];
 }
 
+#[test]
+fn test_jo() {
+    use yaxpeax_core::analyses::constant_propagation::propagate_constants;
+
+    let instructions = &[
+        0x48, 0x85, 0xc0,                                              // 0x00: test rax, rax
+        0x70, 0x01,                                                    // 0x03: jo 0x06
+        0xc3,                                                          // 0x05: ret
+        0xc3,                                                          // 0x06: ret
+    ];
+
+    let (cfg, mut dfg, _) = do_analyses(instructions, None);
+    let constants = propagate_constants(&instructions.to_vec(), &cfg, &mut dfg);
+
+    // `test` clears OF, so `jo` falls through.
+    assert!(constants.is_feasible(0x00, 0x05));
+    assert!(!constants.is_feasible(0x00, 0x06));
+}
+
+#[test]
+fn test_jle() {
+    use yaxpeax_core::analyses::constant_propagation::propagate_constants;
+
+    let jle_taken = |rax: u8| {
+        let instructions = &[
+            0x48, 0xc7, 0xc0, rax, 0x00, 0x00, 0x00,                   // 0x00: mov rax, <rax>
+            0x48, 0x85, 0xc0,                                          // 0x07: test rax, rax
+            0x7e, 0x01,                                                // 0x0a: jle 0x0d
+            0xc3,                                                      // 0x0c: ret
+            0xc3,                                                      // 0x0d: ret
+        ];
+
+        let (cfg, mut dfg, _) = do_analyses(instructions, None);
+        let constants = propagate_constants(&instructions.to_vec(), &cfg, &mut dfg);
+        assert_ne!(constants.is_feasible(0x00, 0x0c), constants.is_feasible(0x00, 0x0d));
+        constants.is_feasible(0x00, 0x0d)
+    };
+
+    // ZF is set, so `jle` is taken even though SF == OF..
+    assert!(jle_taken(0));
+    // .. and with neither ZF set nor SF != OF, it isn't.
+    assert!(!jle_taken(5));
+}
+
+#[test]
+fn test_taint_through_memory() {
+    use yaxpeax_core::analyses::taint::{find_taint_flows, TaintFlow, TaintRules, TaintSink, TaintSource};
+    use yaxpeax_core::arch::{FunctionImpl, Parameter};
+    use yaxpeax_core::arch::x86_64::analyses::data_flow::{ContextualDisambiguation, NoDisambiguation};
+    use yaxpeax_core::data::modifier::InstructionModifiers;
+
+    let instructions = &[
+        0x48, 0x8d, 0x74, 0x24, 0x10,                                  // lea rsi, [rsp + 0x10]
+        0xe8, 0x16, 0x00, 0x00, 0x00,                                  // call recv
+        0x48, 0x8b, 0x54, 0x24, 0x10,                                  // mov rdx, [rsp + 0x10]
+        0xe8, 0x1c, 0x00, 0x00, 0x00,                                  // call memcpy
+        0xc3,                                                          // ret
+    ];
+    let instvec = instructions.to_vec();
+
+    let mut x86_64_data = x86_64Data::default();
+    let cfg = control_flow::AnalysisBuilder::new(&instvec, &mut x86_64_data.contexts)
+        .evaluate();
+
+    for (addr, name) in &[(0x20, "recv"), (0x30, "memcpy")] {
+        let mut f = FunctionImpl::new(name.to_string());
+        for (i, arg) in [Location::rdi(), Location::rsi(), Location::rdx()].iter().enumerate() {
+            f.append_arg((Some(arg.clone()), Parameter::of(&format!("arg{}", i))));
+        }
+        x86_64_data.contexts.functions.borrow_mut().insert(*addr, f);
+    }
+    let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+    modifiers.calls.insert(0x05, 0x20);
+    modifiers.calls.insert(0x0f, 0x30);
+    let functions = x86_64_data.contexts.functions.borrow();
+
+    let layout_of = |dfg| {
+        let mut mem_analysis = MemoryLayout::new(dfg);
+        let mut bfs = Bfs::new(&cfg.graph, cfg.entrypoint);
+        while let Some(k) = bfs.next(&cfg.graph) {
+            let block = cfg.get_block(k);
+            let mut iter = x86_64::instructions_spanning(&instvec, block.start, block.end);
+            while let Some((address, instr)) = iter.next() {
+                semantic::evaluate(address, &instr, &mut mem_analysis);
+            }
+        }
+        mem_analysis
+    };
+
+    let dfg = data_flow::AnalysisBuilder::new(
+        &instvec,
+        &cfg,
+        &*functions,
+        &mut NoDisambiguation::default(),
+    ).with_modifiers(&modifiers).ssa_cytron();
+    let mem_analysis = layout_of(&dfg);
+    let mut disambiguator = ContextualDisambiguation {
+        dfg: &dfg,
+        memory_layout: Some(&mem_analysis),
+    };
+    let mut refined = data_flow::AnalysisBuilder::new(
+        &instvec,
+        &cfg,
+        &*functions,
+        &mut disambiguator,
+    ).with_modifiers(&modifiers).ssa_cytron_refining(&dfg);
+    // fill in what registers hold in the refined dfg, so `rsi` is known to point onto the stack.
+    let expressions = layout_of(&refined).expressions();
+    for (value, expr) in expressions {
+        refined.value_mut(value).data = Some(Data::Expression(expr));
+    }
+
+    let rules = TaintRules::new()
+        .source_pointee("recv", 1)
+        .sink("memcpy", 2);
+    assert_eq!(find_taint_flows(&cfg, &refined, &modifiers, &rules), vec![TaintFlow {
+        source: TaintSource::ArgumentPointee { function: "recv".to_string(), argument: 1 },
+        sink: TaintSink::Argument { function: "memcpy".to_string(), argument: 2 },
+        path: vec![0x05, 0x0a, 0x0f],
+    }]);
+}
+
+#[test]
+fn test_taint_stack_offsets() {
+    use yaxpeax_core::analyses::taint::{find_taint_flows, TaintFlow, TaintRules, TaintSink, TaintSource};
+    use yaxpeax_core::arch::{FunctionImpl, Parameter};
+    use yaxpeax_core::arch::x86_64::analyses::data_flow::{ContextualDisambiguation, NoDisambiguation};
+    use yaxpeax_core::data::modifier::InstructionModifiers;
+
+    let flows = |disp: u8| {
+        let instructions = &[
+            0xe8, 0x1b, 0x00, 0x00, 0x00,                              // call getenv
+            0x50,                                                      // push rax
+            0x48, 0x89, 0x04, 0x24,                                    // mov [rsp], rax
+            0x48, 0x8b, 0x7c, 0x24, disp,                              // mov rdi, [rsp + <disp>]
+            0xe8, 0x1c, 0x00, 0x00, 0x00,                              // call system
+            0xc3,                                                      // ret
+        ];
+        let instvec = instructions.to_vec();
+
+        let mut x86_64_data = x86_64Data::default();
+        let cfg = control_flow::AnalysisBuilder::new(&instvec, &mut x86_64_data.contexts)
+            .evaluate();
+
+        for (addr, name) in &[(0x20, "getenv"), (0x30, "system")] {
+            let mut f = FunctionImpl::new(name.to_string());
+            f.append_arg((Some(Location::rdi()), Parameter::of("arg0")));
+            f.append_ret((Some(Location::rax()), Parameter::of("ret")));
+            x86_64_data.contexts.functions.borrow_mut().insert(*addr, f);
+        }
+        let mut modifiers = InstructionModifiers::new(x86_64_data.contexts.functions.clone());
+        modifiers.calls.insert(0x00, 0x20);
+        modifiers.calls.insert(0x0f, 0x30);
+        let functions = x86_64_data.contexts.functions.borrow();
+
+        let layout_of = |dfg| {
+            let mut mem_analysis = MemoryLayout::new(dfg);
+            let mut bfs = Bfs::new(&cfg.graph, cfg.entrypoint);
+            while let Some(k) = bfs.next(&cfg.graph) {
+                let block = cfg.get_block(k);
+                let mut iter = x86_64::instructions_spanning(&instvec, block.start, block.end);
+                while let Some((address, instr)) = iter.next() {
+                    semantic::evaluate(address, &instr, &mut mem_analysis);
+                }
+            }
+            mem_analysis
+        };
+
+        let dfg = data_flow::AnalysisBuilder::new(
+            &instvec,
+            &cfg,
+            &*functions,
+            &mut NoDisambiguation::default(),
+        ).with_modifiers(&modifiers).ssa_cytron();
+        let mem_analysis = layout_of(&dfg);
+        let mut disambiguator = ContextualDisambiguation {
+            dfg: &dfg,
+            memory_layout: Some(&mem_analysis),
+        };
+        let mut refined = data_flow::AnalysisBuilder::new(
+            &instvec,
+            &cfg,
+            &*functions,
+            &mut disambiguator,
+        ).with_modifiers(&modifiers).ssa_cytron_refining(&dfg);
+        let expressions = layout_of(&refined).expressions();
+        for (value, expr) in expressions {
+            refined.value_mut(value).data = Some(Data::Expression(expr));
+        }
+
+        let rules = TaintRules::new()
+            .source_return("getenv")
+            .sink("system", 0);
+        find_taint_flows(&cfg, &refined, &modifiers, &rules)
+    };
+
+    // reading back the stack slot is reading what `getenv` returned..
+    assert_eq!(flows(0x00), vec![TaintFlow {
+        source: TaintSource::Return { function: "getenv".to_string() },
+        sink: TaintSink::Argument { function: "system".to_string(), argument: 0 },
+        path: vec![0x00, 0x06, 0x0a, 0x0f],
+    }]);
+    // .. but neither moving `rsp` nor storing to one stack slot taints the rest of the stack.
+    assert_eq!(flows(0x10), vec![]);
+}
+
 /* and one more from /bin/bash: 
  *
  *  // this is a hashing loop, ebp *= 0x1000193; rcx += 1; ebp ^= eax; eax = *rcx; eax != 0? loop